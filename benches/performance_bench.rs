@@ -45,6 +45,29 @@ fn kvdb_set_benchmark(c: &mut Criterion) {
     });
 }
 
+/// 与 `kvdb_set_new` 相同，但使用内存映射的存储后端 (需要启用 `mmap` 特性)。
+#[cfg(all(feature = "mmap", unix))]
+fn kvdb_mmap_set_benchmark(c: &mut Criterion) {
+    let temp_dir = tempdir().unwrap();
+    let storage = flashdb_rs::storage::MmapStorage::new(temp_dir.path().join("kv_bench.fdb"), 4096, 10 * 1024 * 1024).unwrap();
+    let mut db = Box::new(KVDB::new(storage));
+    db.set_name("kv_bench_db").unwrap();
+    db.init(None).unwrap();
+    let value = vec![0u8; 256];
+
+    c.bench_function("kvdb_set_new_mmap", |b| {
+        let mut i = 0;
+        b.iter(|| {
+            let key_str = format!("key_{}\0", i);
+            db.set(&key_str, &value).unwrap();
+            i += 1;
+        })
+    });
+}
+
+#[cfg(not(all(feature = "mmap", unix)))]
+fn kvdb_mmap_set_benchmark(_c: &mut Criterion) {}
+
 /// 测试重复读取同一个键的性能。
 fn kvdb_get_benchmark(c: &mut Criterion) {
     let temp_dir = tempdir().unwrap();
@@ -141,6 +164,7 @@ fn tsdb_query_benchmark(c: &mut Criterion) {
 criterion_group!(
     benches,
     kvdb_set_benchmark,
+    kvdb_mmap_set_benchmark,
     kvdb_get_benchmark,
     kvdb_overwrite_benchmark,
     tsdb_append_benchmark,
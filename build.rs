@@ -1,4 +1,6 @@
+use std::collections::BTreeMap;
 use std::env;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
@@ -64,6 +66,129 @@ fn get_compiler_include_paths(compiler_path: &Path) -> Result<Vec<String>, std::
     Ok(clang_args)
 }
 
+// 读取缓存表大小：环境变量优先，其次为 `kv-cache-*` 特性中最大的一个，否则使用 C 库默认值
+fn cache_table_size(env_key: &str, features: &[(bool, u32)]) -> Option<u32> {
+    println!("cargo:rerun-if-env-changed={}", env_key);
+    if let Ok(value) = env::var(env_key) {
+        let size = value
+            .trim()
+            .parse::<u32>()
+            .unwrap_or_else(|_| panic!("{} 必须是整数，当前值: {:?}", env_key, value));
+        // C 库使用 16 位保存缓存表索引
+        assert!(size <= 0xFFFF, "{} 不能超过 65535", env_key);
+        return Some(size);
+    }
+    features
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, size)| *size)
+        .max()
+}
+
+// 读取写入粒度 (bit)：环境变量优先，其次为启用的 `write-gran-*` 特性，否则使用 C 库默认的 1 bit
+fn write_gran(env_key: &str, features: &[(bool, u32)]) -> Option<u32> {
+    const SUPPORTED: [u32; 5] = [1, 8, 32, 64, 128];
+    println!("cargo:rerun-if-env-changed={}", env_key);
+    if let Ok(value) = env::var(env_key) {
+        let gran = value
+            .trim()
+            .parse::<u32>()
+            .unwrap_or_else(|_| panic!("{} 必须是整数，当前值: {:?}", env_key, value));
+        assert!(SUPPORTED.contains(&gran), "{} 只能是 {:?} 之一，当前值: {}", env_key, SUPPORTED, gran);
+        return Some(gran);
+    }
+    let mut enabled = features.iter().filter(|(enabled, _)| *enabled).map(|(_, gran)| *gran);
+    let gran = enabled.next();
+    // 粒度决定了 Flash 上的数据格式，不能像缓存表那样取最大值
+    assert!(enabled.next().is_none(), "write-gran-* 特性只能启用一个");
+    gran
+}
+
+// 对上游 FlashDB C 源码的修改，按顺序应用，说明见各补丁文件的开头
+const PATCHES: &[&str] = &[
+    "0001-kvdb-add-fdb_kv_gc.patch",
+    "0002-tsdb-add-fdb_tsl_purge_before.patch",
+    "0003-tsdb-add-fdb_tsl_vacuum.patch",
+    "0004-tsdb-add-fdb_tsl_verify.patch",
+    "0005-log-prefix-database-name.patch",
+];
+
+// 解析 `@@ -a,b +c,d @@` 中的行数，省略时为 1
+fn hunk_len(range: &str) -> usize {
+    range.split_once(',').map_or(Ok(1), |(_, len)| len.parse()).expect("补丁的 hunk 头格式错误")
+}
+
+// 将 `flashdb/patches/` 下的补丁按顺序应用到 C 源文件，修改后的文件写入 `out_dir`，
+// 返回源文件到修改后文件的映射。补丁的上下文必须与源文件完全一致 (忽略行尾的 `\r`)，否则构建失败。
+fn apply_patches(patches: &[&str], out_dir: &Path) -> BTreeMap<String, PathBuf> {
+    let mut patched: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for name in patches {
+        let path = format!("flashdb/patches/{}", name);
+        println!("cargo:rerun-if-changed={}", path);
+        let patch = fs::read_to_string(&path).unwrap_or_else(|e| panic!("无法读取补丁 {}: {}", path, e));
+        let mut lines = patch.lines().map(|line| line.trim_end_matches('\r'));
+        let mut target = None;
+        let mut cursor = 0;
+        while let Some(line) = lines.next() {
+            if let Some(file) = line.strip_prefix("+++ b/") {
+                println!("cargo:rerun-if-changed={}", file);
+                patched.entry(file.to_string()).or_insert_with(|| {
+                    let source = fs::read_to_string(file).unwrap_or_else(|e| panic!("无法读取 {}: {}", file, e));
+                    source.lines().map(|line| line.trim_end_matches('\r').to_string()).collect()
+                });
+                target = Some(file.to_string());
+                cursor = 0;
+                continue;
+            }
+            let Some(ranges) = line.strip_prefix("@@ -") else {
+                continue;
+            };
+            let file = target.as_ref().unwrap_or_else(|| panic!("补丁 {} 缺少文件头", name));
+            let (old_range, new_range) = ranges.split_once(" +").expect("补丁的 hunk 头格式错误");
+            let new_range = new_range.split(' ').next().unwrap();
+            let (mut old_left, mut new_left) = (hunk_len(old_range), hunk_len(new_range));
+            let (mut old, mut new) = (Vec::new(), Vec::new());
+            while old_left > 0 || new_left > 0 {
+                let line = lines.next().unwrap_or_else(|| panic!("补丁 {} 的 hunk 不完整", name));
+                // 空的上下文行可能被编辑器去掉了行首的空格
+                let (kind, text) = line.split_at(line.len().min(1));
+                match kind {
+                    " " | "" => {
+                        old.push(text);
+                        new.push(text);
+                        old_left -= 1;
+                        new_left -= 1;
+                    }
+                    "-" => {
+                        old.push(text);
+                        old_left -= 1;
+                    }
+                    "+" => {
+                        new.push(text);
+                        new_left -= 1;
+                    }
+                    _ => {}
+                }
+            }
+            let content = patched.get_mut(file).unwrap();
+            let at = (cursor..=content.len().saturating_sub(old.len()))
+                .find(|&at| content[at..at + old.len()].iter().zip(&old).all(|(a, b)| a == b))
+                .unwrap_or_else(|| panic!("补丁 {} 无法应用到 {} (@@ -{})", name, file, old_range));
+            content.splice(at..at + old.len(), new.iter().map(|line| line.to_string()));
+            cursor = at + new.len();
+        }
+    }
+    patched
+        .into_iter()
+        .map(|(file, content)| {
+            let path = out_dir.join(&file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, content.join("\n") + "\n").unwrap();
+            (file, path)
+        })
+        .collect()
+}
+
 fn main() {
     let target = env::var("TARGET").unwrap();
     let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
@@ -132,6 +257,30 @@ fn main() {
     let use_tsdb = cfg!(feature = "tsdb");
     let use_log = cfg!(feature = "log");
     let debug_enabled = cfg!(debug_assertions);
+    let write_gran = write_gran(
+        "FLASHDB_WRITE_GRAN",
+        &[
+            (cfg!(feature = "write-gran-8"), 8),
+            (cfg!(feature = "write-gran-32"), 32),
+            (cfg!(feature = "write-gran-64"), 64),
+            (cfg!(feature = "write-gran-128"), 128),
+        ],
+    );
+    // C 库的 TSDB 尚不支持 64 / 128 bit 写入粒度 (fdb_tsdb.c 中的 #error)
+    assert!(
+        !(use_tsdb && write_gran.is_some_and(|gran| gran >= 64)),
+        "TSDB 不支持 64 / 128 bit 写入粒度，请关闭 tsdb 特性"
+    );
+    let kv_cache_size = cache_table_size(
+        "FLASHDB_KV_CACHE_TABLE_SIZE",
+        &[
+            (cfg!(feature = "kv-cache-none"), 0),
+            (cfg!(feature = "kv-cache-16"), 16),
+            (cfg!(feature = "kv-cache-32"), 32),
+            (cfg!(feature = "kv-cache-128"), 128),
+        ],
+    );
+    let sector_cache_size = cache_table_size("FLASHDB_SECTOR_CACHE_TABLE_SIZE", &[]);
 
     {
         let linker = match target.as_str() {
@@ -157,12 +306,27 @@ fn main() {
     if use_log {
         // 编译 shim.c 并重定向 FDB_PRINT 到 Rust
         srcs.push("flashdb/shim.c");
-        build.define("FDB_PRINT(...)", "fdb_log_printf(__VA_ARGS__)");
+        // 强制包含 shim.h，FDB_PRINT 将所在函数的数据库指针 `db` 一并传给 Rust
+        let shim = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("flashdb/shim.h");
+        if build.get_compiler().is_like_msvc() {
+            build.flag(format!("/FI{}", shim.display()));
+        } else {
+            build.flag("-include").flag(&shim);
+        }
+        build.define("FDB_PRINT(...)", "fdb_log_printf(db, __VA_ARGS__)");
+        println!("cargo:rerun-if-changed=flashdb/shim.h");
     } else {
         // 不启用 log 时，将 FDB_PRINT 定义为空操作，移除 stdio 依赖
         build.define("FDB_PRINT(...)", "((void)0)");
     }
 
+    // 有补丁的源文件改为编译 OUT_DIR 中修改后的副本
+    let patched = apply_patches(PATCHES, &out_path);
+    let srcs: Vec<PathBuf> = srcs
+        .iter()
+        .map(|src| patched.get(*src).cloned().unwrap_or_else(|| PathBuf::from(src)))
+        .collect();
+
     build
         .flag("-std=c99")
         .files(&srcs)
@@ -184,6 +348,15 @@ fn main() {
     if debug_enabled {
         build.define("FDB_DEBUG_ENABLE", "1");
     }
+    if let Some(size) = kv_cache_size {
+        build.define("FDB_KV_CACHE_TABLE_SIZE", size.to_string().as_str());
+    }
+    if let Some(size) = sector_cache_size {
+        build.define("FDB_SECTOR_CACHE_TABLE_SIZE", size.to_string().as_str());
+    }
+    if let Some(gran) = write_gran {
+        build.define("FDB_WRITE_GRAN", gran.to_string().as_str());
+    }
 
     build.compile("flashdb");
 
@@ -216,6 +389,17 @@ fn main() {
     if debug_enabled {
         bindings = bindings.clang_arg("-DFDB_DEBUG_ENABLE=1");
     }
+    // 缓存表位于 fdb_kvdb 结构体中，绑定必须使用相同的大小
+    if let Some(size) = kv_cache_size {
+        bindings = bindings.clang_arg(format!("-DFDB_KV_CACHE_TABLE_SIZE={}", size));
+    }
+    if let Some(size) = sector_cache_size {
+        bindings = bindings.clang_arg(format!("-DFDB_SECTOR_CACHE_TABLE_SIZE={}", size));
+    }
+    // Rust 侧按绑定中的 FDB_WRITE_GRAN 解析 Flash 上的数据格式，必须与 C 库一致
+    if let Some(gran) = write_gran {
+        bindings = bindings.clang_arg(format!("-DFDB_WRITE_GRAN={}", gran));
+    }
     if !use_log {
         bindings = bindings.clang_arg("-DFDB_PRINT(...)=");
     }
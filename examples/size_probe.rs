@@ -0,0 +1,79 @@
+//! `size_report` 使用的探针程序。
+//!
+//! 在内存 Flash 上执行已启用子系统的典型操作，使对应代码被链接进二进制文件；
+//! 未启用的子系统不会被引用，用于验证其代码能否被完全消除。
+//! 该程序本身也可以在任意特性组合下直接运行。
+
+use embedded_storage::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+
+/// 16 KiB 的内存 Flash
+struct Ram([u8; 4 * 4096]);
+
+impl ErrorType for Ram {
+    type Error = flashdb_rs::Error;
+}
+
+impl ReadNorFlash for Ram {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        bytes.copy_from_slice(&self.0[offset as usize..offset as usize + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl NorFlash for Ram {
+    const WRITE_SIZE: usize = 1;
+    const ERASE_SIZE: usize = 4096;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.0[from as usize..to as usize].fill(0xFF);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.0[offset as usize..offset as usize + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "kvdb")]
+fn probe_kvdb() -> Result<(), flashdb_rs::Error> {
+    let mut db = Box::new(flashdb_rs::KVDB::new(Ram([0xFF; 4 * 4096])));
+    db.init(None)?;
+    db.set("boot_count", &1u32.to_le_bytes())?;
+    assert!(db.contains_key(std::hint::black_box("boot_count"))?);
+    db.delete("boot_count")
+}
+
+#[cfg(feature = "tsdb")]
+fn probe_tsdb() -> Result<(), flashdb_rs::Error> {
+    let mut db = Box::new(flashdb_rs::TSDB::new(Ram([0xFF; 4 * 4096])));
+    db.init(64)?;
+    for time in 1..=4 {
+        db.append_with_timestamp(time, std::hint::black_box(b"sample"))?;
+    }
+    let mut seen = 0;
+    db.tsdb_iter(
+        |_, _| {
+            seen += 1;
+            true
+        },
+        false,
+    );
+    assert_eq!(seen, 4);
+    Ok(())
+}
+
+fn main() -> Result<(), flashdb_rs::Error> {
+    #[cfg(feature = "kvdb")]
+    probe_kvdb()?;
+    #[cfg(feature = "tsdb")]
+    probe_tsdb()?;
+    std::hint::black_box(Ram([0xFF; 4 * 4096]).capacity());
+    Ok(())
+}
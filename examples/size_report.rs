@@ -0,0 +1,178 @@
+//! 统计各个特性对代码体积的影响。
+//!
+//! 依次以不同的特性组合编译 `size_probe` 示例 (release)，读取生成的 ELF 文件中
+//! `.text` 与 `.rodata` 段的大小，并打印相对基准配置的增量：
+//!
+//! ```text
+//! cargo run --example size_report
+//! cargo run --example size_report -- --target aarch64-unknown-linux-gnu
+//! ```
+//!
+//! 单独的 `kvdb` / `tsdb` 行以不启用任何特性的配置为基准，`+` 开头的行以 `kvdb,tsdb` 为基准，
+//! 从而得到每个可选特性单独带来的开销。绝对数值取决于目标平台与编译器版本，只有增量具有参考意义。
+//! 目前只支持输出 ELF 文件的目标。
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// 一种特性组合
+struct Config {
+    name: &'static str,
+    features: &'static str,
+    /// 用作基准的配置名
+    base: Option<&'static str>,
+}
+
+const CONFIGS: &[Config] = &[
+    Config { name: "none", features: "", base: None },
+    Config { name: "kvdb", features: "kvdb", base: Some("none") },
+    Config { name: "tsdb", features: "tsdb", base: Some("none") },
+    Config { name: "kvdb,tsdb", features: "kvdb,tsdb", base: Some("none") },
+    Config { name: "+log", features: "kvdb,tsdb,log", base: Some("kvdb,tsdb") },
+    Config { name: "+time64", features: "kvdb,tsdb,time64", base: Some("kvdb,tsdb") },
+    Config { name: "+alloc", features: "kvdb,tsdb,alloc", base: Some("kvdb,tsdb") },
+    Config { name: "+kv-index", features: "kvdb,tsdb,kv-index", base: Some("kvdb,tsdb") },
+    Config { name: "+std", features: "kvdb,tsdb,std", base: Some("kvdb,tsdb") },
+];
+
+/// 代码段大小
+#[derive(Clone, Copy, Default)]
+struct Sizes {
+    text: u64,
+    rodata: u64,
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut target = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--target" => target = args.next(),
+            _ => {
+                eprintln!("usage: size_report [--target <triple>]");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let target_dir = manifest_dir.join("target").join("size-report");
+    let mut results: Vec<(&str, Sizes)> = Vec::new();
+
+    println!("{:<12} {:>10} {:>10} {:>10} {:>10}", "config", ".text", ".rodata", "Δ.text", "Δ.rodata");
+    for config in CONFIGS {
+        let binary = match build(manifest_dir, &target_dir, target.as_deref(), config) {
+            Ok(binary) => binary,
+            Err(err) => {
+                eprintln!("{}: {}", config.name, err);
+                std::process::exit(1);
+            }
+        };
+        let sizes = match fs::read(&binary).map_err(|e| e.to_string()).and_then(|data| elf_sizes(&data)) {
+            Ok(sizes) => sizes,
+            Err(err) => {
+                eprintln!("{}: {}: {}", config.name, binary.display(), err);
+                std::process::exit(1);
+            }
+        };
+
+        let base = config
+            .base
+            .and_then(|base| results.iter().find(|(name, _)| *name == base))
+            .map(|(_, sizes)| *sizes);
+        match base {
+            Some(base) => println!(
+                "{:<12} {:>10} {:>10} {:>+10} {:>+10}",
+                config.name,
+                sizes.text,
+                sizes.rodata,
+                sizes.text as i64 - base.text as i64,
+                sizes.rodata as i64 - base.rodata as i64,
+            ),
+            None => println!("{:<12} {:>10} {:>10} {:>10} {:>10}", config.name, sizes.text, sizes.rodata, "-", "-"),
+        }
+        results.push((config.name, sizes));
+    }
+}
+
+/// 以指定的特性组合编译 `size_probe`，返回生成的二进制文件路径
+fn build(manifest_dir: &Path, target_dir: &Path, target: Option<&str>, config: &Config) -> Result<PathBuf, String> {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".into());
+    let mut cmd = Command::new(cargo);
+    cmd.current_dir(manifest_dir)
+        .env("CARGO_TARGET_DIR", target_dir)
+        .args(["build", "--quiet", "--release", "--example", "size_probe", "--no-default-features"]);
+    if !config.features.is_empty() {
+        cmd.args(["--features", config.features]);
+    }
+    if let Some(target) = target {
+        cmd.args(["--target", target]);
+    }
+    let status = cmd.status().map_err(|e| format!("failed to run cargo: {}", e))?;
+    if !status.success() {
+        return Err(format!("cargo build failed ({})", status));
+    }
+
+    let mut binary = target_dir.to_path_buf();
+    if let Some(target) = target {
+        binary.push(target);
+    }
+    binary.push("release");
+    binary.push("examples");
+    binary.push("size_probe");
+    Ok(binary)
+}
+
+/// 从 ELF 文件的段表中累加 `.text*` 与 `.rodata*` 段的大小
+fn elf_sizes(data: &[u8]) -> Result<Sizes, String> {
+    if data.len() < 0x34 || data[..4] != *b"\x7fELF" {
+        return Err("not an ELF file".into());
+    }
+    let is_64 = match data[4] {
+        1 => false,
+        2 => true,
+        _ => return Err("invalid ELF class".into()),
+    };
+    if data[5] != 1 {
+        return Err("only little-endian ELF files are supported".into());
+    }
+
+    let read = |offset: usize, len: usize| -> Result<u64, String> {
+        let bytes = data.get(offset..offset + len).ok_or("truncated ELF file")?;
+        Ok(bytes.iter().rev().fold(0u64, |acc, &b| (acc << 8) | b as u64))
+    };
+    let word = if is_64 { 8 } else { 4 };
+    let (shoff, shentsize, shnum, shstrndx) = if is_64 {
+        (read(0x28, 8)?, read(0x3A, 2)?, read(0x3C, 2)?, read(0x3E, 2)?)
+    } else {
+        (read(0x20, 4)?, read(0x2E, 2)?, read(0x30, 2)?, read(0x32, 2)?)
+    };
+
+    // 段头中 sh_name 位于开头，sh_offset 与 sh_size 分别位于 flags、addr 之后
+    let section = |index: u64| -> Result<(u64, u64, u64), String> {
+        let base = (shoff + index * shentsize) as usize;
+        let name = read(base, 4)?;
+        let offset = read(base + 8 + 2 * word, word)?;
+        let size = read(base + 8 + 3 * word, word)?;
+        Ok((name, offset, size))
+    };
+    let (_, strtab, _) = section(shstrndx)?;
+
+    let mut sizes = Sizes::default();
+    for index in 0..shnum {
+        let (name, _, size) = section(index)?;
+        let start = (strtab + name) as usize;
+        let name = data
+            .get(start..)
+            .and_then(|s| s.split(|&b| b == 0).next())
+            .ok_or("invalid section name")?;
+        if name.starts_with(b".text") {
+            sizes.text += size;
+        } else if name.starts_with(b".rodata") {
+            sizes.rodata += size;
+        }
+    }
+    Ok(sizes)
+}
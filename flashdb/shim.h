@@ -0,0 +1,13 @@
+#ifndef _FDB_SHIM_H_
+#define _FDB_SHIM_H_
+
+/*
+ * 由 build.rs 强制包含到每个 C 文件中。FDB_PRINT 展开为 fdb_log_printf(db, ...)，
+ * 数据库函数的参数或局部变量 db 会遮蔽这里的空指针，使日志带上所属的数据库；
+ * 不在数据库上下文中的日志 (例如 fdb_utils.c 中的断言) 传入 NULL。
+ */
+static const void *const db = 0;
+
+void fdb_log_printf(const void *db, const char *format, ...);
+
+#endif /* _FDB_SHIM_H_ */
@@ -0,0 +1,206 @@
+//! 适用于 embassy 等异步执行器的数据库接口。
+//!
+//! FlashDB 的每个操作都是同步执行的，一次写入可能触发耗时的垃圾回收与扇区擦除，
+//! 遍历整个数据库更会长时间占用执行器。本模块的异步方法在每个数据库操作之后让出执行器，
+//! 遍历时每处理 `chunk` 条数据让出一次，使同一执行器上的其他任务 (例如通信协议栈) 得以运行。
+//!
+//! 这些方法不依赖任何执行器，让出通过返回一次 `Poll::Pending` 并立即唤醒实现。
+//!
+//! ```
+//! # use flashdb_rs::asynch::AsyncKVDB;
+//! # use flashdb_rs::KVDB;
+//! # fn block_on<F: core::future::Future>(f: F) -> F::Output {
+//! #     let mut f = core::pin::pin!(f);
+//! #     let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+//! #     loop {
+//! #         if let core::task::Poll::Ready(out) = f.as_mut().poll(&mut cx) {
+//! #             return out;
+//! #         }
+//! #     }
+//! # }
+//! # let dir = tempfile::tempdir()?;
+//! let mut db = KVDB::new_file("async_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+//! block_on(async {
+//!     let mut db = AsyncKVDB::new(&mut db);
+//!     db.set("boot_count", b"1").await?;
+//!     assert_eq!(db.get("boot_count").await?.as_deref(), Some(&b"1"[..]));
+//!     Ok::<(), flashdb_rs::Error>(())
+//! })?;
+//! # Ok::<(), flashdb_rs::Error>(())
+//! ```
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+#[cfg(feature = "kvdb")]
+use alloc::vec::Vec;
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+#[cfg(feature = "kvdb")]
+use crate::KVDB;
+#[cfg(feature = "tsdb")]
+use crate::{TSLEntry, TSDB};
+
+/// 遍历时默认每处理多少条数据让出一次执行器
+pub const DEFAULT_CHUNK: usize = 16;
+
+/// 让出执行器一次。
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+/// `yield_now()` 返回的 future
+pub struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// KVDB 的异步接口，详见 [`crate::asynch`]。
+#[cfg(feature = "kvdb")]
+pub struct AsyncKVDB<'a, S: NorFlash> {
+    db: &'a mut KVDB<S>,
+    chunk: usize,
+}
+
+#[cfg(feature = "kvdb")]
+impl<'a, S: NorFlash> AsyncKVDB<'a, S> {
+    /// 包装已初始化的数据库。
+    pub fn new(db: &'a mut KVDB<S>) -> Self {
+        Self { db, chunk: DEFAULT_CHUNK }
+    }
+
+    /// 设置遍历时每处理多少条数据让出一次执行器，最小为 1。
+    pub fn with_chunk(mut self, chunk: usize) -> Self {
+        self.chunk = chunk.max(1);
+        self
+    }
+
+    /// 根据键获取其值。
+    pub async fn get(&mut self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let value = self.db.get(key);
+        yield_now().await;
+        value
+    }
+
+    /// 存储一个键值对，可能触发垃圾回收。
+    pub async fn set(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
+        let result = self.db.set(key, value);
+        yield_now().await;
+        result
+    }
+
+    /// 删除一个键值对。
+    pub async fn delete(&mut self, key: &str) -> Result<(), Error> {
+        let result = self.db.delete(key);
+        yield_now().await;
+        result
+    }
+
+    /// 依次访问所有键值对，回调返回 `false` 时提前终止。
+    ///
+    /// 先收集所有键名，再逐个读取值，遍历期间其他任务无法修改数据库。
+    pub async fn for_each(&mut self, mut f: impl FnMut(&str, &[u8]) -> bool) -> Result<(), Error> {
+        let keys: Vec<_> = self.db.iter_keys().collect();
+        yield_now().await;
+        for (i, key) in keys.iter().enumerate() {
+            let Some(name) = key.as_str() else { continue };
+            if let Some(value) = self.db.get(name)? {
+                if !f(name, &value) {
+                    break;
+                }
+            }
+            if (i + 1) % self.chunk == 0 {
+                yield_now().await;
+            }
+        }
+        Ok(())
+    }
+
+    /// 获取被包装的数据库。
+    pub fn inner(&mut self) -> &mut KVDB<S> {
+        self.db
+    }
+}
+
+/// TSDB 的异步接口，详见 [`crate::asynch`]。
+#[cfg(feature = "tsdb")]
+pub struct AsyncTSDB<'a, S: NorFlash> {
+    db: &'a mut TSDB<S>,
+    chunk: usize,
+}
+
+#[cfg(feature = "tsdb")]
+impl<'a, S: NorFlash> AsyncTSDB<'a, S> {
+    /// 包装已初始化的数据库。
+    pub fn new(db: &'a mut TSDB<S>) -> Self {
+        Self { db, chunk: DEFAULT_CHUNK }
+    }
+
+    /// 设置遍历时每处理多少条数据让出一次执行器，最小为 1。
+    pub fn with_chunk(mut self, chunk: usize) -> Self {
+        self.chunk = chunk.max(1);
+        self
+    }
+
+    /// 以数据库的时间戳来源追加一条日志，详见 `TSDB::append`。
+    pub async fn append(&mut self, data: &[u8]) -> Result<(), Error> {
+        let result = self.db.append(data);
+        yield_now().await;
+        result
+    }
+
+    /// 追加一条指定时间戳的日志。
+    pub async fn append_with_timestamp(&mut self, timestamp: i64, data: &[u8]) -> Result<(), Error> {
+        let result = self.db.append_with_timestamp(timestamp, data);
+        yield_now().await;
+        result
+    }
+
+    /// 按时间顺序访问 `[from, to]` 范围内的日志及其数据，回调返回 `false` 时提前终止。
+    ///
+    /// 每条日志都单独查找，让出执行器期间不持有 C 库的迭代状态。
+    pub async fn for_each_by_time(
+        &mut self,
+        from: i64,
+        to: i64,
+        mut f: impl FnMut(&TSLEntry, &[u8]) -> bool,
+    ) -> Result<(), Error> {
+        let mut cursor = from;
+        let mut visited = 0;
+        while cursor <= to {
+            let Some(tsl) = self.db.iter_by_time(cursor..=to).next() else { break };
+            let value = self.db.get_value(&tsl)?.unwrap_or_default();
+            if !f(&tsl, &value) {
+                break;
+            }
+            visited += 1;
+            if visited % self.chunk == 0 {
+                yield_now().await;
+            }
+            match tsl.time().checked_add(1) {
+                Some(next) => cursor = next,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// 获取被包装的数据库。
+    pub fn inner(&mut self) -> &mut TSDB<S> {
+        self.db
+    }
+}
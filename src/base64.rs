@@ -0,0 +1,48 @@
+//! 标准 base64 编解码，用于以文本形式导出与导入二进制数据。
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::Error;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// 标准 base64 编码 (带填充)
+pub(crate) fn encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// 标准 base64 解码，要求带有正确的填充
+pub(crate) fn decode(text: &str) -> Result<Vec<u8>, Error> {
+    let text = text.as_bytes();
+    if text.len() % 4 != 0 {
+        return Err(Error::InvalidArgument);
+    }
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    for (index, chunk) in text.chunks(4).enumerate() {
+        let last = index == text.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return Err(Error::InvalidArgument);
+        }
+        let mut n = 0u32;
+        for &c in &chunk[..4 - padding] {
+            let digit = ALPHABET.iter().position(|&a| a == c).ok_or(Error::InvalidArgument)?;
+            n = n << 6 | digit as u32;
+        }
+        n <<= 6 * padding;
+        out.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    }
+    Ok(out)
+}
@@ -0,0 +1,333 @@
+//! 标准化的性能测试负载。
+//!
+//! 与 `benches/` 下基于 criterion 的测试不同，此模块可以针对任意 `NorFlash`
+//! 存储后端运行，方便在目标硬件或自定义配置上比较吞吐量、延迟与磨损情况。
+//!
+//! ```no_run
+//! use flashdb_rs::bench::{run_kvdb, BenchConfig, KvWorkload};
+//! use flashdb_rs::storage::{FileStrategy, StdStorage};
+//!
+//! let storage = StdStorage::new("/tmp/bench", "bench", 4096, 64 * 1024, FileStrategy::Multi)?;
+//! let report = run_kvdb(storage, KvWorkload::Churn, &BenchConfig::default())?;
+//! println!("{}", report);
+//! # Ok::<(), flashdb_rs::Error>(())
+//! ```
+
+use core::fmt;
+use std::time::{Duration, Instant};
+
+use embedded_storage::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+
+use crate::Error;
+#[cfg(feature = "kvdb")]
+use crate::KVDB;
+#[cfg(feature = "tsdb")]
+use crate::TSDB;
+
+/// KVDB 测试负载
+#[cfg(feature = "kvdb")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KvWorkload {
+    /// 顺序写入 `ops` 个不重复的键
+    Fill,
+    /// 在 `key_count` 个键上反复覆盖写入
+    Overwrite,
+    /// 交替写入与删除，持续触发垃圾回收
+    Churn,
+}
+
+/// TSDB 测试负载
+#[cfg(feature = "tsdb")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsWorkload {
+    /// 顺序追加 `ops` 条日志
+    Append,
+    /// 先追加 `ops` 条日志，再执行 `ops` 次随机时间范围查询
+    TimeRangeQuery,
+}
+
+/// 测试参数
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    /// 计时的操作次数
+    pub ops: usize,
+    /// 每次写入的数据长度
+    pub value_size: usize,
+    /// `Overwrite` / `Churn` 负载使用的键数量
+    pub key_count: usize,
+    /// TSDB 单条日志最大长度
+    pub entry_max: usize,
+    /// `TimeRangeQuery` 每次查询覆盖的时间跨度
+    pub query_span: i64,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            ops: 1000,
+            value_size: 64,
+            key_count: 16,
+            entry_max: 256,
+            query_span: 100,
+        }
+    }
+}
+
+/// 测试结果
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    /// 负载名称
+    pub workload: &'static str,
+    /// 完成的操作次数
+    pub ops: usize,
+    /// 用户数据字节数
+    pub payload_bytes: u64,
+    /// 总耗时
+    pub elapsed: Duration,
+    /// 单次操作最小延迟
+    pub min_latency: Duration,
+    /// 单次操作最大延迟
+    pub max_latency: Duration,
+    /// 擦除的扇区数
+    pub erases: u64,
+    /// 实际写入 Flash 的字节数
+    pub bytes_programmed: u64,
+}
+
+impl BenchReport {
+    /// 每秒操作数
+    pub fn ops_per_sec(&self) -> f64 {
+        self.ops as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// 平均延迟
+    pub fn avg_latency(&self) -> Duration {
+        if self.ops == 0 {
+            Duration::ZERO
+        } else {
+            self.elapsed / self.ops as u32
+        }
+    }
+
+    /// 写放大系数：实际写入字节数 / 用户数据字节数
+    pub fn write_amplification(&self) -> f64 {
+        if self.payload_bytes == 0 {
+            0.0
+        } else {
+            self.bytes_programmed as f64 / self.payload_bytes as f64
+        }
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} ops in {:?} ({:.0} ops/s, avg {:?}, min {:?}, max {:?}), {} erases, {} bytes programmed (WA {:.2})",
+            self.workload,
+            self.ops,
+            self.elapsed,
+            self.ops_per_sec(),
+            self.avg_latency(),
+            self.min_latency,
+            self.max_latency,
+            self.erases,
+            self.bytes_programmed,
+            self.write_amplification(),
+        )
+    }
+}
+
+/// 在给定存储上运行一个 KVDB 负载。
+///
+/// 存储会被初始化（必要时格式化）为一个新的 KVDB。
+#[cfg(feature = "kvdb")]
+pub fn run_kvdb<S: NorFlash>(
+    storage: S,
+    workload: KvWorkload,
+    config: &BenchConfig,
+) -> Result<BenchReport, Error> {
+    let mut db = Box::new(KVDB::new(Instrumented::new(storage)));
+    db.set_name("bench")?;
+    db.init(None)?;
+
+    let value = vec![0x5Au8; config.value_size];
+    let key_count = config.key_count.max(1);
+    let mut timer = Timer::new();
+
+    for i in 0..config.ops {
+        match workload {
+            KvWorkload::Fill => {
+                let key = format!("k{}", i);
+                timer.measure(|| db.set(&key, &value))?;
+            }
+            KvWorkload::Overwrite => {
+                let key = format!("k{}", i % key_count);
+                timer.measure(|| db.set(&key, &value))?;
+            }
+            KvWorkload::Churn => {
+                // 每个键先写入、随后删除
+                let key = format!("k{}", (i / 2) % key_count);
+                if i % 2 == 0 {
+                    timer.measure(|| db.set(&key, &value))?;
+                } else {
+                    timer.measure(|| db.delete(&key))?;
+                }
+            }
+        }
+    }
+
+    let payload = match workload {
+        KvWorkload::Churn => (config.ops as u64).div_ceil(2) * config.value_size as u64,
+        _ => config.ops as u64 * config.value_size as u64,
+    };
+    let name = match workload {
+        KvWorkload::Fill => "kvdb_fill",
+        KvWorkload::Overwrite => "kvdb_overwrite",
+        KvWorkload::Churn => "kvdb_churn",
+    };
+    Ok(timer.finish(name, payload, db.storage()))
+}
+
+/// 在给定存储上运行一个 TSDB 负载。
+///
+/// 存储会被初始化（必要时格式化）为一个新的 TSDB，并在开始前清空。
+#[cfg(feature = "tsdb")]
+pub fn run_tsdb<S: NorFlash>(
+    storage: S,
+    workload: TsWorkload,
+    config: &BenchConfig,
+) -> Result<BenchReport, Error> {
+    let mut db = Box::new(TSDB::new(Instrumented::new(storage)));
+    db.set_name("bench")?;
+    db.init(config.entry_max)?;
+    db.reset()?;
+
+    let value = vec![0x5Au8; config.value_size];
+    let mut timer = Timer::new();
+
+    match workload {
+        TsWorkload::Append => {
+            for i in 0..config.ops {
+                timer.measure(|| db.append_with_timestamp(i as i64 + 1, &value))?;
+            }
+            let payload = config.ops as u64 * config.value_size as u64;
+            Ok(timer.finish("tsdb_append", payload, db.storage()))
+        }
+        TsWorkload::TimeRangeQuery => {
+            for i in 0..config.ops {
+                db.append_with_timestamp(i as i64 + 1, &value)?;
+            }
+            let last = db.last_time().max(1);
+            // 简单的线性同余序列，避免引入随机数依赖
+            let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+            for _ in 0..config.ops {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                let from = (seed >> 33) as i64 % last + 1;
+                let to = from + config.query_span;
+                timer.measure(|| {
+                    let mut hits = 0usize;
+                    db.tsdb_iter_by_time(from, to, |_, _| {
+                        hits += 1;
+                        true
+                    });
+                    Ok::<_, Error>(hits)
+                })?;
+            }
+            Ok(timer.finish("tsdb_time_range_query", 0, db.storage()))
+        }
+    }
+}
+
+/// 记录每次操作延迟的计时器
+struct Timer {
+    ops: usize,
+    elapsed: Duration,
+    min: Duration,
+    max: Duration,
+}
+
+impl Timer {
+    fn new() -> Self {
+        Self {
+            ops: 0,
+            elapsed: Duration::ZERO,
+            min: Duration::MAX,
+            max: Duration::ZERO,
+        }
+    }
+
+    fn measure<T>(&mut self, op: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+        let start = Instant::now();
+        let result = op()?;
+        let latency = start.elapsed();
+        self.ops += 1;
+        self.elapsed += latency;
+        self.min = self.min.min(latency);
+        self.max = self.max.max(latency);
+        Ok(result)
+    }
+
+    fn finish<S>(self, workload: &'static str, payload_bytes: u64, storage: &Instrumented<S>) -> BenchReport {
+        BenchReport {
+            workload,
+            ops: self.ops,
+            payload_bytes,
+            elapsed: self.elapsed,
+            min_latency: if self.ops == 0 { Duration::ZERO } else { self.min },
+            max_latency: self.max,
+            erases: storage.erases,
+            bytes_programmed: storage.bytes_programmed,
+        }
+    }
+}
+
+/// 统计擦除次数与写入字节数的存储包装器
+struct Instrumented<S> {
+    inner: S,
+    erases: u64,
+    bytes_programmed: u64,
+}
+
+impl<S> Instrumented<S> {
+    fn new(inner: S) -> Self {
+        Self {
+            inner,
+            erases: 0,
+            bytes_programmed: 0,
+        }
+    }
+}
+
+impl<S: ErrorType> ErrorType for Instrumented<S> {
+    type Error = S::Error;
+}
+
+impl<S: ReadNorFlash> ReadNorFlash for Instrumented<S> {
+    const READ_SIZE: usize = S::READ_SIZE;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.inner.read(offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+}
+
+impl<S: NorFlash> NorFlash for Instrumented<S> {
+    const WRITE_SIZE: usize = S::WRITE_SIZE;
+    const ERASE_SIZE: usize = S::ERASE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.inner.erase(from, to)?;
+        self.erases += ((to - from) as usize).div_ceil(S::ERASE_SIZE) as u64;
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.inner.write(offset, bytes)?;
+        self.bytes_programmed += bytes.len() as u64;
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+//! 合并顺序写入的缓冲存储。
+
+use embedded_storage::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+
+/// 带有写入缓冲、需要显式写入的存储。
+///
+/// 通过 `KVDB::set_flush_on_sync()` / `TSDB::set_flush_on_sync()` 启用后，
+/// C 库要求同步写入 (例如提交状态表) 时会调用 `flush()`。
+pub trait FlushStorage: NorFlash {
+    /// 将缓冲的内容全部写入底层 Flash。
+    fn flush(&mut self) -> Result<(), Self::Error>;
+}
+
+/// 将连续的小块写入合并为整页写入的存储包装器。
+///
+/// 数据库的一次提交通常由多个相邻的小块写入组成 (头部、键名、值、状态)，
+/// 逐个写入在 NAND 类介质上会产生大量编程周期，在 `StdStorage` 上则意味着大量系统调用。
+/// 此包装器将紧接着上一次写入末尾的写入缓存在 `N` 字节的页缓冲区中，
+/// 写满一页、跨越页边界、写入不连续的地址、擦除或调用 `flush()` 时一次性写入。
+/// 读取会合并缓冲区中尚未写入的内容。
+///
+/// **注意**: 缓冲的内容在 `flush()` 之前不会落盘，建议配合 `set_flush_on_sync(true)` 使用，
+/// 使 C 库的每次同步写入都会写入缓冲区。
+///
+/// # 示例
+///
+/// ```
+/// # use flashdb_rs::buffered::BufferedStorage;
+/// # use flashdb_rs::storage::{FileStrategy, StdStorage};
+/// # use flashdb_rs::KVDB;
+/// # let dir = tempfile::tempdir()?;
+/// let storage = StdStorage::new(dir.path(), "buffered_doc", 4096, 4 * 4096, FileStrategy::Multi)?;
+/// let mut db = Box::new(KVDB::new(BufferedStorage::<_, 256>::new(storage)));
+/// db.set_name("buffered_doc")?;
+/// db.set_flush_on_sync(true);
+/// db.init(None)?;
+/// db.set("key", b"value")?;
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct BufferedStorage<S: NorFlash, const N: usize = 256> {
+    inner: S,
+    /// 缓冲区对应的起始地址
+    start: u32,
+    /// 缓冲区中的字节数
+    len: usize,
+    buf: [u8; N],
+}
+
+impl<S: NorFlash, const N: usize> BufferedStorage<S, N> {
+    /// 包装底层 Flash，`N` 为页大小，应为 `S::WRITE_SIZE` 的整数倍。
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            start: 0,
+            len: 0,
+            buf: [0xFF; N],
+        }
+    }
+
+    /// 获取底层 Flash 的引用。
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// 写入所有缓冲的内容并取回底层 Flash。
+    pub fn into_inner(mut self) -> Result<S, S::Error> {
+        FlushStorage::flush(&mut self)?;
+        Ok(self.inner)
+    }
+
+    /// 缓冲区中尚未写入的字节数。
+    pub fn pending(&self) -> usize {
+        self.len
+    }
+
+    /// 缓冲区末尾对应的地址
+    fn end(&self) -> u32 {
+        self.start + self.len as u32
+    }
+}
+
+impl<S: NorFlash, const N: usize> FlushStorage for BufferedStorage<S, N> {
+    fn flush(&mut self) -> Result<(), S::Error> {
+        if self.len > 0 {
+            let len = core::mem::take(&mut self.len);
+            self.inner.write(self.start, &self.buf[..len])?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: NorFlash, const N: usize> ErrorType for BufferedStorage<S, N> {
+    type Error = S::Error;
+}
+
+impl<S: NorFlash, const N: usize> ReadNorFlash for BufferedStorage<S, N> {
+    const READ_SIZE: usize = S::READ_SIZE;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.inner.read(offset, bytes)?;
+        // 合并尚未写入的内容
+        let start = offset.max(self.start);
+        let end = (offset + bytes.len() as u32).min(self.end());
+        if start < end {
+            bytes[(start - offset) as usize..(end - offset) as usize]
+                .copy_from_slice(&self.buf[(start - self.start) as usize..(end - self.start) as usize]);
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+}
+
+impl<S: NorFlash, const N: usize> NorFlash for BufferedStorage<S, N> {
+    const WRITE_SIZE: usize = S::WRITE_SIZE;
+    const ERASE_SIZE: usize = S::ERASE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        if self.len > 0 {
+            if self.start >= from && self.end() <= to {
+                // 被擦除的内容不需要再写入
+                self.len = 0;
+            } else {
+                FlushStorage::flush(self)?;
+            }
+        }
+        self.inner.erase(from, to)
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let (mut pos, mut data) = (offset, bytes);
+        if self.len > 0 && pos != self.end() {
+            FlushStorage::flush(self)?;
+        }
+        while !data.is_empty() {
+            if self.len == 0 {
+                // 超过一页的对齐写入直接写入
+                if data.len() >= N && pos as usize % N == 0 {
+                    let len = data.len() - data.len() % N;
+                    self.inner.write(pos, &data[..len])?;
+                    data = &data[len..];
+                    pos += len as u32;
+                    continue;
+                }
+                self.start = pos;
+            }
+            // 缓冲区不跨越页边界
+            let page_end = (self.start as usize / N + 1) * N;
+            let room = page_end - self.end() as usize;
+            let len = room.min(data.len());
+            self.buf[self.len..self.len + len].copy_from_slice(&data[..len]);
+            self.len += len;
+            data = &data[len..];
+            pos += len as u32;
+            if self.end() as usize == page_end {
+                FlushStorage::flush(self)?;
+            }
+        }
+        Ok(())
+    }
+}
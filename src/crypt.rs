@@ -0,0 +1,160 @@
+//! 存储层的静态加密 (encryption at rest)。
+//!
+//! 与只加密值的 `KVDB::encrypted()` 不同，[`EncryptedStorage`] 位于数据库之下，
+//! 扇区中的所有内容 (包括键名、时间戳与状态表) 都以密文保存。
+
+use embedded_storage::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+
+/// 支持的最大写入单元 (512 bit)
+const MAX_UNIT: usize = 64;
+
+/// 为 [`EncryptedStorage`] 提供按写入单元加解密的算法。
+///
+/// 本库不内置密码学实现，由用户基于 `aes` + `ctr` / `xts-mode` 或芯片的硬件加密引擎实现此 trait。
+/// 扇区序号与单元在扇区内的偏移作为 tweak (或 CTR 模式的计数器) 传入，
+/// 因此相同的明文在不同位置产生不同的密文。
+///
+/// # 示例
+///
+/// ```ignore
+/// use aes::cipher::{KeyIvInit, StreamCipher};
+///
+/// struct AesCtr([u8; 16]);
+///
+/// impl flashdb_rs::crypt::SectorCipher for AesCtr {
+///     fn encrypt(&self, sector: u32, offset: u32, unit: &mut [u8]) {
+///         let mut iv = [0u8; 16];
+///         iv[..4].copy_from_slice(&sector.to_be_bytes());
+///         iv[12..].copy_from_slice(&(offset / 16).to_be_bytes());
+///         ctr::Ctr128BE::<aes::Aes128>::new(&self.0.into(), &iv.into()).apply_keystream(unit);
+///     }
+///
+///     fn decrypt(&self, sector: u32, offset: u32, unit: &mut [u8]) {
+///         self.encrypt(sector, offset, unit)
+///     }
+/// }
+/// ```
+pub trait SectorCipher {
+    /// 就地加密位于 `sector` 扇区内 `offset` 处的一个写入单元。
+    fn encrypt(&self, sector: u32, offset: u32, unit: &mut [u8]);
+
+    /// 就地解密位于 `sector` 扇区内 `offset` 处的一个写入单元。
+    fn decrypt(&self, sector: u32, offset: u32, unit: &mut [u8]);
+}
+
+/// 透明加密扇区内容的存储包装器。
+///
+/// 数据按底层 Flash 的写入单元 (`S::WRITE_SIZE`) 整块加密，未写满的单元以明文 `0xFF` 补齐后加密。
+/// 读取时处于擦除状态 (全部为 `0xFF`) 的单元按原样返回，因此数据库仍然可以识别空白区域。
+///
+/// **注意**:
+/// - 每个写入单元在擦除后只能写入一次，C 库需要以与 `S::WRITE_SIZE` 相同的 `FDB_WRITE_GRAN`
+///   编译 (`write-gran-64` / `write-gran-128` 特性)，此时状态表的每次变化都写入独立的单元。
+///   C 库的 TSDB 不支持这两种粒度，因此只能用于 KVDB。
+/// - 写入单元至少为 8 字节，密文恰好全部为 `0xFF` 而被误认为擦除状态的概率可以忽略。
+///
+/// # 示例
+///
+/// ```ignore
+/// use flashdb_rs::crypt::EncryptedStorage;
+///
+/// let storage = EncryptedStorage::new(internal_flash, AesCtr(device_key));
+/// let mut db = Box::new(KVDB::new(storage));
+/// db.set_name("secure")?;
+/// db.init(None)?;
+/// ```
+pub struct EncryptedStorage<S: NorFlash, C: SectorCipher> {
+    inner: S,
+    cipher: C,
+}
+
+impl<S: NorFlash, C: SectorCipher> EncryptedStorage<S, C> {
+    /// 编译期检查底层 Flash 的写入单元
+    const UNIT_CHECK: () = assert!(
+        S::WRITE_SIZE >= 8 && S::WRITE_SIZE <= MAX_UNIT && S::WRITE_SIZE % S::READ_SIZE == 0,
+        "EncryptedStorage requires a write unit of 8 to 64 bytes"
+    );
+
+    /// 使用 `cipher` 包装底层 Flash。
+    pub fn new(inner: S, cipher: C) -> Self {
+        let () = Self::UNIT_CHECK;
+        Self { inner, cipher }
+    }
+
+    /// 获取底层 Flash 的引用。
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// 取回底层 Flash。
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// 内部方法：单元所在的扇区与扇区内的偏移
+    fn locate(addr: u32) -> (u32, u32) {
+        (addr / S::ERASE_SIZE as u32, addr % S::ERASE_SIZE as u32)
+    }
+}
+
+impl<S: NorFlash, C: SectorCipher> ErrorType for EncryptedStorage<S, C> {
+    type Error = S::Error;
+}
+
+impl<S: NorFlash, C: SectorCipher> ReadNorFlash for EncryptedStorage<S, C> {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let unit = S::WRITE_SIZE as u32;
+        let (mut pos, mut done) = (offset, 0);
+        while done < bytes.len() {
+            let start = pos - pos % unit;
+            let within = (pos - start) as usize;
+            let mut block = [0xFF; MAX_UNIT];
+            let block = &mut block[..S::WRITE_SIZE];
+            self.inner.read(start, block)?;
+            // 擦除状态的单元没有密文
+            if block.iter().any(|&b| b != 0xFF) {
+                let (sector, offset) = Self::locate(start);
+                self.cipher.decrypt(sector, offset, block);
+            }
+            let len = (S::WRITE_SIZE - within).min(bytes.len() - done);
+            bytes[done..done + len].copy_from_slice(&block[within..within + len]);
+            done += len;
+            pos += len as u32;
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+}
+
+impl<S: NorFlash, C: SectorCipher> NorFlash for EncryptedStorage<S, C> {
+    const WRITE_SIZE: usize = S::WRITE_SIZE;
+    const ERASE_SIZE: usize = S::ERASE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.inner.erase(from, to)
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let unit = S::WRITE_SIZE as u32;
+        let (mut pos, mut data) = (offset, bytes);
+        while !data.is_empty() {
+            let start = pos - pos % unit;
+            let within = (pos - start) as usize;
+            let len = (S::WRITE_SIZE - within).min(data.len());
+            let mut block = [0xFF; MAX_UNIT];
+            let block = &mut block[..S::WRITE_SIZE];
+            block[within..within + len].copy_from_slice(&data[..len]);
+            let (sector, offset) = Self::locate(start);
+            self.cipher.encrypt(sector, offset, block);
+            self.inner.write(start, block)?;
+            data = &data[len..];
+            pos += len as u32;
+        }
+        Ok(())
+    }
+}
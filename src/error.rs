@@ -1,3 +1,6 @@
+use core::fmt;
+
+use embedded_storage::nor_flash::{NorFlashError, NorFlashErrorKind};
 use thiserror::Error;
 
 use crate::{
@@ -8,7 +11,218 @@ use crate::{
 
 type Result<T> = core::result::Result<T, Error>;
 
+/// 错误的类别，便于按失败的原因统一处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Flash 或文件读写失败、超时
+    Io,
+    /// 存储空间已满
+    Capacity,
+    /// 数据损坏或无法通过校验
+    Corruption,
+    /// 参数、键名或时间戳不合法
+    InvalidInput,
+    /// 键或分区不存在
+    NotFound,
+    /// 键已存在
+    AlreadyExists,
+    /// 数据的格式或版本不受支持
+    Unsupported,
+    /// 数据库只读或写入被拒绝
+    Denied,
+    /// 数据库未初始化、初始化失败或区域被占用
+    State,
+    /// 序列化或反序列化失败
+    Encoding,
+    /// 其他错误
+    Other,
+}
+
+/// 失败的存储后端操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageOp {
+    Read,
+    Write,
+    Erase,
+}
+
+/// 存储后端返回的原始错误。
+///
+/// C 回调只能向 C 库返回失败，原始错误会暂存在数据库中，
+/// 数据库操作因此失败时以 `Error::Storage` 代替 `ReadError` / `WriteError` / `EraseError` 返回。
+#[derive(Debug)]
+pub struct StorageError {
+    op: StorageOp,
+    addr: u32,
+    kind: NorFlashErrorKind,
+    #[cfg(feature = "alloc")]
+    detail: alloc::string::String,
+}
+
+impl StorageError {
+    /// 内部方法：记录存储后端在 `addr` 处执行 `op` 时返回的错误
+    pub(crate) fn new<E: NorFlashError>(op: StorageOp, addr: u32, err: &E) -> Self {
+        Self {
+            op,
+            addr,
+            kind: err.kind(),
+            #[cfg(feature = "alloc")]
+            detail: alloc::format!("{err:?}"),
+        }
+    }
+
+    /// 失败的操作
+    pub fn op(&self) -> StorageOp {
+        self.op
+    }
+
+    /// 操作的起始地址 (相对于存储后端)
+    pub fn addr(&self) -> u32 {
+        self.addr
+    }
+
+    /// 存储后端报告的错误类别
+    pub fn kind(&self) -> NorFlashErrorKind {
+        self.kind
+    }
+
+    /// 存储后端错误的 `Debug` 输出，例如 `StdStorage` 返回的 `IO(Os { code: 28, .. })`
+    #[cfg(feature = "alloc")]
+    pub fn detail(&self) -> &str {
+        &self.detail
+    }
+
+    /// 内部方法：是否对应 C 库返回的 `error`
+    pub(crate) fn matches(&self, error: &Error) -> bool {
+        matches!(
+            (self.op, error),
+            (StorageOp::Read, Error::ReadError)
+                | (StorageOp::Write, Error::WriteError)
+                | (StorageOp::Erase, Error::EraseError)
+        )
+    }
+}
+
+/// 出错的数据库操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Operation {
+    /// `get()`、`get_entry()`、`contains_key()` 与 `get_reader()`
+    Get,
+    /// `get_checked()` 与 `get_redundant()`
+    GetChecked,
+    Set,
+    SetNx,
+    Delete,
+    Append,
+    /// `set_status()` 与 `set_status_batch()`
+    SetStatus,
+    /// 读取日志的值，或通过 `KVReader` / `TSDBReader` 读取
+    Read,
+    /// 遍历数据库的迭代器
+    Iterate,
+    /// `compact()`、`purge_before()` 与 `vacuum()`
+    Gc,
+    Init,
+}
+
+/// 读写失败时的上下文：出错的操作、键或时间戳，以及存储后端失败的地址。
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorContext {
+    op: Operation,
+    key: Option<alloc::string::String>,
+    timestamp: Option<i64>,
+    addr: Option<u32>,
+}
+
+#[cfg(feature = "alloc")]
+impl ErrorContext {
+    /// 内部方法：没有键与时间戳的上下文
+    pub(crate) fn new(op: Operation) -> Self {
+        Self { op, key: None, timestamp: None, addr: None }
+    }
+
+    /// 内部方法：访问键 `key` 时的上下文
+    #[cfg(feature = "kvdb")]
+    pub(crate) fn for_key(op: Operation, key: &str) -> Self {
+        Self { key: Some(key.into()), ..Self::new(op) }
+    }
+
+    /// 内部方法：访问时间戳 `timestamp` 时的上下文
+    #[cfg(feature = "tsdb")]
+    pub(crate) fn for_timestamp(op: Operation, timestamp: i64) -> Self {
+        Self { timestamp: Some(timestamp), ..Self::new(op) }
+    }
+
+    /// 出错的操作
+    pub fn op(&self) -> Operation {
+        self.op
+    }
+
+    /// 出错的键
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    /// 出错的日志时间戳
+    pub fn timestamp(&self) -> Option<i64> {
+        self.timestamp
+    }
+
+    /// 存储后端失败的地址
+    pub fn addr(&self) -> Option<u32> {
+        self.addr
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {
+            Operation::Get => "get",
+            Operation::GetChecked => "get_checked",
+            Operation::Set => "set",
+            Operation::SetNx => "set_nx",
+            Operation::Delete => "delete",
+            Operation::Append => "append",
+            Operation::SetStatus => "set_status",
+            Operation::Read => "read",
+            Operation::Iterate => "iterate",
+            Operation::Gc => "gc",
+            Operation::Init => "init",
+        };
+        f.write_str(op)?;
+        if let Some(key) = &self.key {
+            write!(f, " {key:?}")?;
+        }
+        if let Some(timestamp) = self.timestamp {
+            write!(f, " @{timestamp}")?;
+        }
+        if let Some(addr) = self.addr {
+            write!(f, " at {addr:#010x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {
+            StorageOp::Read => "read",
+            StorageOp::Write => "write",
+            StorageOp::Erase => "erase",
+        };
+        write!(f, "Storage {op} failed at {:#010x}", self.addr)?;
+        #[cfg(feature = "alloc")]
+        write!(f, ": {}", self.detail)?;
+        Ok(())
+    }
+}
+
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     #[error("Ok")]
     Ok,
@@ -34,14 +248,129 @@ pub enum Error {
     InvalidArgument,
     #[error("Key not found")]
     KeyNotFound,
+    #[error("Value length mismatch")]
+    ValueLengthMismatch,
+    #[error("Data corrupted")]
+    Corrupted,
+    #[error("Unsupported schema version")]
+    UnsupportedVersion,
+    #[error("Flash operation timed out")]
+    Timeout,
+    #[error("Incompatible on-flash format version {found} (supported: {supported})")]
+    IncompatibleFormat { found: u8, supported: u8 },
+    #[error("Decryption or authentication failed")]
+    DecryptError,
+    #[error("Write rejected by write hook")]
+    WriteProtected,
+    #[error("Timestamp out of range for fdb_time_t (32-bit unless the `time64` feature is enabled)")]
+    TimestampOutOfRange,
+    #[error("Database is read-only")]
+    ReadOnly,
+    #[error("Database is not initialized")]
+    NotInitialized,
+    #[error("Flash region is already in use by another database")]
+    Busy,
+    #[error("{0}")]
+    Storage(StorageError),
+    /// 读写失败时附加了出错的操作、键或时间戳，原始错误由 `root()` 获取
+    #[cfg(feature = "alloc")]
+    #[error("{context}: {source}")]
+    Context {
+        context: ErrorContext,
+        source: alloc::boxed::Box<Error>,
+    },
     // #[error("Locking error: {0}")]
     // LockingError(String),
     #[cfg(feature = "std")]
     #[error("IO error: {0}")]
     IO(std::io::Error),
+    #[cfg(feature = "json")]
+    #[error("JSON error: {0}")]
+    Json(serde_json::Error),
+    #[cfg(feature = "serde")]
+    #[error("Serialization error: {0}")]
+    Serde(postcard::Error),
 }
 
 impl Error {
+    /// 去掉上下文后的原始错误，没有上下文时返回自身。
+    pub fn root(&self) -> &Error {
+        match self {
+            #[cfg(feature = "alloc")]
+            Error::Context { source, .. } => source.root(),
+            err => err,
+        }
+    }
+
+    /// 出错的操作、键或时间戳，以及存储后端失败的地址。
+    #[cfg(feature = "alloc")]
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Error::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// 错误的类别，附加了上下文的错误返回原始错误的类别。
+    pub fn kind(&self) -> ErrorKind {
+        match self.root() {
+            Error::ReadError | Error::WriteError | Error::EraseError | Error::Storage(_) | Error::Timeout => ErrorKind::Io,
+            #[cfg(feature = "std")]
+            Error::IO(_) => ErrorKind::Io,
+            Error::SavedFull => ErrorKind::Capacity,
+            Error::Corrupted | Error::DecryptError => ErrorKind::Corruption,
+            Error::InvalidArgument
+            | Error::KvNameError
+            | Error::ValueLengthMismatch
+            | Error::TimestampOutOfRange => ErrorKind::InvalidInput,
+            Error::KeyNotFound | Error::PartNotFound => ErrorKind::NotFound,
+            Error::KvNameExist => ErrorKind::AlreadyExists,
+            Error::UnsupportedVersion | Error::IncompatibleFormat { .. } => ErrorKind::Unsupported,
+            Error::ReadOnly | Error::WriteProtected => ErrorKind::Denied,
+            Error::InitFailed | Error::NotInitialized | Error::Busy => ErrorKind::State,
+            #[cfg(feature = "json")]
+            Error::Json(_) => ErrorKind::Encoding,
+            #[cfg(feature = "serde")]
+            Error::Serde(_) => ErrorKind::Encoding,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// 对应的 C 库错误码 `fdb_err_t`，错误不是由 C 库返回时为 `None`。
+    ///
+    /// 存储后端的错误返回 C 库因此报告的读、写或擦除错误码。
+    pub fn code(&self) -> Option<u32> {
+        let code = match self.root() {
+            Error::Ok => fdb_err_t_FDB_NO_ERR,
+            Error::EraseError => fdb_err_t_FDB_ERASE_ERR,
+            Error::ReadError => fdb_err_t_FDB_READ_ERR,
+            Error::WriteError => fdb_err_t_FDB_WRITE_ERR,
+            Error::PartNotFound => fdb_err_t_FDB_PART_NOT_FOUND,
+            Error::KvNameError => fdb_err_t_FDB_KV_NAME_ERR,
+            Error::KvNameExist => fdb_err_t_FDB_KV_NAME_EXIST,
+            Error::SavedFull => fdb_err_t_FDB_SAVED_FULL,
+            Error::InitFailed => fdb_err_t_FDB_INIT_FAILED,
+            Error::Storage(err) => match err.op {
+                StorageOp::Read => fdb_err_t_FDB_READ_ERR,
+                StorageOp::Write => fdb_err_t_FDB_WRITE_ERR,
+                StorageOp::Erase => fdb_err_t_FDB_ERASE_ERR,
+            },
+            _ => return None,
+        };
+        Some(code as u32)
+    }
+
+    /// 内部方法：为读写失败附加上下文，其他错误原样返回
+    #[cfg(feature = "alloc")]
+    pub(crate) fn with_context(self, mut context: ErrorContext) -> Error {
+        match self {
+            Error::ReadError | Error::WriteError | Error::EraseError | Error::SavedFull => {}
+            Error::Storage(ref err) => context.addr = Some(err.addr),
+            err => return err,
+        }
+        Error::Context { context, source: alloc::boxed::Box::new(self) }
+    }
+
     pub fn convert(error: fdb_err_t) -> Result<()> {
         if error == fdb_err_t_FDB_NO_ERR {
             Ok(())
@@ -84,6 +413,20 @@ impl From<std::io::Error> for Error {
     }
 }
 
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<postcard::Error> for Error {
+    fn from(err: postcard::Error) -> Self {
+        Self::Serde(err)
+    }
+}
+
 impl embedded_io::Error for Error {
     fn kind(&self) -> embedded_io::ErrorKind {
         match self {
@@ -97,10 +440,31 @@ impl embedded_io::Error for Error {
             Error::KvNameExist => embedded_io::ErrorKind::AlreadyExists,
             Error::SavedFull => embedded_io::ErrorKind::OutOfMemory,
             Error::InvalidArgument => embedded_io::ErrorKind::InvalidInput,
+            Error::ValueLengthMismatch => embedded_io::ErrorKind::InvalidData,
+            Error::Corrupted => embedded_io::ErrorKind::InvalidData,
+            Error::UnsupportedVersion => embedded_io::ErrorKind::Unsupported,
+            Error::Timeout => embedded_io::ErrorKind::TimedOut,
+            Error::IncompatibleFormat { .. } => embedded_io::ErrorKind::Unsupported,
+            Error::DecryptError => embedded_io::ErrorKind::PermissionDenied,
+            Error::WriteProtected => embedded_io::ErrorKind::PermissionDenied,
+            Error::TimestampOutOfRange => embedded_io::ErrorKind::InvalidInput,
+            Error::ReadOnly => embedded_io::ErrorKind::PermissionDenied,
+            Error::NotInitialized => embedded_io::ErrorKind::Other,
+            Error::Busy => embedded_io::ErrorKind::AddrInUse,
+            #[cfg(feature = "alloc")]
+            Error::Context { source, .. } => embedded_io::Error::kind(&**source),
+            Error::Storage(err) => match err.kind {
+                NorFlashErrorKind::NotAligned | NorFlashErrorKind::OutOfBounds => embedded_io::ErrorKind::InvalidInput,
+                _ => embedded_io::ErrorKind::Other,
+            },
             Error::UnknownError => embedded_io::ErrorKind::Other,
             Error::Ok => embedded_io::ErrorKind::Other, // 这是一个特殊情况，通常不应作为错误返回
             #[cfg(feature = "std")]
             Error::IO(err) => err.kind().into(),
+            #[cfg(feature = "json")]
+            Error::Json(_) => embedded_io::ErrorKind::InvalidData,
+            #[cfg(feature = "serde")]
+            Error::Serde(_) => embedded_io::ErrorKind::InvalidData,
         }
     }
 }
@@ -110,6 +474,10 @@ impl embedded_storage::nor_flash::NorFlashError for Error {
         match self {
             // Map specific errors if they correspond to alignment or out-of-bounds issues
             Error::InvalidArgument => embedded_storage::nor_flash::NorFlashErrorKind::NotAligned,
+            // 保留存储后端报告的类别
+            Error::Storage(err) => err.kind,
+            #[cfg(feature = "alloc")]
+            Error::Context { source, .. } => NorFlashError::kind(&**source),
             // Most other errors from this library can be categorized as 'Other'
             _ => embedded_storage::nor_flash::NorFlashErrorKind::Other,
         }
@@ -0,0 +1,137 @@
+//! ESP-IDF 分区存储后端。
+//!
+//! 直接调用 ESP-IDF 的 `esp_partition` C 接口，不依赖 `esp-idf-svc`，
+//! 只在启用 `esp-idf` 特性并以 `*-espidf` 目标编译时可用。
+//!
+//! 在分区表中为数据库添加一个数据分区：
+//!
+//! ```text
+//! # Name,   Type, SubType, Offset,  Size
+//! fdb_kv,   data, 0x40,    ,        64K
+//! ```
+//!
+//! ```ignore
+//! use flashdb_rs::esp::EspPartition;
+//! use flashdb_rs::KVDB;
+//!
+//! let storage = EspPartition::find("fdb_kv")?;
+//! let mut db = Box::new(KVDB::new(storage));
+//! db.set_name("env")?;
+//! db.init(None)?;
+//! ```
+
+use core::ffi::{c_char, c_int, c_void, CStr};
+
+use embedded_storage::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+
+use crate::Error;
+
+type esp_err_t = c_int;
+
+const ESP_OK: esp_err_t = 0;
+/// `ESP_PARTITION_TYPE_DATA`
+const ESP_PARTITION_TYPE_DATA: c_int = 0x01;
+/// `ESP_PARTITION_SUBTYPE_ANY`
+const ESP_PARTITION_SUBTYPE_ANY: c_int = 0xff;
+
+/// `esp_partition_t` 的前缀，只通过 ESP-IDF 返回的指针访问。
+///
+/// 这些字段的布局自 ESP-IDF v4.0 起保持不变，之后的字段随版本变化，因此不在此声明。
+#[repr(C)]
+struct esp_partition_t {
+    flash_chip: *mut c_void,
+    type_: c_int,
+    subtype: c_int,
+    address: u32,
+    size: u32,
+}
+
+extern "C" {
+    fn esp_partition_find_first(type_: c_int, subtype: c_int, label: *const c_char) -> *const esp_partition_t;
+    fn esp_partition_read(partition: *const esp_partition_t, src_offset: usize, dst: *mut c_void, size: usize) -> esp_err_t;
+    fn esp_partition_write(partition: *const esp_partition_t, dst_offset: usize, src: *const c_void, size: usize) -> esp_err_t;
+    fn esp_partition_erase_range(partition: *const esp_partition_t, offset: usize, size: usize) -> esp_err_t;
+}
+
+/// 基于 ESP-IDF 数据分区的 `NorFlash` 实现。
+///
+/// 数据库的容量即分区的大小，分区大小应为 4096 字节扇区的整数倍。
+pub struct EspPartition {
+    partition: *const esp_partition_t,
+}
+
+// 分区描述符由 ESP-IDF 静态分配，读写接口可以在任意任务中调用
+unsafe impl Send for EspPartition {}
+
+impl EspPartition {
+    /// 按名称查找数据分区，未找到时返回 `Error::PartNotFound`。
+    pub fn find(label: &str) -> Result<Self, Error> {
+        // 分区名称最多 16 字节
+        let mut buf = [0u8; 17];
+        if label.len() >= buf.len() {
+            return Err(Error::InvalidArgument);
+        }
+        buf[..label.len()].copy_from_slice(label.as_bytes());
+        let label = CStr::from_bytes_until_nul(&buf).map_err(|_| Error::InvalidArgument)?;
+
+        let partition =
+            unsafe { esp_partition_find_first(ESP_PARTITION_TYPE_DATA, ESP_PARTITION_SUBTYPE_ANY, label.as_ptr()) };
+        if partition.is_null() {
+            return Err(Error::PartNotFound);
+        }
+        Ok(Self { partition })
+    }
+
+    /// 分区在 Flash 中的起始地址。
+    pub fn address(&self) -> u32 {
+        unsafe { (*self.partition).address }
+    }
+}
+
+impl ErrorType for EspPartition {
+    type Error = Error;
+}
+
+impl ReadNorFlash for EspPartition {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let result = unsafe {
+            esp_partition_read(self.partition, offset as usize, bytes.as_mut_ptr() as *mut c_void, bytes.len())
+        };
+        if result != ESP_OK {
+            return Err(Error::ReadError);
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        unsafe { (*self.partition).size as usize }
+    }
+}
+
+impl NorFlash for EspPartition {
+    const WRITE_SIZE: usize = 1;
+    const ERASE_SIZE: usize = 4096;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        if to < from {
+            return Err(Error::InvalidArgument);
+        }
+        let result = unsafe { esp_partition_erase_range(self.partition, from as usize, (to - from) as usize) };
+        if result != ESP_OK {
+            return Err(Error::EraseError);
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let result = unsafe {
+            esp_partition_write(self.partition, offset as usize, bytes.as_ptr() as *const c_void, bytes.len())
+        };
+        if result != ESP_OK {
+            return Err(Error::WriteError);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+//! 数据库内部事件通知。
+//!
+//! 垃圾回收、扇区回收、TSDB 翻转写入等内部事件原本只能通过 C 库的日志字符串观察到。
+//! 通过 `KVDB::set_event_handler()` / `TSDB::set_event_handler()` 注册回调，
+//! 或在 `std` 环境下通过 `event_channel()` 获取通道，即可以结构化的形式接收这些事件。
+
+use alloc::boxed::Box;
+
+use crate::FlashDispatch;
+#[cfg(feature = "tsdb")]
+use crate::{
+    fdb_sector_store_status_FDB_SECTOR_STORE_FULL, fdb_sector_store_status_FDB_SECTOR_STORE_USING,
+    fdb_time_t, fdb_tsl_status_FDB_TSL_WRITE,
+    utils::{get_status, status_table_size},
+    FDB_SECTOR_STORE_STATUS_NUM, FDB_TSL_STATUS_NUM,
+};
+
+/// 数据库内部事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// KVDB 开始垃圾回收
+    GcStarted,
+    /// 扇区中的数据已被回收，扇区被擦除后重新投入使用
+    SectorRetired {
+        /// 扇区起始地址
+        addr: u32,
+    },
+    /// 发现 CRC32 校验失败的 KV
+    CrcError {
+        /// KV 起始地址
+        addr: u32,
+    },
+    /// TSDB 翻转写入覆盖了最旧的扇区，该时间范围内的日志已丢失
+    Rollover {
+        /// 丢失日志的最早时间戳
+        lost_from: i64,
+        /// 丢失日志的最晚时间戳
+        lost_to: i64,
+    },
+}
+
+/// 产生事件的数据库类型
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Source {
+    #[cfg(feature = "kvdb")]
+    Kvdb,
+    #[cfg(feature = "tsdb")]
+    Tsdb,
+}
+
+/// 已注册的事件回调与其状态
+pub(crate) struct EventHook {
+    handler: Box<dyn FnMut(&Event) + Send>,
+    source: Source,
+    /// 当前是否处于可能触发垃圾回收或翻转写入的写操作中
+    armed: bool,
+    /// 本次写操作是否已经报告过 `GcStarted`
+    gc_reported: bool,
+}
+
+impl EventHook {
+    pub(crate) fn new(source: Source, handler: impl FnMut(&Event) + Send + 'static) -> Self {
+        Self {
+            handler: Box::new(handler),
+            source,
+            armed: false,
+            gc_reported: false,
+        }
+    }
+}
+
+/// 在 `std` 环境下创建一个事件通道，返回的发送端可直接作为事件回调。
+///
+/// # 示例
+///
+/// ```
+/// # use flashdb_rs::KVDB;
+/// # let dir = tempfile::tempdir()?;
+/// # let mut db = KVDB::new_file("events_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+/// let (handler, events) = flashdb_rs::events::event_channel();
+/// db.set_event_handler(handler);
+/// db.set("key", b"value")?;
+/// for event in events.try_iter() {
+///     println!("{event:?}");
+/// }
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+#[cfg(feature = "std")]
+pub fn event_channel() -> (impl FnMut(&Event) + Send + 'static, std::sync::mpsc::Receiver<Event>) {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    (
+        move |event: &Event| {
+            // 接收端被丢弃后事件直接丢弃
+            let _ = sender.send(*event);
+        },
+        receiver,
+    )
+}
+
+/// TSDB 扇区头，与 `fdb_tsdb.c` 中的 `sector_hdr_data` 保持一致
+#[cfg(feature = "tsdb")]
+#[repr(C)]
+struct TsdbSectorHdr {
+    status: [u8; status_table_size(FDB_SECTOR_STORE_STATUS_NUM as usize)],
+    magic: u32,
+    start_time: fdb_time_t,
+    end_info: [TsdbEndInfo; 2],
+    _reserved: u32,
+}
+
+#[cfg(feature = "tsdb")]
+#[repr(C)]
+struct TsdbEndInfo {
+    time: fdb_time_t,
+    _index: u32,
+    status: [u8; status_table_size(FDB_TSL_STATUS_NUM as usize)],
+}
+
+/// TSDB 扇区头 magic (`T`, `S`, `L`, `0`)
+#[cfg(feature = "tsdb")]
+const TSDB_SECTOR_MAGIC_WORD: u32 = 0x304C5354;
+
+impl FlashDispatch {
+    /// 开始一次可能触发垃圾回收或翻转写入的写操作
+    pub(crate) fn arm_events(&mut self) {
+        if let Some(hook) = self.events.as_mut() {
+            hook.armed = true;
+            hook.gc_reported = false;
+        }
+    }
+
+    /// 结束当前写操作
+    pub(crate) fn disarm_events(&mut self) {
+        if let Some(hook) = self.events.as_mut() {
+            hook.armed = false;
+        }
+    }
+
+    /// 发送一个事件
+    #[cfg(feature = "kvdb")]
+    pub(crate) fn emit(&mut self, event: Event) {
+        if let Some(hook) = self.events.as_mut() {
+            (hook.handler)(&event);
+        }
+    }
+
+    /// 在写操作中擦除扇区之前调用，根据扇区的原有内容产生事件
+    pub(crate) fn before_erase(&mut self, addr: u32) {
+        let hook = match self.events.as_mut() {
+            Some(hook) if hook.armed => hook,
+            _ => return,
+        };
+        match hook.source {
+            // KVDB 只会在垃圾回收时擦除扇区
+            #[cfg(feature = "kvdb")]
+            Source::Kvdb => {
+                if !hook.gc_reported {
+                    hook.gc_reported = true;
+                    (hook.handler)(&Event::GcStarted);
+                }
+            }
+            // TSDB 只会在翻转写入时擦除有数据的扇区
+            #[cfg(feature = "tsdb")]
+            Source::Tsdb => {
+                let mut buf = [0u8; core::mem::size_of::<TsdbSectorHdr>()];
+                if unsafe { (self.vtable.read)(self.instance, addr, buf.as_mut_ptr(), buf.len(), &mut None) } != 0 {
+                    return;
+                }
+                let hdr = unsafe { core::ptr::read_unaligned(buf.as_ptr() as *const TsdbSectorHdr) };
+                let store = get_status(&hdr.status, FDB_SECTOR_STORE_STATUS_NUM as usize) as u32;
+                let in_use = store == fdb_sector_store_status_FDB_SECTOR_STORE_USING
+                    || store == fdb_sector_store_status_FDB_SECTOR_STORE_FULL;
+                if hdr.magic != TSDB_SECTOR_MAGIC_WORD || !in_use {
+                    return;
+                }
+                // 与 `read_sector_info` 相同：优先使用第一个已写入的结束节点
+                let end = hdr.end_info.iter().find(|end| {
+                    get_status(&end.status, FDB_TSL_STATUS_NUM as usize) as u32 == fdb_tsl_status_FDB_TSL_WRITE
+                });
+                if let Some(end) = end {
+                    (hook.handler)(&Event::Rollover {
+                        lost_from: hdr.start_time as i64,
+                        lost_to: end.time as i64,
+                    });
+                }
+            }
+        }
+        (hook.handler)(&Event::SectorRetired { addr });
+    }
+}
@@ -0,0 +1,77 @@
+//! Flash 上的存储格式版本。
+//!
+//! FlashDB 没有单独的版本字段，扇区头 magic 的最后一个字节即为格式版本号：
+//! KVDB 为 `FDB0`，TSDB 为 `TSL0`。C 库遇到 magic 不匹配的扇区时会将其视为未格式化并重新格式化，
+//! 因此如果 OTA 升级后内置的 C 库改变了存储格式，旧数据会在 `init()` 时被静默擦除。
+//!
+//! `init()` 之前会先检查所有扇区头，发现其他版本的数据时返回 `Error::IncompatibleFormat`，
+//! 而不会交给 C 库处理。目前 FlashDB 只有一个格式版本，因此不存在可用的升级路径，
+//! 遇到该错误时应由应用决定是回退固件，还是备份数据后擦除存储区再重新初始化。
+
+use embedded_storage::nor_flash::NorFlash;
+
+#[cfg(feature = "kvdb")]
+use crate::kvdb::layout::SECTOR_MAGIC_OFFSET;
+#[cfg(feature = "tsdb")]
+use crate::{utils::status_table_size, FDB_SECTOR_STORE_STATUS_NUM};
+use crate::Error;
+
+/// 当前 C 库使用的格式版本
+pub const FORMAT_VERSION: u8 = 0;
+
+/// 某类数据库扇区头 magic 的位置与内容
+pub(crate) struct SectorMagic {
+    /// magic 在扇区头中的偏移
+    offset: usize,
+    /// magic 中除版本号以外的前三个字节
+    prefix: [u8; 3],
+}
+
+/// KVDB 扇区头 magic (`F`, `D`, `B`, 版本)
+#[cfg(feature = "kvdb")]
+pub(crate) const KVDB_MAGIC: SectorMagic = SectorMagic {
+    offset: SECTOR_MAGIC_OFFSET,
+    prefix: *b"FDB",
+};
+
+/// TSDB 扇区头 magic (`T`, `S`, `L`, 版本)，位于存储状态表之后
+#[cfg(feature = "tsdb")]
+pub(crate) const TSDB_MAGIC: SectorMagic = SectorMagic {
+    offset: (status_table_size(FDB_SECTOR_STORE_STATUS_NUM as usize) + 3) / 4 * 4,
+    prefix: *b"TSL",
+};
+
+impl SectorMagic {
+    /// 检查所有扇区头，返回 Flash 上数据的格式版本。
+    ///
+    /// 存在不兼容的扇区时优先返回其版本，没有任何已格式化的扇区时返回 `None`。
+    pub(crate) fn probe<S: NorFlash>(&self, storage: &mut S, sec_size: u32) -> Result<Option<u8>, Error> {
+        let mut found = None;
+        for addr in (0..storage.capacity() as u32).step_by(sec_size as usize) {
+            let mut magic = [0u8; 4];
+            storage
+                .read(addr + self.offset as u32, &mut magic)
+                .map_err(|_| Error::ReadError)?;
+            if magic[..3] != self.prefix || !magic[3].is_ascii_digit() {
+                continue;
+            }
+            let version = magic[3] - b'0';
+            if version != FORMAT_VERSION {
+                return Ok(Some(version));
+            }
+            found = Some(version);
+        }
+        Ok(found)
+    }
+
+    /// 在交给 C 库初始化之前拒绝不兼容的数据
+    pub(crate) fn check<S: NorFlash>(&self, storage: &mut S, sec_size: u32) -> Result<(), Error> {
+        match self.probe(storage, sec_size)? {
+            Some(found) if found != FORMAT_VERSION => Err(Error::IncompatibleFormat {
+                found,
+                supported: FORMAT_VERSION,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! 写入粒度适配器，用于按字 / 双字编程的 MCU 内部 Flash。
+//!
+//! C 库按编译时的 `FDB_WRITE_GRAN` 对齐地址，但日志和 KV 值的末尾并不会补齐，
+//! 而 STM32 等内部 Flash 只能以完整的写入单元 (`S::WRITE_SIZE`，例如 4 / 8 / 16 字节) 编程。
+//! [`GranAdaptor`] 将任意写入补齐为完整的写入单元，未覆盖的部分以 `0xFF` 填充，
+//! 不需要用户自行编写这部分适配代码。
+
+use embedded_storage::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+
+use crate::FDB_WRITE_GRAN;
+
+/// 支持的最大写入 / 读取单元 (128 bit)
+const MAX_UNIT: usize = 16;
+
+/// 将写入对齐到底层 Flash 写入单元的存储包装器。
+///
+/// 对外的 `WRITE_SIZE` 与 C 库的 `FDB_WRITE_GRAN` 一致，可以直接通过 `check_geometry`。
+/// 写入单元未被写满时，其内容先保存在缓冲区中，后续的顺序写入会合并到同一单元，
+/// 避免同一单元被编程两次；写入其他单元、擦除或调用 `flush()` 时以 `0xFF` 补齐后写入。
+/// 读取会合并缓冲区中尚未写入的内容。
+///
+/// C 库需要以与底层写入单元相同的 `FDB_WRITE_GRAN` 编译 (`write-gran-*` 特性或 `FLASHDB_WRITE_GRAN`
+/// 环境变量)，此时状态表的每次变化都写入独立的单元。默认的 1 bit 粒度下 C 库会在同一单元中逐位改写状态，
+/// 不能用于每个单元只能编程一次的 Flash。C 库的 TSDB 不支持 64 / 128 bit 粒度。
+///
+/// **注意**: 值末尾未写满的单元会留在缓冲区中直到下一次写入，需要在断电前调用 `flush()`，
+/// 或通过 `set_flush_on_sync(true)` 在 C 库同步写入时自动写入。
+///
+/// # 示例
+///
+/// ```
+/// # use embedded_storage::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+/// # use flashdb_rs::gran::GranAdaptor;
+/// # use flashdb_rs::storage::{FileStrategy, StdStorage};
+/// # let dir = tempfile::tempdir()?;
+/// # let storage = StdStorage::new(dir.path(), "gran_doc", 4096, 4 * 4096, FileStrategy::Multi)?;
+/// let mut flash = GranAdaptor::new(storage);
+/// flash.write(0, b"abc")?;
+/// flash.flush()?;
+/// let mut buf = [0; 3];
+/// flash.read(0, &mut buf)?;
+/// assert_eq!(&buf, b"abc");
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct GranAdaptor<S: NorFlash> {
+    inner: S,
+    /// 未写满的写入单元的地址
+    pending: Option<u32>,
+    /// 未写满的写入单元的内容，未写入的部分为 `0xFF`
+    buf: [u8; MAX_UNIT],
+}
+
+impl<S: NorFlash> GranAdaptor<S> {
+    /// 编译期检查底层 Flash 的读写单元
+    const UNIT_CHECK: () = assert!(
+        S::WRITE_SIZE <= MAX_UNIT && S::READ_SIZE <= MAX_UNIT,
+        "GranAdaptor supports read / write units of at most 16 bytes"
+    );
+
+    /// 包装底层 Flash。
+    pub fn new(inner: S) -> Self {
+        let () = Self::UNIT_CHECK;
+        Self {
+            inner,
+            pending: None,
+            buf: [0xFF; MAX_UNIT],
+        }
+    }
+
+    /// 将未写满的写入单元以 `0xFF` 补齐后写入。
+    pub fn flush(&mut self) -> Result<(), S::Error> {
+        if let Some(addr) = self.pending.take() {
+            self.inner.write(addr, &self.buf[..S::WRITE_SIZE])?;
+        }
+        Ok(())
+    }
+
+    /// 获取底层 Flash 的引用。
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// 写入所有缓冲的内容并取回底层 Flash。
+    pub fn into_inner(mut self) -> Result<S, S::Error> {
+        self.flush()?;
+        Ok(self.inner)
+    }
+
+    /// 内部方法：以底层读取单元读取任意范围
+    fn read_unaligned(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), S::Error> {
+        let unit = S::READ_SIZE as u32;
+        let (mut pos, mut done) = (offset, 0);
+        while done < bytes.len() {
+            let start = pos - pos % unit;
+            let within = (pos - start) as usize;
+            let rest = bytes.len() - done;
+            if within == 0 && rest >= S::READ_SIZE {
+                let len = rest - rest % S::READ_SIZE;
+                self.inner.read(pos, &mut bytes[done..done + len])?;
+                done += len;
+                pos += len as u32;
+            } else {
+                let mut block = [0xFF; MAX_UNIT];
+                self.inner.read(start, &mut block[..S::READ_SIZE])?;
+                let len = (S::READ_SIZE - within).min(rest);
+                bytes[done..done + len].copy_from_slice(&block[within..within + len]);
+                done += len;
+                pos += len as u32;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: NorFlash> crate::buffered::FlushStorage for GranAdaptor<S> {
+    fn flush(&mut self) -> Result<(), S::Error> {
+        GranAdaptor::flush(self)
+    }
+}
+
+impl<S: NorFlash> ErrorType for GranAdaptor<S> {
+    type Error = S::Error;
+}
+
+impl<S: NorFlash> ReadNorFlash for GranAdaptor<S> {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.read_unaligned(offset, bytes)?;
+        // 合并尚未写入的内容
+        if let Some(addr) = self.pending {
+            let unit_end = addr as usize + S::WRITE_SIZE;
+            let start = (offset as usize).max(addr as usize);
+            let end = (offset as usize + bytes.len()).min(unit_end);
+            if start < end {
+                bytes[start - offset as usize..end - offset as usize]
+                    .copy_from_slice(&self.buf[start - addr as usize..end - addr as usize]);
+            }
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+}
+
+impl<S: NorFlash> NorFlash for GranAdaptor<S> {
+    const WRITE_SIZE: usize = (FDB_WRITE_GRAN as usize + 7) / 8;
+    const ERASE_SIZE: usize = S::ERASE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        if let Some(addr) = self.pending {
+            if addr >= from && addr < to {
+                // 被擦除的单元不需要再写入
+                self.pending = None;
+            } else {
+                self.flush()?;
+            }
+        }
+        self.inner.erase(from, to)
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let unit = S::WRITE_SIZE as u32;
+        let (mut pos, mut data) = (offset, bytes);
+        while !data.is_empty() {
+            let start = pos - pos % unit;
+            let within = (pos - start) as usize;
+            if self.pending.is_some_and(|addr| addr != start) {
+                self.flush()?;
+            }
+            if within == 0 && self.pending.is_none() && data.len() >= S::WRITE_SIZE {
+                // 完整的写入单元直接写入
+                let len = data.len() - data.len() % S::WRITE_SIZE;
+                self.inner.write(pos, &data[..len])?;
+                data = &data[len..];
+                pos += len as u32;
+                continue;
+            }
+            if self.pending.is_none() {
+                self.buf = [0xFF; MAX_UNIT];
+                self.pending = Some(start);
+            }
+            let len = (S::WRITE_SIZE - within).min(data.len());
+            self.buf[within..within + len].copy_from_slice(&data[..len]);
+            data = &data[len..];
+            pos += len as u32;
+            if within + len == S::WRITE_SIZE {
+                self.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+//! 在专用线程上运行数据库、通过异步方法访问的句柄，适用于 tokio 等异步运行时中的服务。
+//!
+//! 数据库的每个操作都会同步访问文件，直接在异步任务中调用会阻塞运行时的工作线程。
+//! [`DbHandle::spawn`] 在一个专用线程上打开并持有数据库，句柄的异步方法将操作发送到该线程执行，
+//! 等待结果时不占用运行时的线程。句柄可以克隆并发送到任意任务，所有克隆都被丢弃后数据库线程退出。
+//!
+//! 句柄只依赖 `std`，可以在任意异步运行时中使用。
+//!
+//! ```
+//! # use flashdb_rs::handle::KvdbHandle;
+//! # use flashdb_rs::KVDB;
+//! # fn block_on<F: core::future::Future>(f: F) -> F::Output {
+//! #     let mut f = core::pin::pin!(f);
+//! #     let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+//! #     loop {
+//! #         if let core::task::Poll::Ready(out) = f.as_mut().poll(&mut cx) {
+//! #             return out;
+//! #         }
+//! #         std::thread::yield_now();
+//! #     }
+//! # }
+//! # let dir = tempfile::tempdir()?;
+//! let path = dir.path().to_str().unwrap().to_string();
+//! let db = KvdbHandle::spawn(move || KVDB::new_file("handle_doc", &path, 4096, 4 * 4096, None))?;
+//!
+//! // 在异步任务中
+//! block_on(async {
+//!     db.set("session", b"token").await?;
+//!     assert_eq!(db.get("session").await?.as_deref(), Some(&b"token"[..]));
+//!     Ok::<(), flashdb_rs::Error>(())
+//! })?;
+//! # Ok::<(), flashdb_rs::Error>(())
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+#[cfg(feature = "kvdb")]
+use std::string::String;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll, Waker};
+use std::vec::Vec;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+#[cfg(feature = "kvdb")]
+use crate::KVDB;
+#[cfg(feature = "tsdb")]
+use crate::{StatusSet, TSDB};
+
+/// 在数据库线程上执行的操作
+type Job<D> = Box<dyn FnOnce(&mut D) + Send>;
+
+/// 在专用线程上运行数据库的句柄，详见 [`crate::handle`]。
+pub struct DbHandle<D> {
+    jobs: Sender<Job<D>>,
+}
+
+/// 在专用线程上运行的 KVDB
+#[cfg(feature = "kvdb")]
+pub type KvdbHandle<S = crate::StdStorage> = DbHandle<KVDB<S>>;
+
+/// 在专用线程上运行的 TSDB
+#[cfg(feature = "tsdb")]
+pub type TsdbHandle<S = crate::StdStorage> = DbHandle<TSDB<S>>;
+
+impl<D> Clone for DbHandle<D> {
+    fn clone(&self) -> Self {
+        Self { jobs: self.jobs.clone() }
+    }
+}
+
+impl<D: 'static> DbHandle<D> {
+    /// 启动数据库线程并在其中调用 `open` 打开数据库。
+    ///
+    /// 数据库不能在线程间移动，因此必须在数据库线程中创建。此函数会阻塞到 `open` 返回，
+    /// 并返回其错误；线程启动失败时返回 `Error::InitFailed`。
+    pub fn spawn<F>(open: F) -> Result<Self, Error>
+    where
+        F: FnOnce() -> Result<Box<D>, Error> + Send + 'static,
+    {
+        let (jobs, queue) = mpsc::channel::<Job<D>>();
+        let (opened, result) = mpsc::sync_channel(1);
+        std::thread::Builder::new()
+            .name("flashdb".into())
+            .spawn(move || {
+                let mut db = match open() {
+                    Ok(db) => {
+                        let _ = opened.send(Ok(()));
+                        db
+                    }
+                    Err(err) => {
+                        let _ = opened.send(Err(err));
+                        return;
+                    }
+                };
+                // 所有句柄都被丢弃后退出
+                for job in queue {
+                    job(&mut db);
+                }
+            })
+            .map_err(|_| Error::InitFailed)?;
+        result.recv().map_err(|_| Error::InitFailed)??;
+        Ok(Self { jobs })
+    }
+
+    /// 在数据库线程上执行 `f` 并等待其返回。
+    ///
+    /// 数据库线程已经退出 (例如之前的操作 panic) 时返回 `Error::UnknownError`。
+    pub fn call<R, F>(&self, f: F) -> Reply<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut D) -> R + Send + 'static,
+    {
+        let slot = Arc::new(Mutex::new(Slot { value: None, waker: None, done: false }));
+        let responder = Responder { slot: slot.clone() };
+        let job: Job<D> = Box::new(move |db| responder.send(f(db)));
+        // 数据库线程已退出时任务被退回并丢弃，`Responder` 会将结果标记为失败
+        let _ = self.jobs.send(job);
+        Reply { slot }
+    }
+}
+
+/// `DbHandle::call()` 返回的 future
+pub struct Reply<R> {
+    slot: Arc<Mutex<Slot<R>>>,
+}
+
+struct Slot<R> {
+    value: Option<R>,
+    waker: Option<Waker>,
+    /// 结果已经写入，或者操作已被丢弃
+    done: bool,
+}
+
+impl<R> Future for Reply<R> {
+    type Output = Result<R, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.lock().unwrap_or_else(PoisonError::into_inner);
+        if slot.done {
+            return Poll::Ready(slot.value.take().ok_or(Error::UnknownError));
+        }
+        slot.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// 写入操作结果；未写入就被丢弃时 (线程退出或操作 panic) 同样唤醒等待方
+struct Responder<R> {
+    slot: Arc<Mutex<Slot<R>>>,
+}
+
+impl<R> Responder<R> {
+    fn send(self, value: R) {
+        self.slot.lock().unwrap_or_else(PoisonError::into_inner).value = Some(value);
+    }
+}
+
+impl<R> Drop for Responder<R> {
+    fn drop(&mut self) {
+        let mut slot = self.slot.lock().unwrap_or_else(PoisonError::into_inner);
+        slot.done = true;
+        if let Some(waker) = slot.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(feature = "kvdb")]
+impl<S: NorFlash + 'static> DbHandle<KVDB<S>> {
+    /// 根据键获取其值。
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let key = String::from(key);
+        self.call(move |db| db.get(&key)).await?
+    }
+
+    /// 存储一个键值对。
+    pub async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        let (key, value) = (String::from(key), value.to_vec());
+        self.call(move |db| db.set(&key, &value)).await?
+    }
+
+    /// 删除一个键值对。
+    pub async fn delete(&self, key: &str) -> Result<(), Error> {
+        let key = String::from(key);
+        self.call(move |db| db.delete(&key)).await?
+    }
+}
+
+#[cfg(feature = "tsdb")]
+impl<S: NorFlash + 'static> DbHandle<TSDB<S>> {
+    /// 以数据库的时间戳来源追加一条日志，详见 `TSDB::append`。
+    pub async fn append(&self, data: &[u8]) -> Result<(), Error> {
+        let data = data.to_vec();
+        self.call(move |db| db.append(&data)).await?
+    }
+
+    /// 追加一条指定时间戳的日志。
+    pub async fn append_with_timestamp(&self, timestamp: i64, data: &[u8]) -> Result<(), Error> {
+        let data = data.to_vec();
+        self.call(move |db| db.append_with_timestamp(timestamp, &data)).await?
+    }
+
+    /// 查询时间范围内指定状态的日志条数。
+    pub async fn count(&self, from: i64, to: i64, statuses: impl Into<StatusSet>) -> Result<usize, Error> {
+        let statuses = statuses.into();
+        self.call(move |db| db.count(from, to, statuses)).await
+    }
+
+    /// 读取时间范围 (包含两端) 内所有日志的时间戳与数据。
+    pub async fn query(&self, from: i64, to: i64) -> Result<Vec<(i64, Vec<u8>)>, Error> {
+        self.call(move |db| {
+            let tsls: Vec<_> = db.iter_by_time(from..=to).collect();
+            tsls.into_iter()
+                .map(|tsl| Ok((tsl.time(), db.get_value(&tsl)?.unwrap_or_default())))
+                .collect()
+        })
+        .await?
+    }
+}
@@ -0,0 +1,97 @@
+//! 加速查找不存在的键的布隆过滤器。
+//!
+//! 过滤器在 `init()` 时通过扫描全部键建立，之后每次写入都会加入新的键。
+//! 删除的键无法从过滤器中移除，只会让之后对该键的查找退回到常规查找，不影响正确性。
+
+use alloc::{vec, vec::Vec};
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::fdb_calc_crc32;
+
+use super::{KVStatus, KVDB};
+
+/// 每个键在过滤器中设置的位数
+const HASHES: u32 = 3;
+/// 第二个哈希函数使用的 CRC32 初始值
+const SECOND_SEED: u32 = 0x9E37_79B9;
+
+/// 固定大小的布隆过滤器
+pub(super) struct Bloom {
+    words: Vec<u64>,
+}
+
+impl Bloom {
+    fn new(bits: usize) -> Self {
+        Self {
+            words: vec![0; bits.div_ceil(64).max(1)],
+        }
+    }
+
+    fn bits(&self) -> u32 {
+        (self.words.len() * 64) as u32
+    }
+
+    /// 双重哈希：第 i 个位置为 h1 + i * h2
+    fn positions(&self, key: &[u8]) -> impl Iterator<Item = u32> {
+        let crc = |seed: u32| unsafe { fdb_calc_crc32(seed, key.as_ptr() as *const _, key.len()) };
+        let (h1, h2) = (crc(0), crc(SECOND_SEED) | 1);
+        let bits = self.bits();
+        (0..HASHES).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % bits)
+    }
+
+    fn insert(&mut self, key: &[u8]) {
+        for pos in self.positions(key) {
+            self.words[pos as usize / 64] |= 1 << (pos % 64);
+        }
+    }
+
+    fn may_contain(&self, key: &[u8]) -> bool {
+        self.positions(key)
+            .all(|pos| self.words[pos as usize / 64] & (1 << (pos % 64)) != 0)
+    }
+
+    fn clear(&mut self) {
+        self.words.fill(0);
+    }
+}
+
+impl<S: NorFlash> KVDB<S> {
+    /// 启用大小为 `bits` 位的布隆过滤器，`0` 表示禁用。
+    ///
+    /// 启用后，查找不存在的键 (例如启动时探测大量可选配置) 通常无需访问 Flash 即可返回。
+    /// 每个键分配约 10 位时误判率约为 2%，误判只会退回到常规查找。
+    ///
+    /// **注意**: 此方法必须在 `init()` 之前调用。
+    pub fn set_bloom_filter(&mut self, bits: usize) {
+        self.bloom = (bits > 0).then(|| Bloom::new(bits));
+    }
+
+    /// 内部方法：键是否一定不存在
+    pub(super) fn bloom_rejects(&self, key: &str) -> bool {
+        self.bloom
+            .as_ref()
+            .is_some_and(|bloom| !bloom.may_contain(key.as_bytes()))
+    }
+
+    /// 内部方法：记录新写入的键
+    pub(super) fn bloom_insert(&mut self, key: &str) {
+        if let Some(bloom) = self.bloom.as_mut() {
+            bloom.insert(key.as_bytes());
+        }
+    }
+
+    /// 内部方法：重新扫描数据库，重建过滤器
+    pub(super) fn bloom_rebuild(&mut self) {
+        let mut bloom = match self.bloom.take() {
+            Some(bloom) => bloom,
+            None => return,
+        };
+        bloom.clear();
+        for kv in self.iter().filter(|kv| kv.status() == KVStatus::Write) {
+            let name = &kv.inner.name[..kv.inner.name_len as usize];
+            bloom.insert(unsafe { core::slice::from_raw_parts(name.as_ptr() as *const u8, name.len()) });
+        }
+        self.bloom = Some(bloom);
+    }
+}
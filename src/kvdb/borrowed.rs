@@ -0,0 +1,93 @@
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{fdb_blob_make_by, Error};
+
+use super::{KVEntry, KVStatus, KVDB};
+
+/// `get_chunks` 每次读取使用的栈缓冲区大小
+const CHUNK_SIZE: usize = 64;
+
+impl<S: NorFlash> KVDB<S> {
+    /// 以借用切片的形式访问值，避免每次读取都分配新的 `Vec`。
+    ///
+    /// 值会被读取到数据库内部的缓冲区中再交给 `f`，该缓冲区在多次调用之间复用，
+    /// 只有遇到更长的值时才会扩容，适合在热路径上频繁读取。
+    ///
+    /// # 返回
+    /// - `Ok(Some(r))`: 找到键，`r` 为 `f` 的返回值。
+    /// - `Ok(None)`: 未找到键，`f` 不会被调用。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::KVDB;
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = KVDB::new_file("get_with_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+    /// db.set("name", b"flashdb")?;
+    /// let len = db.get_with("name", |value| value.len())?;
+    /// assert_eq!(len, Some(7));
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn get_with<R>(&mut self, key: &str, f: impl FnOnce(&[u8]) -> R) -> Result<Option<R>, Error> {
+        if let Some(value) = self.overlay_get(key) {
+            return Ok(Some(f(value)));
+        }
+        let kv = match self.readable_kv(key)? {
+            Some(kv) => kv,
+            None => return Ok(None),
+        };
+
+        // 读取期间将缓冲区移出，避免与 `self` 的可变借用冲突
+        let mut scratch = core::mem::take(&mut self.scratch);
+        scratch.clear();
+        scratch.resize(kv.value_len(), 0);
+        let mut blob = fdb_blob_make_by(&mut scratch, &kv, 0);
+        let result = if self.fdb_blob_read(&mut blob) == scratch.len() {
+            Ok(Some(f(&scratch)))
+        } else {
+            self.user_data.finish(Err(Error::ReadError))
+        };
+        self.scratch = scratch;
+        result
+    }
+
+    /// 分段读取值，不需要 `alloc`。
+    ///
+    /// 值按顺序被读取到一个小的栈缓冲区中，每读取一段就以该段数据调用一次 `f`，
+    /// 适合计算校验和或转发到串口等无需完整值的场景。
+    ///
+    /// # 返回
+    /// - `Ok(Some(len))`: 找到键，`len` 为值的总长度。
+    /// - `Ok(None)`: 未找到键，`f` 不会被调用。
+    pub fn get_chunks(&mut self, key: &str, mut f: impl FnMut(&[u8])) -> Result<Option<usize>, Error> {
+        if let Some(value) = self.overlay_get(key) {
+            f(value);
+            return Ok(Some(value.len()));
+        }
+        let kv = match self.readable_kv(key)? {
+            Some(kv) => kv,
+            None => return Ok(None),
+        };
+
+        let mut chunk = [0u8; CHUNK_SIZE];
+        let mut offset = 0;
+        while offset < kv.value_len() {
+            let len = CHUNK_SIZE.min(kv.value_len() - offset);
+            let mut blob = fdb_blob_make_by(&mut chunk[..len], &kv, offset);
+            if self.fdb_blob_read(&mut blob) != len {
+                return self.user_data.finish(Err(Error::ReadError));
+            }
+            f(&chunk[..len]);
+            offset += len;
+        }
+        Ok(Some(kv.value_len()))
+    }
+
+    /// 内部方法：获取处于可读状态的 KV 对象
+    pub(super) fn readable_kv(&mut self, key: &str) -> Result<Option<KVEntry>, Error> {
+        Ok(self
+            .fdb_kv_get_obj(key)?
+            .filter(|kv| matches!(kv.status(), KVStatus::PRE_WRITE | KVStatus::Write)))
+    }
+}
@@ -0,0 +1,164 @@
+use alloc::{string::String, vec::Vec};
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{fdb_default_kv, Error};
+
+use super::KVDB;
+
+/// 带 LRU 值缓存的 KVDB。
+///
+/// 最近读取过的键 (包括不存在的键) 会被缓存在内存中，在轮询等反复读取热点键的场景下，
+/// 命中缓存的 `get()` 完全不访问 Flash。所有经过此包装的写入都会同步写入 Flash 并更新缓存，
+/// 因此缓存中的值始终与 Flash 一致。
+///
+/// **注意**: 通过 `inner_mut()` 直接修改数据库会清空缓存。
+///
+/// # 示例
+///
+/// ```
+/// use flashdb_rs::{CachedKVDB, KVDB, StdStorage};
+/// use flashdb_rs::storage::FileStrategy;
+///
+/// # let dir = tempfile::tempdir()?;
+/// let storage = StdStorage::new(dir.path(), "cached", 4096, 4 * 4096, FileStrategy::Multi)?;
+/// let mut db = Box::new(CachedKVDB::new(KVDB::new(storage), 8));
+/// db.init(None)?;
+/// db.set("mode", b"auto")?;
+///
+/// // 写入后缓存即为最新值，之后的读取都不再访问 Flash
+/// for _ in 0..100 {
+///     assert_eq!(db.get("mode")?, Some(&b"auto"[..]));
+/// }
+/// assert_eq!(db.misses(), 0);
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct CachedKVDB<S: NorFlash> {
+    db: KVDB<S>,
+    /// 按最近使用排序的缓存项，越靠后越新
+    entries: Vec<(String, Option<Vec<u8>>)>,
+    capacity: usize,
+    hits: u32,
+    misses: u32,
+}
+
+impl<S: NorFlash> CachedKVDB<S> {
+    /// 包装一个数据库，最多缓存 `capacity` 个键 (至少为 1)。
+    pub fn new(db: KVDB<S>, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            db,
+            entries: Vec::with_capacity(capacity),
+            capacity,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// 初始化数据库，与 `KVDB::init` 相同。
+    pub fn init(&mut self, default_kv: Option<&'static fdb_default_kv>) -> Result<(), Error> {
+        self.entries.clear();
+        self.db.init(default_kv)
+    }
+
+    /// 获取被包装的数据库。
+    pub fn inner(&self) -> &KVDB<S> {
+        &self.db
+    }
+
+    /// 获取被包装的数据库的可变引用，由于无法追踪之后的修改，缓存会被清空。
+    pub fn inner_mut(&mut self) -> &mut KVDB<S> {
+        self.entries.clear();
+        &mut self.db
+    }
+
+    /// 取出被包装的数据库。
+    pub fn into_inner(self) -> KVDB<S> {
+        self.db
+    }
+
+    /// 根据键获取其值，优先从缓存读取。
+    pub fn get(&mut self, key: &str) -> Result<Option<&[u8]>, Error> {
+        let pos = match self.position(key) {
+            Some(pos) => {
+                self.hits = self.hits.wrapping_add(1);
+                pos
+            }
+            None => {
+                self.misses = self.misses.wrapping_add(1);
+                let value = self.db.get(key)?;
+                self.insert(key, value)
+            }
+        };
+        Ok(self.touch(pos).1.as_deref())
+    }
+
+    /// 存储一个键值对，同时更新缓存。
+    pub fn set(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.invalidate(key);
+        self.db.set(key, value)?;
+        self.insert(key, Some(value.to_vec()));
+        Ok(())
+    }
+
+    /// 删除一个键值对，同时更新缓存。
+    pub fn delete(&mut self, key: &str) -> Result<(), Error> {
+        self.invalidate(key);
+        self.db.delete(key)?;
+        self.insert(key, None);
+        Ok(())
+    }
+
+    /// 将数据库恢复为默认键值对，同时清空缓存。
+    pub fn reset(&mut self) -> Result<(), Error> {
+        self.entries.clear();
+        self.db.reset()
+    }
+
+    /// 使某个键的缓存失效，下一次读取时重新从 Flash 读取。
+    pub fn invalidate(&mut self, key: &str) {
+        if let Some(pos) = self.position(key) {
+            self.entries.remove(pos);
+        }
+    }
+
+    /// 清空全部缓存。
+    pub fn clear_cache(&mut self) {
+        self.entries.clear();
+    }
+
+    /// 当前缓存的键数。
+    pub fn cached_len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 命中缓存的读取次数。
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    /// 未命中缓存、从 Flash 读取的次数。
+    pub fn misses(&self) -> u32 {
+        self.misses
+    }
+
+    fn position(&self, key: &str) -> Option<usize> {
+        self.entries.iter().rposition(|(name, _)| name == key)
+    }
+
+    /// 加入新的缓存项，必要时淘汰最久未使用的项，返回其位置
+    fn insert(&mut self, key: &str, value: Option<Vec<u8>>) -> usize {
+        if self.entries.len() >= self.capacity {
+            self.entries.remove(0);
+        }
+        self.entries.push((key.into(), value));
+        self.entries.len() - 1
+    }
+
+    /// 将缓存项标记为最近使用
+    fn touch(&mut self, pos: usize) -> &(String, Option<Vec<u8>>) {
+        let entry = self.entries.remove(pos);
+        self.entries.push(entry);
+        &self.entries[self.entries.len() - 1]
+    }
+}
@@ -0,0 +1,148 @@
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{fdb_err_t, fdb_kvdb_t, Error, RawHandle};
+
+use super::layout::{Layout, SectorDirty, SectorStore};
+use super::{GcPolicy, KVDB};
+
+extern "C" {
+    /// 由 `flashdb/patches/0001-kvdb-add-fdb_kv_gc.patch` 添加：立即回收所有脏扇区
+    fn fdb_kv_gc(db: fdb_kvdb_t) -> fdb_err_t;
+}
+
+impl<S: NorFlash> KVDB<S> {
+    /// 立即整理数据库，返回被回收的扇区数。
+    ///
+    /// C 库只在空扇区耗尽时才在写入过程中触发垃圾回收，使某次写入的耗时远超平常。
+    /// 在空闲时主动调用此方法，可以提前将脏扇区中仍然有效的 KV 搬移到其他扇区并擦除这些扇区，
+    /// 之后的写入就不会再因为垃圾回收而被阻塞。
+    ///
+    /// 整理过程与 C 库的垃圾回收相同，中途掉电后会在下次 `init()` 时继续，不会丢失数据。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::KVDB;
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = KVDB::new_file("compact_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+    /// for i in 0..100u32 {
+    ///     db.set("counter", &i.to_le_bytes())?;
+    /// }
+    /// db.compact()?;
+    /// assert_eq!(db.verify()?.dirty_sectors, 0);
+    /// assert_eq!(db.get_u32("counter")?, Some(99));
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn compact(&mut self) -> Result<usize, Error> {
+        if !self.initialized {
+            return Err(Error::InitFailed);
+        }
+        let result = self.sector_usage().and_then(|before| {
+            self.collect_garbage()?;
+            Ok(before.dirty.saturating_sub(self.sector_usage()?.dirty))
+        });
+        #[cfg(feature = "alloc")]
+        let result = result.map_err(|err| err.with_context(crate::ErrorContext::new(crate::Operation::Gc)));
+        result
+    }
+
+    /// 设置垃圾回收策略，初始化前后均可调用。
+    ///
+    /// 策略在每次 `set()` 与 `delete()` 成功后检查，满足条件时立即回收所有脏扇区，
+    /// 效果与 `compact()` 相同。C 库自身在空扇区耗尽时的回收不受影响。
+    /// 非默认策略在每次写入后都需要读取所有扇区头。
+    ///
+    /// # 返回
+    /// - `Err(Error::InvalidArgument)`: 百分比大于 100，或保留的空扇区数为 0 或不少于扇区总数。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{GcPolicy, KVDB};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = KVDB::new_file("gc_policy_doc", dir.path().to_str().unwrap(), 4096, 8 * 4096, None)?;
+    /// db.set_gc_policy(GcPolicy::new().with_dirty_percent(25))?;
+    /// for i in 0..200u32 {
+    ///     db.set("counter", &i.to_le_bytes())?;
+    ///     assert!(db.verify()?.dirty_sectors < 2);
+    /// }
+    /// assert_eq!(db.gc_policy().dirty_percent(), Some(25));
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn set_gc_policy(&mut self, policy: GcPolicy) -> Result<(), Error> {
+        let sectors = self.storage.capacity() / self.sec_size as usize;
+        if policy.dirty_percent().is_some_and(|percent| percent > 100)
+            || policy.reserved_sectors() == 0
+            || policy.reserved_sectors() as usize >= sectors
+        {
+            return Err(Error::InvalidArgument);
+        }
+        self.gc_policy = policy;
+        Ok(())
+    }
+
+    /// 当前的垃圾回收策略。
+    pub fn gc_policy(&self) -> GcPolicy {
+        self.gc_policy
+    }
+
+    /// 内部方法：写入后按策略检查是否需要回收
+    pub(super) fn gc_maybe(&mut self) -> Result<(), Error> {
+        if self.gc_policy.is_default() || !self.initialized {
+            return Ok(());
+        }
+        let usage = self.sector_usage()?;
+        let too_dirty = self
+            .gc_policy
+            .dirty_percent()
+            .is_some_and(|percent| usage.dirty * 100 >= percent as usize * usage.total);
+        let too_full = usage.empty < self.gc_policy.reserved_sectors() as usize;
+        // 没有脏扇区时回收不能释放任何空间
+        if usage.dirty > 0 && (too_dirty || too_full) {
+            self.collect_garbage()?;
+        }
+        Ok(())
+    }
+
+    /// 内部方法：回收所有脏扇区
+    fn collect_garbage(&mut self) -> Result<(), Error> {
+        #[cfg(feature = "alloc")]
+        self.user_data.arm_events();
+        self.user_data.begin_write();
+        let result = Error::convert(unsafe { fdb_kv_gc(self.handle()) });
+        // KV 被搬移到了新的地址
+        #[cfg(feature = "kv-index")]
+        self.index_rebuild();
+        self.user_data.finish(result)
+    }
+
+    /// 内部方法：统计各类扇区的数量
+    fn sector_usage(&mut self) -> Result<SectorUsage, Error> {
+        let sec_size = self.raw().parent.sec_size;
+        let max_size = self.raw().parent.max_size;
+        let mut layout = Layout::new(&mut *self.storage, sec_size, max_size);
+        let mut usage = SectorUsage::default();
+        for addr in (0..max_size / sec_size).map(|i| i * sec_size) {
+            let sector = layout.read_sector(addr)?;
+            usage.total += 1;
+            if !sector.header_ok {
+                continue;
+            }
+            if sector.store == SectorStore::Empty {
+                usage.empty += 1;
+            }
+            if matches!(sector.dirty, SectorDirty::True | SectorDirty::Gc) {
+                usage.dirty += 1;
+            }
+        }
+        Ok(usage)
+    }
+}
+
+/// 扇区统计
+#[derive(Default)]
+struct SectorUsage {
+    total: usize,
+    empty: usize,
+    dirty: usize,
+}
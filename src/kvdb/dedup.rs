@@ -0,0 +1,177 @@
+use alloc::{format, string::String, vec::Vec};
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{fdb_calc_crc32, Error};
+
+use super::KVDB;
+
+/// 记录类型：值直接内联存储
+const TAG_INLINE: u8 = 0;
+/// 记录类型：值为指向共享数据块的引用
+const TAG_REF: u8 = 1;
+
+/// 共享数据块与引用计数使用的键前缀
+const BLOB_PREFIX: &str = "\u{1}dd.b/";
+const REFS_PREFIX: &str = "\u{1}dd.r/";
+
+/// 按内容去重的 KV 视图。
+///
+/// 通过 `KVDB::dedup()` 获取。不同键上相同的值（例如证书）只会在 Flash 中存储一份，
+/// 并通过引用计数管理其生命周期。内容 ID 由值的 CRC32 和长度组成，
+/// 发生哈希冲突时会自动回退为内联存储，因此不会返回错误的数据。
+///
+/// **注意**: 通过此视图写入的值带有内部记录头，必须同样通过此视图读取和删除。
+pub struct Dedup<'a, S: NorFlash> {
+    db: &'a mut KVDB<S>,
+    threshold: usize,
+}
+
+impl<S: NorFlash> KVDB<S> {
+    /// 获取按内容去重的 KV 视图。
+    ///
+    /// 默认只有长度不小于 64 字节的值才会参与去重。
+    pub fn dedup(&mut self) -> Dedup<'_, S> {
+        Dedup {
+            db: self,
+            threshold: 64,
+        }
+    }
+}
+
+impl<'a, S: NorFlash> Dedup<'a, S> {
+    /// 设置参与去重的最小值长度，更短的值将直接内联存储。
+    pub fn with_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// 存储一个键值对，相同内容的值只保存一份。
+    pub fn set(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
+        let old = self.read_record(key)?;
+
+        let record = if value.len() >= self.threshold {
+            let id = content_id(value);
+            if self.acquire(&id, value)? {
+                let mut record = Vec::with_capacity(1 + id.len());
+                record.push(TAG_REF);
+                record.extend_from_slice(id.as_bytes());
+                record
+            } else {
+                inline_record(value)
+            }
+        } else {
+            inline_record(value)
+        };
+        self.db.set(key, &record)?;
+
+        // 先增加新引用再释放旧引用，保证内容相同时数据块不会被误删
+        if let Some(Record::Ref(old_id)) = old {
+            self.release(&old_id)?;
+        }
+        Ok(())
+    }
+
+    /// 根据键获取其值，自动解析共享数据块。
+    pub fn get(&mut self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        match self.read_record(key)? {
+            None => Ok(None),
+            Some(Record::Inline(value)) => Ok(Some(value)),
+            Some(Record::Ref(id)) => match self.db.get(&blob_key(&id))? {
+                Some(value) => Ok(Some(value)),
+                None => Err(Error::Corrupted),
+            },
+        }
+    }
+
+    /// 删除一个键值对，并在引用计数归零时删除共享数据块。
+    pub fn delete(&mut self, key: &str) -> Result<(), Error> {
+        let old = self.read_record(key)?;
+        self.db.delete(key)?;
+        if let Some(Record::Ref(id)) = old {
+            self.release(&id)?;
+        }
+        Ok(())
+    }
+
+    /// 查询某个值当前被引用的次数，未被共享存储时返回 0。
+    pub fn ref_count(&mut self, value: &[u8]) -> Result<u32, Error> {
+        let id = content_id(value);
+        Ok(self.db.get_u32(&refs_key(&id))?.unwrap_or(0))
+    }
+
+    /// 读取并解析键对应的记录。
+    fn read_record(&mut self, key: &str) -> Result<Option<Record>, Error> {
+        let raw = match self.db.get(key)? {
+            Some(raw) => raw,
+            None => return Ok(None),
+        };
+        match raw.split_first() {
+            Some((&TAG_INLINE, value)) => Ok(Some(Record::Inline(value.to_vec()))),
+            Some((&TAG_REF, id)) => {
+                let id = core::str::from_utf8(id).map_err(|_| Error::Corrupted)?;
+                Ok(Some(Record::Ref(id.into())))
+            }
+            _ => Err(Error::Corrupted),
+        }
+    }
+
+    /// 增加共享数据块的引用，必要时创建数据块。
+    ///
+    /// 当同一内容 ID 下已存在内容不同的数据块（哈希冲突）时返回 `false`。
+    fn acquire(&mut self, id: &str, value: &[u8]) -> Result<bool, Error> {
+        let refs_key = refs_key(id);
+        let count = match self.db.get_u32(&refs_key)? {
+            Some(count) if count > 0 => {
+                if self.db.get(&blob_key(id))?.as_deref() != Some(value) {
+                    return Ok(false);
+                }
+                count
+            }
+            _ => {
+                self.db.set(&blob_key(id), value)?;
+                0
+            }
+        };
+        self.db.set_u32(&refs_key, count + 1)?;
+        Ok(true)
+    }
+
+    /// 释放共享数据块的一个引用。
+    fn release(&mut self, id: &str) -> Result<(), Error> {
+        let refs_key = refs_key(id);
+        match self.db.get_u32(&refs_key)? {
+            Some(count) if count > 1 => self.db.set_u32(&refs_key, count - 1),
+            _ => {
+                self.db.delete(&blob_key(id))?;
+                self.db.delete(&refs_key)
+            }
+        }
+    }
+}
+
+enum Record {
+    Inline(Vec<u8>),
+    Ref(String),
+}
+
+fn inline_record(value: &[u8]) -> Vec<u8> {
+    let mut record = Vec::with_capacity(1 + value.len());
+    record.push(TAG_INLINE);
+    record.extend_from_slice(value);
+    record
+}
+
+/// 计算值的内容 ID：CRC32 + 长度
+fn content_id(value: &[u8]) -> String {
+    let crc = unsafe { fdb_calc_crc32(0, value.as_ptr() as *const _, value.len()) };
+    format!("{:08x}{:08x}", crc, value.len() as u32)
+}
+
+fn blob_key(id: &str) -> String {
+    format!("{}{}", BLOB_PREFIX, id)
+}
+
+fn refs_key(id: &str) -> String {
+    format!("{}{}", REFS_PREFIX, id)
+}
@@ -0,0 +1,94 @@
+use alloc::vec::Vec;
+
+use crate::{fdb_default_kv, fdb_default_kv_node, Error, FDB_KV_NAME_MAX};
+
+/// 运行时构建的默认键值对集合。
+///
+/// 与 `define_default_kvs!` 不同，`DefaultKvs` 可以由堆上的数据在运行时构建，
+/// 并通过 `KVDB::init_with_defaults` 交给数据库持有，其生命周期由 `KVDB` 管理。
+///
+/// # 示例
+///
+/// ```
+/// use flashdb_rs::DefaultKvs;
+///
+/// let version = format!("{}.{}", 1, 0);
+/// let defaults = DefaultKvs::new()
+///     .add("version", version.as_bytes())
+///     .add("boot_count", &0u32.to_le_bytes());
+/// assert_eq!(defaults.len(), 2);
+/// ```
+#[derive(Debug, Default)]
+pub struct DefaultKvs {
+    // 以 '\0' 结尾的键
+    keys: Vec<Vec<u8>>,
+    // 以 '\0' 结尾的值，C 库在 value_len 为 0 时会使用 strlen 计算长度
+    values: Vec<Vec<u8>>,
+    nodes: Vec<fdb_default_kv_node>,
+    raw: fdb_default_kv,
+}
+
+impl DefaultKvs {
+    /// 创建一个空的默认键值对集合。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加一个默认键值对（构建器风格）。
+    pub fn add(mut self, key: &str, value: &[u8]) -> Self {
+        self.insert(key, value);
+        self
+    }
+
+    /// 添加一个默认键值对。
+    pub fn insert(&mut self, key: &str, value: &[u8]) -> &mut Self {
+        let mut key_buf = Vec::with_capacity(key.len() + 1);
+        key_buf.extend_from_slice(key.as_bytes());
+        key_buf.push(0);
+        let mut value_buf = Vec::with_capacity(value.len() + 1);
+        value_buf.extend_from_slice(value);
+        value_buf.push(0);
+
+        // 内层 Vec 的堆内存地址不会因外层 Vec 扩容而改变
+        self.nodes.push(fdb_default_kv_node {
+            key: key_buf.as_ptr() as *mut _,
+            value: value_buf.as_ptr() as *mut _,
+            value_len: value.len(),
+        });
+        self.keys.push(key_buf);
+        self.values.push(value_buf);
+        self
+    }
+
+    /// 默认键值对的数量。
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// 集合是否为空。
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// 检查所有键是否为合法的 KV 名称。
+    pub(super) fn validate(&self) -> Result<(), Error> {
+        for key in &self.keys {
+            let key = &key[..key.len() - 1];
+            if key.is_empty() || key.len() > FDB_KV_NAME_MAX as usize || key.contains(&0) {
+                return Err(Error::KvNameError);
+            }
+        }
+        Ok(())
+    }
+
+    /// 获取传递给 C 库的 `fdb_default_kv` 指针。
+    ///
+    /// 返回的指针在 `self` 被移动或销毁前有效。
+    pub(super) fn as_raw(&mut self) -> *mut fdb_default_kv {
+        self.raw = fdb_default_kv {
+            kvs: self.nodes.as_mut_ptr(),
+            num: self.nodes.len(),
+        };
+        &mut self.raw
+    }
+}
@@ -0,0 +1,147 @@
+use alloc::vec::Vec;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+
+use super::KVDB;
+
+/// 加密记录的格式版本
+const RECORD_VERSION: u8 = 1;
+/// 记录头：格式版本 + 密钥 ID
+const RECORD_HDR_SIZE: usize = 2;
+
+/// 为加密 KV 提供密钥与 AEAD 算法。
+///
+/// 本库不内置密码学实现，由用户基于 `chacha20poly1305`、`aes-gcm` 或芯片的硬件加密引擎实现此 trait。
+/// 方法的形式与 RustCrypto 的 `AeadInPlace` 一致：加密时在 `buffer` 末尾追加认证标签，
+/// 解密时校验并去除标签。键名会作为附加认证数据 (AAD) 传入，因此密文无法被挪用到其他键上。
+///
+/// 每个密钥有一个 ID 并随密文一同保存，新写入的值使用 `key_id()` 返回的密钥，
+/// 旧值仍可用原密钥解密，从而支持密钥轮换。
+///
+/// # 示例
+///
+/// ```ignore
+/// use chacha20poly1305::{aead::AeadInPlace, ChaCha20Poly1305, KeyInit};
+///
+/// struct DeviceKey(ChaCha20Poly1305);
+///
+/// impl flashdb_rs::KeyProvider for DeviceKey {
+///     const NONCE_LEN: usize = 12;
+///
+///     fn key_id(&self) -> u8 {
+///         0
+///     }
+///
+///     fn fill_nonce(&mut self, nonce: &mut [u8]) {
+///         hardware_rng_fill(nonce);
+///     }
+///
+///     fn encrypt(&self, _key_id: u8, nonce: &[u8], aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), flashdb_rs::Error> {
+///         self.0
+///             .encrypt_in_place(nonce.into(), aad, buffer)
+///             .map_err(|_| flashdb_rs::Error::InvalidArgument)
+///     }
+///
+///     fn decrypt(&self, key_id: u8, nonce: &[u8], aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), flashdb_rs::Error> {
+///         if key_id != 0 {
+///             return Err(flashdb_rs::Error::DecryptError);
+///         }
+///         self.0
+///             .decrypt_in_place(nonce.into(), aad, buffer)
+///             .map_err(|_| flashdb_rs::Error::DecryptError)
+///     }
+/// }
+/// ```
+pub trait KeyProvider {
+    /// 随机数 (nonce) 的长度
+    const NONCE_LEN: usize;
+
+    /// 新写入的值使用的密钥 ID
+    fn key_id(&self) -> u8;
+
+    /// 生成一个新的随机数。同一密钥下随机数不能重复，通常应使用硬件随机数发生器。
+    fn fill_nonce(&mut self, nonce: &mut [u8]);
+
+    /// 使用 `key_id` 对应的密钥就地加密 `buffer`，并在末尾追加认证标签。
+    fn encrypt(&self, key_id: u8, nonce: &[u8], aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), Error>;
+
+    /// 使用 `key_id` 对应的密钥校验并就地解密 `buffer`，认证失败时返回 `Error::DecryptError`。
+    fn decrypt(&self, key_id: u8, nonce: &[u8], aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), Error>;
+}
+
+/// 透明加密值的 KV 视图。
+///
+/// 通过 `KVDB::encrypted()` 获取。值在写入 Flash 前加密，读取时解密并校验，
+/// 适合保存 WiFi 密码、访问令牌等敏感数据。键名本身不加密。
+///
+/// **注意**: 通过此视图写入的值必须同样通过此视图读取。
+pub struct EncryptedKVDB<'a, S: NorFlash, K: KeyProvider> {
+    db: &'a mut KVDB<S>,
+    keys: &'a mut K,
+}
+
+impl<S: NorFlash> KVDB<S> {
+    /// 获取使用 `keys` 加密值的 KV 视图。
+    pub fn encrypted<'a, K: KeyProvider>(&'a mut self, keys: &'a mut K) -> EncryptedKVDB<'a, S, K> {
+        EncryptedKVDB { db: self, keys }
+    }
+}
+
+impl<'a, S: NorFlash, K: KeyProvider> EncryptedKVDB<'a, S, K> {
+    /// 加密并存储一个键值对。
+    pub fn set(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
+        let key_id = self.keys.key_id();
+        let mut record = Vec::with_capacity(RECORD_HDR_SIZE + K::NONCE_LEN + value.len() + 16);
+        record.extend_from_slice(&[RECORD_VERSION, key_id]);
+        record.resize(RECORD_HDR_SIZE + K::NONCE_LEN, 0);
+        self.keys.fill_nonce(&mut record[RECORD_HDR_SIZE..]);
+
+        let mut buffer = value.to_vec();
+        self.keys
+            .encrypt(key_id, &record[RECORD_HDR_SIZE..], key.as_bytes(), &mut buffer)?;
+        record.extend_from_slice(&buffer);
+        self.db.set(key, &record)
+    }
+
+    /// 读取并解密键的值。
+    ///
+    /// # 返回
+    /// - `Err(Error::DecryptError)`: 密钥不匹配或密文被篡改。
+    /// - `Err(Error::Corrupted)`: 记录格式无效。
+    pub fn get(&mut self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let record = match self.db.get(key)? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        if record.len() < RECORD_HDR_SIZE + K::NONCE_LEN || record[0] != RECORD_VERSION {
+            return Err(Error::Corrupted);
+        }
+        let key_id = record[1];
+        let (nonce, ciphertext) = record[RECORD_HDR_SIZE..].split_at(K::NONCE_LEN);
+        let mut buffer = ciphertext.to_vec();
+        self.keys.decrypt(key_id, nonce, key.as_bytes(), &mut buffer)?;
+        Ok(Some(buffer))
+    }
+
+    /// 删除一个键值对。
+    pub fn delete(&mut self, key: &str) -> Result<(), Error> {
+        self.db.delete(key)
+    }
+
+    /// 使用当前密钥重新加密键的值，用于密钥轮换后淘汰旧密钥。
+    ///
+    /// 值已使用当前密钥加密时不做任何写入，返回是否发生了重新加密。
+    pub fn reencrypt(&mut self, key: &str) -> Result<bool, Error> {
+        let current = self.keys.key_id();
+        match self.db.get_with(key, |record| record.get(1).copied())? {
+            Some(Some(key_id)) if key_id != current => {}
+            _ => return Ok(false),
+        }
+        match self.get(key)? {
+            Some(value) => self.set(key, &value).map(|_| true),
+            None => Ok(false),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use alloc::vec::Vec;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+
+use super::KVDB;
+
+/// 数据库中某个键的视图，用于"不存在则写入默认值"等操作。
+///
+/// 通过 `KVDB::entry()` 获取，用法与 `std::collections::HashMap::entry` 类似。
+///
+/// # 示例
+///
+/// ```
+/// # use flashdb_rs::KVDB;
+/// # let dir = tempfile::tempdir()?;
+/// # let mut db = KVDB::new_file("entry_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+/// // 首次启动时写入默认值，之后每次启动计数加一
+/// let boot_count = db
+///     .entry("boot_count")?
+///     .and_modify(|v| {
+///         let count = u32::from_le_bytes(v[..].try_into().unwrap()) + 1;
+///         *v = count.to_le_bytes().to_vec();
+///     })?
+///     .or_insert(&0u32.to_le_bytes())?;
+/// assert_eq!(boot_count, 0u32.to_le_bytes());
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct Entry<'a, S: NorFlash> {
+    db: &'a mut KVDB<S>,
+    key: &'a str,
+    value: Option<Vec<u8>>,
+}
+
+impl<S: NorFlash> KVDB<S> {
+    /// 获取键对应的 `Entry`，会立即读取键的当前值。
+    pub fn entry<'a>(&'a mut self, key: &'a str) -> Result<Entry<'a, S>, Error> {
+        let value = self.get(key)?;
+        Ok(Entry { db: self, key, value })
+    }
+}
+
+impl<'a, S: NorFlash> Entry<'a, S> {
+    /// 键名
+    pub fn key(&self) -> &str {
+        self.key
+    }
+
+    /// 键的当前值，不存在时为 `None`
+    pub fn get(&self) -> Option<&[u8]> {
+        self.value.as_deref()
+    }
+
+    /// 键是否已存在
+    pub fn is_occupied(&self) -> bool {
+        self.value.is_some()
+    }
+
+    /// 如果键已存在，使用 `f` 修改其值并立即写回数据库。
+    pub fn and_modify(mut self, f: impl FnOnce(&mut Vec<u8>)) -> Result<Self, Error> {
+        if let Some(value) = self.value.as_mut() {
+            f(value);
+            self.db.set(self.key, value)?;
+        }
+        Ok(self)
+    }
+
+    /// 如果键不存在，写入 `default`，返回键最终的值。
+    pub fn or_insert(self, default: &[u8]) -> Result<Vec<u8>, Error> {
+        self.or_insert_with(|| default.to_vec())
+    }
+
+    /// 如果键不存在，写入 `f` 的返回值，返回键最终的值。
+    ///
+    /// `f` 只在键不存在时才会被调用。
+    pub fn or_insert_with(self, f: impl FnOnce() -> Vec<u8>) -> Result<Vec<u8>, Error> {
+        match self.value {
+            Some(value) => Ok(value),
+            None => {
+                let value = f();
+                self.db.set(self.key, &value)?;
+                Ok(value)
+            }
+        }
+    }
+}
@@ -0,0 +1,111 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+
+use super::KVDB;
+
+/// 被拦截的写操作
+#[derive(Debug, Clone, Copy)]
+pub enum WriteOp<'a> {
+    /// `set()` 写入键值对
+    Set { key: &'a str, value: &'a [u8] },
+    /// `delete()` 删除键
+    Delete { key: &'a str },
+    /// `reset()` 恢复默认键值对
+    Reset,
+    /// `clear()` 清空数据库
+    Clear,
+}
+
+impl<'a> WriteOp<'a> {
+    /// 操作涉及的键，`Reset` 与 `Clear` 涉及所有键，返回 `None`。
+    pub fn key(&self) -> Option<&'a str> {
+        match *self {
+            WriteOp::Set { key, .. } | WriteOp::Delete { key } => Some(key),
+            WriteOp::Reset | WriteOp::Clear => None,
+        }
+    }
+}
+
+/// 写入前后的回调
+#[cfg(feature = "alloc")]
+pub(super) struct WriteHooks {
+    pre: Box<dyn FnMut(&WriteOp) -> Result<(), Error> + Send>,
+    post: Box<dyn FnMut(&WriteOp) + Send>,
+}
+
+impl<S: NorFlash> KVDB<S> {
+    /// 注册写入拦截回调。
+    ///
+    /// `pre` 在每次 `set()`、`delete()`、`reset()`、`clear()` 执行之前被调用，
+    /// 返回 `Err` 时写入被取消，该错误原样返回给调用方 (通常使用 `Error::WriteProtected`)。
+    /// `post` 只在写入成功后被调用，可用于审计或通知。
+    ///
+    /// 基于 `KVDB` 的包装器 (`CachedKVDB`、`Entry`、加密视图等) 最终都经过这些方法，因此同样受到拦截。
+    /// 回调中不能访问数据库本身。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{Error, KVDB, WriteOp};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = KVDB::new_file("hooks_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+    /// db.set("factory.serial", b"SN-0001")?;
+    ///
+    /// // 出厂数据只读
+    /// db.set_write_hooks(
+    ///     |op| match op.key() {
+    ///         Some(key) if key.starts_with("factory.") => Err(Error::WriteProtected),
+    ///         None => Err(Error::WriteProtected),
+    ///         _ => Ok(()),
+    ///     },
+    ///     |op| println!("written: {:?}", op.key()),
+    /// );
+    /// assert!(matches!(db.set("factory.serial", b"forged"), Err(Error::WriteProtected)));
+    /// assert!(matches!(db.clear(), Err(Error::WriteProtected)));
+    /// db.set("wifi.ssid", b"office")?;
+    /// assert_eq!(db.get("factory.serial")?.unwrap(), b"SN-0001");
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn set_write_hooks(
+        &mut self,
+        pre: impl FnMut(&WriteOp) -> Result<(), Error> + Send + 'static,
+        post: impl FnMut(&WriteOp) + Send + 'static,
+    ) {
+        self.write_hooks = Some(WriteHooks {
+            pre: Box::new(pre),
+            post: Box::new(post),
+        });
+    }
+
+    /// 移除写入拦截回调。
+    #[cfg(feature = "alloc")]
+    pub fn clear_write_hooks(&mut self) {
+        self.write_hooks = None;
+    }
+
+    /// 内部方法：在写入回调之间执行写操作
+    pub(super) fn intercept<T>(
+        &mut self,
+        op: WriteOp<'_>,
+        write: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        self.check_initialized()?;
+        #[cfg(feature = "alloc")]
+        if let Some(hooks) = self.write_hooks.as_mut() {
+            (hooks.pre)(&op)?;
+        }
+        let result = write(self)?;
+        #[cfg(feature = "alloc")]
+        if let Some(hooks) = self.write_hooks.as_mut() {
+            (hooks.post)(&op);
+        }
+        #[cfg(not(feature = "alloc"))]
+        let _ = op;
+        Ok(result)
+    }
+}
@@ -0,0 +1,162 @@
+//! 内存中的 KV 索引 (键名哈希 -> Flash 地址)。
+//!
+//! 每个键只占用 8 字节内存：键名的 CRC32 与 KV 在 Flash 中的起始地址，按哈希排序存放。
+//! 查找时通过二分查找得到地址，再校验该地址处的 KV 头部与名称，
+//! 地址失效 (被更新、删除或被垃圾回收移动) 或发生哈希冲突时回退到常规查找并更新索引。
+
+use alloc::vec::Vec;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{fdb_calc_crc32, Error};
+
+use super::layout::Layout;
+use super::{KVEntry, KVStatus, KVDB};
+
+/// 索引项：(键名哈希, KV 起始地址)
+type Slot = (u32, u32);
+
+/// 按键名哈希排序的 KV 索引
+pub(super) struct Index {
+    slots: Vec<Slot>,
+    capacity: usize,
+}
+
+impl Index {
+    fn new(capacity: usize) -> Self {
+        Self {
+            slots: Vec::new(),
+            capacity,
+        }
+    }
+
+    fn hash(key: &[u8]) -> u32 {
+        unsafe { fdb_calc_crc32(0, key.as_ptr() as *const _, key.len()) }
+    }
+
+    fn get(&self, key: &[u8]) -> Option<u32> {
+        let hash = Self::hash(key);
+        self.slots
+            .binary_search_by_key(&hash, |slot| slot.0)
+            .ok()
+            .map(|i| self.slots[i].1)
+    }
+
+    fn insert(&mut self, key: &[u8], addr: u32) {
+        let hash = Self::hash(key);
+        match self.slots.binary_search_by_key(&hash, |slot| slot.0) {
+            Ok(i) => self.slots[i].1 = addr,
+            // 容量已满时不再索引新的键，这些键使用常规查找
+            Err(_) if self.slots.len() >= self.capacity => {}
+            Err(i) => self.slots.insert(i, (hash, addr)),
+        }
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        let hash = Self::hash(key);
+        if let Ok(i) = self.slots.binary_search_by_key(&hash, |slot| slot.0) {
+            self.slots.remove(i);
+        }
+    }
+}
+
+impl<S: NorFlash> KVDB<S> {
+    /// 启用或禁用初始化时预取全部 KV 元数据。
+    ///
+    /// 启用后 `init()` 会完整扫描一次数据库，在内存中建立键名到 Flash 地址的索引，
+    /// 之后 `get()` 只需一次二分查找与一次 KV 头部校验即可定位数据，而不必在 Flash 上查找。
+    /// 以更慢的启动和额外的内存 (每个键 8 字节，见 `set_index_capacity()`) 换取稳定的读取延迟，
+    /// 适合键数量较多、对运行时延迟敏感的设备。
+    ///
+    /// 缓存的地址在读取前会校验 KV 头部与名称 (不校验 CRC)，
+    /// 数据被更新或被垃圾回收移动后会自动回退到常规查找并更新索引。
+    ///
+    /// **注意**: 此方法必须在 `init()` 之前调用。
+    pub fn set_prefetch(&mut self, enable: bool) {
+        let capacity = self.index.as_ref().map_or(usize::MAX, |index| index.capacity);
+        self.index = enable.then(|| Index::new(capacity));
+    }
+
+    /// 是否启用了初始化时预取。
+    pub fn prefetch(&self) -> bool {
+        self.index.is_some()
+    }
+
+    /// 限制索引最多包含的键数，索引占用的内存不超过 `max_keys * 8` 字节。
+    ///
+    /// 超出容量的键不会被索引，读取时使用常规查找。同时会启用预取。
+    ///
+    /// **注意**: 此方法必须在 `init()` 之前调用。
+    pub fn set_index_capacity(&mut self, max_keys: usize) {
+        self.index = Some(Index::new(max_keys));
+    }
+
+    /// 当前被索引的键数。
+    pub fn index_len(&self) -> usize {
+        self.index.as_ref().map_or(0, |index| index.slots.len())
+    }
+
+    /// 索引当前占用的内存 (字节)。
+    pub fn index_memory(&self) -> usize {
+        self.index
+            .as_ref()
+            .map_or(0, |index| index.slots.capacity() * core::mem::size_of::<Slot>())
+    }
+
+    /// 内部方法：重新扫描数据库，重建索引
+    pub(super) fn index_rebuild(&mut self) {
+        let mut index = match self.index.take() {
+            Some(index) => Index::new(index.capacity),
+            None => return,
+        };
+        for kv in self.iter().filter(|kv| kv.status() == KVStatus::Write) {
+            let name = &kv.inner.name[..kv.inner.name_len as usize];
+            let name = unsafe { core::slice::from_raw_parts(name.as_ptr() as *const u8, name.len()) };
+            index.insert(name, kv.inner.addr.start);
+        }
+        index.slots.shrink_to_fit();
+        self.index = Some(index);
+    }
+
+    /// 内部方法：通过索引查找仍然有效的 KV 对象
+    pub(super) fn index_get(&mut self, key: &str) -> Result<Option<KVEntry>, Error> {
+        let addr = match self.index.as_ref().and_then(|index| index.get(key.as_bytes())) {
+            Some(addr) => addr,
+            None => return Ok(None),
+        };
+
+        let sec_size = self.raw().parent.sec_size;
+        let max_size = self.raw().parent.max_size;
+        let mut layout = Layout::new(self.storage_mut(), sec_size, max_size);
+        match layout.load_entry(addr, key.as_bytes())? {
+            Some(kv) => Ok(Some(kv.into())),
+            None => {
+                self.index_remove(key);
+                Ok(None)
+            }
+        }
+    }
+
+    /// 内部方法：记录常规查找的结果
+    pub(super) fn index_insert(&mut self, key: &str, kv: &KVEntry) {
+        if let Some(index) = self.index.as_mut() {
+            if kv.status() == KVStatus::Write {
+                index.insert(key.as_bytes(), kv.inner.addr.start);
+            }
+        }
+    }
+
+    /// 内部方法：键被修改后使其索引失效，下一次读取时重新查找
+    pub(super) fn index_remove(&mut self, key: &str) {
+        if let Some(index) = self.index.as_mut() {
+            index.remove(key.as_bytes());
+        }
+    }
+
+    /// 内部方法：数据库被整体修改后清空索引
+    pub(super) fn index_clear(&mut self) {
+        if let Some(index) = self.index.as_mut() {
+            index.slots.clear();
+        }
+    }
+}
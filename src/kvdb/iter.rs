@@ -1,8 +1,14 @@
 use embedded_storage::nor_flash::NorFlash;
 
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec, vec::Vec};
+
+#[cfg(feature = "alloc")]
+use crate::fdb_blob_make_by;
 use crate::{fdb_kv_iterate, fdb_kv_iterator, Error, RawHandle};
 
-use super::{KVEntry, KVReader, KVDB};
+use super::layout::{EntryInfo, Layout, SectorInfo, SectorStore, SECTOR_HDR_SIZE};
+use super::{KVEntry, KVReader, KVStatusMask, KeyName, KVDB};
 
 pub struct KVDBIterator<'a, S: NorFlash> {
     inner: &'a mut KVDB<S>,    // 数据库实例的可变引用
@@ -32,7 +38,8 @@ impl<'a, S: NorFlash> KVDBIterator<'a, S> {
             return None;
         }
 
-        Some(Ok(KVReader::new(self.inner, self.iterator.curr_kv.into())))
+        let entry = self.entry();
+        Some(Ok(KVReader::new(self.inner, entry)))
     }
 }
 
@@ -49,6 +56,156 @@ impl<'a, S: NorFlash> Iterator for KVDBIterator<'a, S> {
             self.is_done = true;
             return None;
         }
-        return Some(self.iterator.curr_kv.into());
+        return Some(self.entry());
+    }
+}
+
+impl<'a, S: NorFlash> KVDBIterator<'a, S> {
+    /// 内部方法：构造当前 KV 的条目
+    fn entry(&mut self) -> KVEntry {
+        #[allow(unused_mut)]
+        let mut kv: KVEntry = self.iterator.curr_kv.into();
+        #[cfg(feature = "alloc")]
+        self.inner.load_modified(&mut kv);
+        kv
+    }
+}
+
+/// 只返回键名的轻量迭代器。
+///
+/// 适用于“列出所有配置项”等只需要名称的场景，不会构造读取器。
+pub struct KVDBKeyIterator<'a, S: NorFlash> {
+    inner: KVDBIterator<'a, S>,
+}
+
+impl<'a, S: NorFlash> KVDBKeyIterator<'a, S> {
+    pub fn new(inner: &'a mut KVDB<S>) -> Self {
+        Self {
+            inner: KVDBIterator::new(inner),
+        }
+    }
+}
+
+impl<'a, S: NorFlash> Iterator for KVDBKeyIterator<'a, S> {
+    type Item = KeyName;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| KeyName::from(&entry))
+    }
+}
+
+/// 直接返回键名与值的迭代器，由 `KVDB::iter_with_values()` 返回。
+///
+/// 每个值都会被完整读入内存，适合导出全部数据等简单场景；值较大时应使用 `next_reader()` 流式读取。
+/// 不是有效 UTF-8 的键名会被有损转换。
+#[cfg(feature = "alloc")]
+pub struct KVDBValueIterator<'a, S: NorFlash> {
+    inner: KVDBIterator<'a, S>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, S: NorFlash> KVDBValueIterator<'a, S> {
+    pub fn new(inner: &'a mut KVDB<S>) -> Self {
+        Self {
+            inner: KVDBIterator::new(inner),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, S: NorFlash> Iterator for KVDBValueIterator<'a, S> {
+    type Item = Result<(String, Vec<u8>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.inner.next()?;
+        let name = String::from_utf8_lossy(KeyName::from(&entry).as_bytes()).into_owned();
+        let mut value = vec![0u8; entry.value_len()];
+        let mut blob = fdb_blob_make_by(&mut value, &entry, 0);
+        if self.inner.inner.fdb_blob_read(&mut blob) != value.len() {
+            let result = self.inner.inner.user_data.finish(Err(Error::ReadError));
+            return Some(result.map_err(|err| err.with_context(crate::ErrorContext::for_key(crate::Operation::Iterate, &name))));
+        }
+        Some(Ok((name, value)))
+    }
+}
+
+/// 包含已删除、损坏等非生效 KV 的迭代器，由 `KVDB::iter_with_status()` 返回。
+///
+/// 直接解析 Flash 上的数据而不经过 C 库，按地址顺序返回状态属于筛选集合的 KV。
+/// 与 `iter()` 不同，CRC 校验失败的 KV 同样会被返回 (`KVEntry::is_valid()` 为 `false`)，
+/// 头部损坏的 KV 没有名称，值的长度为 0。读取 Flash 失败时返回错误并结束迭代。
+pub struct KVDBStatusIterator<'a, S: NorFlash> {
+    inner: &'a mut KVDB<S>,
+    filter: KVStatusMask,
+    /// 下一个要读取的扇区地址
+    next_sector: u32,
+    /// 当前扇区及其中下一个 KV 的地址
+    cursor: Option<(SectorInfo, u32)>,
+    is_done: bool,
+}
+
+impl<'a, S: NorFlash> KVDBStatusIterator<'a, S> {
+    pub fn new(inner: &'a mut KVDB<S>, filter: KVStatusMask) -> Self {
+        Self {
+            inner,
+            filter,
+            next_sector: 0,
+            cursor: None,
+            is_done: false,
+        }
+    }
+
+    /// 内部方法：读取下一个 KV，不论其状态
+    fn advance(&mut self) -> Result<Option<EntryInfo>, Error> {
+        if !self.inner.initialized {
+            return Err(Error::InitFailed);
+        }
+        let sec_size = self.inner.raw().parent.sec_size;
+        let max_size = self.inner.raw().parent.max_size;
+        let mut layout = Layout::new(&mut *self.inner.storage, sec_size, max_size);
+        loop {
+            if let Some((sector, addr)) = self.cursor {
+                let entry = layout.read_entry(addr)?;
+                self.cursor = layout.next_entry(&sector, &entry)?.map(|next| (sector, next));
+                return Ok(Some(entry));
+            }
+            if self.next_sector >= max_size {
+                return Ok(None);
+            }
+            let sector = layout.read_sector(self.next_sector)?;
+            self.next_sector += sec_size;
+            if sector.header_ok && matches!(sector.store, SectorStore::Using | SectorStore::Full) {
+                self.cursor = Some((sector, sector.addr + SECTOR_HDR_SIZE as u32));
+            }
+        }
+    }
+}
+
+impl<'a, S: NorFlash> Iterator for KVDBStatusIterator<'a, S> {
+    type Item = Result<KVEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.is_done {
+            match self.advance() {
+                Ok(Some(entry)) if self.filter.contains(entry.status) => {
+                    #[allow(unused_mut)]
+                    let mut kv: KVEntry = entry.to_kv().into();
+                    #[cfg(feature = "alloc")]
+                    if entry.header_ok {
+                        self.inner.load_modified(&mut kv);
+                    }
+                    return Some(Ok(kv));
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => self.is_done = true,
+                Err(err) => {
+                    self.is_done = true;
+                    #[cfg(feature = "alloc")]
+                    let err = err.with_context(crate::ErrorContext::new(crate::Operation::Iterate));
+                    return Some(Err(err));
+                }
+            }
+        }
+        None
     }
 }
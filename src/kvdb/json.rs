@@ -0,0 +1,95 @@
+use std::string::String;
+use std::vec::Vec;
+
+use embedded_storage::nor_flash::NorFlash;
+use serde_json::{Map, Value};
+
+use crate::Error;
+
+use super::KVDB;
+
+/// 二进制值在 JSON 中的字段名
+const BASE64_FIELD: &str = "base64";
+
+impl<S: NorFlash> KVDB<S> {
+    /// 将所有键值对导出为 JSON 对象。
+    ///
+    /// 值是可打印的 UTF-8 文本时直接保存为字符串，否则保存为 `{"base64": "..."}`，
+    /// 键按字典序排列，便于人工查看与比较。内存覆盖层中的值不会被导出。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::KVDB;
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = KVDB::new_file("to_json_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+    /// db.set("ssid", b"office")?;
+    /// db.set("channel", &[6])?;
+    /// let json = db.to_json()?;
+    /// assert_eq!(
+    ///     json,
+    ///     "{\n  \"channel\": {\n    \"base64\": \"Bg==\"\n  },\n  \"ssid\": \"office\"\n}"
+    /// );
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn to_json(&mut self) -> Result<String, Error> {
+        let mut map = Map::new();
+        for kv in self.iter_with_values() {
+            let (key, value) = kv?;
+            map.insert(key, encode_value(value));
+        }
+        Ok(serde_json::to_string_pretty(&Value::Object(map))?)
+    }
+
+    /// 从 `to_json()` 格式的 JSON 对象导入键值对，返回写入的键数。
+    ///
+    /// 已有的同名键会被覆盖，JSON 中没有的键保持不变；需要完全替换时应先调用 `clear()`。
+    /// 所有值会在写入前完成解析，格式错误时不会写入任何数据。
+    ///
+    /// # 返回
+    /// - `Err(Error::Json)`: 不是有效的 JSON。
+    /// - `Err(Error::InvalidArgument)`: 顶层不是对象，或某个值既不是字符串也不是 base64 对象。
+    pub fn from_json(&mut self, json: &str) -> Result<usize, Error> {
+        let map = match serde_json::from_str::<Value>(json)? {
+            Value::Object(map) => map,
+            _ => return Err(Error::InvalidArgument),
+        };
+        let entries = map
+            .into_iter()
+            .map(|(key, value)| Ok((key, decode_value(value)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        for (key, value) in &entries {
+            self.set(key, value)?;
+        }
+        Ok(entries.len())
+    }
+}
+
+/// 将值转换为 JSON，可打印的文本保存为字符串
+fn encode_value(value: Vec<u8>) -> Value {
+    match String::from_utf8(value) {
+        Ok(text) if !text.chars().any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t')) => {
+            Value::String(text)
+        }
+        Ok(text) => base64_value(text.as_bytes()),
+        Err(err) => base64_value(err.as_bytes()),
+    }
+}
+
+fn base64_value(bytes: &[u8]) -> Value {
+    let mut map = Map::new();
+    map.insert(String::from(BASE64_FIELD), Value::String(crate::base64::encode(bytes)));
+    Value::Object(map)
+}
+
+/// 从 JSON 中还原值
+fn decode_value(value: Value) -> Result<Vec<u8>, Error> {
+    match value {
+        Value::String(text) => Ok(text.into_bytes()),
+        Value::Object(map) if map.len() == 1 => match map.get(BASE64_FIELD) {
+            Some(Value::String(encoded)) => crate::base64::decode(encoded),
+            _ => Err(Error::InvalidArgument),
+        },
+        _ => Err(Error::InvalidArgument),
+    }
+}
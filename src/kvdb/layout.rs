@@ -0,0 +1,355 @@
+//! KVDB 在 Flash 上的存储格式，与 `fdb_kvdb.c` 中的 `sector_hdr_data` / `kv_hdr_data` 保持一致。
+//!
+//! C 库在读取时会跳过或就地修改损坏的数据，这里只做只读解析，供完整性检查、修复与预取索引的校验使用。
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{
+    fdb_calc_crc32, fdb_kv, fdb_sector_dirty_status_FDB_SECTOR_DIRTY_TRUE, Error, FDB_KV_NAME_MAX, FDB_KV_STATUS_NUM, FDB_SECTOR_DIRTY_STATUS_NUM,
+    FDB_SECTOR_STORE_STATUS_NUM, FDB_WRITE_GRAN,
+};
+
+use crate::utils::{get_status, status_table_size};
+
+use super::{KVStatus, KeyName};
+
+/// 扇区头 magic (`F`, `D`, `B`, `0`)
+const SECTOR_MAGIC_WORD: u32 = 0x30424446;
+/// KV 头 magic (`K`, `V`, `0`, `0`)
+const KV_MAGIC_WORD: u32 = 0x3030564B;
+const SECTOR_NOT_COMBINED: u32 = 0xFFFFFFFF;
+const SECTOR_COMBINED: u32 = 0x00000000;
+const BYTE_ERASED: u8 = 0xFF;
+const BYTE_WRITTEN: u8 = 0x00;
+
+/// 写入粒度 (字节)
+const WG: usize = (FDB_WRITE_GRAN as usize + 7) / 8;
+
+const fn align(size: usize, align: usize) -> usize {
+    (size + align - 1) / align * align
+}
+
+const fn wg_align(size: usize) -> usize {
+    align(size, WG)
+}
+
+// 64 / 128 bit 写入粒度下，C 结构体末尾带有对齐填充
+const SECTOR_HDR_PADDING: usize = if FDB_WRITE_GRAN >= 64 { 4 } else { 0 };
+const KV_HDR_PADDING: usize = match FDB_WRITE_GRAN {
+    64 => 4,
+    128 => 12,
+    _ => 0,
+};
+
+const STORE_STATUS_SIZE: usize = status_table_size(FDB_SECTOR_STORE_STATUS_NUM as usize);
+const DIRTY_STATUS_SIZE: usize = status_table_size(FDB_SECTOR_DIRTY_STATUS_NUM as usize);
+pub(crate) const SECTOR_MAGIC_OFFSET: usize = align(STORE_STATUS_SIZE + DIRTY_STATUS_SIZE, 4);
+const SECTOR_COMBINED_OFFSET: usize = SECTOR_MAGIC_OFFSET + 4;
+const SECTOR_HDR_RAW_SIZE: usize = SECTOR_MAGIC_OFFSET + 12 + SECTOR_HDR_PADDING;
+pub(super) const SECTOR_HDR_SIZE: usize = wg_align(SECTOR_HDR_RAW_SIZE);
+
+const KV_STATUS_SIZE: usize = status_table_size(FDB_KV_STATUS_NUM as usize);
+const KV_MAGIC_OFFSET: usize = align(KV_STATUS_SIZE, 4);
+const KV_LEN_OFFSET: usize = KV_MAGIC_OFFSET + 4;
+const KV_CRC_OFFSET: usize = KV_MAGIC_OFFSET + 8;
+const KV_NAME_LEN_OFFSET: usize = KV_MAGIC_OFFSET + 12;
+const KV_VALUE_LEN_OFFSET: usize = KV_MAGIC_OFFSET + 16;
+const KV_HDR_RAW_SIZE: usize = KV_MAGIC_OFFSET + 20 + KV_HDR_PADDING;
+pub(super) const KV_HDR_SIZE: usize = wg_align(KV_HDR_RAW_SIZE);
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]])
+}
+
+fn crc32(crc: u32, data: &[u8]) -> u32 {
+    unsafe { fdb_calc_crc32(crc, data.as_ptr() as *const _, data.len()) }
+}
+
+/// 扇区存储状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum SectorStore {
+    Unused,
+    Empty,
+    Using,
+    Full,
+}
+
+/// 扇区脏状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum SectorDirty {
+    Unused,
+    False,
+    True,
+    Gc,
+}
+
+/// 解析后的扇区头
+#[derive(Debug, Clone, Copy)]
+pub(super) struct SectorInfo {
+    pub addr: u32,
+    /// magic 与合并标志是否有效
+    pub header_ok: bool,
+    /// 扇区头是否为全擦除状态
+    pub erased: bool,
+    pub store: SectorStore,
+    pub dirty: SectorDirty,
+}
+
+/// 解析后的 KV 头
+#[derive(Debug, Clone, Copy)]
+pub(super) struct EntryInfo {
+    pub addr: u32,
+    /// KV 总长度，头部损坏时与 C 库一致取 `KV_HDR_SIZE`
+    pub len: u32,
+    pub status: KVStatus,
+    /// 长度字段是否有效
+    pub header_ok: bool,
+    pub crc_ok: bool,
+    pub name: Option<KeyName>,
+    /// 值的长度，头部损坏时为 0
+    pub value_len: u32,
+}
+
+impl EntryInfo {
+    /// 转换为 C 库的 KV 对象，头部损坏时名称与值均为空
+    pub fn to_kv(&self) -> fdb_kv {
+        let name = self.name.as_ref().map_or(&[][..], |name| name.as_bytes());
+        let mut kv = fdb_kv {
+            status: self.status as _,
+            crc_is_ok: self.crc_ok,
+            name_len: name.len() as u8,
+            magic: KV_MAGIC_WORD,
+            len: self.len,
+            value_len: self.value_len,
+            ..Default::default()
+        };
+        for (dst, src) in kv.name.iter_mut().zip(name) {
+            *dst = *src as _;
+        }
+        kv.addr.start = self.addr;
+        kv.addr.value = self.addr + (KV_HDR_SIZE + wg_align(name.len())) as u32;
+        kv
+    }
+}
+
+/// 对存储分区的只读访问
+pub(super) struct Layout<'a, S: NorFlash> {
+    storage: &'a mut S,
+    sec_size: u32,
+    max_size: u32,
+}
+
+impl<'a, S: NorFlash> Layout<'a, S> {
+    pub fn new(storage: &'a mut S, sec_size: u32, max_size: u32) -> Self {
+        Self {
+            storage,
+            sec_size,
+            max_size,
+        }
+    }
+
+    fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Error> {
+        self.storage.read(addr, buf).map_err(|_| Error::ReadError)
+    }
+
+    /// 与 `_fdb_write_status` 相同：只写入状态表中发生变化的一个写入单元
+    fn write_status(&mut self, addr: u32, status_num: usize, index: usize) -> Result<(), Error> {
+        debug_assert!(index > 0 && index < status_num);
+        let mut unit = [BYTE_ERASED; WG];
+        let byte_index = if FDB_WRITE_GRAN == 1 {
+            unit[0] = 0xFF >> (index % 8);
+            (index - 1) / 8
+        } else {
+            unit[0] = BYTE_WRITTEN;
+            (index - 1) * WG
+        };
+        self.storage
+            .write(addr + byte_index as u32, &unit)
+            .map_err(|_| Error::WriteError)
+    }
+
+    /// 将 KV 标记为 `ERR_HDR`，并将其所在扇区标记为脏，使其空间能被垃圾回收
+    pub fn drop_entry(&mut self, sector: &SectorInfo, addr: u32) -> Result<(), Error> {
+        self.write_status(addr, FDB_KV_STATUS_NUM as usize, KVStatus::ERR_HDR as usize)?;
+        if sector.dirty == SectorDirty::False {
+            self.write_status(
+                sector.addr + STORE_STATUS_SIZE as u32,
+                FDB_SECTOR_DIRTY_STATUS_NUM as usize,
+                fdb_sector_dirty_status_FDB_SECTOR_DIRTY_TRUE as usize,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// 擦除整个扇区，扇区头会在数据库重新加载时由 C 库重新格式化
+    pub fn erase_sector(&mut self, addr: u32) -> Result<(), Error> {
+        self.storage
+            .erase(addr, addr + self.sec_size)
+            .map_err(|_| Error::EraseError)
+    }
+
+    pub fn read_sector(&mut self, addr: u32) -> Result<SectorInfo, Error> {
+        let mut hdr = [0u8; SECTOR_HDR_RAW_SIZE];
+        self.read(addr, &mut hdr)?;
+
+        let magic = read_u32(&hdr, SECTOR_MAGIC_OFFSET);
+        let combined = read_u32(&hdr, SECTOR_COMBINED_OFFSET);
+        let header_ok =
+            magic == SECTOR_MAGIC_WORD && (combined == SECTOR_NOT_COMBINED || combined == SECTOR_COMBINED);
+        let (store, dirty) = if header_ok {
+            let store = get_status(&hdr[..STORE_STATUS_SIZE], FDB_SECTOR_STORE_STATUS_NUM as usize);
+            let dirty = get_status(
+                &hdr[STORE_STATUS_SIZE..STORE_STATUS_SIZE + DIRTY_STATUS_SIZE],
+                FDB_SECTOR_DIRTY_STATUS_NUM as usize,
+            );
+            (
+                [SectorStore::Unused, SectorStore::Empty, SectorStore::Using, SectorStore::Full][store],
+                [SectorDirty::Unused, SectorDirty::False, SectorDirty::True, SectorDirty::Gc][dirty],
+            )
+        } else {
+            (SectorStore::Unused, SectorDirty::Unused)
+        };
+
+        Ok(SectorInfo {
+            addr,
+            header_ok,
+            erased: hdr.iter().all(|b| *b == BYTE_ERASED),
+            store,
+            dirty,
+        })
+    }
+
+    /// 与 `read_kv` 相同的校验逻辑，但不会修改 Flash
+    pub fn read_entry(&mut self, addr: u32) -> Result<EntryInfo, Error> {
+        let mut hdr = [0u8; KV_HDR_RAW_SIZE];
+        self.read(addr, &mut hdr)?;
+
+        let status = KVStatus::from(get_status(&hdr[..KV_STATUS_SIZE], FDB_KV_STATUS_NUM as usize) as u32);
+        let len = read_u32(&hdr, KV_LEN_OFFSET);
+        if len == u32::MAX
+            || len > self.max_size
+            || (len as usize) < KV_HDR_SIZE
+            || addr as u64 + len as u64 > self.max_size as u64
+        {
+            return Ok(EntryInfo {
+                addr,
+                len: KV_HDR_SIZE as u32,
+                status,
+                header_ok: false,
+                crc_ok: false,
+                name: None,
+                value_len: 0,
+            });
+        }
+
+        // CRC32 覆盖 name_len (按 4 字节计算以兼容 V1.x)、value_len、名称与值
+        let mut crc = crc32(0, &hdr[KV_NAME_LEN_OFFSET..KV_NAME_LEN_OFFSET + 4]);
+        crc = crc32(crc, &hdr[KV_VALUE_LEN_OFFSET..KV_VALUE_LEN_OFFSET + 4]);
+        let data_len = len as usize - KV_HDR_SIZE;
+        let mut buf = [0u8; 32];
+        let mut done = 0;
+        while done < data_len {
+            let size = (data_len - done).min(buf.len());
+            self.read(addr + (KV_HDR_SIZE + done) as u32, &mut buf[..size])?;
+            crc = crc32(crc, &buf[..size]);
+            done += size;
+        }
+
+        let name_len = (hdr[KV_NAME_LEN_OFFSET] as usize)
+            .min(FDB_KV_NAME_MAX as usize)
+            .min(data_len);
+        let mut name = [0u8; FDB_KV_NAME_MAX as usize];
+        self.read(addr + KV_HDR_SIZE as u32, &mut name[..name_len])?;
+
+        // CRC 错误时值长度不可信，限制在 KV 的范围内
+        let value_len = (read_u32(&hdr, KV_VALUE_LEN_OFFSET) as usize)
+            .min(data_len.saturating_sub(wg_align(name_len)));
+
+        Ok(EntryInfo {
+            addr,
+            len,
+            status,
+            header_ok: true,
+            crc_ok: crc == read_u32(&hdr, KV_CRC_OFFSET),
+            name: Some(KeyName::from_bytes(&name[..name_len])),
+            value_len: value_len as u32,
+        })
+    }
+
+    /// 读取 `addr` 处名为 `name` 的有效 KV，不校验 CRC。
+    ///
+    /// 该位置已不是该 KV (已被更新、删除或被垃圾回收移动) 时返回 `None`。
+    #[cfg(feature = "kv-index")]
+    pub fn load_entry(&mut self, addr: u32, name: &[u8]) -> Result<Option<fdb_kv>, Error> {
+        if name.len() > FDB_KV_NAME_MAX as usize || addr as u64 + KV_HDR_SIZE as u64 > self.max_size as u64 {
+            return Ok(None);
+        }
+        let mut hdr = [0u8; KV_HDR_RAW_SIZE];
+        self.read(addr, &mut hdr)?;
+
+        let status = get_status(&hdr[..KV_STATUS_SIZE], FDB_KV_STATUS_NUM as usize);
+        let len = read_u32(&hdr, KV_LEN_OFFSET);
+        let value_len = read_u32(&hdr, KV_VALUE_LEN_OFFSET);
+        if read_u32(&hdr, KV_MAGIC_OFFSET) != KV_MAGIC_WORD
+            || KVStatus::from(status as u32) != KVStatus::Write
+            || hdr[KV_NAME_LEN_OFFSET] as usize != name.len()
+            || len == u32::MAX
+            || addr as u64 + len as u64 > self.max_size as u64
+        {
+            return Ok(None);
+        }
+        let mut stored = [0u8; FDB_KV_NAME_MAX as usize];
+        self.read(addr + KV_HDR_SIZE as u32, &mut stored[..name.len()])?;
+        if &stored[..name.len()] != name {
+            return Ok(None);
+        }
+
+        let mut kv = fdb_kv {
+            status: status as _,
+            crc_is_ok: true,
+            name_len: name.len() as u8,
+            magic: KV_MAGIC_WORD,
+            len,
+            value_len,
+            ..Default::default()
+        };
+        for (dst, src) in kv.name.iter_mut().zip(name) {
+            *dst = *src as _;
+        }
+        kv.addr.start = addr;
+        kv.addr.value = addr + (KV_HDR_SIZE + wg_align(name.len())) as u32;
+        Ok(Some(kv))
+    }
+
+    /// 与 `get_next_kv_addr` 相同：查找扇区内下一个 KV 的地址
+    pub fn next_entry(&mut self, sector: &SectorInfo, prev: &EntryInfo) -> Result<Option<u32>, Error> {
+        if sector.store == SectorStore::Empty || prev.len == 0 {
+            return Ok(None);
+        }
+        let start = if prev.crc_ok {
+            prev.addr + prev.len
+        } else {
+            // CRC 错误时无法信任长度字段，逐个对齐单元向后查找
+            prev.addr + wg_align(1) as u32
+        };
+        let end = sector.addr + self.sec_size - SECTOR_HDR_SIZE as u32;
+        self.find_magic(start, end)
+    }
+
+    fn find_magic(&mut self, start: u32, end: u32) -> Result<Option<u32>, Error> {
+        let mut buf = [0u8; 32];
+        let mut pos = start;
+        while pos + 4 <= end {
+            let size = ((end - pos) as usize).min(buf.len());
+            self.read(pos, &mut buf[..size])?;
+            for i in 0..=size - 4 {
+                let at = pos + i as u32;
+                if read_u32(&buf, i) == KV_MAGIC_WORD && at >= start + KV_MAGIC_OFFSET as u32 {
+                    return Ok(Some(at - KV_MAGIC_OFFSET as u32));
+                }
+            }
+            pos += (size - 3) as u32;
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,86 @@
+use embedded_storage::nor_flash::NorFlash;
+
+use super::KVDB;
+
+/// KVDB 的写放大与磨损统计，通过 `KVDB::metrics()` 获取。
+///
+/// 统计从创建 `KVDB` 实例 (或上次调用 `reset_metrics()`) 开始，不会保存到 Flash 中。
+/// 需要跨重启累计时，应用可以定期将增量保存到某个键中。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KVDBMetrics {
+    /// 通过 `set()` 写入的键与值的字节数
+    pub payload_bytes: u64,
+    /// 实际写入 Flash 的字节数，包括 KV 头部、状态位与垃圾回收搬移的数据
+    pub flash_bytes_written: u64,
+    /// 擦除的扇区数
+    pub erases: u64,
+    /// 垃圾回收的次数，包括 `compact()` 与 `GcPolicy` 触发的回收
+    pub gc_runs: u64,
+    /// 数据库的扇区总数
+    pub sectors: u32,
+}
+
+impl KVDBMetrics {
+    /// 写放大系数：实际写入 Flash 的字节数与应用写入的字节数之比，尚未写入数据时返回 `None`。
+    pub fn write_amplification(&self) -> Option<f32> {
+        if self.payload_bytes == 0 {
+            return None;
+        }
+        Some(self.flash_bytes_written as f32 / self.payload_bytes as f32)
+    }
+
+    /// 每个扇区的平均擦除次数。
+    ///
+    /// FlashDB 按顺序轮流使用各个扇区，各扇区的擦除次数基本均衡，
+    /// 将此值与 Flash 的标称擦写寿命比较即可估算剩余寿命。
+    pub fn erases_per_sector(&self) -> f32 {
+        if self.sectors == 0 {
+            return 0.0;
+        }
+        self.erases as f32 / self.sectors as f32
+    }
+}
+
+impl<S: NorFlash> KVDB<S> {
+    /// 获取写放大与磨损统计。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::KVDB;
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = KVDB::new_file("metrics_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+    /// db.reset_metrics();
+    /// for i in 0..500u32 {
+    ///     db.set("counter", &i.to_le_bytes())?;
+    /// }
+    /// let metrics = db.metrics();
+    /// assert_eq!(metrics.payload_bytes, 500 * (7 + 4));
+    /// assert!(metrics.write_amplification().unwrap() > 1.0);
+    /// assert!(metrics.gc_runs > 0);
+    ///
+    /// // 按每天同样的写入量，估算 10 万次擦写寿命的 Flash 可以使用多少天
+    /// let days = 100_000.0 / metrics.erases_per_sector();
+    /// # assert!(days > 0.0);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn metrics(&self) -> KVDBMetrics {
+        let counters = &self.user_data.counters;
+        KVDBMetrics {
+            payload_bytes: counters.payload_bytes,
+            flash_bytes_written: counters.bytes_written,
+            erases: counters.erases,
+            gc_runs: counters.gc_runs,
+            sectors: self.storage.capacity() as u32 / self.sec_size,
+        }
+    }
+
+    /// 清零写放大与磨损统计。
+    pub fn reset_metrics(&mut self) {
+        let counters = &mut self.user_data.counters;
+        counters.payload_bytes = 0;
+        counters.bytes_written = 0;
+        counters.erases = 0;
+        counters.gc_runs = 0;
+    }
+}
@@ -0,0 +1,88 @@
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+
+use super::KVDB;
+
+/// 保存数据布局版本号的键
+pub const SCHEMA_VERSION_KEY: &str = "__schema_ver";
+
+/// 将数据从上一个版本升级到 `version` 的迁移步骤
+pub struct Migration<S: NorFlash> {
+    /// 迁移完成后的版本号，从 1 开始
+    pub version: u32,
+    /// 执行迁移
+    pub apply: fn(&mut KVDB<S>) -> Result<(), Error>,
+}
+
+impl<S: NorFlash> KVDB<S> {
+    /// 当前的数据布局版本号，从未迁移过的数据库为 0。
+    pub fn schema_version(&mut self) -> Result<u32, Error> {
+        Ok(self.get_u32(SCHEMA_VERSION_KEY)?.unwrap_or(0))
+    }
+
+    /// 按顺序执行尚未完成的迁移，返回迁移后的版本号。
+    ///
+    /// 应在 `init()` 之后、读取业务数据之前调用。版本号保存在 `SCHEMA_VERSION_KEY` 中，
+    /// 每完成一步立即写入，因此迁移过程中掉电后，下次调用会从中断的那一步继续。
+    /// 中断的那一步会被重新执行，所以每个迁移步骤都必须可以安全地重复执行
+    /// (例如先写入新键，最后再删除旧键)。
+    ///
+    /// # 返回
+    /// - `Err(Error::InvalidArgument)`: `migrations` 的版本号没有严格递增。
+    /// - `Err(Error::UnsupportedVersion)`: 数据的版本高于最新的迁移 (例如固件被回退)。
+    /// - 迁移步骤返回的错误，此时版本号停留在上一步。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{Migration, KVDB, StdStorage};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = KVDB::new_file("migrate_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+    /// const MIGRATIONS: &[Migration<StdStorage>] = &[
+    ///     // v1: 将 "ssid" 改名为 "wifi.ssid"
+    ///     Migration {
+    ///         version: 1,
+    ///         apply: |db| {
+    ///             if let Some(ssid) = db.get("ssid")? {
+    ///                 db.set("wifi.ssid", &ssid)?;
+    ///                 db.delete("ssid")?;
+    ///             }
+    ///             Ok(())
+    ///         },
+    ///     },
+    ///     // v2: 新增带默认值的配置项
+    ///     Migration {
+    ///         version: 2,
+    ///         apply: |db| db.set_nx("wifi.retries", &[3]).map(|_| ()),
+    ///     },
+    /// ];
+    ///
+    /// db.set("ssid", b"office")?;
+    /// assert_eq!(db.migrate(MIGRATIONS)?, 2);
+    /// assert_eq!(db.get("wifi.ssid")?.unwrap(), b"office");
+    /// // 已完成的迁移不会再次执行
+    /// assert_eq!(db.migrate(MIGRATIONS)?, 2);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn migrate(&mut self, migrations: &[Migration<S>]) -> Result<u32, Error> {
+        if migrations.windows(2).any(|w| w[0].version >= w[1].version)
+            || migrations.first().is_some_and(|m| m.version == 0)
+        {
+            return Err(Error::InvalidArgument);
+        }
+        let mut version = self.schema_version()?;
+        if version > migrations.last().map_or(0, |m| m.version) {
+            return Err(Error::UnsupportedVersion);
+        }
+        for migration in migrations {
+            if migration.version <= version {
+                continue;
+            }
+            (migration.apply)(self)?;
+            self.set_u32(SCHEMA_VERSION_KEY, migration.version)?;
+            version = migration.version;
+        }
+        Ok(version)
+    }
+}
@@ -4,29 +4,117 @@ mod types;
 pub use types::*;
 mod iter;
 pub use iter::*;
+mod typed;
+mod borrowed;
+mod overlay;
+pub use overlay::*;
+pub(crate) mod layout;
+mod verify;
+pub use verify::*;
+mod repair;
+pub use repair::*;
+#[cfg(feature = "alloc")]
+mod defaults;
+#[cfg(feature = "alloc")]
+pub use defaults::*;
+#[cfg(feature = "alloc")]
+mod dedup;
+#[cfg(feature = "alloc")]
+pub use dedup::*;
+#[cfg(feature = "kv-index")]
+mod index;
+#[cfg(feature = "alloc")]
+mod bloom;
+#[cfg(feature = "alloc")]
+mod entry;
+#[cfg(feature = "alloc")]
+pub use entry::*;
+#[cfg(feature = "alloc")]
+mod cache;
+#[cfg(feature = "alloc")]
+pub use cache::*;
+#[cfg(feature = "alloc")]
+mod encrypted;
+#[cfg(feature = "alloc")]
+pub use encrypted::*;
+mod hooks;
+pub use hooks::*;
+mod migrate;
+pub use migrate::*;
+mod compact;
+#[cfg(feature = "json")]
+mod json;
+mod metrics;
+pub use metrics::*;
+#[cfg(feature = "alloc")]
+mod object;
+#[cfg(feature = "alloc")]
+pub use object::*;
+#[cfg(feature = "alloc")]
+mod modified;
+#[cfg(feature = "alloc")]
+pub use modified::*;
 
 use crate::{
     fdb_blob, fdb_blob__bindgen_ty_1, fdb_blob_read, fdb_db_t, fdb_kv, fdb_kv_del, fdb_kv_get_obj,
     fdb_kv_set_blob, fdb_kv_set_default, fdb_kvdb, fdb_kvdb_control_read, fdb_kvdb_control_write,
     fdb_kvdb_deinit, fdb_kvdb_init, Error, FlashDispatch, RawHandle, FDB_KVDB_CTRL_SET_MAX_SIZE,
-    FDB_KVDB_CTRL_SET_NOT_FORMAT, FDB_KVDB_CTRL_SET_SEC_SIZE, FDB_KV_NAME_MAX,
+    FDB_KVDB_CTRL_SET_NOT_FORMAT, FDB_KVDB_CTRL_SET_SEC_SIZE, FDB_KV_CACHE_TABLE_SIZE, FDB_KV_NAME_MAX,
+    FDB_SECTOR_CACHE_TABLE_SIZE,
 };
+use crate::lock::DbLock;
+use crate::timeout::{OpTimeout, Timer};
 use core::{
-    ffi::{c_char, c_void, CStr},
+    cell::UnsafeCell,
+    ffi::{c_char, CStr},
     marker::PhantomData,
+    mem::ManuallyDrop,
+    time::Duration,
 };
 
 use embedded_storage::nor_flash::NorFlash;
 
 pub struct KVDB<S: NorFlash> {
-    inner: fdb_kvdb,
-    storage: S,
+    // C 库在 `&self` 方法中同样会修改数据库结构体
+    inner: UnsafeCell<fdb_kvdb>,
+    // 由 `Drop` 释放，或被 `close()` 取出
+    storage: ManuallyDrop<S>,
     user_data: FlashDispatch,
     key_buf: [u8; FDB_KV_NAME_MAX as usize + 1],
     #[cfg(feature = "log")]
     name_buf: [u8; FDB_KV_NAME_MAX as usize + 1],
+    // 运行时构建的默认键值对，C 库在 reset 时仍会引用其中的数据
+    #[cfg(feature = "alloc")]
+    owned_default_kvs: Option<DefaultKvs>,
+    // `get_with` 复用的读取缓冲区
+    #[cfg(feature = "alloc")]
+    scratch: alloc::vec::Vec<u8>,
+    // 初始化时预取的 KV 索引，`None` 表示未启用
+    #[cfg(feature = "kv-index")]
+    index: Option<index::Index>,
+    // 加速查找不存在的键，`None` 表示未启用
+    #[cfg(feature = "alloc")]
+    bloom: Option<bloom::Bloom>,
+    // 值末尾附加修改时间，`None` 表示未启用
+    #[cfg(feature = "alloc")]
+    modified: Option<modified::Modified>,
+    // 写入拦截回调
+    #[cfg(feature = "alloc")]
+    write_hooks: Option<hooks::WriteHooks>,
+    overlay: Option<Overlay>,
+    // `get()` 是否与 `get_checked()` 一样报告损坏的值
+    verify_reads: bool,
+    // 在 C 库之外额外触发垃圾回收的条件
+    gc_policy: GcPolicy,
+    // 数据库的扇区大小，默认为 `S::ERASE_SIZE`
+    sec_size: u32,
     initialized: bool,
-    // 由于fdb_kvdb内部引用了 storage 和 name_buf 所以结构体无法移动，否则会导致悬空指针
+    // 存储已被 `close()` 取出
+    closed: bool,
+    // 初始化时登记的 Flash 区域
+    #[cfg(feature = "registry")]
+    region: Option<crate::registry::Region>,
+    // C 库通过裸指针访问存储与调度器，不能在线程间共享
     _marker: PhantomData<*const ()>, // for !Send and !Sync
 }
 
@@ -34,8 +122,7 @@ pub struct KVDB<S: NorFlash> {
 impl KVDB<crate::storage::StdStorage> {
     /// 在 `std` 环境下，创建一个基于文件的 KVDB 实例。
     ///
-    /// 此函数返回一个 `Box<KVDB<StdStorage>>`，以确保数据库实例在内存中的地址是稳定的，
-    /// 防止因栈帧移动导致传递给 C 库的内部指针失效。
+    /// 此函数返回一个 `Box<KVDB<StdStorage>>`，避免在栈上放置较大的结构体。
     ///
     /// # 参数
     /// - `name`: 数据库名称
@@ -50,16 +137,48 @@ impl KVDB<crate::storage::StdStorage> {
         max_size: u32,
         default_kvs: Option<&'static crate::fdb_default_kv>,
     ) -> Result<Box<Self>, Error> {
-        let storage = crate::storage::StdStorage::new(
-            path,
+        Self::new_file_with_strategy(
             name,
+            path,
             sec_size,
             max_size,
+            default_kvs,
             crate::storage::FileStrategy::Multi,
-        )?;
+        )
+    }
+
+    /// 与 `new_file()` 相同，但可以选择文件存储策略。
+    ///
+    /// `FileStrategy::Single` 将整个数据库保存在 `path` 目录下的 `<name>.fdb` 单个文件中，
+    /// 便于作为一个文件备份或复制；`FileStrategy::Multi` 与 `new_file()` 相同，每个扇区一个文件。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::KVDB;
+    /// # use flashdb_rs::storage::FileStrategy;
+    /// # let dir = tempfile::tempdir()?;
+    /// let path = dir.path().to_str().unwrap();
+    /// let mut db = KVDB::new_file_with_strategy("settings", path, 4096, 4 * 4096, None, FileStrategy::Single)?;
+    /// db.set("volume", b"7")?;
+    /// assert!(dir.path().join("settings.fdb").is_file());
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn new_file_with_strategy(
+        name: &str,
+        path: &str,
+        sec_size: u32,
+        max_size: u32,
+        default_kvs: Option<&'static crate::fdb_default_kv>,
+        strategy: crate::storage::FileStrategy,
+    ) -> Result<Box<Self>, Error> {
+        let storage = crate::storage::StdStorage::in_dir(path, name, sec_size, max_size, strategy)?;
 
         let mut db = Box::new(KVDB::new(storage));
         db.set_name(name)?;
+        db.set_sec_size(sec_size)?;
+        #[cfg(feature = "registry")]
+        db.set_region(crate::registry::Region::for_path(&std::path::Path::new(path).join(name)))?;
         db.init(default_kvs)?;
         Ok(db)
     }
@@ -69,23 +188,113 @@ impl<S: NorFlash> KVDB<S> {
     /// 创建一个未初始化的 KVDB 实例。
     ///
     /// 在 `no_std` 环境下，这是创建数据库实例的主要方式。
-    /// 实例创建后，必须调用 `.init()` 方法才能使用。
+    /// 实例创建后，必须调用 `.init()` 方法才能使用。初始化后的实例同样可以被移动，
+    /// 例如从函数中返回或放入集合。
     ///
     /// # 参数
     /// * `storage` - 一个实现了 `embedded_storage::nor_flash::NorFlash` trait 的存储后端实例。
     pub fn new(storage: S) -> Self {
         Self {
-            inner: Default::default(),
-            storage,
+            inner: UnsafeCell::new(Default::default()),
+            storage: ManuallyDrop::new(storage),
             user_data: FlashDispatch::new::<S>(),
             key_buf: [0; FDB_KV_NAME_MAX as usize + 1],
             #[cfg(feature = "log")]
             name_buf: [0; FDB_KV_NAME_MAX as usize + 1],
+            #[cfg(feature = "alloc")]
+            owned_default_kvs: None,
+            #[cfg(feature = "alloc")]
+            scratch: alloc::vec::Vec::new(),
+            #[cfg(feature = "kv-index")]
+            index: None,
+            #[cfg(feature = "alloc")]
+            bloom: None,
+            #[cfg(feature = "alloc")]
+            modified: None,
+            #[cfg(feature = "alloc")]
+            write_hooks: None,
+            overlay: None,
+            verify_reads: false,
+            gc_policy: GcPolicy::new(),
+            sec_size: S::ERASE_SIZE as u32,
             initialized: false,
+            closed: false,
+            #[cfg(feature = "registry")]
+            region: None,
             _marker: PhantomData,
         }
     }
 
+    /// 获取底层存储后端的只读引用。
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// 数据库是否已经成功初始化。
+    pub fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+
+    /// 设置数据库占用的 Flash 区域，`init()` 时若与其他数据库重叠则返回 `Error::Busy`。
+    ///
+    /// 详见 [`crate::registry`]。**注意**: 此方法必须在 `init()` 之前调用。
+    #[cfg(feature = "registry")]
+    pub fn set_region(&mut self, region: crate::registry::Region) -> Result<(), Error> {
+        if self.initialized {
+            return Err(Error::InvalidArgument);
+        }
+        self.region = Some(region);
+        Ok(())
+    }
+
+    /// 释放数据库，之后可以修改扇区大小等配置并再次调用 `init()`。
+    ///
+    /// 释放后读写数据返回 `Error::NotInitialized`。存储外部被修改后，也可以通过
+    /// `deinit()` 与 `init()` 重新加载。未初始化时什么也不做。
+    pub fn deinit(&mut self) {
+        self.deinit_raw();
+    }
+
+    /// 关闭数据库并取回存储后端，例如在 OTA 升级时复用同一块 Flash。
+    ///
+    /// 未初始化的数据库直接返回存储后端。
+    pub fn close(mut self) -> S {
+        self.deinit_raw();
+        self.closed = true;
+        // SAFETY: 设置 `closed` 后 `Drop` 不再释放存储，其余字段照常释放
+        unsafe { ManuallyDrop::take(&mut self.storage) }
+    }
+
+    /// 内部方法：通知 C 库释放数据库，未初始化时什么也不做
+    fn deinit_raw(&mut self) {
+        if self.initialized {
+            #[cfg(feature = "registry")]
+            if let Some(region) = self.region {
+                crate::registry::release(region);
+            }
+            unsafe { fdb_kvdb_deinit(self.handle()) };
+            self.initialized = false;
+        }
+    }
+
+    /// 内部方法：未初始化时返回 `Error::NotInitialized`
+    pub(crate) fn check_initialized(&self) -> Result<(), Error> {
+        if !self.initialized {
+            return Err(Error::NotInitialized);
+        }
+        Ok(())
+    }
+
+    /// 内部方法：读取 C 数据库结构体，不能跨越 C 库调用持有。
+    pub(crate) fn raw(&self) -> &fdb_kvdb {
+        unsafe { &*self.inner.get() }
+    }
+
+    /// 内部方法：获取底层存储后端的可变引用，调用方需独占借用数据库。
+    pub(crate) fn storage_mut(&mut self) -> &mut S {
+        &mut *self.storage
+    }
+
     /// 设置数据库名称，仅用于日志输出。
     ///
     /// **注意**: 此方法必须在 `init()` 之前调用。
@@ -101,6 +310,47 @@ impl<S: NorFlash> KVDB<S> {
         Ok(())
     }
 
+    /// 开启或关闭该数据库的日志输出，可随时调用，默认开启。
+    ///
+    /// 只影响本数据库的 C 库日志，其他数据库与全局的 `log` 配置不受影响。
+    #[cfg(feature = "log")]
+    pub fn set_logging(&mut self, enabled: bool) {
+        self.user_data.logging = enabled;
+    }
+
+    /// 设置该数据库输出日志的最低严重程度，可随时调用，默认输出全部日志。
+    ///
+    /// C 库的日志按内容分为 `Error`、`Warn` 与 `Info` 三级，例如设为 `Level::Warn` 时只输出错误与警告。
+    #[cfg(feature = "log")]
+    pub fn set_log_level(&mut self, level: log::Level) {
+        self.user_data.log_level = level;
+    }
+
+    /// 设置数据库的扇区大小，必须是 `S::ERASE_SIZE` 的非零整数倍，否则返回 `Error::InvalidArgument`。
+    ///
+    /// 默认等于 `S::ERASE_SIZE`。较大的扇区可以容纳更大的KV，但垃圾回收时需要搬移更多数据。
+    ///
+    /// **注意**: 此方法必须在 `init()` 之前调用，且同一份数据必须始终以相同的扇区大小打开。
+    pub fn set_sec_size(&mut self, sec_size: u32) -> Result<(), Error> {
+        if self.initialized || sec_size == 0 || sec_size as usize % S::ERASE_SIZE != 0 {
+            return Err(Error::InvalidArgument);
+        }
+        self.sec_size = sec_size;
+        Ok(())
+    }
+
+    /// 安装或移除内存覆盖层。
+    ///
+    /// 安装后，`get()` 及类型化读取方法会优先返回覆盖层中的值。
+    pub fn set_overlay(&mut self, overlay: Option<Overlay>) {
+        self.overlay = overlay;
+    }
+
+    /// 获取当前覆盖层的可变引用，用于在运行时调整覆盖值。
+    pub fn overlay_mut(&mut self) -> Option<&mut Overlay> {
+        self.overlay.as_mut()
+    }
+
     /// 设置数据库为不可格式化模式。
     ///
     /// 在此模式下，如果数据库初始化时发现头部信息损坏，将返回错误而不是自动格式化。
@@ -109,6 +359,84 @@ impl<S: NorFlash> KVDB<S> {
         self.fdb_kvdb_control_write(FDB_KVDB_CTRL_SET_NOT_FORMAT, enable);
     }
 
+    /// 设置 `get()` 是否与 `get_checked()` 相同，在值损坏时返回 `Error::Corrupted`。
+    pub fn set_verify_reads(&mut self, enable: bool) {
+        self.verify_reads = enable;
+    }
+
+    /// `get()` 是否校验值的完整性。
+    pub fn verify_reads(&self) -> bool {
+        self.verify_reads
+    }
+
+    /// 设置 C 库要求同步写入时是否调用存储的 `flush()`。
+    ///
+    /// 用于 `BufferedStorage` 等带有写入缓冲的存储，详见 [`crate::buffered`]。
+    pub fn set_flush_on_sync(&mut self, enable: bool)
+    where
+        S: crate::buffered::FlushStorage,
+    {
+        self.user_data.vtable.sync = if enable { Some(crate::vtable_sync::<S>) } else { None };
+    }
+
+    /// 为每次 Flash 操作设置超时，超时后数据库操作返回 `Error::Timeout`。
+    ///
+    /// 详见 [`crate::timeout`]。
+    pub fn set_timeout(&mut self, timer: &'static dyn Timer, timeout: Duration) {
+        self.user_data.timeout = Some(OpTimeout::new(timer, timeout));
+    }
+
+    /// 取消 Flash 操作超时。
+    pub fn clear_timeout(&mut self) {
+        self.user_data.timeout = None;
+    }
+
+    /// 安装数据库锁，C 库的每个操作都在锁内执行。详见 [`crate::lock`]。
+    pub fn set_lock(&mut self, lock: &'static dyn DbLock) {
+        self.user_data.install_lock(&mut self.inner.get_mut().parent, Some(lock));
+    }
+
+    /// 移除数据库锁。
+    pub fn clear_lock(&mut self) {
+        self.user_data.install_lock(&mut self.inner.get_mut().parent, None);
+    }
+
+    /// 读取 Flash 上数据的格式版本，详见 [`crate::format`]。
+    ///
+    /// 存储区尚未格式化时返回 `None`。初始化前后均可调用。
+    pub fn format_version(&mut self) -> Result<Option<u8>, Error> {
+        crate::format::KVDB_MAGIC.probe(&mut *self.storage, self.sec_size)
+    }
+
+    /// 获取数据库的有效配置，初始化前后均可调用。
+    ///
+    /// 缓存表占用的内存包含在 `KVDB` 结构体中：每个 KV 缓存项 8 字节，
+    /// 每个扇区缓存项 24~32 字节。只要其中一个缓存表大小为 0，C 库就不会使用缓存。
+    pub fn config(&self) -> KVDBConfig {
+        let cached = FDB_KV_CACHE_TABLE_SIZE > 0 && FDB_SECTOR_CACHE_TABLE_SIZE > 0;
+        KVDBConfig {
+            sec_size: self.sec_size,
+            max_size: self.storage.capacity() as u32,
+            kv_cache_table_size: if cached { FDB_KV_CACHE_TABLE_SIZE as usize } else { 0 },
+            sector_cache_table_size: if cached { FDB_SECTOR_CACHE_TABLE_SIZE as usize } else { 0 },
+            gc_policy: self.gc_policy,
+        }
+    }
+
+    /// 注册内部事件回调，详见 [`crate::events`]。
+    ///
+    /// KVDB 会产生 `GcStarted`、`SectorRetired` 以及完整性检查发现的 `CrcError` 事件。
+    #[cfg(feature = "alloc")]
+    pub fn set_event_handler(&mut self, handler: impl FnMut(&crate::events::Event) + Send + 'static) {
+        self.user_data.events = Some(crate::events::EventHook::new(crate::events::Source::Kvdb, handler));
+    }
+
+    /// 移除内部事件回调。
+    #[cfg(feature = "alloc")]
+    pub fn clear_event_handler(&mut self) {
+        self.user_data.events = None;
+    }
+
     /// 检查数据库是否处于不可格式化模式。
     pub fn not_formatable(&mut self) -> bool {
         let mut enable = false;
@@ -127,11 +455,59 @@ impl<S: NorFlash> KVDB<S> {
         &mut self,
         default_kvs: Option<&'static crate::fdb_default_kv>,
     ) -> Result<(), Error> {
+        let default_kvs_ptr = match default_kvs {
+            Some(kvs) => kvs as *const _ as *mut _,
+            None => core::ptr::null_mut(),
+        };
+        self.init_raw(default_kvs_ptr)
+    }
+
+    /// 使用运行时构建的默认键值对初始化数据库。
+    ///
+    /// `defaults` 的所有权将转移给数据库，在数据库的整个生命周期内保持有效，
+    /// 因此后续的 `reset()` 同样会恢复到这些值。
+    ///
+    /// # 示例
+    ///
+    /// ```no_run
+    /// # use flashdb_rs::{DefaultKvs, KVDB, StdStorage};
+    /// # fn demo(db: &mut KVDB<StdStorage>) -> Result<(), flashdb_rs::Error> {
+    /// let serial = String::from("SN-0001");
+    /// db.init_with_defaults(DefaultKvs::new().add("serial", serial.as_bytes()))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn init_with_defaults(&mut self, defaults: DefaultKvs) -> Result<(), Error> {
         if self.initialized {
             return Ok(());
         }
-        // 从 NorFlash trait 获取扇区大小和总容量
-        let sec_size = S::ERASE_SIZE as u32;
+        defaults.validate()?;
+        let default_kvs_ptr = self.owned_default_kvs.insert(defaults).as_raw();
+        self.init_raw(default_kvs_ptr)
+    }
+
+    /// 内部方法：执行实际的初始化流程。
+    fn init_raw(&mut self, default_kvs_ptr: *mut crate::fdb_default_kv) -> Result<(), Error> {
+        let result = self.init_db(default_kvs_ptr);
+        #[cfg(feature = "alloc")]
+        let result = result.map_err(|err| err.with_context(crate::ErrorContext::new(crate::Operation::Init)));
+        result
+    }
+
+    /// 内部方法：检查格式版本并调用 C 库初始化数据库
+    fn init_db(&mut self, default_kvs_ptr: *mut crate::fdb_default_kv) -> Result<(), Error> {
+        if self.initialized {
+            return Ok(());
+        }
+        // 拒绝其他格式版本的数据，避免被 C 库当作未格式化的扇区擦除
+        crate::format::KVDB_MAGIC.check(&mut *self.storage, self.sec_size)?;
+        #[cfg(feature = "registry")]
+        if let Some(region) = self.region {
+            crate::registry::claim(region)?;
+        }
+        // 扇区大小默认取自 NorFlash trait 的擦除大小
+        let sec_size = self.sec_size;
         let max_size = self.storage.capacity() as u32;
 
         unsafe {
@@ -142,33 +518,35 @@ impl<S: NorFlash> KVDB<S> {
             self.fdb_kvdb_control_write(FDB_KVDB_CTRL_SET_SEC_SIZE, sec_size);
             self.fdb_kvdb_control_write(FDB_KVDB_CTRL_SET_MAX_SIZE, max_size);
 
-            // 只有这里获取才不会导致悬空指针
-            self.user_data.instance = &mut self.storage as *mut _ as *mut c_void;
+            let user_data = self.user_data.bind(&mut self.inner.get_mut().parent, &mut *self.storage);
 
             #[cfg(feature = "log")]
             let name = self.name_buf.as_ptr() as *const c_char;
             #[cfg(not(feature = "log"))]
             let name = b"\0".as_ptr() as *const c_char;
 
-            let default_kvs_ptr = match default_kvs {
-                Some(kvs) => kvs as *const _ as *mut _,
-                None => core::ptr::null_mut(),
-            };
-
             let result = fdb_kvdb_init(
                 db_ptr as *mut fdb_kvdb,
                 name,
                 core::ptr::null(),
                 default_kvs_ptr,
-                &mut self.user_data as *mut _ as *mut c_void,
+                user_data,
             );
 
+            #[cfg(feature = "registry")]
+            if let (false, Some(region)) = (result == crate::fdb_err_t_FDB_NO_ERR, self.region) {
+                crate::registry::release(region);
+            }
             if result == crate::fdb_err_t_FDB_NO_ERR {
                 self.initialized = true;
-                Ok(())
-            } else {
-                Err(result.into())
+                #[cfg(feature = "kv-index")]
+                self.index_rebuild();
+                #[cfg(feature = "alloc")]
+                self.bloom_rebuild();
+                #[cfg(feature = "alloc")]
+                self.modified_rebuild();
             }
+            self.user_data.finish(Error::convert(result))
         }
     }
 }
@@ -188,6 +566,17 @@ impl<S: NorFlash> KVDB<S> {
     /// 内部方法：获取键对应的KV对象
     #[inline]
     fn fdb_kv_get_obj(&mut self, key: &str) -> Result<Option<KVEntry>, Error> {
+        self.check_initialized()?;
+        #[cfg(feature = "alloc")]
+        if self.bloom_rejects(key) {
+            return Ok(None);
+        }
+        #[cfg(feature = "kv-index")]
+        if let Some(mut kv) = self.index_get(key)? {
+            #[cfg(feature = "alloc")]
+            self.load_modified(&mut kv);
+            return Ok(Some(kv));
+        }
         let handle = self.handle();
         let cstr_key = self.to_cstr(key)?;
         let mut kv_obj = unsafe { core::mem::zeroed::<fdb_kv>() };
@@ -195,17 +584,39 @@ impl<S: NorFlash> KVDB<S> {
         if unsafe { fdb_kv_get_obj(handle, cstr_key.as_ptr(), &mut kv_obj) }
             == core::ptr::null_mut()
         {
-            return Ok(None);
+            // 读取超时也会导致查找失败，不能当作键不存在
+            return self.user_data.finish(Ok(None));
         };
-        Ok(Some(kv_obj.into()))
+        #[allow(unused_mut)]
+        let mut kv: KVEntry = kv_obj.into();
+        #[cfg(feature = "kv-index")]
+        self.index_insert(key, &kv);
+        #[cfg(feature = "alloc")]
+        self.load_modified(&mut kv);
+        self.user_data.finish(Ok(Some(kv)))
+    }
+
+    /// 内部方法：查询内存覆盖层
+    #[inline]
+    fn overlay_get(&self, key: &str) -> Option<&[u8]> {
+        self.overlay.as_ref().and_then(|overlay| overlay.get(key))
     }
 
     /// 内部方法：通过blob写入键值对
     #[inline]
     fn fdb_blob_write(&mut self, key: &str, blob: &mut fdb_blob) -> Result<(), Error> {
         let handle = self.handle();
+        #[cfg(feature = "alloc")]
+        self.user_data.arm_events();
+        self.user_data.begin_write();
         let cstr_key = self.to_cstr(key)?;
-        Error::convert(unsafe { fdb_kv_set_blob(handle, cstr_key.as_ptr(), blob) })
+        let result = Error::convert(unsafe { fdb_kv_set_blob(handle, cstr_key.as_ptr(), blob) });
+        #[cfg(feature = "kv-index")]
+        self.index_remove(key);
+        #[cfg(feature = "alloc")]
+        self.bloom_insert(key);
+        self.user_data.finish(result)?;
+        self.gc_maybe()
     }
 
     /// 内部方法：从blob读取数据
@@ -237,8 +648,54 @@ impl<S: NorFlash> KVDB<S> {
     /// - `key`: 键
     /// - `value`: 值，一个字节切片。
     pub fn set(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
-        let mut blob = fdb_blob_make_write(value); // 创建写入用的blob结构
-        self.fdb_blob_write(key, &mut blob)
+        let result = self.store(key, value);
+        #[cfg(feature = "alloc")]
+        let result = result.map_err(|err| err.with_context(crate::ErrorContext::for_key(crate::Operation::Set, key)));
+        result
+    }
+
+    /// 内部方法：写入键值对，不附加错误上下文
+    fn store(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.intercept(WriteOp::Set { key, value }, |db| {
+            #[cfg(feature = "alloc")]
+            let record = db.modified_record(value);
+            #[cfg(feature = "alloc")]
+            let mut blob = fdb_blob_make_write(record.as_deref().unwrap_or(value));
+            #[cfg(not(feature = "alloc"))]
+            let mut blob = fdb_blob_make_write(value); // 创建写入用的blob结构
+            db.fdb_blob_write(key, &mut blob)?;
+            db.user_data.counters.payload_bytes += (key.len() + value.len()) as u64;
+            Ok(())
+        })
+    }
+
+    /// 仅当键不存在时才写入，适用于设备 ID、校准数据等只应写入一次的数据。
+    ///
+    /// 只检查 Flash 中的数据，内存覆盖层 (`Overlay`) 中的键不影响写入。
+    ///
+    /// # 返回
+    /// - `Ok(true)`: 键不存在，已写入。
+    /// - `Ok(false)`: 键已存在，未做任何修改。
+    pub fn set_nx(&mut self, key: &str, value: &[u8]) -> Result<bool, Error> {
+        let result = match self.readable_kv(key) {
+            Ok(Some(_)) => Ok(false),
+            Ok(None) => self.store(key, value).map(|()| true),
+            Err(err) => Err(err),
+        };
+        #[cfg(feature = "alloc")]
+        let result = result.map_err(|err| err.with_context(crate::ErrorContext::for_key(crate::Operation::SetNx, key)));
+        result
+    }
+
+    /// 检查键是否存在，不读取其值。
+    pub fn contains_key(&mut self, key: &str) -> Result<bool, Error> {
+        if self.overlay_get(key).is_some() {
+            return Ok(true);
+        }
+        let result = self.readable_kv(key).map(|kv| kv.is_some());
+        #[cfg(feature = "alloc")]
+        let result = result.map_err(|err| err.with_context(crate::ErrorContext::for_key(crate::Operation::Get, key)));
+        result
     }
 
     /// 根据键获取其值。
@@ -250,12 +707,88 @@ impl<S: NorFlash> KVDB<S> {
     /// - `Ok(Some(Vec<u8>))`: 找到键，返回其值。
     /// - `Ok(None)`: 未找到键。
     /// - `Err(Error)`: 读取时发生错误。
+    ///
+    /// CRC 校验失败的值会被 C 库跳过，因此损坏的键与不存在的键一样返回 `Ok(None)`，
+    /// 需要区分两者时使用 `get_checked()` 或 `set_verify_reads(true)`。
     #[cfg(feature = "alloc")]
     pub fn get(&mut self, key: &str) -> Result<Option<alloc::vec::Vec<u8>>, Error> {
+        let checked = self.verify_reads;
+        self.get_value(key, checked)
+            .map_err(|err| err.with_context(crate::ErrorContext::for_key(crate::Operation::Get, key)))
+    }
+
+    /// 根据键获取其值，并校验值的完整性。
+    ///
+    /// 与 `get()` 不同，当键的值 CRC 校验失败时返回 `Err(Error::Corrupted)` 而不是 `Ok(None)`。
+    /// 为此在未找到键时需要额外扫描一次数据库，读取不存在的键的开销约为 `get()` 的两倍。
+    #[cfg(feature = "alloc")]
+    pub fn get_checked(&mut self, key: &str) -> Result<Option<alloc::vec::Vec<u8>>, Error> {
+        self.get_value(key, true)
+            .map_err(|err| err.with_context(crate::ErrorContext::for_key(crate::Operation::GetChecked, key)))
+    }
+
+    /// 与 `get_checked()` 相同，但在值损坏或读取失败时切换到存储的另一份副本并重新读取。
+    ///
+    /// 适用于 [`MirroredStorage`](crate::mirror::MirroredStorage) 等冗余存储，切换后的副本会继续用于之后的读取。
+    #[cfg(feature = "alloc")]
+    pub fn get_redundant(&mut self, key: &str) -> Result<Option<alloc::vec::Vec<u8>>, Error>
+    where
+        S: crate::mirror::FailoverStorage,
+    {
+        let result = match self.get_value(key, true) {
+            Err(Error::Corrupted | Error::ReadError | Error::Storage(_)) if self.storage.fail_over() => self.get_value(key, true),
+            result => result,
+        };
+        result.map_err(|err| err.with_context(crate::ErrorContext::for_key(crate::Operation::GetChecked, key)))
+    }
+
+    /// 根据键同时获取其元数据与值，只需查找一次。
+    ///
+    /// 返回的 `KVEntry` 包含状态、长度、CRC 校验结果等信息。内存覆盖层中的值没有对应的 KV，
+    /// 因此此方法只读取 Flash 中的数据。与 `get()` 相同，启用 `set_verify_reads()` 后会校验值的完整性。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{KVDB, KVStatus};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = KVDB::new_file("get_entry_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+    /// db.set("name", b"flashdb")?;
+    /// let (entry, value) = db.get_entry("name")?.unwrap();
+    /// assert_eq!(entry.status(), KVStatus::Write);
+    /// assert_eq!(entry.value_len(), value.len());
+    /// assert_eq!(value, b"flashdb");
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn get_entry(&mut self, key: &str) -> Result<Option<(KVEntry, alloc::vec::Vec<u8>)>, Error> {
+        let checked = self.verify_reads;
+        self.get_stored(key, checked)
+            .map_err(|err| err.with_context(crate::ErrorContext::for_key(crate::Operation::Get, key)))
+    }
+
+    #[cfg(feature = "alloc")]
+    fn get_value(&mut self, key: &str, checked: bool) -> Result<Option<alloc::vec::Vec<u8>>, Error> {
+        if let Some(value) = self.overlay_get(key) {
+            return Ok(Some(value.into()));
+        }
+        Ok(self.get_stored(key, checked)?.map(|(_, value)| value))
+    }
+
+    /// 内部方法：读取 Flash 中键的元数据与值
+    #[cfg(feature = "alloc")]
+    fn get_stored(
+        &mut self,
+        key: &str,
+        checked: bool,
+    ) -> Result<Option<(KVEntry, alloc::vec::Vec<u8>)>, Error> {
         match self.fdb_kv_get_obj(key)? {
             Some(kv) => match kv.status() {
                 // 处理预写入或已写入状态的值
                 KVStatus::PRE_WRITE | KVStatus::Write => {
+                    if checked && !self.entry_crc_ok(&kv)? {
+                        return Err(Error::Corrupted);
+                    }
                     // 初始化缓冲区
 
                     let mut data: alloc::vec::Vec<u8> =
@@ -263,28 +796,73 @@ impl<S: NorFlash> KVDB<S> {
                     unsafe { data.set_len(kv.value_len()) }; // 预分配缓冲区大小
 
                     // 创建读取用的blob结构
-                    let mut blob = fdb_blob_make_by(&mut data, &kv.into(), 0);
+                    let mut blob = fdb_blob_make_by(&mut data, &kv, 0);
 
                     // 读取数据
                     let read_len = self.fdb_blob_read(&mut blob);
                     if read_len != data.len() {
-                        return Err(Error::ReadError);
+                        return self.user_data.finish(Err(Error::ReadError));
                     }
-                    Ok(Some(data))
+                    Ok(Some((kv, data)))
                 }
                 _ => Ok(None), // 其他状态(如已删除)返回None
             },
+            None if checked && self.has_corrupted(key)? => Err(Error::Corrupted),
             None => return Ok(None), // 键不存在
         }
     }
 
+    /// 读取-修改-写入：以键的当前值调用 `f`，并将 `f` 写入 `out` 的内容作为新值保存。
+    ///
+    /// `old` 为 `None` 表示键不存在。整个过程在一次调用内完成，
+    /// 共享数据库的包装器在持有锁期间调用即可保证更新不会被其他写入打断。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::KVDB;
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = KVDB::new_file("update_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+    /// db.update("log", |old, out| {
+    ///     out.extend_from_slice(old.unwrap_or_default());
+    ///     out.extend_from_slice(b"boot;");
+    /// })?;
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn update(
+        &mut self,
+        key: &str,
+        f: impl FnOnce(Option<&[u8]>, &mut alloc::vec::Vec<u8>),
+    ) -> Result<(), Error> {
+        let mut out = alloc::vec::Vec::new();
+        let mut f = Some(f);
+        self.get_with(key, |old| (f.take().unwrap())(Some(old), &mut out))?;
+        if let Some(f) = f {
+            f(None, &mut out);
+        }
+        self.set(key, &out)
+    }
+
     /// 删除一个键值对。
     ///
     /// 这是一个逻辑删除，数据占用的空间将在未来的垃圾回收 (GC) 过程中被回收。
     pub fn delete(&mut self, key: &str) -> Result<(), Error> {
-        let handle = self.handle();
-        let cstr_key = self.to_cstr(key)?;
-        Error::convert(unsafe { fdb_kv_del(handle, cstr_key.as_ptr()) })
+        let result = self.intercept(WriteOp::Delete { key }, |db| {
+            let handle = db.handle();
+            #[cfg(feature = "alloc")]
+            db.user_data.arm_events();
+            db.user_data.begin_write();
+            let cstr_key = db.to_cstr(key)?;
+            let result = Error::convert(unsafe { fdb_kv_del(handle, cstr_key.as_ptr()) });
+            #[cfg(feature = "kv-index")]
+            db.index_remove(key);
+            db.user_data.finish(result)?;
+            db.gc_maybe()
+        });
+        #[cfg(feature = "alloc")]
+        let result = result.map_err(|err| err.with_context(crate::ErrorContext::for_key(crate::Operation::Delete, key)));
+        result
     }
 
     /// 重置数据库到其默认状态。
@@ -294,43 +872,138 @@ impl<S: NorFlash> KVDB<S> {
     ///
     /// **警告**: 此操作会删除所有当前数据。
     pub fn reset(&mut self) -> Result<(), Error> {
-        Error::convert(unsafe { fdb_kv_set_default(self.handle()) })
+        self.intercept(WriteOp::Reset, |db| {
+            let result = Error::convert(unsafe { fdb_kv_set_default(db.handle()) });
+            #[cfg(feature = "kv-index")]
+            db.index_clear();
+            #[cfg(feature = "alloc")]
+            db.bloom_rebuild();
+            db.user_data.finish(result)
+        })
+    }
+
+    /// 清空数据库。
+    ///
+    /// 与 `reset()` 不同，此操作会格式化整个 KV 区域且**不会**写入默认键值对，
+    /// 适用于必须擦除全部用户数据的恢复出厂流程。默认键值对配置本身会被保留，
+    /// 之后调用 `reset()` 仍可恢复默认值。
+    ///
+    /// **警告**: 此操作会删除所有当前数据。
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.intercept(WriteOp::Clear, |db| {
+            // 临时移除默认键值对，格式化完成后恢复
+            let default_kvs = core::mem::take(&mut db.inner.get_mut().default_kvs);
+            let result = unsafe { fdb_kv_set_default(db.handle()) };
+            db.inner.get_mut().default_kvs = default_kvs;
+            #[cfg(feature = "kv-index")]
+            db.index_clear();
+            #[cfg(feature = "alloc")]
+            db.bloom_rebuild();
+            db.user_data.finish(Error::convert(result))
+        })
     }
 
     /// 获取一个用于流式读取键值的 `KVReader`。
     ///
     /// 这对于读取大尺寸的值非常有用，可以避免一次性将整个值加载到内存中。
     pub fn get_reader<'a>(&'_ mut self, key: &str) -> Result<KVReader<'_, S>, Error> {
+        self.check_initialized()?;
         let handle = self.handle();
         let cstr_key = self.to_cstr(key)?;
         let mut kv_obj = unsafe { core::mem::zeroed::<fdb_kv>() };
         if unsafe { fdb_kv_get_obj(handle, cstr_key.as_ptr(), &mut kv_obj) }
             == core::ptr::null_mut()
         {
-            return Err(Error::ReadError);
+            let err = Error::ReadError;
+            #[cfg(feature = "alloc")]
+            let err = err.with_context(crate::ErrorContext::for_key(crate::Operation::Get, key));
+            return Err(err);
         };
 
-        Ok(KVReader::new(self, kv_obj.into()))
+        #[allow(unused_mut)]
+        let mut kv: KVEntry = kv_obj.into();
+        #[cfg(feature = "alloc")]
+        self.load_modified(&mut kv);
+        Ok(KVReader::new(self, kv))
     }
 
     pub fn iter(&mut self) -> KVDBIterator<'_, S> {
         KVDBIterator::new(self)
     }
+
+    /// 获取包含已删除、损坏等 KV 的迭代器，只返回状态属于 `filter` 的 KV。
+    ///
+    /// 适用于诊断工具分析空间被哪些数据占用，详见 [`KVDBStatusIterator`]。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{KVDB, KVStatus, KVStatusMask};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = KVDB::new_file("status_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+    /// db.set("temp", b"1")?;
+    /// db.set("temp", b"2")?;
+    /// db.delete("temp")?;
+    ///
+    /// let mut garbage = 0;
+    /// for kv in db.iter_with_status(KVStatusMask::GARBAGE) {
+    ///     let kv = kv?;
+    ///     assert_eq!(kv.name(), Some("temp"));
+    ///     garbage += kv.value_len();
+    /// }
+    /// assert_eq!(garbage, 2);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn iter_with_status(&mut self, filter: KVStatusMask) -> KVDBStatusIterator<'_, S> {
+        KVDBStatusIterator::new(self, filter)
+    }
+
+    /// 获取直接返回 `(键名, 值)` 的迭代器，无需处理 `next_reader()` 的借用。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::KVDB;
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = KVDB::new_file("values_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+    /// db.set("ssid", b"office")?;
+    /// db.set("channel", &[6])?;
+    /// for kv in db.iter_with_values() {
+    ///     let (key, value) = kv?;
+    ///     println!("{key} = {value:02x?}");
+    /// }
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn iter_with_values(&mut self) -> KVDBValueIterator<'_, S> {
+        KVDBValueIterator::new(self)
+    }
+
+    /// 获取只返回键名的迭代器。
+    pub fn iter_keys(&mut self) -> KVDBKeyIterator<'_, S> {
+        KVDBKeyIterator::new(self)
+    }
 }
 
 impl<S: NorFlash> RawHandle for KVDB<S> {
     type Handle = *mut fdb_kvdb;
     fn handle(&self) -> Self::Handle {
-        &self.inner as *const _ as *mut _
+        let db: Self::Handle = self.inner.get();
+        // 名称只用于日志，每次调用 C 库前指向当前位置的缓冲区；结构体位于 `UnsafeCell` 中，可以经 `&self` 修改
+        #[cfg(feature = "log")]
+        if self.initialized {
+            unsafe { (*db).parent.name = self.name_buf.as_ptr() as *const c_char };
+        }
+        db
     }
 }
 
 impl<S: NorFlash> Drop for KVDB<S> {
     fn drop(&mut self) {
-        if self.initialized {
-            unsafe {
-                fdb_kvdb_deinit(self.handle());
-            }
+        self.deinit_raw();
+        if !self.closed {
+            // SAFETY: 存储尚未被 `close()` 取出，此后不会再被访问
+            unsafe { ManuallyDrop::drop(&mut self.storage) };
         }
     }
 }
@@ -347,17 +1020,4 @@ pub fn fdb_blob_make_by(v: &mut [u8], kv: &KVEntry, offset: usize) -> fdb_blob {
     }
 }
 
-pub fn fdb_blob_make_read(v: &mut [u8]) -> fdb_blob {
-    fdb_blob {
-        buf: v.as_mut_ptr() as *mut _,
-        size: v.len(),
-        saved: unsafe { core::mem::zeroed() },
-    }
-}
-pub fn fdb_blob_make_write(v: &[u8]) -> fdb_blob {
-    fdb_blob {
-        buf: v.as_ptr() as *const _ as *mut _,
-        size: v.len(),
-        saved: unsafe { core::mem::zeroed() },
-    }
-}
+pub use crate::utils::{fdb_blob_make_read, fdb_blob_make_write};
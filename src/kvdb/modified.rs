@@ -0,0 +1,137 @@
+use alloc::vec::Vec;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::timeout::Timer;
+use crate::{fdb_blob_make_by, Error};
+
+use super::{KVEntry, KVStatus, KVDB};
+
+/// 修改时间尾部的标记
+const MODIFIED_MAGIC: [u8; 4] = [0xA5, b'M', b'T', 0x01];
+/// 修改时间尾部：8 字节小端序时间戳 + 标记
+const MODIFIED_TRAILER_LEN: usize = 8 + MODIFIED_MAGIC.len();
+
+/// 修改时间的来源
+#[derive(Clone, Copy)]
+pub enum ModifiedStamp {
+    /// 使用时钟的当前值，应当跨重启保持递增，例如 RTC 提供的 Unix 时间
+    Clock(&'static dyn Timer),
+    /// 使用递增的序号，`init()` 时从已有数据中的最大值继续，不依赖时钟
+    Sequence,
+}
+
+/// 修改时间记录的配置与状态
+pub(super) struct Modified {
+    stamp: ModifiedStamp,
+    /// 下一个序号
+    next_seq: u64,
+}
+
+impl<S: NorFlash> KVDB<S> {
+    /// 启用或禁用记录每个键的最后修改时间。
+    ///
+    /// 启用后，每次 `set()` 都会在值的末尾附加 12 字节的修改时间，读取时自动去除，
+    /// 通过 `KVEntry::modified_at()` 或 `KVDB::modified_at()` 获取。
+    /// 同步程序可以据此找出上次上传之后被修改过的键。
+    ///
+    /// 启用前写入的值、默认键值对以及内存覆盖层中的值没有修改时间。
+    /// 已删除的键无法被遍历到，需要同步删除操作时应由应用自行记录。
+    ///
+    /// **注意**: 记录了修改时间的数据库在之后的每次启动中都应启用此选项，
+    /// 否则读到的值会包含修改时间尾部。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{KVDB, KVStatus, ModifiedStamp};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = KVDB::new_file("modified_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+    /// db.set_track_modified(Some(ModifiedStamp::Sequence));
+    /// db.set("ssid", b"office")?;
+    /// let uploaded = db.modified_at("ssid")?.unwrap();
+    ///
+    /// db.set("password", b"secret")?;
+    /// let changed: Vec<_> = db
+    ///     .iter()
+    ///     .filter(|kv| kv.status() == KVStatus::Write && kv.modified_at() > Some(uploaded))
+    ///     .filter_map(|kv| kv.name().map(String::from))
+    ///     .collect();
+    /// assert_eq!(changed, ["password"]);
+    /// assert_eq!(db.get("password")?.as_deref(), Some(&b"secret"[..]));
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn set_track_modified(&mut self, stamp: Option<ModifiedStamp>) {
+        self.modified = stamp.map(|stamp| Modified { stamp, next_seq: 1 });
+        if self.initialized {
+            self.modified_rebuild();
+        }
+    }
+
+    /// 是否记录了修改时间。
+    pub fn track_modified(&self) -> bool {
+        self.modified.is_some()
+    }
+
+    /// 获取键的最后修改时间，键不存在或没有记录修改时间时返回 `None`。
+    pub fn modified_at(&mut self, key: &str) -> Result<Option<u64>, Error> {
+        Ok(self.readable_kv(key)?.and_then(|kv| kv.modified_at()))
+    }
+
+    /// 内部方法：初始化后从已有数据中恢复下一个序号
+    pub(super) fn modified_rebuild(&mut self) {
+        if !matches!(self.modified, Some(Modified { stamp: ModifiedStamp::Sequence, .. })) {
+            return;
+        }
+        let last = self
+            .iter()
+            .filter(|kv| kv.status() == KVStatus::Write)
+            .filter_map(|kv| kv.modified_at())
+            .max()
+            .unwrap_or(0);
+        if let Some(modified) = self.modified.as_mut() {
+            modified.next_seq = last + 1;
+        }
+    }
+
+    /// 内部方法：为待写入的值附加修改时间，未启用时返回 `None`
+    pub(super) fn modified_record(&mut self, value: &[u8]) -> Option<Vec<u8>> {
+        let modified = self.modified.as_mut()?;
+        let stamp = match modified.stamp {
+            ModifiedStamp::Clock(clock) => clock.now_ms(),
+            ModifiedStamp::Sequence => {
+                modified.next_seq += 1;
+                modified.next_seq - 1
+            }
+        };
+        let mut record = Vec::with_capacity(value.len() + MODIFIED_TRAILER_LEN);
+        record.extend_from_slice(value);
+        record.extend_from_slice(&stamp.to_le_bytes());
+        record.extend_from_slice(&MODIFIED_MAGIC);
+        Some(record)
+    }
+
+    /// 内部方法：读取 KV 的修改时间，并将尾部从值的长度中去除
+    pub(super) fn load_modified(&mut self, kv: &mut KVEntry) {
+        if self.modified.is_none() || kv.value_len() < MODIFIED_TRAILER_LEN {
+            return;
+        }
+        let offset = kv.value_len() - MODIFIED_TRAILER_LEN;
+        let mut trailer = [0u8; MODIFIED_TRAILER_LEN];
+        let mut blob = fdb_blob_make_by(&mut trailer, kv, offset);
+        if self.fdb_blob_read(&mut blob) != MODIFIED_TRAILER_LEN || trailer[8..] != MODIFIED_MAGIC {
+            return;
+        }
+        kv.modified = Some(u64::from_le_bytes(trailer[..8].try_into().unwrap()));
+        kv.inner.value_len -= MODIFIED_TRAILER_LEN as u32;
+    }
+}
+
+impl KVEntry {
+    /// 获取 KV 的最后修改时间，详见 `KVDB::set_track_modified()`。
+    ///
+    /// 值没有记录修改时间时返回 `None`。
+    pub fn modified_at(&self) -> Option<u64> {
+        self.modified
+    }
+}
@@ -0,0 +1,366 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use embedded_io::{Read, Seek, SeekFrom};
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{Error, FDB_KV_NAME_MAX};
+
+use super::layout::{KV_HDR_SIZE, SECTOR_HDR_SIZE};
+use super::KVDB;
+
+/// 清单的标记与格式版本
+const MANIFEST_MAGIC: [u8; 4] = [b'O', b'B', b'J', 1];
+/// 清单：标记 + 块大小 + 对象长度 (小端序)
+const MANIFEST_LEN: usize = MANIFEST_MAGIC.len() + 8;
+
+/// 大对象的清单
+#[derive(Debug, Clone, Copy)]
+struct Manifest {
+    chunk_size: usize,
+    len: usize,
+}
+
+impl Manifest {
+    fn encode(&self) -> [u8; MANIFEST_LEN] {
+        let mut buf = [0u8; MANIFEST_LEN];
+        buf[..4].copy_from_slice(&MANIFEST_MAGIC);
+        buf[4..8].copy_from_slice(&(self.chunk_size as u32).to_le_bytes());
+        buf[8..].copy_from_slice(&(self.len as u32).to_le_bytes());
+        buf
+    }
+
+    fn decode(buf: &[u8]) -> Result<Self, Error> {
+        if buf.len() != MANIFEST_LEN || buf[..4] != MANIFEST_MAGIC {
+            return Err(Error::Corrupted);
+        }
+        let chunk_size = u32::from_le_bytes(buf[4..8].try_into().unwrap()) as usize;
+        let len = u32::from_le_bytes(buf[8..].try_into().unwrap()) as usize;
+        if chunk_size == 0 {
+            return Err(Error::Corrupted);
+        }
+        Ok(Self { chunk_size, len })
+    }
+
+    fn chunks(&self) -> usize {
+        self.len.div_ceil(self.chunk_size)
+    }
+}
+
+/// 第 `index` 个块的键名
+fn chunk_key(key: &str, index: usize) -> String {
+    format!("{}.{}", key, index)
+}
+
+/// 大对象存储。
+///
+/// 通过 `KVDB::objects()` 获取。单个 KV 不能跨扇区保存，值的大小受扇区大小限制；
+/// 大对象存储将值拆分为 `key.0`、`key.1` ... 多个块，并在 `key` 中保存记录总长度与块大小的清单，
+/// 通过流式的读写接口访问，适合保存证书、固件配置文件等较大的数据。
+///
+/// 写入开始时清单会先被删除，全部块写入后才重新写入清单，因此写入中途掉电后对象不存在，
+/// 而不会读到新旧混合的数据。遗留的块会在下一次写入或删除该对象时被清理。
+///
+/// **注意**: 键名与块编号合计不能超过 `FDB_KV_NAME_MAX`，大对象的键不能再通过 `KVDB::get()` 直接读取。
+///
+/// # 示例
+///
+/// ```
+/// # use flashdb_rs::KVDB;
+/// # let dir = tempfile::tempdir()?;
+/// # let mut db = KVDB::new_file("object_doc", dir.path().to_str().unwrap(), 4096, 8 * 4096, None)?;
+/// use embedded_io::{Read, Write};
+///
+/// let cert = vec![0x30u8; 10 * 1024];
+/// let mut objects = db.objects();
+/// let mut writer = objects.writer("cert")?;
+/// for part in cert.chunks(1000) {
+///     writer.write_all(part)?;
+/// }
+/// writer.finish()?;
+///
+/// let mut reader = objects.reader("cert")?.unwrap();
+/// assert_eq!(reader.len(), cert.len());
+/// let mut head = [0u8; 16];
+/// reader.read_exact(&mut head).map_err(|_| flashdb_rs::Error::ReadError)?;
+/// assert_eq!(objects.get("cert")?.unwrap(), cert);
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct ObjectStore<'a, S: NorFlash> {
+    db: &'a mut KVDB<S>,
+    chunk_size: usize,
+}
+
+impl<S: NorFlash> KVDB<S> {
+    /// 获取大对象存储，默认块大小为扇区大小的一半。
+    pub fn objects(&mut self) -> ObjectStore<'_, S> {
+        let chunk_size = self.raw().parent.sec_size as usize / 2;
+        ObjectStore { db: self, chunk_size }
+    }
+}
+
+impl<'a, S: NorFlash> ObjectStore<'a, S> {
+    /// 设置写入时使用的块大小。
+    ///
+    /// 块越大，KV 头部的开销越小，但扇区末尾无法利用的空间越多。
+    /// 已写入的对象按其清单中记录的块大小读取，不受影响。
+    ///
+    /// # 返回
+    /// - `Err(Error::InvalidArgument)`: 块大小为 0，或一个块无法放入单个扇区。
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Result<Self, Error> {
+        let max = (self.db.raw().parent.sec_size as usize)
+            .saturating_sub(SECTOR_HDR_SIZE + KV_HDR_SIZE + FDB_KV_NAME_MAX as usize);
+        if chunk_size == 0 || chunk_size > max {
+            return Err(Error::InvalidArgument);
+        }
+        self.chunk_size = chunk_size;
+        Ok(self)
+    }
+
+    /// 写入时使用的块大小。
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// 开始写入对象，已有的同名对象立即失效。
+    ///
+    /// 必须调用 `ObjectWriter::finish()` 完成写入，否则对象不存在。
+    pub fn writer(&mut self, key: &str) -> Result<ObjectWriter<'_, S>, Error> {
+        if self.db.contains_key(key)? {
+            self.db.delete(key)?;
+        }
+        Ok(ObjectWriter {
+            db: &mut *self.db,
+            key: String::from(key),
+            chunk_size: self.chunk_size,
+            buf: Vec::with_capacity(self.chunk_size),
+            written: 0,
+        })
+    }
+
+    /// 打开对象进行流式读取，对象不存在时返回 `None`。
+    ///
+    /// # 返回
+    /// - `Err(Error::Corrupted)`: `key` 的值不是大对象清单。
+    pub fn reader(&mut self, key: &str) -> Result<Option<ObjectReader<'_, S>>, Error> {
+        let manifest = match self.manifest(key)? {
+            Some(manifest) => manifest,
+            None => return Ok(None),
+        };
+        Ok(Some(ObjectReader {
+            db: &mut *self.db,
+            key: String::from(key),
+            manifest,
+            position: 0,
+        }))
+    }
+
+    /// 一次性写入整个对象。
+    pub fn put(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
+        let mut writer = self.writer(key)?;
+        writer.write_chunks(value)?;
+        writer.finish()
+    }
+
+    /// 一次性读取整个对象。
+    ///
+    /// # 返回
+    /// - `Err(Error::Corrupted)`: 清单无效或块缺失。
+    pub fn get(&mut self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let mut reader = match self.reader(key)? {
+            Some(reader) => reader,
+            None => return Ok(None),
+        };
+        let mut value = alloc::vec![0u8; reader.len()];
+        let mut filled = 0;
+        while filled < value.len() {
+            match reader.read(&mut value[filled..])? {
+                0 => return Err(Error::Corrupted),
+                n => filled += n,
+            }
+        }
+        Ok(Some(value))
+    }
+
+    /// 获取对象的长度，对象不存在时返回 `None`。
+    pub fn len(&mut self, key: &str) -> Result<Option<usize>, Error> {
+        Ok(self.manifest(key)?.map(|manifest| manifest.len))
+    }
+
+    /// 删除对象及其所有块，返回对象是否存在。
+    pub fn remove(&mut self, key: &str) -> Result<bool, Error> {
+        let existed = self.db.contains_key(key)?;
+        if existed {
+            // 先删除清单，中途掉电时不会留下不完整的对象
+            self.db.delete(key)?;
+        }
+        remove_chunks(self.db, key, 0)?;
+        Ok(existed)
+    }
+
+    /// 内部方法：读取对象的清单
+    fn manifest(&mut self, key: &str) -> Result<Option<Manifest>, Error> {
+        self.db.get_with(key, Manifest::decode)?.transpose()
+    }
+}
+
+/// 删除从 `from` 开始的连续块
+fn remove_chunks<S: NorFlash>(db: &mut KVDB<S>, key: &str, from: usize) -> Result<(), Error> {
+    let mut index = from;
+    loop {
+        let name = chunk_key(key, index);
+        if !db.contains_key(&name)? {
+            return Ok(());
+        }
+        db.delete(&name)?;
+        index += 1;
+    }
+}
+
+/// 大对象的流式写入器，通过 `ObjectStore::writer()` 获取。
+///
+/// 数据先在内存中缓存一个块，满一个块后写入 Flash。
+pub struct ObjectWriter<'a, S: NorFlash> {
+    db: &'a mut KVDB<S>,
+    key: String,
+    chunk_size: usize,
+    /// 尚未写入的块
+    buf: Vec<u8>,
+    /// 已写入 Flash 的字节数
+    written: usize,
+}
+
+impl<'a, S: NorFlash> ObjectWriter<'a, S> {
+    /// 写入剩余的数据与清单，并清理旧对象多余的块。
+    pub fn finish(mut self) -> Result<(), Error> {
+        if !self.buf.is_empty() {
+            self.flush_chunk()?;
+        }
+        let manifest = Manifest {
+            chunk_size: self.chunk_size,
+            len: self.written,
+        };
+        remove_chunks(self.db, &self.key, manifest.chunks())?;
+        self.db.set(&self.key, &manifest.encode())
+    }
+
+    /// 已写入的字节数。
+    pub fn len(&self) -> usize {
+        self.written + self.buf.len()
+    }
+
+    /// 是否尚未写入任何数据。
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 内部方法：写入数据，每满一个块写入 Flash
+    fn write_chunks(&mut self, mut data: &[u8]) -> Result<(), Error> {
+        while !data.is_empty() {
+            let take = (self.chunk_size - self.buf.len()).min(data.len());
+            self.buf.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.buf.len() == self.chunk_size {
+                self.flush_chunk()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 内部方法：将缓存的块写入 Flash
+    fn flush_chunk(&mut self) -> Result<(), Error> {
+        if self.written + self.buf.len() > u32::MAX as usize {
+            return Err(Error::InvalidArgument);
+        }
+        let name = chunk_key(&self.key, self.written / self.chunk_size);
+        self.db.set(&name, &self.buf)?;
+        self.written += self.buf.len();
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+impl<'a, S: NorFlash> embedded_io::ErrorType for ObjectWriter<'a, S> {
+    type Error = Error;
+}
+
+impl<'a, S: NorFlash> embedded_io::Write for ObjectWriter<'a, S> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.write_chunks(buf)?;
+        Ok(buf.len())
+    }
+
+    /// 不足一个块的数据会保留在内存中，直到 `finish()` 时写入。
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// 大对象的流式读取器，通过 `ObjectStore::reader()` 获取。
+///
+/// 每次读取最多返回当前块中剩余的数据。
+pub struct ObjectReader<'a, S: NorFlash> {
+    db: &'a mut KVDB<S>,
+    key: String,
+    manifest: Manifest,
+    position: usize,
+}
+
+impl<'a, S: NorFlash> ObjectReader<'a, S> {
+    /// 对象的总长度。
+    pub fn len(&self) -> usize {
+        self.manifest.len
+    }
+
+    /// 对象是否为空。
+    pub fn is_empty(&self) -> bool {
+        self.manifest.len == 0
+    }
+}
+
+impl<'a, S: NorFlash> embedded_io::ErrorType for ObjectReader<'a, S> {
+    type Error = Error;
+}
+
+impl<'a, S: NorFlash> embedded_io::Read for ObjectReader<'a, S> {
+    /// 读取数据，块缺失或长度与清单不符时返回 `Error::Corrupted`。
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.position >= self.manifest.len || buf.is_empty() {
+            return Ok(0);
+        }
+        let chunk_size = self.manifest.chunk_size;
+        let index = self.position / chunk_size;
+        let offset = self.position % chunk_size;
+        let expected = chunk_size.min(self.manifest.len - index * chunk_size);
+
+        let name = chunk_key(&self.key, index);
+        if !self.db.contains_key(&name)? {
+            return Err(Error::Corrupted);
+        }
+        let mut chunk = self.db.get_reader(&name)?;
+        if chunk.entry.value_len() != expected {
+            return Err(Error::Corrupted);
+        }
+        chunk.seek(SeekFrom::Start(offset as u64))?;
+        let len = (expected - offset).min(buf.len());
+        let read = chunk.read(&mut buf[..len])?;
+        self.position += read;
+        Ok(read)
+    }
+}
+
+impl<'a, S: NorFlash> embedded_io::Seek for ObjectReader<'a, S> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::End(offset) => self.manifest.len as i64 + offset,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+        };
+        if new_pos < 0 || new_pos as usize > self.manifest.len {
+            return Err(Error::InvalidArgument);
+        }
+        self.position = new_pos as usize;
+        Ok(new_pos as u64)
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(feature = "alloc")]
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+/// 内存中的只读覆盖层。
+///
+/// 通过 `KVDB::set_overlay` 安装后，`get()` 以及类型化读取方法会先查询覆盖层，
+/// 命中时直接返回覆盖值而不访问 Flash。覆盖值不会写入 Flash，
+/// 适用于开发和测试期间临时调整配置，避免磨损真实分区。
+///
+/// 查找顺序：运行时插入的条目 (需要 `alloc`) 优先于静态表。
+#[derive(Debug, Default, Clone)]
+pub struct Overlay {
+    table: &'static [(&'static str, &'static [u8])],
+    #[cfg(feature = "alloc")]
+    entries: BTreeMap<String, Vec<u8>>,
+}
+
+impl Overlay {
+    /// 创建一个空的覆盖层。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从静态表创建覆盖层，适用于没有堆的 `no_std` 环境。
+    ///
+    /// ```
+    /// use flashdb_rs::Overlay;
+    ///
+    /// static OVERRIDES: &[(&str, &[u8])] = &[("log_level", b"debug")];
+    /// let overlay = Overlay::from_table(OVERRIDES);
+    /// assert_eq!(overlay.get("log_level"), Some(&b"debug"[..]));
+    /// ```
+    pub fn from_table(table: &'static [(&'static str, &'static [u8])]) -> Self {
+        Self {
+            table,
+            ..Default::default()
+        }
+    }
+
+    /// 从环境变量创建覆盖层。
+    ///
+    /// 所有以 `prefix` 开头的环境变量都会被加入覆盖层，键名为去掉前缀后的小写形式，
+    /// 例如 `prefix = "FDB_"` 时，`FDB_WIFI_SSID=lab` 会覆盖键 `wifi_ssid`。
+    #[cfg(feature = "std")]
+    pub fn from_env(prefix: &str) -> Self {
+        let mut overlay = Self::new();
+        for (name, value) in std::env::vars() {
+            if let Some(key) = name.strip_prefix(prefix) {
+                if !key.is_empty() {
+                    overlay.insert(&key.to_lowercase(), value.as_bytes());
+                }
+            }
+        }
+        overlay
+    }
+
+    /// 插入或替换一个覆盖值。
+    #[cfg(feature = "alloc")]
+    pub fn insert(&mut self, key: &str, value: &[u8]) -> &mut Self {
+        self.entries.insert(key.into(), value.into());
+        self
+    }
+
+    /// 移除一个运行时插入的覆盖值。
+    #[cfg(feature = "alloc")]
+    pub fn remove(&mut self, key: &str) -> Option<Vec<u8>> {
+        self.entries.remove(key)
+    }
+
+    /// 查询覆盖值。
+    pub fn get(&self, key: &str) -> Option<&[u8]> {
+        #[cfg(feature = "alloc")]
+        if let Some(value) = self.entries.get(key) {
+            return Some(value);
+        }
+        self.table
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| *value)
+    }
+
+    /// 覆盖层是否为空。
+    pub fn is_empty(&self) -> bool {
+        #[cfg(feature = "alloc")]
+        if !self.entries.is_empty() {
+            return false;
+        }
+        self.table.is_empty()
+    }
+}
@@ -41,6 +41,16 @@ impl<'a, S: NorFlash> embedded_io::Read for KVReader<'a, S> {
         }
         let mut blob = fdb_blob_make_by(buf, &self.entry, self.position);
         let read_len = unsafe { fdb_blob_read(self.inner.handle() as *mut _, &mut blob) };
+        // C 库读取失败时返回 0，未到末尾的非空读取返回 0 即为失败
+        if read_len == 0 && !buf.is_empty() {
+            let result = self.inner.user_data.finish(Err(Error::ReadError));
+            #[cfg(feature = "alloc")]
+            let result = result.map_err(|err| {
+                let key = alloc::string::String::from_utf8_lossy(super::KeyName::from(&self.entry).as_bytes()).into_owned();
+                err.with_context(crate::ErrorContext::for_key(crate::Operation::Read, &key))
+            });
+            return result;
+        }
         self.position += read_len;
         Ok(read_len)
     }
@@ -0,0 +1,80 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+
+#[cfg(feature = "alloc")]
+use super::Issue;
+use super::{IssueKind, KVStatus, KVDB};
+
+/// 修复报告
+#[derive(Debug, Clone, Default)]
+pub struct RepairReport {
+    /// 是否为演练模式 (未修改 Flash)
+    pub dry_run: bool,
+    /// 被丢弃 (标记为 `ERR_HDR`) 的 KV 数
+    pub dropped_entries: usize,
+    /// 被重新格式化的扇区数
+    pub formatted_sectors: usize,
+    /// 被丢弃的 KV 与被格式化的扇区的详细信息
+    #[cfg(feature = "alloc")]
+    pub removed: Vec<Issue>,
+}
+
+impl<S: NorFlash> KVDB<S> {
+    /// 修复损坏的数据，使数据库重新回到一致的状态。
+    ///
+    /// 在 `verify()` 的基础上：
+    /// - 头部或 CRC 损坏、以及写入中断的 KV 会被标记为 `ERR_HDR` 并等待垃圾回收；
+    /// - 扇区头损坏的扇区会被擦除并重新格式化，其中的数据将丢失；
+    /// - 中断的更新与垃圾回收由 C 库在重新加载数据库时恢复。
+    ///
+    /// `dry_run` 为 `true` 时不会修改 Flash，只报告将被移除的内容。
+    ///
+    /// **注意**: 已经损坏的值无法找回，修复只是保证其余数据可以正常读写。
+    pub fn repair(&mut self, dry_run: bool) -> Result<RepairReport, Error> {
+        let mut report = RepairReport {
+            dry_run,
+            ..Default::default()
+        };
+
+        let verify = self.scan(|layout, sector, issue| {
+            match (issue.kind, issue.status) {
+                (IssueKind::BadSectorHeader, _) => {
+                    if !dry_run {
+                        layout.erase_sector(issue.addr)?;
+                    }
+                    report.formatted_sectors += 1;
+                }
+                (IssueKind::BadHeader | IssueKind::InterruptedWrite | IssueKind::CrcMismatch, Some(status))
+                    if status != KVStatus::ERR_HDR =>
+                {
+                    if !dry_run {
+                        layout.drop_entry(sector, issue.addr)?;
+                    }
+                    report.dropped_entries += 1;
+                }
+                // 交由 C 库在加载时恢复
+                _ => return Ok(()),
+            }
+            #[cfg(feature = "alloc")]
+            report.removed.push(*issue);
+            Ok(())
+        })?;
+
+        if !dry_run && !verify.is_clean() {
+            self.reload()?;
+        }
+        Ok(report)
+    }
+
+    /// 内部方法：重新加载数据库，让 C 库重建缓存并执行加载时的恢复流程。
+    fn reload(&mut self) -> Result<(), Error> {
+        self.deinit_raw();
+        // C 库会按值复制默认 KV 表头，其中的节点仍指向原来的数据
+        let mut default_kvs = self.raw().default_kvs;
+        self.init_raw(&mut default_kvs)
+    }
+}
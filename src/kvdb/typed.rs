@@ -0,0 +1,82 @@
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{fdb_blob_make_by, Error};
+
+use super::{KVStatus, KVDB};
+
+/// 为标量类型生成一组 `get_xxx` / `set_xxx` 方法。
+///
+/// 所有数值统一以小端序 (little-endian) 存储。
+macro_rules! impl_scalar_accessors {
+    ($($ty:ty => $get:ident, $set:ident;)*) => {
+        impl<S: NorFlash> KVDB<S> {
+            $(
+                #[doc = concat!("读取一个以小端序存储的 `", stringify!($ty), "` 值。")]
+                ///
+                /// # 返回
+                /// - `Ok(Some(v))`: 找到键且长度匹配。
+                /// - `Ok(None)`: 未找到键。
+                /// - `Err(Error::ValueLengthMismatch)`: 存储的值长度与类型不符。
+                pub fn $get(&mut self, key: &str) -> Result<Option<$ty>, Error> {
+                    Ok(self.get_array(key)?.map(<$ty>::from_le_bytes))
+                }
+
+                #[doc = concat!("以小端序写入一个 `", stringify!($ty), "` 值。")]
+                pub fn $set(&mut self, key: &str, value: $ty) -> Result<(), Error> {
+                    self.set(key, &value.to_le_bytes())
+                }
+            )*
+        }
+    };
+}
+
+impl_scalar_accessors! {
+    u8 => get_u8, set_u8;
+    u16 => get_u16, set_u16;
+    u32 => get_u32, set_u32;
+    u64 => get_u64, set_u64;
+    i32 => get_i32, set_i32;
+    f32 => get_f32, set_f32;
+}
+
+impl<S: NorFlash> KVDB<S> {
+    /// 读取一个布尔值，存储为单字节，非零即为 `true`。
+    pub fn get_bool(&mut self, key: &str) -> Result<Option<bool>, Error> {
+        Ok(self.get_array::<1>(key)?.map(|v| v[0] != 0))
+    }
+
+    /// 写入一个布尔值，存储为单字节 `0` 或 `1`。
+    pub fn set_bool(&mut self, key: &str, value: bool) -> Result<(), Error> {
+        self.set(key, &[value as u8])
+    }
+
+    /// 内部方法：将值读取到定长数组中，不需要 `alloc`。
+    ///
+    /// 值的长度必须与 `N` 完全一致，否则返回 `Error::ValueLengthMismatch`。
+    fn get_array<const N: usize>(&mut self, key: &str) -> Result<Option<[u8; N]>, Error> {
+        if let Some(value) = self.overlay_get(key) {
+            return value
+                .try_into()
+                .map(Some)
+                .map_err(|_| Error::ValueLengthMismatch);
+        }
+        let kv = match self.fdb_kv_get_obj(key)? {
+            Some(kv) => kv,
+            None => return Ok(None),
+        };
+        match kv.status() {
+            KVStatus::PRE_WRITE | KVStatus::Write => {
+                if kv.value_len() != N {
+                    return Err(Error::ValueLengthMismatch);
+                }
+                let mut data = [0u8; N];
+                let mut blob = fdb_blob_make_by(&mut data, &kv, 0);
+                if self.fdb_blob_read(&mut blob) != N {
+                    return self.user_data.finish(Err(Error::ReadError));
+                }
+                Ok(Some(data))
+            }
+            _ => Ok(None),
+        }
+    }
+}
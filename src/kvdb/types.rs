@@ -1,5 +1,5 @@
 use crate::{
-    fdb_kv, fdb_kv_status, fdb_kv_status_FDB_KV_DELETED, fdb_kv_status_FDB_KV_ERR_HDR, fdb_kv_status_FDB_KV_PRE_DELETE, fdb_kv_status_FDB_KV_PRE_WRITE, fdb_kv_status_FDB_KV_UNUSED, fdb_kv_status_FDB_KV_WRITE, fdb_kv_t, RawHandle
+    fdb_kv, fdb_kv_status, fdb_kv_status_FDB_KV_DELETED, fdb_kv_status_FDB_KV_ERR_HDR, fdb_kv_status_FDB_KV_PRE_DELETE, fdb_kv_status_FDB_KV_PRE_WRITE, fdb_kv_status_FDB_KV_UNUSED, fdb_kv_status_FDB_KV_WRITE, fdb_kv_t, RawHandle, FDB_KV_NAME_MAX
 };
 
 /// 键值对状态枚举
@@ -31,9 +31,149 @@ impl From<fdb_kv_status> for KVStatus {
     }
 }
 
+/// `KVStatus` 的集合，用于 `KVDB::iter_with_status()` 筛选 KV。
+///
+/// ```
+/// # use flashdb_rs::{KVStatus, KVStatusMask};
+/// let mask = KVStatusMask::of(KVStatus::PRE_DELETE) | KVStatus::DELETED;
+/// assert!(mask.contains(KVStatus::DELETED));
+/// assert!(!mask.contains(KVStatus::Write));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KVStatusMask(u8);
+
+impl KVStatusMask {
+    /// 不包含任何状态
+    pub const NONE: Self = Self(0);
+    /// 包含所有状态
+    pub const ALL: Self = Self(0x3F);
+    /// 只包含当前生效的 KV (`Write`)
+    pub const LIVE: Self = Self::of(KVStatus::Write);
+    /// 等待垃圾回收的 KV (`PRE_DELETE`、`DELETED`、`ERR_HDR`)
+    pub const GARBAGE: Self = Self::of(KVStatus::PRE_DELETE)
+        .with(KVStatus::DELETED)
+        .with(KVStatus::ERR_HDR);
+
+    /// 只包含 `status` 的集合
+    pub const fn of(status: KVStatus) -> Self {
+        Self(1 << status as u32)
+    }
+
+    /// 加入 `status`
+    pub const fn with(self, status: KVStatus) -> Self {
+        Self(self.0 | Self::of(status).0)
+    }
+
+    /// 是否包含 `status`
+    pub const fn contains(self, status: KVStatus) -> bool {
+        self.0 & Self::of(status).0 != 0
+    }
+}
+
+impl From<KVStatus> for KVStatusMask {
+    fn from(status: KVStatus) -> Self {
+        Self::of(status)
+    }
+}
+
+impl<T: Into<KVStatusMask>> core::ops::BitOr<T> for KVStatusMask {
+    type Output = Self;
+
+    fn bitor(self, rhs: T) -> Self {
+        Self(self.0 | rhs.into().0)
+    }
+}
+
+/// KVDB 的有效配置，通过 `KVDB::config()` 获取。
+///
+/// 缓存表大小在编译时确定，可通过 `kv-cache-*` 特性或环境变量
+/// `FLASHDB_KV_CACHE_TABLE_SIZE` / `FLASHDB_SECTOR_CACHE_TABLE_SIZE` 调整。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KVDBConfig {
+    /// 扇区大小 (字节)
+    pub sec_size: u32,
+    /// 数据库总容量 (字节)
+    pub max_size: u32,
+    /// KV 缓存表的项数，为 0 时不使用缓存
+    pub kv_cache_table_size: usize,
+    /// 扇区缓存表的项数，为 0 时不使用缓存
+    pub sector_cache_table_size: usize,
+    /// 当前的垃圾回收策略
+    pub gc_policy: GcPolicy,
+}
+
+/// C 库在空扇区不多于此数量时触发垃圾回收 (`FDB_GC_EMPTY_SEC_THRESHOLD`)
+pub(super) const GC_EMPTY_SEC_THRESHOLD: u16 = 1;
+
+/// 垃圾回收策略，通过 `KVDB::set_gc_policy()` 设置。
+///
+/// C 库只在空扇区即将耗尽时于写入过程中触发垃圾回收。提前回收会增加搬移有效数据带来的写放大，
+/// 但每次回收的扇区更少、耗时更平稳；推迟回收则相反。
+///
+/// # 示例
+///
+/// ```
+/// # use flashdb_rs::GcPolicy;
+/// // 脏扇区达到一半，或空扇区少于 2 个时回收
+/// let policy = GcPolicy::new().with_dirty_percent(50).with_reserved_sectors(2);
+/// assert_eq!(policy.dirty_percent(), Some(50));
+/// assert_eq!(policy.reserved_sectors(), 2);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GcPolicy {
+    dirty_percent: Option<u8>,
+    reserved_sectors: u16,
+}
+
+impl GcPolicy {
+    /// 与 C 库一致的默认策略：只保留 1 个空扇区，不按脏扇区比例回收。
+    pub const fn new() -> Self {
+        Self {
+            dirty_percent: None,
+            reserved_sectors: GC_EMPTY_SEC_THRESHOLD,
+        }
+    }
+
+    /// 脏扇区占全部扇区的百分比达到 `percent` 时回收。
+    pub const fn with_dirty_percent(mut self, percent: u8) -> Self {
+        self.dirty_percent = Some(percent);
+        self
+    }
+
+    /// 空扇区少于 `sectors` 个时回收，不能小于 1。
+    pub const fn with_reserved_sectors(mut self, sectors: u16) -> Self {
+        self.reserved_sectors = sectors;
+        self
+    }
+
+    /// 触发回收的脏扇区百分比，`None` 表示不按比例回收。
+    pub const fn dirty_percent(&self) -> Option<u8> {
+        self.dirty_percent
+    }
+
+    /// 保留的空扇区数。
+    pub const fn reserved_sectors(&self) -> u16 {
+        self.reserved_sectors
+    }
+
+    /// 是否与 C 库的默认行为相同
+    pub(super) fn is_default(&self) -> bool {
+        *self == Self::new()
+    }
+}
+
+impl Default for GcPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct KVEntry {
     pub(super) inner: fdb_kv,
+    // 值末尾记录的修改时间，见 `KVDB::set_track_modified()`
+    #[cfg(feature = "alloc")]
+    pub(super) modified: Option<u64>,
 }
 
 impl KVEntry {
@@ -77,6 +217,103 @@ impl RawHandle for KVEntry {
 
 impl From<fdb_kv> for KVEntry {
     fn from(value: fdb_kv) -> Self {
-        Self { inner: value }
+        Self {
+            inner: value,
+            #[cfg(feature = "alloc")]
+            modified: None,
+        }
+    }
+}
+
+/// 定长的 KV 名称，无需堆分配。
+///
+/// 由 `KVDB::iter_keys()` 返回，只包含键名而不携带值的元数据。
+#[derive(Clone, Copy)]
+pub struct KeyName {
+    buf: [u8; FDB_KV_NAME_MAX as usize],
+    len: u8,
+}
+
+impl KeyName {
+    /// 获取名称的原始字节。
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len as usize]
+    }
+
+    /// 获取名称字符串，如果名称不是有效的 UTF-8 编码则返回 `None`。
+    pub fn as_str(&self) -> Option<&str> {
+        core::str::from_utf8(self.as_bytes()).ok()
+    }
+
+    /// 内部方法：从原始字节构建名称，超出 `FDB_KV_NAME_MAX` 的部分会被截断。
+    pub(super) fn from_bytes(bytes: &[u8]) -> Self {
+        let len = bytes.len().min(FDB_KV_NAME_MAX as usize);
+        let mut buf = [0u8; FDB_KV_NAME_MAX as usize];
+        buf[..len].copy_from_slice(&bytes[..len]);
+        Self {
+            buf,
+            len: len as u8,
+        }
+    }
+}
+
+impl From<&KVEntry> for KeyName {
+    fn from(entry: &KVEntry) -> Self {
+        let len = (entry.inner.name_len as usize).min(FDB_KV_NAME_MAX as usize);
+        let mut buf = [0u8; FDB_KV_NAME_MAX as usize];
+        for (dst, src) in buf.iter_mut().zip(&entry.inner.name[..len]) {
+            *dst = *src as u8;
+        }
+        Self {
+            buf,
+            len: len as u8,
+        }
+    }
+}
+
+impl PartialEq for KeyName {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for KeyName {}
+
+impl PartialOrd for KeyName {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for KeyName {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+impl core::hash::Hash for KeyName {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state)
+    }
+}
+
+impl PartialEq<str> for KeyName {
+    fn eq(&self, other: &str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for KeyName {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl core::fmt::Debug for KeyName {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.as_str() {
+            Some(name) => core::fmt::Debug::fmt(name, f),
+            None => core::fmt::Debug::fmt(self.as_bytes(), f),
+        }
     }
 }
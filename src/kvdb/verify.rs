@@ -0,0 +1,247 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+
+use super::layout::{EntryInfo, Layout, SectorDirty, SectorInfo, SectorStore, SECTOR_HDR_SIZE};
+#[cfg(feature = "alloc")]
+use super::KVEntry;
+use super::{KVStatus, KeyName, KVDB};
+
+/// 完整性检查发现的问题类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    /// 扇区头的 magic 或合并标志无效，整个扇区的数据无法读取
+    BadSectorHeader,
+    /// 扇区在垃圾回收过程中断电
+    InterruptedGc,
+    /// KV 头部损坏 (长度无效)，无法定位其数据
+    BadHeader,
+    /// KV 写入过程中断电 (`PRE_WRITE`)
+    InterruptedWrite,
+    /// KV 更新过程中断电，旧值仍处于 `PRE_DELETE` 状态
+    InterruptedUpdate,
+    /// KV 的 CRC32 校验失败
+    CrcMismatch,
+}
+
+/// 完整性检查发现的一个问题
+#[derive(Debug, Clone, Copy)]
+pub struct Issue {
+    /// 问题所在的 Flash 地址 (扇区或 KV 的起始地址)
+    pub addr: u32,
+    /// 问题类型
+    pub kind: IssueKind,
+    /// KV 的状态，扇区级别的问题为 `None`
+    pub status: Option<KVStatus>,
+    /// KV 名称，头部损坏时无法读取，为 `None`
+    pub name: Option<KeyName>,
+    /// 是否可以在不丢失已提交数据的情况下恢复
+    pub recoverable: bool,
+}
+
+/// 完整性检查报告
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    /// 检查的扇区数
+    pub sectors: usize,
+    /// 含有待回收数据的扇区数
+    pub dirty_sectors: usize,
+    /// 有效的 KV 数
+    pub valid_entries: usize,
+    /// 已删除或已隔离 (`ERR_HDR`)、等待垃圾回收的 KV 数
+    pub deleted_entries: usize,
+    /// 可恢复的问题数
+    pub recoverable: usize,
+    /// 不可恢复的问题数，对应的数据已经丢失
+    pub unrecoverable: usize,
+    /// 所有问题的详细信息
+    #[cfg(feature = "alloc")]
+    pub issues: Vec<Issue>,
+}
+
+impl VerifyReport {
+    /// 是否未发现任何问题
+    pub fn is_clean(&self) -> bool {
+        self.recoverable == 0 && self.unrecoverable == 0
+    }
+
+    fn record(&mut self, issue: &Issue) {
+        if issue.recoverable {
+            self.recoverable += 1;
+        } else {
+            self.unrecoverable += 1;
+        }
+    }
+}
+
+impl<S: NorFlash> KVDB<S> {
+    /// 检查数据库的完整性 (fsck)。
+    ///
+    /// 遍历所有扇区与 KV，校验扇区头、KV 头与 CRC32，并返回结构化的报告。
+    /// 与普通读取不同，损坏的数据不会被静默跳过，检查过程也不会修改 Flash。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::KVDB;
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = KVDB::new_file("verify_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+    /// db.set("boot_count", &1u32.to_le_bytes())?;
+    /// let report = db.verify()?;
+    /// assert!(report.is_clean());
+    /// assert_eq!(report.valid_entries, 1);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn verify(&mut self) -> Result<VerifyReport, Error> {
+        #[cfg(feature = "alloc")]
+        {
+            let mut issues = Vec::new();
+            let mut report = self.verify_with(|issue| issues.push(*issue))?;
+            report.issues = issues;
+            Ok(report)
+        }
+        #[cfg(not(feature = "alloc"))]
+        self.verify_with(|_| {})
+    }
+
+    /// 检查数据库的完整性，并对每个发现的问题调用 `f`。
+    ///
+    /// 适用于没有 `alloc` 的环境，返回的报告中只包含统计信息。
+    pub fn verify_with(&mut self, mut f: impl FnMut(&Issue)) -> Result<VerifyReport, Error> {
+        self.scan(|_, _, issue| {
+            f(issue);
+            Ok(())
+        })
+    }
+
+    /// 内部方法：KV 的 CRC 是否正确。
+    ///
+    /// C 库只会返回 CRC 正确的 KV，只有通过预取索引找到的 KV 需要重新计算。
+    #[cfg(feature = "alloc")]
+    pub(super) fn entry_crc_ok(&mut self, kv: &KVEntry) -> Result<bool, Error> {
+        if !kv.is_valid() {
+            return Ok(false);
+        }
+        #[cfg(feature = "kv-index")]
+        if self.index.is_some() {
+            let sec_size = self.raw().parent.sec_size;
+            let max_size = self.raw().parent.max_size;
+            let mut layout = Layout::new(&mut *self.storage, sec_size, max_size);
+            return Ok(layout.read_entry(kv.inner.addr.start)?.crc_ok);
+        }
+        Ok(true)
+    }
+
+    /// 内部方法：是否存在名为 `key`、已提交但 CRC 校验失败的 KV
+    #[cfg(feature = "alloc")]
+    pub(super) fn has_corrupted(&mut self, key: &str) -> Result<bool, Error> {
+        let name = KeyName::from_bytes(key.as_bytes());
+        let mut found = false;
+        self.scan(|_, _, issue| {
+            found |= issue.kind == IssueKind::CrcMismatch
+                && issue.status == Some(KVStatus::Write)
+                && issue.name == Some(name);
+            Ok(())
+        })?;
+        Ok(found)
+    }
+
+    /// 内部方法：遍历所有扇区与 KV，对每个问题调用 `visit`。
+    ///
+    /// `visit` 可以通过 `Layout` 修改 Flash，但不能改变后续 KV 的位置。
+    pub(super) fn scan(
+        &mut self,
+        mut visit: impl FnMut(&mut Layout<'_, S>, &SectorInfo, &Issue) -> Result<(), Error>,
+    ) -> Result<VerifyReport, Error> {
+        if !self.initialized {
+            return Err(Error::InitFailed);
+        }
+        let sec_size = self.raw().parent.sec_size;
+        let max_size = self.raw().parent.max_size;
+        // 直接借用字段，以便在遍历过程中发送事件
+        let mut layout = Layout::new(&mut *self.storage, sec_size, max_size);
+        let mut report = VerifyReport::default();
+
+        for addr in (0..max_size / sec_size).map(|i| i * sec_size) {
+            report.sectors += 1;
+            let sector = layout.read_sector(addr)?;
+            if !sector.header_ok {
+                let issue = Issue {
+                    addr,
+                    kind: IssueKind::BadSectorHeader,
+                    status: None,
+                    name: None,
+                    // 完全擦除的扇区中没有任何数据，重新格式化即可
+                    recoverable: sector.erased,
+                };
+                report.record(&issue);
+                visit(&mut layout, &sector, &issue)?;
+                continue;
+            }
+            if matches!(sector.dirty, SectorDirty::True | SectorDirty::Gc) {
+                report.dirty_sectors += 1;
+            }
+            if sector.dirty == SectorDirty::Gc {
+                let issue = Issue {
+                    addr,
+                    kind: IssueKind::InterruptedGc,
+                    status: None,
+                    name: None,
+                    recoverable: true,
+                };
+                report.record(&issue);
+                visit(&mut layout, &sector, &issue)?;
+            }
+            if !matches!(sector.store, SectorStore::Using | SectorStore::Full) {
+                continue;
+            }
+
+            let mut next = Some(addr + SECTOR_HDR_SIZE as u32);
+            while let Some(entry_addr) = next {
+                let entry = layout.read_entry(entry_addr)?;
+                match classify(&entry) {
+                    Some((kind, recoverable)) => {
+                        let issue = Issue {
+                            addr: entry.addr,
+                            kind,
+                            status: Some(entry.status),
+                            name: entry.name,
+                            recoverable,
+                        };
+                        report.record(&issue);
+                        #[cfg(feature = "alloc")]
+                        if kind == IssueKind::CrcMismatch {
+                            self.user_data.emit(crate::events::Event::CrcError { addr: entry.addr });
+                        }
+                        visit(&mut layout, &sector, &issue)?;
+                    }
+                    None if entry.status == KVStatus::Write => report.valid_entries += 1,
+                    None => report.deleted_entries += 1,
+                }
+                next = layout.next_entry(&sector, &entry)?;
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// 判断 KV 是否存在问题，返回问题类型以及是否可恢复。
+fn classify(entry: &EntryInfo) -> Option<(IssueKind, bool)> {
+    // 处于 WRITE / PRE_DELETE 状态的 KV 是当前生效的值，损坏即意味着数据丢失
+    let committed = matches!(entry.status, KVStatus::Write | KVStatus::PRE_DELETE);
+    match entry.status {
+        // 已被 C 库或 `repair()` 隔离的 KV 与已删除的 KV 一样等待垃圾回收
+        KVStatus::ERR_HDR => None,
+        _ if !entry.header_ok => Some((IssueKind::BadHeader, !committed)),
+        KVStatus::PRE_WRITE => Some((IssueKind::InterruptedWrite, true)),
+        _ if !entry.crc_ok => Some((IssueKind::CrcMismatch, !committed)),
+        KVStatus::PRE_DELETE => Some((IssueKind::InterruptedUpdate, true)),
+        // 状态表从未写入却有完整的头部，视为未完成的写入
+        KVStatus::UNUSED => Some((IssueKind::BadHeader, true)),
+        KVStatus::Write | KVStatus::DELETED => None,
+    }
+}
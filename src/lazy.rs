@@ -0,0 +1,127 @@
+//! 延迟初始化的数据库包装器。
+//!
+//! 部分系统上 Flash 的电源或总线在启动后较晚才就绪，或者首次启动时分区尚不存在。
+//! `LazyDb` 将 `init()` 推迟到第一次访问数据库时才执行，并支持在失败后重试。
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+#[cfg(feature = "kvdb")]
+use crate::{fdb_default_kv, KVDB};
+#[cfg(feature = "tsdb")]
+use crate::TSDB;
+
+/// 可以被 `LazyDb` 延迟初始化的数据库
+pub trait LazyInit {
+    /// 初始化参数
+    type Args;
+
+    /// 数据库是否已经初始化
+    fn is_initialized(&self) -> bool;
+
+    /// 使用给定参数初始化数据库
+    fn lazy_init(&mut self, args: &Self::Args) -> Result<(), Error>;
+}
+
+#[cfg(feature = "kvdb")]
+impl<S: NorFlash> LazyInit for KVDB<S> {
+    /// 默认键值对，与 `KVDB::init` 的参数相同
+    type Args = Option<&'static fdb_default_kv>;
+
+    fn is_initialized(&self) -> bool {
+        self.is_initialized()
+    }
+
+    fn lazy_init(&mut self, args: &Self::Args) -> Result<(), Error> {
+        self.init(*args)
+    }
+}
+
+#[cfg(feature = "tsdb")]
+impl<S: NorFlash> LazyInit for TSDB<S> {
+    /// 单条日志最大长度，与 `TSDB::init` 的参数相同
+    type Args = usize;
+
+    fn is_initialized(&self) -> bool {
+        self.is_initialized()
+    }
+
+    fn lazy_init(&mut self, args: &Self::Args) -> Result<(), Error> {
+        self.init(*args)
+    }
+}
+
+/// 在第一次访问时才初始化的数据库。
+///
+/// 名称等配置需要在交给 `LazyDb` 之前设置好。
+///
+/// # 示例
+///
+/// ```
+/// use flashdb_rs::{LazyDb, KVDB, StdStorage};
+/// use flashdb_rs::storage::FileStrategy;
+///
+/// # let dir = tempfile::tempdir()?;
+/// let storage = StdStorage::new(dir.path(), "lazy", 4096, 4 * 4096, FileStrategy::Multi)?;
+/// let mut db = Box::new(LazyDb::new(KVDB::new(storage), None));
+/// assert!(!db.is_initialized());
+///
+/// // 第一次访问时才会初始化
+/// db.get()?.set("boot_count", &1u32.to_le_bytes())?;
+/// assert!(db.is_initialized());
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct LazyDb<D: LazyInit> {
+    db: D,
+    args: D::Args,
+}
+
+impl<D: LazyInit> LazyDb<D> {
+    /// 包装一个尚未初始化的数据库，`args` 为首次访问时传给 `init()` 的参数。
+    pub fn new(db: D, args: D::Args) -> Self {
+        Self { db, args }
+    }
+
+    /// 数据库是否已经初始化
+    pub fn is_initialized(&self) -> bool {
+        self.db.is_initialized()
+    }
+
+    /// 获取数据库，必要时先进行初始化。
+    pub fn get(&mut self) -> Result<&mut D, Error> {
+        if !self.db.is_initialized() {
+            self.db.lazy_init(&self.args)?;
+        }
+        Ok(&mut self.db)
+    }
+
+    /// 反复尝试初始化，直到成功或 `retry` 返回 `false`。
+    ///
+    /// 每次初始化失败后都会以该次的错误调用 `retry`，调用方可以在其中等待、
+    /// 检查超时或重新上电，返回 `false` 时放弃并返回最后一次的错误。
+    pub fn try_init_with(&mut self, mut retry: impl FnMut(&Error) -> bool) -> Result<&mut D, Error> {
+        loop {
+            match self.db.lazy_init(&self.args) {
+                Ok(()) => return Ok(&mut self.db),
+                Err(err) if retry(&err) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// 在 `timeout` 内反复尝试初始化，每次失败后等待一小段时间再重试。
+    #[cfg(feature = "std")]
+    pub fn try_init_with_timeout(&mut self, timeout: std::time::Duration) -> Result<&mut D, Error> {
+        const RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_millis(10);
+
+        let deadline = std::time::Instant::now() + timeout;
+        self.try_init_with(|_| {
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            std::thread::sleep(RETRY_INTERVAL.min(deadline - now));
+            true
+        })
+    }
+}
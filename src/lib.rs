@@ -3,22 +3,59 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 #![allow(clippy::all)]
+// 两种数据库都未启用时只剩下公共部分，用于 `size_report` 测量基线体积
+#![cfg_attr(not(any(feature = "kvdb", feature = "tsdb")), allow(unused))]
 
 #![doc = include_str!("../README.md")]
 
 #[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "alloc")]
+pub mod asynch;
+#[cfg(any(all(feature = "kvdb", feature = "json"), all(feature = "tsdb", feature = "alloc")))]
+mod base64;
+pub mod buffered;
+pub mod crypt;
 pub mod error;
+#[cfg(all(feature = "esp-idf", target_os = "espidf"))]
+pub mod esp;
+#[cfg(feature = "alloc")]
+pub mod events;
+pub mod format;
+pub mod gran;
+#[cfg(feature = "std")]
+pub mod handle;
+#[cfg(feature = "kvdb")]
 pub mod kvdb;
+pub mod lazy;
+pub mod lock;
+mod metrics;
+pub mod mirror;
+pub mod partition;
+#[cfg(feature = "registry")]
+pub mod registry;
+#[cfg(feature = "std")]
+pub mod shared;
+pub mod slice;
+pub mod strict;
+#[cfg(feature = "alloc")]
+pub mod test_utils;
 // pub mod time;
+pub mod timeout;
+pub mod transfer;
+#[cfg(feature = "tsdb")]
 pub mod tsdb;
 pub mod utils;
+#[cfg(feature = "alloc")]
+pub mod wear;
 
 use core::ffi::c_void;
 
 use embedded_storage::nor_flash::NorFlash;
 
+#[cfg(feature = "std")]
+pub mod bench;
 #[cfg(feature = "std")]
 pub mod storage;
 #[cfg(feature = "std")]
@@ -26,7 +63,12 @@ pub use storage::StdStorage;
 
 pub use error::*;
 
+#[cfg(feature = "kvdb")]
 pub use kvdb::*;
+pub use lazy::{LazyDb, LazyInit};
+pub use lock::DbLock;
+pub use timeout::Timer;
+#[cfg(feature = "tsdb")]
 pub use tsdb::*;
 pub use utils::*;
 
@@ -78,6 +120,68 @@ macro_rules! define_default_kvs {
     };
 }
 
+/// 在编译时检查存储后端与数据库参数是否相互匹配。
+///
+/// 检查项包括：
+/// - `sec_size` 是 `S::ERASE_SIZE` 的整数倍；
+/// - `S::ERASE_SIZE` 是 `S::WRITE_SIZE` 的整数倍；
+/// - `S::WRITE_SIZE` 与 C 库编译时的 `FDB_WRITE_GRAN` 一致；
+/// - (可选) 单条 TSL 最大长度 `entry_max` 小于 `sec_size`。
+///
+/// 任何一项不满足都会导致编译失败，而不是在运行时返回 `InitFailed`。
+///
+/// # 示例
+///
+/// ```
+/// use flashdb_rs::gran::GranAdaptor;
+/// use flashdb_rs::{static_assert_geometry, StdStorage};
+///
+/// // KVDB: 只检查扇区大小。GranAdaptor 的写入单元与任意 write-gran-* 特性一致
+/// static_assert_geometry!(GranAdaptor<StdStorage>, 4096);
+/// // TSDB: 同时检查单条日志最大长度
+/// static_assert_geometry!(GranAdaptor<StdStorage>, 8192, 1024);
+/// ```
+///
+/// ```compile_fail
+/// use flashdb_rs::{static_assert_geometry, StdStorage};
+///
+/// // 扇区大小不是擦除大小的整数倍
+/// static_assert_geometry!(StdStorage, 1000);
+/// ```
+#[macro_export]
+macro_rules! static_assert_geometry {
+    ($storage:ty, $sec_size:expr) => {
+        const _: () = $crate::check_geometry::<$storage>($sec_size as usize, 0);
+    };
+    ($storage:ty, $sec_size:expr, $entry_max:expr) => {
+        const _: () = $crate::check_geometry::<$storage>($sec_size as usize, $entry_max as usize);
+    };
+}
+
+/// `static_assert_geometry!` 使用的常量检查函数，也可以在运行时调用。
+///
+/// 参数不一致时会 panic；在常量上下文中调用时表现为编译错误。
+pub const fn check_geometry<S: NorFlash>(sec_size: usize, entry_max: usize) {
+    // C 库按 FDB_WRITE_GRAN (bit) 对齐写入，换算为字节
+    let write_gran = (FDB_WRITE_GRAN as usize + 7) / 8;
+
+    assert!(S::ERASE_SIZE > 0, "ERASE_SIZE must not be zero");
+    assert!(S::WRITE_SIZE > 0, "WRITE_SIZE must not be zero");
+    assert!(
+        S::ERASE_SIZE % S::WRITE_SIZE == 0,
+        "ERASE_SIZE must be a multiple of WRITE_SIZE"
+    );
+    assert!(
+        S::WRITE_SIZE == write_gran,
+        "WRITE_SIZE does not match the FDB_WRITE_GRAN the C library was built with"
+    );
+    assert!(
+        sec_size >= S::ERASE_SIZE && sec_size % S::ERASE_SIZE == 0,
+        "sec_size must be a multiple of ERASE_SIZE"
+    );
+    assert!(entry_max < sec_size, "entry_max must be smaller than sec_size");
+}
+
 pub trait RawHandle {
     type Handle;
 
@@ -86,25 +190,94 @@ pub trait RawHandle {
     fn handle(&self) -> Self::Handle;
 }
 
-// 暴露给 C 的日志函数
+/// 正在拼接的一行日志
+///
+/// C 库将一行日志拆成前缀 "[FlashDB][tag]"、"[名称][路径] " 与正文多次输出，
+/// 每个数据库在自己的缓冲区中拼接完整的一行后再交给 `log`，使每条日志都带有数据库名称。
+#[cfg(feature = "log")]
+pub(crate) struct LogLine {
+    buf: [u8; 256],
+    len: usize,
+}
+
+#[cfg(feature = "log")]
+impl LogLine {
+    const fn new() -> Self {
+        Self { buf: [0; 256], len: 0 }
+    }
+
+    /// 追加一段输出，每遇到换行或缓冲区写满时输出一行
+    fn push(&mut self, fragment: &[u8], dispatch: &FlashDispatch) {
+        for &byte in fragment {
+            if byte != b'\n' {
+                self.buf[self.len] = byte;
+                self.len += 1;
+            }
+            if byte == b'\n' || self.len == self.buf.len() {
+                dispatch.emit_log(&self.buf[..self.len]);
+                self.len = 0;
+            }
+        }
+    }
+}
+
+/// 按 `String::from_utf8_lossy` 的方式输出日志，无效的 UTF-8 序列替换为 U+FFFD
+#[cfg(feature = "log")]
+struct LossyLine<'a>(&'a [u8]);
+
+#[cfg(feature = "log")]
+impl core::fmt::Display for LossyLine<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for chunk in self.0.trim_ascii_end().utf8_chunks() {
+            f.write_str(chunk.valid())?;
+            if !chunk.invalid().is_empty() {
+                f.write_str("\u{FFFD}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// C 库的日志都以 INFO 输出，按内容区分错误与警告
+#[cfg(feature = "log")]
+fn log_level_of(line: &[u8]) -> log::Level {
+    let contains = |needle: &[u8]| line.windows(needle.len()).any(|window| window == needle);
+    if contains(b"Error") || contains(b"assert failed") {
+        log::Level::Error
+    } else if contains(b"Warning") {
+        log::Level::Warn
+    } else {
+        log::Level::Info
+    }
+}
+
+// 暴露给 C 的日志函数，`db` 为输出日志的数据库，`message` 是一行日志的一部分
 #[no_mangle]
 #[cfg(feature = "log")]
-pub extern "C" fn rust_log(message: *const core::ffi::c_char) {
-    let c_str = unsafe { core::ffi::CStr::from_ptr(message) };
-    if let Ok(message_str) = c_str.to_str() {
-        // 使用 Rust 的 log 宏输出（日志级别设为 INFO）
-        log::log!(log::Level::Info, "{message_str}");
+pub extern "C" fn rust_log(db: *const c_void, message: *const core::ffi::c_char) {
+    let fragment = unsafe { core::ffi::CStr::from_ptr(message) }.to_bytes();
+    // SAFETY: C 库只在数据库的调用期间输出日志，此时调度器有效，且同一时刻只有一个调用方持有该数据库
+    match unsafe { FlashDispatch::log_target(db as fdb_db_t) } {
+        Some(dispatch) => unsafe { (*(*dispatch).log_line.get()).push(fragment, &*dispatch) },
+        // 不属于任何数据库的日志 (例如 fdb_utils.c 中的断言) 没有拼接缓冲区，
+        // 前缀单独丢弃，只输出以换行结尾的正文
+        None if fragment.ends_with(b"\n") => {
+            log::log!(log_level_of(fragment), "[FlashDB] {}", LossyLine(fragment));
+        }
+        None => {}
     }
 }
 
 #[doc(hidden)]
 #[repr(C)]
 pub struct FlashVTable {
-    pub read:
-        unsafe extern "C" fn(storage: *mut c_void, addr: u32, buf: *mut u8, size: usize) -> i32,
+    // 失败时将存储后端的原始错误写入 `error`
+    pub read: unsafe fn(storage: *mut c_void, addr: u32, buf: *mut u8, size: usize, error: &mut Option<StorageError>) -> i32,
     pub write:
-        unsafe extern "C" fn(storage: *mut c_void, addr: u32, buf: *const u8, size: usize) -> i32,
-    pub erase: unsafe extern "C" fn(storage: *mut c_void, addr: u32, size: usize) -> i32,
+        unsafe fn(storage: *mut c_void, addr: u32, buf: *const u8, size: usize, error: &mut Option<StorageError>) -> i32,
+    pub erase: unsafe fn(storage: *mut c_void, addr: u32, size: usize, error: &mut Option<StorageError>) -> i32,
+    /// C 库要求同步写入时调用，由 `set_flush_on_sync()` 设置
+    pub sync: Option<unsafe fn(storage: *mut c_void, error: &mut Option<StorageError>) -> i32>,
 }
 
 // 调度器结构体
@@ -113,6 +286,26 @@ pub struct FlashVTable {
 pub struct FlashDispatch {
     pub vtable: FlashVTable,
     pub instance: *mut c_void,
+    /// 存储后端相对于调度器的偏移量，由 `bind()` 设置
+    pub(crate) storage_offset: isize,
+    pub(crate) timeout: Option<timeout::OpTimeout>,
+    /// 由 `set_lock()` 安装的数据库锁
+    pub(crate) lock: Option<&'static dyn lock::DbLock>,
+    pub(crate) counters: metrics::FlashCounters,
+    /// 为 `true` 时拒绝一切写入与擦除
+    pub(crate) read_only: bool,
+    #[cfg(feature = "alloc")]
+    pub(crate) events: Option<events::EventHook>,
+    /// 最近一次失败的存储后端操作，由 `finish()` 附加到返回的错误上
+    pub(crate) last_error: Option<StorageError>,
+    /// 为 `false` 时丢弃该数据库的全部日志，由 `set_logging()` 设置
+    #[cfg(feature = "log")]
+    pub(crate) logging: bool,
+    /// 输出日志的最低严重程度，由 `set_log_level()` 设置
+    #[cfg(feature = "log")]
+    pub(crate) log_level: log::Level,
+    #[cfg(feature = "log")]
+    log_line: core::cell::UnsafeCell<LogLine>,
 }
 
 impl FlashDispatch {
@@ -122,50 +315,126 @@ impl FlashDispatch {
                 read: vtable_read::<T>,
                 write: vtable_write::<T>,
                 erase: vtable_erase::<T>,
+                sync: None,
             },
             instance: core::ptr::null_mut(),
+            storage_offset: 0,
+            timeout: None,
+            lock: None,
+            counters: Default::default(),
+            read_only: false,
+            #[cfg(feature = "alloc")]
+            events: None,
+            last_error: None,
+            #[cfg(feature = "log")]
+            logging: true,
+            #[cfg(feature = "log")]
+            log_level: log::Level::Trace,
+            #[cfg(feature = "log")]
+            log_line: core::cell::UnsafeCell::new(LogLine::new()),
         };
     }
+
+    /// 按该数据库的日志设置输出完整的一行
+    #[cfg(feature = "log")]
+    fn emit_log(&self, line: &[u8]) {
+        let level = log_level_of(line);
+        if self.logging && level <= self.log_level {
+            log::log!(level, "{}", LossyLine(line));
+        }
+    }
+
+    /// 内部方法：将调度器绑定到数据库，返回传给 C 库的 `user_data`。
+    ///
+    /// `db`、调度器与 `storage` 位于同一个结构体中，C 结构体中只保存它们之间的偏移量，
+    /// 回调时再由 `db` 的地址计算出实际地址，因此数据库初始化后仍然可以被移动。
+    pub(crate) fn bind<T>(&mut self, db: &mut fdb_db, storage: &mut T) -> *mut c_void {
+        let dispatch = self as *mut Self as isize;
+        self.storage_offset = storage as *mut T as isize - dispatch;
+        (dispatch - db as *mut fdb_db as isize) as *mut c_void
+    }
+
+    /// 内部方法：由 C 库传入的数据库指针找到调度器，并刷新存储后端的地址
+    pub(crate) unsafe fn from_db<'a>(db: fdb_db_t) -> &'a mut Self {
+        let dispatch = db.byte_offset((*db).user_data as isize) as *mut Self;
+        (*dispatch).instance = dispatch.byte_offset((*dispatch).storage_offset) as *mut c_void;
+        &mut *dispatch
+    }
+
+    /// 内部方法：由 C 库输出日志时传入的数据库指针找到调度器，数据库尚未绑定时返回 `None`
+    #[cfg(feature = "log")]
+    unsafe fn log_target(db: fdb_db_t) -> Option<*const Self> {
+        if db.is_null() || (*db).user_data.is_null() {
+            return None;
+        }
+        Some(db.byte_offset((*db).user_data as isize) as *const Self)
+    }
 }
 
 // --- VTable 的具体实现函数  ---
-unsafe extern "C" fn vtable_read<F: NorFlash>(
+unsafe fn vtable_read<F: NorFlash>(
     storage: *mut c_void,
     addr: u32,
     buf: *mut u8,
     size: usize,
+    error: &mut Option<StorageError>,
 ) -> i32 {
     let flash = &mut *(storage as *mut F);
     let slice = core::slice::from_raw_parts_mut(buf, size);
     match flash.read(addr, slice) {
         Ok(_) => 0,
-        Err(_) => -1,
+        Err(err) => {
+            *error = Some(StorageError::new(StorageOp::Read, addr, &err));
+            -1
+        }
     }
 }
 
-unsafe extern "C" fn vtable_write<F: NorFlash>(
+unsafe fn vtable_write<F: NorFlash>(
     storage: *mut c_void,
     addr: u32,
     buf: *const u8,
     size: usize,
+    error: &mut Option<StorageError>,
 ) -> i32 {
     let flash = &mut *(storage as *mut F);
     let slice = core::slice::from_raw_parts(buf, size);
     match flash.write(addr, slice) {
         Ok(_) => 0,
-        Err(_) => -1,
+        Err(err) => {
+            *error = Some(StorageError::new(StorageOp::Write, addr, &err));
+            -1
+        }
+    }
+}
+
+pub(crate) unsafe fn vtable_sync<F: buffered::FlushStorage>(
+    storage: *mut c_void,
+    error: &mut Option<StorageError>,
+) -> i32 {
+    let flash = &mut *(storage as *mut F);
+    match flash.flush() {
+        Ok(_) => 0,
+        Err(err) => {
+            *error = Some(StorageError::new(StorageOp::Write, 0, &err));
+            -1
+        }
     }
 }
 
-unsafe extern "C" fn vtable_erase<F: NorFlash>(
+unsafe fn vtable_erase<F: NorFlash>(
     storage: *mut c_void,
     addr: u32,
     size: usize,
+    error: &mut Option<StorageError>,
 ) -> i32 {
     let flash = &mut *(storage as *mut F);
     match flash.erase(addr, addr + size as u32) {
         Ok(_) => 0,
-        Err(_) => -1,
+        Err(err) => {
+            *error = Some(StorageError::new(StorageOp::Erase, addr, &err));
+            -1
+        }
     }
 }
 
@@ -176,9 +445,8 @@ pub unsafe extern "C" fn fdb_custom_read(
     buf: *mut c_void,
     size: usize,
 ) -> fdb_err_t {
-    let dispatch = &*((*db).user_data as *const FlashDispatch);
-    let result = (dispatch.vtable.read)(dispatch.instance, addr, buf as *mut u8, size);
-    if result == 0 {
+    let dispatch = FlashDispatch::from_db(db);
+    if dispatch.run(|vtable, instance, error| (vtable.read)(instance, addr, buf as *mut u8, size, error)) {
         crate::fdb_err_t_FDB_NO_ERR
     } else {
         crate::fdb_err_t_FDB_READ_ERR
@@ -191,11 +459,17 @@ pub unsafe extern "C" fn fdb_custom_write(
     addr: u32,
     buf: *const c_void,
     size: usize,
-    _sync: bool,
+    sync: bool,
 ) -> fdb_err_t {
-    let dispatch = &*((*db).user_data as *const FlashDispatch);
-    let result = (dispatch.vtable.write)(dispatch.instance, addr, buf as *const u8, size);
-    if result == 0 {
+    let dispatch = FlashDispatch::from_db(db);
+    if dispatch.read_only {
+        return crate::fdb_err_t_FDB_WRITE_ERR;
+    }
+    if dispatch.run(|vtable, instance, error| match (vtable.write)(instance, addr, buf as *const u8, size, error) {
+        0 if sync => vtable.sync.map_or(0, |flush| flush(instance, error)),
+        result => result,
+    }) {
+        dispatch.count_write(size);
         crate::fdb_err_t_FDB_NO_ERR
     } else {
         crate::fdb_err_t_FDB_WRITE_ERR
@@ -204,9 +478,14 @@ pub unsafe extern "C" fn fdb_custom_write(
 
 #[no_mangle]
 pub unsafe extern "C" fn fdb_custom_erase(db: fdb_db_t, addr: u32, size: usize) -> fdb_err_t {
-    let dispatch = &*((*db).user_data as *const FlashDispatch);
-    let result = (dispatch.vtable.erase)(dispatch.instance, addr, size);
-    if result == 0 {
+    let dispatch = FlashDispatch::from_db(db);
+    if dispatch.read_only {
+        return crate::fdb_err_t_FDB_ERASE_ERR;
+    }
+    #[cfg(feature = "alloc")]
+    dispatch.before_erase(addr);
+    if dispatch.run(|vtable, instance, error| (vtable.erase)(instance, addr, size, error)) {
+        dispatch.count_erase();
         crate::fdb_err_t_FDB_NO_ERR
     } else {
         crate::fdb_err_t_FDB_ERASE_ERR
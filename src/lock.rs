@@ -0,0 +1,214 @@
+//! C 库的数据库锁。
+//!
+//! FlashDB 在每个公开操作 (读写 KV、追加 TSL、迭代、垃圾回收等) 的开始和结束调用用户提供的
+//! `lock` / `unlock` 钩子。通过 `KVDB::set_lock()` / `TSDB::set_lock()` 安装 [`DbLock`] 后，
+//! 即使 C 库的函数被中断服务程序或其他任务直接调用，同一数据库上的操作也不会交错执行。
+//!
+//! 常用的实现：
+//! - [`NoLock`]: 不加锁，与不安装锁相同，适合单线程环境；
+//! - 一对闭包 `(lock, unlock)`: 例如关闭 / 恢复中断的临界区，或 RTOS 的互斥量；
+//! - [`CriticalSectionLock`]: 进入 / 退出临界区，适合没有 RTOS、由中断服务程序写入的裸机环境；
+//! - [`StdLock`]: 基于 `std::sync` 的锁 (需要 `std` 特性)。
+//!
+//! **注意**: 钩子在 C 库内部调用，不能 panic，也不能在钩子中访问同一个数据库。
+
+use core::cell::Cell;
+
+use crate::{fdb_db, fdb_db_t, FlashDispatch};
+
+/// 数据库锁
+///
+/// 数据库可能通过 `split()` 在多个线程间共享，因此锁必须是 `Sync` 的。
+pub trait DbLock: Sync {
+    /// 获取锁，在锁被占用时等待
+    fn lock(&self);
+
+    /// 释放锁
+    fn unlock(&self);
+}
+
+/// 不加锁
+pub struct NoLock;
+
+impl DbLock for NoLock {
+    fn lock(&self) {}
+
+    fn unlock(&self) {}
+}
+
+impl<L: Fn() + Sync, U: Fn() + Sync> DbLock for (L, U) {
+    fn lock(&self) {
+        (self.0)()
+    }
+
+    fn unlock(&self) {
+        (self.1)()
+    }
+}
+
+/// 在临界区内执行数据库操作的锁，例如定时器中断追加 TSL、主循环读取的裸机程序。
+///
+/// `acquire` 进入临界区 (通常是关闭中断) 并返回恢复状态，`release` 以该状态退出临界区。
+/// 两者的签名与 `critical-section` crate 的 `critical_section::acquire` / `release` 相同，
+/// 可以直接传入；也可以传入芯片 HAL 中关闭 / 恢复中断的函数。
+///
+/// 已经处于临界区内 (例如在中断服务程序中，或在另一个数据库的迭代回调中) 再次加锁是安全的。
+/// 临界区会阻塞所有中断，只应用于写入少量数据等耗时较短的操作，
+/// 擦除扇区的垃圾回收可能长时间关闭中断。
+///
+/// # 示例
+///
+/// ```
+/// # use flashdb_rs::lock::CriticalSectionLock;
+/// # use flashdb_rs::test_utils::FaultyFlash;
+/// # use flashdb_rs::TSDB;
+/// # use core::sync::atomic::{AtomicBool, Ordering};
+/// static MASKED: AtomicBool = AtomicBool::new(false);
+///
+/// /// 关闭中断并返回之前的状态
+/// unsafe fn acquire() -> bool {
+///     MASKED.swap(true, Ordering::SeqCst)
+/// }
+///
+/// /// 恢复之前的中断状态
+/// unsafe fn release(was_masked: bool) {
+///     MASKED.store(was_masked, Ordering::SeqCst);
+/// }
+///
+/// static LOCK: CriticalSectionLock<bool> = CriticalSectionLock::new(acquire, release);
+///
+/// let mut db = Box::new(TSDB::new(FaultyFlash::new(4 * 4096)));
+/// db.set_lock(&LOCK);
+/// db.init(64)?;
+/// db.append_with_timestamp(1, b"sample")?;
+/// assert!(!MASKED.load(Ordering::SeqCst));
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct CriticalSectionLock<R> {
+    acquire: unsafe fn() -> R,
+    release: unsafe fn(R),
+    /// 最外层 `acquire` 返回的恢复状态
+    state: Cell<Option<R>>,
+    /// 嵌套层数
+    depth: Cell<u32>,
+}
+
+// SAFETY: `state` 与 `depth` 只在临界区内访问
+unsafe impl<R: Send> Sync for CriticalSectionLock<R> {}
+
+impl<R> CriticalSectionLock<R> {
+    /// 以进入 / 退出临界区的函数创建锁。
+    pub const fn new(acquire: unsafe fn() -> R, release: unsafe fn(R)) -> Self {
+        Self {
+            acquire,
+            release,
+            state: Cell::new(None),
+            depth: Cell::new(0),
+        }
+    }
+}
+
+impl<R: Send> DbLock for CriticalSectionLock<R> {
+    fn lock(&self) {
+        let state = unsafe { (self.acquire)() };
+        match self.depth.get() {
+            0 => self.state.set(Some(state)),
+            // 嵌套的临界区立即以其状态退出，仍然处于外层临界区中
+            _ => unsafe { (self.release)(state) },
+        }
+        self.depth.set(self.depth.get() + 1);
+    }
+
+    fn unlock(&self) {
+        let depth = self.depth.get().saturating_sub(1);
+        self.depth.set(depth);
+        if depth == 0 {
+            if let Some(state) = self.state.take() {
+                unsafe { (self.release)(state) };
+            }
+        }
+    }
+}
+
+/// 基于 `std::sync::Mutex` 与 `Condvar` 的锁
+///
+/// # 示例
+///
+/// ```
+/// # use flashdb_rs::lock::StdLock;
+/// # use flashdb_rs::KVDB;
+/// static LOCK: StdLock = StdLock::new();
+///
+/// # let dir = tempfile::tempdir()?;
+/// let mut db = KVDB::new_file("lock_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+/// db.set_lock(&LOCK);
+/// db.set("key", b"value")?;
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+#[cfg(feature = "std")]
+pub struct StdLock {
+    locked: std::sync::Mutex<bool>,
+    released: std::sync::Condvar,
+}
+
+#[cfg(feature = "std")]
+impl StdLock {
+    /// 创建一个未被占用的锁。
+    pub const fn new() -> Self {
+        Self {
+            locked: std::sync::Mutex::new(false),
+            released: std::sync::Condvar::new(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for StdLock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl DbLock for StdLock {
+    fn lock(&self) {
+        let mut locked = self.locked.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        while *locked {
+            locked = self
+                .released
+                .wait(locked)
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+        }
+        *locked = true;
+    }
+
+    fn unlock(&self) {
+        *self.locked.lock().unwrap_or_else(std::sync::PoisonError::into_inner) = false;
+        self.released.notify_one();
+    }
+}
+
+/// 内部方法：C 库的 `lock` 钩子，转发给调度器中的 `DbLock`
+unsafe extern "C" fn dispatch_lock(db: fdb_db_t) {
+    let dispatch = &*(db.byte_offset((*db).user_data as isize) as *const FlashDispatch);
+    if let Some(lock) = dispatch.lock {
+        lock.lock();
+    }
+}
+
+/// 内部方法：C 库的 `unlock` 钩子
+unsafe extern "C" fn dispatch_unlock(db: fdb_db_t) {
+    let dispatch = &*(db.byte_offset((*db).user_data as isize) as *const FlashDispatch);
+    if let Some(lock) = dispatch.lock {
+        lock.unlock();
+    }
+}
+
+impl FlashDispatch {
+    /// 内部方法：安装或移除锁，并设置 C 结构体中的钩子
+    pub(crate) fn install_lock(&mut self, db: &mut fdb_db, lock: Option<&'static dyn DbLock>) {
+        self.lock = lock;
+        db.lock = lock.map(|_| dispatch_lock as unsafe extern "C" fn(fdb_db_t));
+        db.unlock = lock.map(|_| dispatch_unlock as unsafe extern "C" fn(fdb_db_t));
+    }
+}
@@ -0,0 +1,50 @@
+//! Flash 操作计数，供 `KVDB::metrics()` 统计写放大与磨损。
+
+// 仅启用 TSDB 时计数不会被读取
+#![cfg_attr(not(feature = "kvdb"), allow(dead_code))]
+
+use crate::FlashDispatch;
+
+/// 通过数据库实例产生的 Flash 操作计数
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct FlashCounters {
+    /// 写入 Flash 的字节数
+    pub bytes_written: u64,
+    /// 擦除的扇区数
+    pub erases: u64,
+    /// 应用写入的键与值的字节数
+    pub payload_bytes: u64,
+    /// 垃圾回收次数
+    pub gc_runs: u64,
+    /// 当前是否处于可能触发垃圾回收的写操作中
+    in_write: bool,
+    /// 本次写操作是否已经计入过垃圾回收
+    gc_counted: bool,
+}
+
+impl FlashDispatch {
+    /// 开始一次可能触发垃圾回收的写操作
+    pub(crate) fn begin_write(&mut self) {
+        self.counters.in_write = true;
+        self.counters.gc_counted = false;
+    }
+
+    /// 结束当前写操作
+    pub(crate) fn end_write(&mut self) {
+        self.counters.in_write = false;
+    }
+
+    /// 记录一次成功的写入
+    pub(crate) fn count_write(&mut self, size: usize) {
+        self.counters.bytes_written += size as u64;
+    }
+
+    /// 记录一次成功的擦除，写操作中的擦除只会来自垃圾回收
+    pub(crate) fn count_erase(&mut self) {
+        self.counters.erases += 1;
+        if self.counters.in_write && !self.counters.gc_counted {
+            self.counters.gc_counted = true;
+            self.counters.gc_runs += 1;
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! 将数据同时保存在两块 Flash 上的冗余存储，用于需要冗余配置存储的安全关键设备。
+
+use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash};
+
+/// `resync()` 复制数据时使用的缓冲区大小
+const CHUNK: usize = 256;
+
+/// 镜像中的一份副本
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MirrorSide {
+    /// 第一块 Flash
+    A,
+    /// 第二块 Flash
+    B,
+}
+
+impl MirrorSide {
+    /// 另一份副本
+    pub fn other(self) -> Self {
+        match self {
+            MirrorSide::A => MirrorSide::B,
+            MirrorSide::B => MirrorSide::A,
+        }
+    }
+}
+
+/// 可以在读取到损坏的数据后切换到另一份副本的存储。
+///
+/// `KVDB::get_redundant()` 在 CRC 校验失败时通过 `fail_over()` 切换副本并重新读取。
+pub trait FailoverStorage: NorFlash {
+    /// 切换到另一份健康的副本，没有可用的副本时返回 `false`。
+    fn fail_over(&mut self) -> bool;
+}
+
+/// `MirroredStorage` 的错误
+#[derive(Debug)]
+pub enum MirrorError<EA, EB> {
+    /// 副本 A 的错误，副本 B 已被标记为不可用
+    A(EA),
+    /// 副本 B 的错误，副本 A 已被标记为不可用
+    B(EB),
+    /// 两份副本都失败
+    Both(EA, EB),
+}
+
+impl<EA: NorFlashError, EB: NorFlashError> NorFlashError for MirrorError<EA, EB> {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            MirrorError::A(err) | MirrorError::Both(err, _) => err.kind(),
+            MirrorError::B(err) => err.kind(),
+        }
+    }
+}
+
+/// 将每次写入与擦除同时作用于两块 Flash、读取时在副本之间自动切换的存储包装器。
+///
+/// - 写入与擦除只要有一份副本成功即视为成功，失败的副本被标记为降级 (`degraded()`)，
+///   此后不再从中读取，直到 `resync()` 从健康的副本恢复。
+/// - 读取从当前副本 (`active()`) 进行，失败时自动改为读取另一份副本。
+/// - 数据损坏 (例如位翻转) 在存储层无法发现，`KVDB::get_redundant()` 在 CRC 校验失败时
+///   会通过 [`FailoverStorage`] 切换副本并重试。
+///
+/// 两块 Flash 的擦除大小必须相同，容量取两者中较小的一个。
+///
+/// # 示例
+///
+/// ```
+/// # use flashdb_rs::mirror::MirroredStorage;
+/// # use flashdb_rs::test_utils::FaultyFlash;
+/// # use flashdb_rs::KVDB;
+/// let mirror = MirroredStorage::new(FaultyFlash::new(4 * 4096), FaultyFlash::new(4 * 4096));
+/// let mut db = Box::new(KVDB::new(mirror));
+/// db.set_name("mirror_doc")?;
+/// db.init(None)?;
+/// db.set("serial", b"SN-0001")?;
+/// assert_eq!(db.get_redundant("serial")?.as_deref(), Some(&b"SN-0001"[..]));
+/// assert_eq!(db.storage().degraded(), None);
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct MirroredStorage<A: NorFlash, B: NorFlash> {
+    a: A,
+    b: B,
+    active: MirrorSide,
+    degraded: Option<MirrorSide>,
+}
+
+impl<A: NorFlash, B: NorFlash> MirroredStorage<A, B> {
+    /// 编译期检查两块 Flash 的几何参数
+    const GEOMETRY_CHECK: () = assert!(
+        A::ERASE_SIZE == B::ERASE_SIZE
+            && Self::READ_SIZE % A::READ_SIZE == 0
+            && Self::READ_SIZE % B::READ_SIZE == 0
+            && Self::WRITE_SIZE % A::WRITE_SIZE == 0
+            && Self::WRITE_SIZE % B::WRITE_SIZE == 0
+            && Self::WRITE_SIZE <= CHUNK
+            && Self::READ_SIZE <= CHUNK,
+        "MirroredStorage requires flashes with the same ERASE_SIZE and compatible READ_SIZE / WRITE_SIZE"
+    );
+
+    /// 以 `a` 和 `b` 作为两份副本，初始从 `a` 读取。
+    pub fn new(a: A, b: B) -> Self {
+        let () = Self::GEOMETRY_CHECK;
+        Self {
+            a,
+            b,
+            active: MirrorSide::A,
+            degraded: None,
+        }
+    }
+
+    /// 当前读取的副本。
+    pub fn active(&self) -> MirrorSide {
+        self.active
+    }
+
+    /// 被标记为降级的副本，其中的数据可能已经过时。
+    pub fn degraded(&self) -> Option<MirrorSide> {
+        self.degraded
+    }
+
+    /// 将当前副本的内容完整复制到另一份副本，并清除降级标记。
+    ///
+    /// 通常在启动时、`degraded()` 不为 `None` 时调用，在交给数据库之前完成。
+    pub fn resync(&mut self) -> Result<(), MirrorError<A::Error, B::Error>> {
+        let capacity = self.capacity() as u32;
+        let chunk = CHUNK - CHUNK % Self::WRITE_SIZE.max(Self::READ_SIZE);
+        let mut buf = [0u8; CHUNK];
+        for sector in (0..capacity).step_by(Self::ERASE_SIZE) {
+            let end = sector + Self::ERASE_SIZE as u32;
+            match self.active {
+                MirrorSide::A => self.b.erase(sector, end).map_err(MirrorError::B)?,
+                MirrorSide::B => self.a.erase(sector, end).map_err(MirrorError::A)?,
+            }
+            for pos in (sector..end).step_by(chunk) {
+                let buf = &mut buf[..chunk.min((end - pos) as usize)];
+                match self.active {
+                    MirrorSide::A => {
+                        self.a.read(pos, buf).map_err(MirrorError::A)?;
+                        // 擦除状态的数据不需要复制
+                        if buf.iter().any(|&b| b != 0xFF) {
+                            self.b.write(pos, buf).map_err(MirrorError::B)?;
+                        }
+                    }
+                    MirrorSide::B => {
+                        self.b.read(pos, buf).map_err(MirrorError::B)?;
+                        if buf.iter().any(|&b| b != 0xFF) {
+                            self.a.write(pos, buf).map_err(MirrorError::A)?;
+                        }
+                    }
+                }
+            }
+        }
+        self.degraded = None;
+        Ok(())
+    }
+
+    /// 获取副本 A 的引用。
+    pub fn a(&self) -> &A {
+        &self.a
+    }
+
+    /// 获取副本 B 的引用。
+    pub fn b(&self) -> &B {
+        &self.b
+    }
+
+    /// 取回两块 Flash。
+    pub fn into_inner(self) -> (A, B) {
+        (self.a, self.b)
+    }
+
+    /// 内部方法：合并两份副本的操作结果，只有一份失败时将其标记为降级
+    fn merge(
+        &mut self,
+        a: Result<(), A::Error>,
+        b: Result<(), B::Error>,
+    ) -> Result<(), MirrorError<A::Error, B::Error>> {
+        match (a, b) {
+            (Ok(()), Ok(())) => {}
+            // 唯一健康的副本失败时不能再切换到已过时的副本
+            (Err(a), Ok(())) if self.degraded == Some(MirrorSide::B) => return Err(MirrorError::A(a)),
+            (Ok(()), Err(b)) if self.degraded == Some(MirrorSide::A) => return Err(MirrorError::B(b)),
+            (Err(_), Ok(())) => self.degrade(MirrorSide::A),
+            (Ok(()), Err(_)) => self.degrade(MirrorSide::B),
+            (Err(a), Err(b)) => return Err(MirrorError::Both(a, b)),
+        }
+        Ok(())
+    }
+
+    /// 内部方法：标记降级的副本，并从另一份副本读取
+    fn degrade(&mut self, side: MirrorSide) {
+        self.degraded = Some(side);
+        self.active = side.other();
+    }
+}
+
+impl<A: NorFlash, B: NorFlash> FailoverStorage for MirroredStorage<A, B> {
+    fn fail_over(&mut self) -> bool {
+        if self.degraded.is_some() {
+            return false;
+        }
+        self.degrade(self.active);
+        true
+    }
+}
+
+impl<A: NorFlash, B: NorFlash> ErrorType for MirroredStorage<A, B> {
+    type Error = MirrorError<A::Error, B::Error>;
+}
+
+impl<A: NorFlash, B: NorFlash> ReadNorFlash for MirroredStorage<A, B> {
+    const READ_SIZE: usize = if A::READ_SIZE > B::READ_SIZE { A::READ_SIZE } else { B::READ_SIZE };
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let result = match self.active {
+            MirrorSide::A => self.a.read(offset, bytes).map_err(MirrorError::A),
+            MirrorSide::B => self.b.read(offset, bytes).map_err(MirrorError::B),
+        };
+        match result {
+            Err(err) if self.degraded.is_none() => {
+                // 当前副本读取失败，改为读取另一份副本
+                self.degrade(self.active);
+                let retry = match self.active {
+                    MirrorSide::A => self.a.read(offset, bytes).map_err(MirrorError::A),
+                    MirrorSide::B => self.b.read(offset, bytes).map_err(MirrorError::B),
+                };
+                match (err, retry) {
+                    (_, Ok(())) => Ok(()),
+                    (MirrorError::A(a), Err(MirrorError::B(b))) | (MirrorError::B(b), Err(MirrorError::A(a))) => {
+                        Err(MirrorError::Both(a, b))
+                    }
+                    (_, Err(err)) => Err(err),
+                }
+            }
+            result => result,
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.a.capacity().min(self.b.capacity())
+    }
+}
+
+impl<A: NorFlash, B: NorFlash> NorFlash for MirroredStorage<A, B> {
+    const WRITE_SIZE: usize = if A::WRITE_SIZE > B::WRITE_SIZE { A::WRITE_SIZE } else { B::WRITE_SIZE };
+    const ERASE_SIZE: usize = A::ERASE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        let a = self.a.erase(from, to);
+        let b = self.b.erase(from, to);
+        self.merge(a, b)
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let a = self.a.write(offset, bytes);
+        let b = self.b.write(offset, bytes);
+        self.merge(a, b)
+    }
+}
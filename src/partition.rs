@@ -0,0 +1,183 @@
+//! 将一块 Flash 划分为多个分区，在同一颗外部 Flash 上同时存放多个数据库。
+//!
+//! ```
+//! use core::cell::RefCell;
+//! use flashdb_rs::partition::Partition;
+//! use flashdb_rs::test_utils::FaultyFlash;
+//! use flashdb_rs::{static_assert_partitions, KVDB, TSDB};
+//!
+//! // 64 KiB 的 KVDB 与 192 KiB 的 TSDB
+//! static_assert_partitions!(FaultyFlash, 256 * 1024, [(0, 64 * 1024), (64 * 1024, 192 * 1024)]);
+//!
+//! let chip = RefCell::new(FaultyFlash::new(256 * 1024));
+//! let mut kv = Box::new(KVDB::new(Partition::new(&chip, 0, 64 * 1024)?));
+//! kv.set_name("env")?;
+//! kv.init(None)?;
+//! let mut ts = Box::new(TSDB::new(Partition::new(&chip, 64 * 1024, 192 * 1024)?));
+//! ts.set_name("log")?;
+//! ts.init(128)?;
+//!
+//! kv.set("boot_count", b"1")?;
+//! ts.append_with_timestamp(1, b"boot")?;
+//! # Ok::<(), flashdb_rs::Error>(())
+//! ```
+
+use core::cell::RefCell;
+
+use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash};
+
+use crate::Error;
+
+/// 分区访问的错误
+#[derive(Debug)]
+pub enum PartitionError<E> {
+    /// 访问超出了分区的范围
+    OutOfBounds,
+    /// 底层 Flash 的错误
+    Flash(E),
+}
+
+impl<E: NorFlashError> NorFlashError for PartitionError<E> {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            PartitionError::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            PartitionError::Flash(err) => err.kind(),
+        }
+    }
+}
+
+/// 将底层 Flash 中 `[offset, offset + len)` 的区域作为独立的 `NorFlash`。
+///
+/// 多个分区通过 `RefCell` 共享同一块 Flash，分区内的地址从 0 开始，超出分区的访问返回
+/// `PartitionError::OutOfBounds`，因此一个数据库不会写入另一个数据库的区域。
+/// 分区之间不能重叠，可以使用 [`static_assert_partitions!`](crate::static_assert_partitions) 在编译时检查。
+///
+/// **注意**: 各分区上的数据库操作不能相互嵌套 (例如在一个数据库的回调中操作另一个数据库)，
+/// 否则 `RefCell` 会因重复借用而 panic。
+pub struct Partition<'a, S: NorFlash> {
+    flash: &'a RefCell<S>,
+    offset: u32,
+    len: u32,
+}
+
+impl<'a, S: NorFlash> Partition<'a, S> {
+    /// 创建分区，`offset` 与 `len` 必须按 `S::ERASE_SIZE` 对齐且不超出 Flash 的容量，否则返回 `Error::InvalidArgument`。
+    pub fn new(flash: &'a RefCell<S>, offset: u32, len: u32) -> Result<Self, Error> {
+        let erase_size = S::ERASE_SIZE as u32;
+        let end = offset.checked_add(len).ok_or(Error::InvalidArgument)?;
+        if len == 0 || offset % erase_size != 0 || len % erase_size != 0 || end as usize > flash.borrow().capacity() {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(Self { flash, offset, len })
+    }
+
+    /// 分区在底层 Flash 中的起始地址。
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// 分区的长度。
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// 内部方法：底层 Flash 的标识，即 `RefCell` 的地址
+    #[cfg(feature = "registry")]
+    pub(crate) fn device_id(&self) -> u64 {
+        self.flash as *const RefCell<S> as usize as u64
+    }
+
+    /// 内部方法：将分区内的范围转换为底层 Flash 的地址
+    fn translate(&self, offset: u32, len: usize) -> Result<u32, PartitionError<S::Error>> {
+        match (offset as u64).checked_add(len as u64) {
+            Some(end) if end <= self.len as u64 => Ok(self.offset + offset),
+            _ => Err(PartitionError::OutOfBounds),
+        }
+    }
+}
+
+impl<S: NorFlash> ErrorType for Partition<'_, S> {
+    type Error = PartitionError<S::Error>;
+}
+
+impl<S: NorFlash> ReadNorFlash for Partition<'_, S> {
+    const READ_SIZE: usize = S::READ_SIZE;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let addr = self.translate(offset, bytes.len())?;
+        self.flash.borrow_mut().read(addr, bytes).map_err(PartitionError::Flash)
+    }
+
+    fn capacity(&self) -> usize {
+        self.len as usize
+    }
+}
+
+impl<S: NorFlash> NorFlash for Partition<'_, S> {
+    const WRITE_SIZE: usize = S::WRITE_SIZE;
+    const ERASE_SIZE: usize = S::ERASE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        if to < from {
+            return Err(PartitionError::OutOfBounds);
+        }
+        let addr = self.translate(from, (to - from) as usize)?;
+        self.flash.borrow_mut().erase(addr, addr + (to - from)).map_err(PartitionError::Flash)
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let addr = self.translate(offset, bytes.len())?;
+        self.flash.borrow_mut().write(addr, bytes).map_err(PartitionError::Flash)
+    }
+}
+
+/// 在编译时检查分区表：每个分区 `(offset, len)` 都按 `S::ERASE_SIZE` 对齐、不超出 `capacity` 且互不重叠。
+///
+/// # 示例
+///
+/// ```compile_fail
+/// use flashdb_rs::static_assert_partitions;
+/// use flashdb_rs::StdStorage;
+///
+/// // 两个分区重叠
+/// static_assert_partitions!(StdStorage, 64 * 1024, [(0, 32 * 1024), (16 * 1024, 32 * 1024)]);
+/// ```
+#[macro_export]
+macro_rules! static_assert_partitions {
+    ($storage:ty, $capacity:expr, [$(($offset:expr, $len:expr)),* $(,)?]) => {
+        const _: () = $crate::partition::check_partitions::<$storage>(
+            $capacity as u32,
+            &[$(($offset as u32, $len as u32)),*],
+        );
+    };
+}
+
+/// `static_assert_partitions!` 使用的常量检查函数，也可以在运行时调用。
+///
+/// 分区表无效时会 panic；在常量上下文中调用时表现为编译错误。
+pub const fn check_partitions<S: NorFlash>(capacity: u32, partitions: &[(u32, u32)]) {
+    let erase_size = S::ERASE_SIZE as u32;
+    let mut i = 0;
+    while i < partitions.len() {
+        let (offset, len) = partitions[i];
+        assert!(len > 0, "partition length must not be zero");
+        assert!(
+            offset % erase_size == 0 && len % erase_size == 0,
+            "partitions must be aligned to ERASE_SIZE"
+        );
+        assert!(
+            offset as u64 + len as u64 <= capacity as u64,
+            "partition exceeds the flash capacity"
+        );
+        let mut j = 0;
+        while j < i {
+            let (other, other_len) = partitions[j];
+            assert!(
+                offset as u64 + len as u64 <= other as u64 || other as u64 + other_len as u64 <= offset as u64,
+                "partitions overlap"
+            );
+            j += 1;
+        }
+        i += 1;
+    }
+}
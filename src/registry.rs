@@ -0,0 +1,149 @@
+//! 防止同一块 Flash 区域被多个数据库同时打开。
+//!
+//! 两个数据库实例初始化在重叠的区域上时，双方都会把对方写入的数据当作损坏的扇区回收，
+//! 最终导致数据丢失。为数据库设置 [`Region`] 后，`init()` 会在全局登记表中登记该区域，
+//! 与已登记的区域重叠时返回 `Error::Busy`，`deinit()`、`close()` 或销毁数据库时注销。
+//!
+//! - `std` 环境下 `new_file()` 以数据库文件的路径自动设置区域；
+//! - `no_std` 环境下以 Flash 设备的标识与地址范围描述区域，也可以由 [`Partition`] 得到。
+//!
+//! 登记表最多同时容纳 [`CAPACITY`] 个区域，没有空位时同样返回 `Error::Busy`。
+//!
+//! # 示例
+//!
+//! ```
+//! # use flashdb_rs::registry::Region;
+//! # use flashdb_rs::test_utils::FaultyFlash;
+//! # use flashdb_rs::{Error, KVDB};
+//! // 外部 SPI Flash 上的 0x0000 ~ 0x4000
+//! const CONFIG: Region = Region::new(1, 0x0000..0x4000);
+//!
+//! let mut config = Box::new(KVDB::new(FaultyFlash::new(4 * 4096)));
+//! config.set_region(CONFIG)?;
+//! config.init(None)?;
+//!
+//! // 误将另一个数据库配置到重叠的区域
+//! let mut other = Box::new(KVDB::new(FaultyFlash::new(4 * 4096)));
+//! other.set_region(Region::new(1, 0x2000..0x6000))?;
+//! assert!(matches!(other.init(None), Err(Error::Busy)));
+//! # Ok::<(), flashdb_rs::Error>(())
+//! ```
+//!
+//! [`Partition`]: crate::partition::Partition
+
+use core::cell::UnsafeCell;
+use core::ops::Range;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+
+/// 登记表最多同时容纳的区域数
+pub const CAPACITY: usize = 16;
+
+/// 一块 Flash 区域：设备标识与字节地址范围 `[start, end)`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    device: u64,
+    start: u64,
+    end: u64,
+}
+
+impl Region {
+    /// 设备 `device` 上的地址范围 `range`。
+    ///
+    /// `device` 由使用者约定，用于区分不同的 Flash 芯片，例如片选编号或映射的基地址。
+    pub const fn new(device: u64, range: Range<u64>) -> Self {
+        Self {
+            device,
+            start: range.start,
+            end: range.end,
+        }
+    }
+
+    /// 分区在共享 Flash 上占据的区域，以 `RefCell` 的地址作为设备标识。
+    pub fn of_partition<S: NorFlash>(partition: &crate::partition::Partition<'_, S>) -> Self {
+        let start = partition.offset() as u64;
+        Self::new(partition.device_id(), start..start + partition.len() as u64)
+    }
+
+    /// 文件 `path` 对应的区域，所在目录会先被规范化，文件本身不必存在。
+    #[cfg(feature = "std")]
+    pub fn for_path(path: &std::path::Path) -> Self {
+        use std::hash::{Hash, Hasher};
+
+        let path = match (path.parent().map(std::fs::canonicalize), path.file_name()) {
+            (Some(Ok(dir)), Some(name)) => dir.join(name),
+            _ => path.to_path_buf(),
+        };
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        path.hash(&mut hasher);
+        Self::new(hasher.finish(), 0..u64::MAX)
+    }
+
+    /// 设备标识
+    pub fn device(&self) -> u64 {
+        self.device
+    }
+
+    /// 地址范围
+    pub fn range(&self) -> Range<u64> {
+        self.start..self.end
+    }
+
+    /// 两块区域是否重叠
+    pub fn overlaps(&self, other: &Region) -> bool {
+        self.device == other.device && self.start < other.end && other.start < self.end
+    }
+}
+
+/// 全局登记表，由自旋锁保护
+struct Registry {
+    locked: AtomicBool,
+    slots: UnsafeCell<[Option<Region>; CAPACITY]>,
+}
+
+// SAFETY: `slots` 只在持有 `locked` 时访问
+unsafe impl Sync for Registry {}
+
+static REGISTRY: Registry = Registry {
+    locked: AtomicBool::new(false),
+    slots: UnsafeCell::new([None; CAPACITY]),
+};
+
+impl Registry {
+    fn with<R>(&self, f: impl FnOnce(&mut [Option<Region>; CAPACITY]) -> R) -> R {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+        let result = f(unsafe { &mut *self.slots.get() });
+        self.locked.store(false, Ordering::Release);
+        result
+    }
+}
+
+/// 内部方法：登记区域，与已登记的区域重叠或登记表已满时返回 `Error::Busy`
+pub(crate) fn claim(region: Region) -> Result<(), Error> {
+    REGISTRY.with(|slots| {
+        if slots.iter().flatten().any(|claimed| claimed.overlaps(&region)) {
+            return Err(Error::Busy);
+        }
+        let slot = slots.iter_mut().find(|slot| slot.is_none()).ok_or(Error::Busy)?;
+        *slot = Some(region);
+        Ok(())
+    })
+}
+
+/// 内部方法：注销区域
+pub(crate) fn release(region: Region) {
+    REGISTRY.with(|slots| {
+        if let Some(slot) = slots.iter_mut().find(|slot| **slot == Some(region)) {
+            *slot = None;
+        }
+    })
+}
@@ -0,0 +1,309 @@
+//! 在多个任务之间共享数据库。
+//!
+//! `KVDB::split()` / `TSDB::split()` 将数据库拆分为一个可克隆的 [`Reader`] 与唯一的 [`Writer`]，
+//! 例如采样任务通过 `Writer` 追加日志，上传任务通过 `Reader` 遍历日志，
+//! 调用方无需自行在每次调用外加锁。每个方法只在调用期间独占数据库，调用结束即释放。
+//!
+//! `Reader` 只提供只读操作，因此可以放心交给任意多个任务；修改数据库只能通过 `Writer` 进行。
+//! 当存储后端 `S` 是 `Send` 时，两者都可以发送到其他线程。
+//!
+//! 不需要区分读写的场合可以使用 [`SharedKVDB`] / [`SharedTSDB`]：它们是 `Send + Sync` 的，
+//! 可以放入 `Arc` 或 `static` 中，通过闭包 `with()` 在锁内访问数据库。
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::vec::Vec;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+#[cfg(feature = "kvdb")]
+use crate::KVDB;
+#[cfg(feature = "tsdb")]
+use crate::{StatusSet, TSLEntry, TSLStatus, TSDB};
+
+/// 数据库的只读句柄，可以克隆后交给多个任务。
+pub struct Reader<D> {
+    db: Arc<Mutex<Box<D>>>,
+}
+
+/// 数据库的读写句柄，每个数据库只有一个。
+pub struct Writer<D> {
+    db: Arc<Mutex<Box<D>>>,
+}
+
+// SAFETY: 数据库中的裸指针只指向数据库自身 (已固定在 `Box` 中) 或存储后端，
+// 所有访问都经过互斥锁串行化。事件回调要求 `Send`，时钟要求 `Sync`，
+// 因此只要存储后端可以跨线程发送，整个数据库就可以跨线程访问。
+#[cfg(feature = "kvdb")]
+unsafe impl<S: NorFlash + Send> Send for Reader<KVDB<S>> {}
+#[cfg(feature = "kvdb")]
+unsafe impl<S: NorFlash + Send> Send for Writer<KVDB<S>> {}
+#[cfg(feature = "tsdb")]
+unsafe impl<S: NorFlash + Send> Send for Reader<TSDB<S>> {}
+#[cfg(feature = "tsdb")]
+unsafe impl<S: NorFlash + Send> Send for Writer<TSDB<S>> {}
+
+/// 内部带锁、可以在线程间共享的数据库，通过 `with()` 在锁内访问。
+///
+/// # 示例
+///
+/// ```
+/// # use flashdb_rs::shared::SharedKVDB;
+/// # use flashdb_rs::KVDB;
+/// # use std::sync::Arc;
+/// # let dir = tempfile::tempdir()?;
+/// let db = KVDB::new_file("shared_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+/// let db = Arc::new(SharedKVDB::new(db));
+///
+/// let workers: Vec<_> = (0..4)
+///     .map(|i| {
+///         let db = db.clone();
+///         std::thread::spawn(move || db.with(|db| db.set(&format!("worker{}", i), b"done")))
+///     })
+///     .collect();
+/// for worker in workers {
+///     worker.join().unwrap()?;
+/// }
+/// assert!(db.with(|db| db.contains_key("worker3"))?);
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct Shared<D> {
+    db: Mutex<Box<D>>,
+}
+
+/// 可以在线程间共享的 KVDB
+#[cfg(feature = "kvdb")]
+pub type SharedKVDB<S> = Shared<KVDB<S>>;
+
+/// 可以在线程间共享的 TSDB
+#[cfg(feature = "tsdb")]
+pub type SharedTSDB<S> = Shared<TSDB<S>>;
+
+// SAFETY: 与 `Reader` / `Writer` 相同，所有访问都经过互斥锁串行化
+#[cfg(feature = "kvdb")]
+unsafe impl<S: NorFlash + Send> Send for Shared<KVDB<S>> {}
+#[cfg(feature = "kvdb")]
+unsafe impl<S: NorFlash + Send> Sync for Shared<KVDB<S>> {}
+#[cfg(feature = "tsdb")]
+unsafe impl<S: NorFlash + Send> Send for Shared<TSDB<S>> {}
+#[cfg(feature = "tsdb")]
+unsafe impl<S: NorFlash + Send> Sync for Shared<TSDB<S>> {}
+
+impl<D> Shared<D> {
+    /// 接管已初始化的数据库。
+    pub fn new(db: Box<D>) -> Self {
+        Self { db: Mutex::new(db) }
+    }
+
+    /// 锁定数据库并在锁内执行 `f`，其他线程的调用会等待 `f` 返回。
+    pub fn with<R>(&self, f: impl FnOnce(&mut D) -> R) -> R {
+        f(&mut lock(&self.db))
+    }
+
+    /// 与 `with()` 相同，但数据库正在被其他线程使用时立即返回 `None`。
+    pub fn try_with<R>(&self, f: impl FnOnce(&mut D) -> R) -> Option<R> {
+        let mut db = match self.db.try_lock() {
+            Ok(db) => db,
+            Err(std::sync::TryLockError::Poisoned(err)) => err.into_inner(),
+            Err(std::sync::TryLockError::WouldBlock) => return None,
+        };
+        Some(f(&mut db))
+    }
+
+    /// 取回数据库。
+    pub fn into_inner(self) -> Box<D> {
+        self.db.into_inner().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn split<D>(db: Box<D>) -> (Reader<D>, Writer<D>) {
+    let db = Arc::new(Mutex::new(db));
+    (Reader { db: db.clone() }, Writer { db })
+}
+
+fn lock<D>(db: &Mutex<Box<D>>) -> MutexGuard<'_, Box<D>> {
+    // 回调中的 panic 不会破坏数据库本身的一致性
+    db.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<D> Clone for Reader<D> {
+    fn clone(&self) -> Self {
+        Self { db: self.db.clone() }
+    }
+}
+
+impl<D> Writer<D> {
+    /// 创建一个新的只读句柄。
+    pub fn reader(&self) -> Reader<D> {
+        Reader { db: self.db.clone() }
+    }
+
+    /// 锁定数据库并获取其可变引用，用于执行 `Writer` 未直接提供的操作。
+    ///
+    /// 持有返回的守卫期间，其他句柄的调用都会被阻塞。
+    pub fn lock(&self) -> MutexGuard<'_, Box<D>> {
+        lock(&self.db)
+    }
+}
+
+#[cfg(feature = "kvdb")]
+impl<S: NorFlash> KVDB<S> {
+    /// 将已初始化的数据库拆分为可克隆的只读句柄与唯一的读写句柄，详见 [`crate::shared`]。
+    pub fn split(self: Box<Self>) -> (Reader<KVDB<S>>, Writer<KVDB<S>>) {
+        split(self)
+    }
+}
+
+#[cfg(feature = "kvdb")]
+impl<S: NorFlash> Reader<KVDB<S>> {
+    /// 根据键获取其值。
+    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        lock(&self.db).get(key)
+    }
+
+    /// 以借用的形式读取键的值，详见 `KVDB::get_with`。
+    pub fn get_with<R>(&self, key: &str, f: impl FnOnce(&[u8]) -> R) -> Result<Option<R>, Error> {
+        lock(&self.db).get_with(key, f)
+    }
+
+    /// 判断键是否存在。
+    pub fn contains_key(&self, key: &str) -> Result<bool, Error> {
+        lock(&self.db).contains_key(key)
+    }
+}
+
+#[cfg(feature = "kvdb")]
+impl<S: NorFlash> Writer<KVDB<S>> {
+    /// 根据键获取其值。
+    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        lock(&self.db).get(key)
+    }
+
+    /// 存储一个键值对。
+    pub fn set(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
+        lock(&self.db).set(key, value)
+    }
+
+    /// 仅在键不存在时写入，详见 `KVDB::set_nx`。
+    pub fn set_nx(&mut self, key: &str, value: &[u8]) -> Result<bool, Error> {
+        lock(&self.db).set_nx(key, value)
+    }
+
+    /// 删除一个键值对。
+    pub fn delete(&mut self, key: &str) -> Result<(), Error> {
+        lock(&self.db).delete(key)
+    }
+}
+
+#[cfg(feature = "tsdb")]
+impl<S: NorFlash> TSDB<S> {
+    /// 将已初始化的数据库拆分为可克隆的只读句柄与唯一的读写句柄，详见 [`crate::shared`]。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::TSDB;
+    /// # let dir = tempfile::tempdir()?;
+    /// let db = TSDB::new_file("split_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// let (reader, mut writer) = db.split();
+    ///
+    /// let sampler = std::thread::spawn(move || {
+    ///     for i in 1..=10 {
+    ///         writer.append_with_timestamp(i, b"sample").unwrap();
+    ///     }
+    /// });
+    /// sampler.join().unwrap();
+    ///
+    /// let mut uploaded = 0;
+    /// reader.iter(|_, value| { uploaded += value.len(); true }, false)?;
+    /// assert_eq!(uploaded, 60);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn split(self: Box<Self>) -> (Reader<TSDB<S>>, Writer<TSDB<S>>) {
+        split(self)
+    }
+}
+
+#[cfg(feature = "tsdb")]
+impl<S: NorFlash> Reader<TSDB<S>> {
+    /// 最后一条日志的时间戳。
+    pub fn last_time(&self) -> i64 {
+        lock(&self.db).last_time()
+    }
+
+    /// 查询时间范围内指定状态的日志条数。
+    pub fn count(&self, from: i64, to: i64, statuses: impl Into<StatusSet>) -> usize {
+        lock(&self.db).count(from, to, statuses)
+    }
+
+    /// 读取日志的数据。
+    pub fn get_value(&self, tsl: &TSLEntry) -> Result<Option<Vec<u8>>, Error> {
+        lock(&self.db).get_value(tsl)
+    }
+
+    /// 迭代所有日志及其数据，回调返回 `false` 时提前终止。
+    ///
+    /// 迭代期间持有锁，`Writer` 的追加会等待迭代结束。
+    pub fn iter<F: FnMut(&TSLEntry, &[u8]) -> bool + Send>(&self, callback: F, reverse: bool) -> Result<(), Error> {
+        let mut db = lock(&self.db);
+        let mut visit = Visit { callback, result: Ok(()) };
+        db.tsdb_iter(|db, tsl| visit.visit(db, tsl), reverse);
+        visit.result
+    }
+
+    /// 按时间范围 (包含两端) 迭代日志及其数据，回调返回 `false` 时提前终止。
+    pub fn iter_by_time<F: FnMut(&TSLEntry, &[u8]) -> bool + Send>(
+        &self,
+        from: i64,
+        to: i64,
+        callback: F,
+    ) -> Result<(), Error> {
+        let mut db = lock(&self.db);
+        let mut visit = Visit { callback, result: Ok(()) };
+        db.tsdb_iter_by_time(from, to, |db, tsl| visit.visit(db, tsl));
+        visit.result
+    }
+}
+
+#[cfg(feature = "tsdb")]
+impl<S: NorFlash> Writer<TSDB<S>> {
+    /// 追加一条指定时间戳的日志。
+    pub fn append_with_timestamp(&mut self, timestamp: i64, data: &[u8]) -> Result<(), Error> {
+        lock(&self.db).append_with_timestamp(timestamp, data)
+    }
+
+    /// 以数据库的时间戳来源追加一条日志，详见 `TSDB::append`。
+    pub fn append(&mut self, data: &[u8]) -> Result<(), Error> {
+        lock(&self.db).append(data)
+    }
+
+    /// 设置日志的状态。
+    pub fn set_status(&mut self, tsl: &mut TSLEntry, status: TSLStatus) -> Result<(), Error> {
+        lock(&self.db).set_status(tsl, status)
+    }
+
+    /// 批量设置日志的状态，详见 `TSDB::set_status_batch`。
+    pub fn set_status_batch(&mut self, tsls: &mut [TSLEntry], status: TSLStatus) -> Result<(), Error> {
+        lock(&self.db).set_status_batch(tsls, status)
+    }
+}
+
+/// 迭代时读取每条日志的数据并转交给用户回调
+#[cfg(feature = "tsdb")]
+struct Visit<F> {
+    callback: F,
+    result: Result<(), Error>,
+}
+
+#[cfg(feature = "tsdb")]
+impl<F: FnMut(&TSLEntry, &[u8]) -> bool> Visit<F> {
+    fn visit<S: NorFlash>(&mut self, db: &mut TSDB<S>, tsl: &mut TSLEntry) -> bool {
+        match db.get_value(tsl) {
+            Ok(value) => (self.callback)(tsl, value.as_deref().unwrap_or_default()),
+            Err(err) => {
+                self.result = Err(err);
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! 基于借用的字节切片的存储，用于 `no_std` 环境下的主机端测试与文档示例。
+
+use embedded_storage::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+
+use crate::{Error, FDB_WRITE_GRAN};
+
+/// 在借用的 `&mut [u8]` 上实现 `NorFlash`，擦除大小为 `ERASE` 字节。
+///
+/// 不需要堆、文件系统或模拟框架，切片可以来自栈上的数组或 `static` 缓冲区。
+/// 写入与真实的 NOR Flash 相同，只能将位从 1 变为 0；切片的已有内容被视为 Flash 的当前内容，
+/// 空白的 Flash 应以 `0xFF` 填充。
+///
+/// # 示例
+///
+/// ```
+/// use flashdb_rs::slice::SliceStorage;
+/// use flashdb_rs::KVDB;
+///
+/// let mut flash = [0xFF; 4 * 4096];
+/// let mut db = KVDB::new(SliceStorage::<4096>::new(&mut flash)?);
+/// db.set_name("slice")?;
+/// db.init(None)?;
+/// db.set("key", b"value")?;
+/// assert!(db.contains_key("key")?);
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct SliceStorage<'a, const ERASE: usize = 4096> {
+    data: &'a mut [u8],
+}
+
+impl<'a, const ERASE: usize> SliceStorage<'a, ERASE> {
+    /// 以 `data` 作为 Flash 的内容，长度必须是 `ERASE` 的非零整数倍，否则返回 `Error::InvalidArgument`。
+    pub fn new(data: &'a mut [u8]) -> Result<Self, Error> {
+        if ERASE == 0 || data.is_empty() || data.len() % ERASE != 0 {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(Self { data })
+    }
+
+    /// Flash 的全部内容。
+    pub fn as_slice(&self) -> &[u8] {
+        self.data
+    }
+
+    /// 取回借用的切片。
+    pub fn into_inner(self) -> &'a mut [u8] {
+        self.data
+    }
+
+    /// 内部方法：检查访问范围
+    fn range(&self, offset: u32, len: usize) -> Option<core::ops::Range<usize>> {
+        let end = (offset as usize).checked_add(len)?;
+        (end <= self.data.len()).then_some(offset as usize..end)
+    }
+}
+
+impl<const ERASE: usize> ErrorType for SliceStorage<'_, ERASE> {
+    type Error = Error;
+}
+
+impl<const ERASE: usize> ReadNorFlash for SliceStorage<'_, ERASE> {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let range = self.range(offset, bytes.len()).ok_or(Error::ReadError)?;
+        bytes.copy_from_slice(&self.data[range]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl<const ERASE: usize> NorFlash for SliceStorage<'_, ERASE> {
+    const WRITE_SIZE: usize = (FDB_WRITE_GRAN as usize + 7) / 8;
+    const ERASE_SIZE: usize = ERASE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        if from as usize % ERASE != 0 || to as usize % ERASE != 0 {
+            return Err(Error::EraseError);
+        }
+        let range = self
+            .range(from, to.saturating_sub(from) as usize)
+            .ok_or(Error::EraseError)?;
+        self.data[range].fill(0xFF);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let range = self.range(offset, bytes.len()).ok_or(Error::WriteError)?;
+        // NOR Flash 只能将位从 1 变为 0
+        for (cell, byte) in self.data[range].iter_mut().zip(bytes) {
+            *cell &= byte;
+        }
+        Ok(())
+    }
+}
@@ -7,6 +7,11 @@ use std::io::ErrorKind;
 use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 
+#[cfg(all(feature = "mmap", unix))]
+mod mmap;
+#[cfg(all(feature = "mmap", unix))]
+pub use mmap::MmapStorage;
+
 /// 定义文件存储策略
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FileStrategy {
@@ -20,6 +25,10 @@ pub enum FileStrategy {
 /// 一个基于 `std::fs::File` 的 `NorFlash` 实现，用于桌面环境。
 ///
 /// 通过 LRU 缓存高效管理文件句柄。
+///
+/// 擦除粒度 `ERASE_SIZE` 固定为 4096 字节，`sec_size` 必须是它的整数倍。
+/// 数据库的扇区大小默认等于 `ERASE_SIZE`，使用其他扇区大小时需要在 `init()` 之前调用
+/// `KVDB::set_sec_size()` / `TSDB::set_sec_size()`，`new_file` 系列构造函数会自动设置为 `sec_size`。
 pub struct StdStorage {
     strategy: FileStrategy,
     db_name: String,
@@ -27,6 +36,8 @@ pub struct StdStorage {
     sec_size: u32,
     capacity: u32,
     file_cache: LruCache<u32, File>,
+    /// 单文件已预分配到 `capacity`，使用定位读写且不再改变文件长度
+    preallocated: bool,
 }
 
 impl StdStorage {
@@ -38,6 +49,12 @@ impl StdStorage {
         capacity: u32,
         strategy: FileStrategy,
     ) -> Result<Self, std::io::Error> {
+        if sec_size == 0 || sec_size as usize % Self::ERASE_SIZE != 0 {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "sec_size must be a multiple of StdStorage::ERASE_SIZE",
+            ));
+        }
         let base_path = path.as_ref().to_path_buf();
         if strategy == FileStrategy::Multi {
             std::fs::create_dir_all(&base_path)?;
@@ -54,9 +71,96 @@ impl StdStorage {
             capacity,
             base_path,
             file_cache: LruCache::new(NonZeroUsize::new(8).unwrap()),
+            preallocated: false,
         })
     }
 
+    /// 将单文件预分配到 `capacity` 字节，之后的读写都使用定位 IO (`pread` / `pwrite`)，不会再扩展文件。
+    ///
+    /// 文件中尚未存在的部分以 `0xFF` (擦除状态) 填充并同步到磁盘，因此磁盘空间在创建时即被占用，
+    /// 不会在事务进行到一半时因为 `ENOSPC` 失败，数据库的容量也不会随写入变化。
+    /// 已存在的内容保持不变。预分配后超出 `capacity` 的访问返回 `Error::InvalidArgument`。
+    ///
+    /// 仅支持单文件模式，多文件模式返回 `ErrorKind::InvalidInput`。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::storage::{FileStrategy, StdStorage};
+    /// # use flashdb_rs::KVDB;
+    /// # let dir = tempfile::tempdir()?;
+    /// let path = dir.path().join("prealloc.fdb");
+    /// let storage = StdStorage::new(&path, "prealloc", 4096, 4 * 4096, FileStrategy::Single)?.preallocate()?;
+    /// assert_eq!(std::fs::metadata(&path)?.len(), 4 * 4096);
+    ///
+    /// let mut db = Box::new(KVDB::new(storage));
+    /// db.set_name("prealloc")?;
+    /// db.init(None)?;
+    /// db.set("key", b"value")?;
+    /// assert_eq!(std::fs::metadata(&path)?.len(), 4 * 4096);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn preallocate(mut self) -> Result<Self, std::io::Error> {
+        if self.strategy != FileStrategy::Single {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "preallocation requires FileStrategy::Single",
+            ));
+        }
+        let capacity = self.capacity as u64;
+        let (file, _) = self.get_file_and_offset(0)?;
+        let mut len = file.metadata()?.len();
+        let buf = vec![0xFF; 64 * 1024];
+        while len < capacity {
+            let chunk = (capacity - len).min(buf.len() as u64) as usize;
+            write_at(file, &buf[..chunk], len)?;
+            len += chunk as u64;
+        }
+        file.sync_all()?;
+        self.preallocated = true;
+        Ok(self)
+    }
+
+    /// 创建时指定的扇区大小，即多文件模式下每个文件的大小。
+    pub fn sec_size(&self) -> u32 {
+        self.sec_size
+    }
+
+    /// 是否已通过 `preallocate()` 预分配。
+    pub fn is_preallocated(&self) -> bool {
+        self.preallocated
+    }
+
+    /// 内部方法：预分配模式下检查访问是否超出容量
+    fn check_preallocated(&self, offset: u32, len: usize) -> Result<(), Error> {
+        if offset as u64 + len as u64 > self.capacity as u64 {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(())
+    }
+
+    /// 内部方法：在目录 `dir` 中创建名为 `db_name` 的数据库存储，供 `new_file` 系列构造函数使用。
+    ///
+    /// 单文件模式使用 `dir/<db_name>.fdb`，多文件模式使用 `dir/<db_name>.fdb.<扇区号>`。
+    pub(crate) fn in_dir(
+        dir: &str,
+        db_name: &str,
+        sec_size: u32,
+        capacity: u32,
+        strategy: FileStrategy,
+    ) -> Result<Self, std::io::Error> {
+        match strategy {
+            FileStrategy::Single => Self::new(
+                Path::new(dir).join(format!("{}.fdb", db_name)),
+                db_name,
+                sec_size,
+                capacity,
+                strategy,
+            ),
+            FileStrategy::Multi => Self::new(dir, db_name, sec_size, capacity, strategy),
+        }
+    }
+
     /// 根据地址获取对应的文件句柄和文件内偏移量。
     fn get_file_and_offset(&mut self, addr: u32) -> Result<(&mut File, u64), std::io::Error> {
         let (sector_index, offset_in_file) = match self.strategy {
@@ -96,6 +200,11 @@ impl ReadNorFlash for StdStorage {
     const READ_SIZE: usize = 1;
 
     fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        if self.preallocated {
+            self.check_preallocated(offset, bytes.len())?;
+            let (file, file_offset) = self.get_file_and_offset(offset)?;
+            return Ok(read_at(file, bytes, file_offset)?);
+        }
         let (file, file_offset) = self.get_file_and_offset(offset)?;
         file.seek(std::io::SeekFrom::Start(file_offset))?;
         match file.read_exact(bytes) {
@@ -116,48 +225,121 @@ impl ReadNorFlash for StdStorage {
 
 impl NorFlash for StdStorage {
     const WRITE_SIZE: usize = 1;
-    const ERASE_SIZE: usize = 4096; // 这是一个典型值，我们将 sec_size 作为擦除大小
+    const ERASE_SIZE: usize = 4096;
 
     fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
-        let size = to - from;
+        let size = to.checked_sub(from).ok_or(Error::InvalidArgument)?;
+        if self.preallocated {
+            self.check_preallocated(from, size as usize)?;
+            let (file, file_offset) = self.get_file_and_offset(from)?;
+            write_at(file, &vec![0xFF; size as usize], file_offset)?;
+            return Ok(());
+        }
         // 擦除操作是基于绝对地址的
-        let (sector_index, offset) = match self.strategy {
-            FileStrategy::Single => (0, from as u64),
+        match self.strategy {
+            FileStrategy::Single => {
+                self.file_cache.pop(&0);
+                let mut file = OpenOptions::new().write(true).create(true).open(&self.base_path)?;
+                file.seek(std::io::SeekFrom::Start(from as u64))?;
+                // 模拟擦除，填充 0xFF
+                file.write_all(&vec![0xFF; size as usize])?;
+                file.flush()?;
+            }
             FileStrategy::Multi => {
-                if from % self.sec_size != 0 || size != self.sec_size {
+                if from as usize % Self::ERASE_SIZE != 0 || size as usize % Self::ERASE_SIZE != 0 {
                     return Err(Error::InvalidArgument);
                 }
-                (from / self.sec_size, 0)
+                // 逐个擦除范围内的扇区文件，数据库的扇区大小可以是 sec_size 的整数倍或约数
+                let mut pos = from;
+                while pos < to {
+                    let sector_index = pos / self.sec_size;
+                    let offset = pos % self.sec_size;
+                    let len = (self.sec_size - offset).min(to - pos);
+                    self.file_cache.pop(&sector_index);
+                    let file_path = self
+                        .base_path
+                        .join(format!("{}.fdb.{}", self.db_name, sector_index));
+                    let mut file = OpenOptions::new().write(true).create(true).open(&file_path)?;
+                    if offset == 0 && len == self.sec_size {
+                        file.set_len(0)?;
+                    }
+                    // 文件末尾到擦除起点之间的空洞同样填充 0xFF，而不是留下 0x00
+                    let start = (offset as u64).min(file.metadata()?.len());
+                    file.seek(std::io::SeekFrom::Start(start))?;
+                    file.write_all(&vec![0xFF; (offset as u64 + len as u64 - start) as usize])?;
+                    file.flush()?;
+                    pos += len;
+                }
             }
-        };
-
-        self.file_cache.pop(&sector_index);
-        let file_path = match self.strategy {
-            FileStrategy::Single => self.base_path.clone(),
-            FileStrategy::Multi => self
-                .base_path
-                .join(format!("{}.fdb.{}", self.db_name, sector_index)),
-        };
-
-        let mut file = OpenOptions::new().write(true).create(true).open(&file_path)?;
-
-        if self.strategy == FileStrategy::Multi {
-            file.set_len(0)?;
         }
-
-        file.seek(std::io::SeekFrom::Start(offset))?;
-        // 模拟擦除，填充 0xFF
-        let buf = vec![0xFF; size as usize];
-        file.write_all(&buf)?;
-        file.flush()?;
         Ok(())
     }
 
     fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        if self.preallocated {
+            self.check_preallocated(offset, bytes.len())?;
+            let (file, file_offset) = self.get_file_and_offset(offset)?;
+            return Ok(write_at(file, bytes, file_offset)?);
+        }
         let (file, file_offset) = self.get_file_and_offset(offset)?;
         file.seek(std::io::SeekFrom::Start(file_offset))?;
         file.write_all(bytes)?;
         file.flush()?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+/// 内部方法：在 `pos` 处定位读取，不移动文件指针
+fn read_at(file: &File, buf: &mut [u8], pos: u64) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::FileExt::read_exact_at(file, buf, pos)
+    }
+    #[cfg(windows)]
+    {
+        let (mut buf, mut pos) = (buf, pos);
+        while !buf.is_empty() {
+            match std::os::windows::fs::FileExt::seek_read(file, buf, pos)? {
+                0 => return Err(ErrorKind::UnexpectedEof.into()),
+                n => {
+                    buf = &mut buf[n..];
+                    pos += n as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let mut file = file;
+        file.seek(std::io::SeekFrom::Start(pos))?;
+        file.read_exact(buf)
+    }
+}
+
+/// 内部方法：在 `pos` 处定位写入，不移动文件指针
+fn write_at(file: &File, buf: &[u8], pos: u64) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::FileExt::write_all_at(file, buf, pos)
+    }
+    #[cfg(windows)]
+    {
+        let (mut buf, mut pos) = (buf, pos);
+        while !buf.is_empty() {
+            match std::os::windows::fs::FileExt::seek_write(file, buf, pos)? {
+                0 => return Err(ErrorKind::WriteZero.into()),
+                n => {
+                    buf = &buf[n..];
+                    pos += n as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let mut file = file;
+        file.seek(std::io::SeekFrom::Start(pos))?;
+        file.write_all(buf)
+    }
+}
@@ -0,0 +1,140 @@
+use crate::error::Error;
+use embedded_storage::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+use std::fs::OpenOptions;
+use std::io::{Seek as StdSeek, SeekFrom, Write as StdWrite};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// 一个基于内存映射单文件的 `NorFlash` 实现，用于桌面环境。
+///
+/// 与 `StdStorage` 相比，读写与擦除都只是内存复制，不需要每次操作的 `seek`/`read` 系统调用，
+/// 适合基准测试以及在桌面上模拟大容量的数据库。文件格式与 `FileStrategy::Single` 相同，
+/// 两者可以互换打开同一个文件。修改由操作系统回写，需要确保落盘时调用 `flush()`，
+/// 析构时也会自动同步。
+///
+/// # 示例
+///
+/// ```
+/// use flashdb_rs::storage::MmapStorage;
+/// use flashdb_rs::KVDB;
+///
+/// # let dir = tempfile::tempdir()?;
+/// let storage = MmapStorage::new(dir.path().join("kv.fdb"), 4096, 16 * 4096)?;
+/// let mut db = Box::new(KVDB::new(storage));
+/// db.set_name("mmap_doc")?;
+/// db.init(None)?;
+/// db.set("key", b"value")?;
+/// assert_eq!(db.get("key")?.as_deref(), Some(&b"value"[..]));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct MmapStorage {
+    ptr: *mut u8,
+    capacity: u32,
+}
+
+// 映射区域只通过 `&mut self` 访问
+unsafe impl Send for MmapStorage {}
+
+impl MmapStorage {
+    /// 打开或创建 `path` 并将其映射到内存，文件不足 `capacity` 的部分以 `0xFF` 填充。
+    pub fn new<P: AsRef<Path>>(path: P, sec_size: u32, capacity: u32) -> Result<Self, std::io::Error> {
+        if sec_size == 0 || capacity == 0 || capacity % sec_size != 0 {
+            return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+        }
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let len = file.metadata()?.len();
+        if len < capacity as u64 {
+            // 未写入的区域与擦除后的 Flash 相同
+            file.seek(SeekFrom::Start(len))?;
+            file.write_all(&vec![0xFF; (capacity as u64 - len) as usize])?;
+            file.flush()?;
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                core::ptr::null_mut(),
+                capacity as usize,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+        // 映射在文件关闭后仍然有效
+        Ok(Self {
+            ptr: ptr as *mut u8,
+            capacity,
+        })
+    }
+
+    /// 将修改同步到文件。
+    pub fn flush(&mut self) -> Result<(), std::io::Error> {
+        if unsafe { libc::msync(self.ptr as *mut libc::c_void, self.capacity as usize, libc::MS_SYNC) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// 内部方法：检查范围并返回对应的映射区域
+    fn region(&mut self, offset: u32, len: usize) -> Result<&mut [u8], Error> {
+        let end = offset as usize + len;
+        if end > self.capacity as usize {
+            return Err(Error::InvalidArgument);
+        }
+        let bytes = unsafe { core::slice::from_raw_parts_mut(self.ptr, self.capacity as usize) };
+        Ok(&mut bytes[offset as usize..end])
+    }
+}
+
+impl Drop for MmapStorage {
+    fn drop(&mut self) {
+        let _ = self.flush();
+        unsafe { libc::munmap(self.ptr as *mut libc::c_void, self.capacity as usize) };
+    }
+}
+
+impl ErrorType for MmapStorage {
+    type Error = Error;
+}
+
+impl ReadNorFlash for MmapStorage {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        bytes.copy_from_slice(self.region(offset, bytes.len())?);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+}
+
+impl NorFlash for MmapStorage {
+    const WRITE_SIZE: usize = 1;
+    const ERASE_SIZE: usize = 4096;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        if to < from {
+            return Err(Error::InvalidArgument);
+        }
+        self.region(from, (to - from) as usize)?.fill(0xFF);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.region(offset, bytes.len())?.copy_from_slice(bytes);
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+//! 检查 C 库访问是否符合 Flash 约束的存储包装器，用于调试新的 Flash 驱动。
+
+use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash};
+
+/// 写入前比较已有内容时使用的缓冲区大小
+const CHUNK: usize = 256;
+
+/// 违反 Flash 约束的访问类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// 读取的地址或长度未按 `READ_SIZE` 对齐
+    UnalignedRead,
+    /// 写入的地址或长度未按 `WRITE_SIZE` 对齐
+    UnalignedWrite,
+    /// 擦除的范围未按 `ERASE_SIZE` 对齐，或结束地址小于起始地址
+    UnalignedErase,
+    /// 访问超出了 Flash 的容量
+    OutOfBounds,
+    /// 写入需要将已编程的位从 0 改回 1，即写入前没有擦除
+    NotErased {
+        /// 冲突的字节在 Flash 中的地址
+        addr: u32,
+        /// Flash 中已有的值
+        current: u8,
+        /// 要写入的值
+        new: u8,
+    },
+}
+
+/// 一次违规访问的详细信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    /// 违规的类型
+    pub kind: ViolationKind,
+    /// 访问的起始地址
+    pub offset: u32,
+    /// 访问的长度
+    pub len: u32,
+}
+
+impl core::fmt::Display for Violation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let range = (self.offset, self.offset as u64 + self.len as u64);
+        match self.kind {
+            ViolationKind::UnalignedRead => write!(f, "unaligned read {:#x}..{:#x}", range.0, range.1),
+            ViolationKind::UnalignedWrite => write!(f, "unaligned write {:#x}..{:#x}", range.0, range.1),
+            ViolationKind::UnalignedErase => write!(f, "unaligned erase {:#x}..{:#x}", range.0, range.1),
+            ViolationKind::OutOfBounds => write!(f, "access {:#x}..{:#x} out of bounds", range.0, range.1),
+            ViolationKind::NotErased { addr, current, new } => write!(
+                f,
+                "write {:#x}..{:#x} programs {:#04x} over {:#04x} at {:#x} without erase",
+                range.0, range.1, new, current, addr
+            ),
+        }
+    }
+}
+
+/// `StrictFlash` 的错误
+#[derive(Debug)]
+pub enum StrictError<E> {
+    /// 访问违反了 Flash 约束，没有交给底层 Flash 执行
+    Violation(Violation),
+    /// 底层 Flash 的错误
+    Flash(E),
+}
+
+impl<E: NorFlashError> NorFlashError for StrictError<E> {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            StrictError::Violation(violation) => match violation.kind {
+                ViolationKind::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+                ViolationKind::NotErased { .. } => NorFlashErrorKind::Other,
+                _ => NorFlashErrorKind::NotAligned,
+            },
+            StrictError::Flash(err) => err.kind(),
+        }
+    }
+}
+
+/// 检查每次读取 / 写入 / 擦除是否符合 `READ_SIZE` / `WRITE_SIZE` / `ERASE_SIZE` 对齐，
+/// 以及写入是否只将位从 1 变为 0 (即写入的区域已经擦除) 的存储包装器。
+///
+/// 违规的访问不会交给底层 Flash，而是返回 `StrictError::Violation`。由于 C 库只能看到失败，
+/// 数据库操作返回的仍是普通的 `Error`，可以通过 `last_violation()` 查看具体原因；
+/// 调试时也可以通过 `set_panic(true)` 在违规处直接 panic，以便获得调用栈。
+///
+/// 写入前需要读取已有内容进行比较，会使读取次数加倍，只适合在开发阶段使用。
+///
+/// # 示例
+///
+/// ```
+/// # use flashdb_rs::storage::{FileStrategy, StdStorage};
+/// # use flashdb_rs::strict::StrictFlash;
+/// # use flashdb_rs::KVDB;
+/// # let dir = tempfile::tempdir()?;
+/// let storage = StdStorage::new(dir.path(), "strict_doc", 4096, 4 * 4096, FileStrategy::Multi)?;
+/// let mut db = Box::new(KVDB::new(StrictFlash::new(storage)));
+/// db.set_name("strict_doc")?;
+/// db.init(None)?;
+/// db.set("key", b"value")?;
+/// assert_eq!(db.storage().violations(), 0);
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct StrictFlash<S: NorFlash> {
+    inner: S,
+    panic: bool,
+    violations: u32,
+    last: Option<Violation>,
+}
+
+impl<S: NorFlash> StrictFlash<S> {
+    /// 包装底层 Flash。
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            panic: false,
+            violations: 0,
+            last: None,
+        }
+    }
+
+    /// 设置为 `true` 时，发现违规的访问立即 panic。
+    pub fn set_panic(&mut self, enable: bool) {
+        self.panic = enable;
+    }
+
+    /// 发现的违规次数。
+    pub fn violations(&self) -> u32 {
+        self.violations
+    }
+
+    /// 最近一次违规的详细信息。
+    pub fn last_violation(&self) -> Option<Violation> {
+        self.last
+    }
+
+    /// 获取底层 Flash 的引用。
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// 取回底层 Flash。
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// 内部方法：记录违规并返回错误
+    fn violation(&mut self, kind: ViolationKind, offset: u32, len: usize) -> StrictError<S::Error> {
+        let violation = Violation { kind, offset, len: len as u32 };
+        if self.panic {
+            panic!("StrictFlash: {}", violation);
+        }
+        self.violations += 1;
+        self.last = Some(violation);
+        StrictError::Violation(violation)
+    }
+
+    /// 内部方法：检查对齐与范围
+    fn check(&mut self, offset: u32, len: usize, align: usize, unaligned: ViolationKind) -> Result<(), StrictError<S::Error>> {
+        if offset as u64 + len as u64 > self.inner.capacity() as u64 {
+            return Err(self.violation(ViolationKind::OutOfBounds, offset, len));
+        }
+        if offset as usize % align != 0 || len % align != 0 {
+            return Err(self.violation(unaligned, offset, len));
+        }
+        Ok(())
+    }
+
+    /// 内部方法：检查写入是否只将位从 1 变为 0
+    fn check_erased(&mut self, offset: u32, bytes: &[u8]) -> Result<(), StrictError<S::Error>> {
+        // 按 WRITE_SIZE 对齐的块读取，块大小同时需要满足 READ_SIZE 对齐
+        let chunk = CHUNK - CHUNK % S::WRITE_SIZE;
+        if chunk == 0 || S::WRITE_SIZE % S::READ_SIZE != 0 {
+            return Ok(());
+        }
+        let mut current = [0u8; CHUNK];
+        for (i, new) in bytes.chunks(chunk).enumerate() {
+            let pos = offset + (i * chunk) as u32;
+            let current = &mut current[..new.len()];
+            self.inner.read(pos, current).map_err(StrictError::Flash)?;
+            if let Some(at) = current.iter().zip(new).position(|(&c, &n)| c & n != n) {
+                let kind = ViolationKind::NotErased {
+                    addr: pos + at as u32,
+                    current: current[at],
+                    new: new[at],
+                };
+                return Err(self.violation(kind, offset, bytes.len()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: NorFlash> ErrorType for StrictFlash<S> {
+    type Error = StrictError<S::Error>;
+}
+
+impl<S: NorFlash> ReadNorFlash for StrictFlash<S> {
+    const READ_SIZE: usize = S::READ_SIZE;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.check(offset, bytes.len(), S::READ_SIZE, ViolationKind::UnalignedRead)?;
+        self.inner.read(offset, bytes).map_err(StrictError::Flash)
+    }
+
+    fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+}
+
+impl<S: NorFlash> NorFlash for StrictFlash<S> {
+    const WRITE_SIZE: usize = S::WRITE_SIZE;
+    const ERASE_SIZE: usize = S::ERASE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        if to < from {
+            return Err(self.violation(ViolationKind::UnalignedErase, from, 0));
+        }
+        self.check(from, (to - from) as usize, S::ERASE_SIZE, ViolationKind::UnalignedErase)?;
+        self.inner.erase(from, to).map_err(StrictError::Flash)
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.check(offset, bytes.len(), S::WRITE_SIZE, ViolationKind::UnalignedWrite)?;
+        self.check_erased(offset, bytes)?;
+        self.inner.write(offset, bytes).map_err(StrictError::Flash)
+    }
+}
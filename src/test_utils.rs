@@ -0,0 +1,201 @@
+//! 测试工具：可注入故障的内存 Flash，用于验证断电与 Flash 故障后的恢复行为。
+//!
+//! ```
+//! use flashdb_rs::test_utils::{Fault, FaultyFlash};
+//! use flashdb_rs::KVDB;
+//!
+//! let mut db = Box::new(KVDB::new(FaultyFlash::new(4 * 4096)));
+//! db.set_name("power_loss")?;
+//! db.init(None)?;
+//! db.set("counter", b"1")?;
+//!
+//! // 下一次写入只写入 2 个字节后断电
+//! db.storage().arm(Fault::TruncateWrite { nth: 1, len: 2 });
+//! assert!(db.set("counter", b"2").is_err());
+//! assert!(db.storage().is_powered_off());
+//!
+//! // 以断电时的 Flash 内容重新启动
+//! let image = db.storage().image().to_vec();
+//! drop(db);
+//! let mut db = Box::new(KVDB::new(FaultyFlash::from_image(image)));
+//! db.set_name("power_loss")?;
+//! db.init(None)?;
+//! assert_eq!(db.get("counter")?.as_deref(), Some(&b"1"[..]));
+//! # Ok::<(), flashdb_rs::Error>(())
+//! ```
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::Cell;
+
+use embedded_storage::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+
+use crate::{Error, FDB_WRITE_GRAN};
+
+/// 要注入的故障，`nth` 从 1 开始，表示从 `arm()` 起的第几次写入或擦除。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// 第 `nth` 次写入失败，不写入任何数据
+    FailWrite { nth: usize },
+    /// 第 `nth` 次写入只写入前 `len` 字节后断电
+    TruncateWrite { nth: usize, len: usize },
+    /// 第 `nth` 次擦除失败，不擦除任何数据
+    FailErase { nth: usize },
+    /// 第 `nth` 次擦除只擦除前 `len` 字节后断电
+    TruncateErase { nth: usize, len: usize },
+}
+
+/// 模拟 NOR Flash 的内存存储，可以注入写入 / 擦除失败、断电与位翻转。
+///
+/// 写入与真实的 NOR Flash 相同，只能将位从 1 变为 0。断电后所有操作都返回错误，
+/// 此时可以通过 `image()` 取出 Flash 内容，再以 `from_image()` 模拟重新上电。
+/// 故障通过 `arm()` 设置，只需要共享引用，因此可以在存储交给数据库之后通过 `storage()` 设置。
+pub struct FaultyFlash {
+    data: Vec<u8>,
+    writes: Cell<usize>,
+    erases: Cell<usize>,
+    /// 要注入的故障以及触发时的写入或擦除次数
+    fault: Cell<Option<(Fault, usize)>>,
+    powered_off: Cell<bool>,
+}
+
+impl FaultyFlash {
+    /// 创建容量为 `capacity` 字节、处于擦除状态的 Flash，容量应为 4096 的整数倍。
+    pub fn new(capacity: usize) -> Self {
+        Self::from_image(vec![0xFF; capacity])
+    }
+
+    /// 以已有的 Flash 内容创建，用于模拟断电后重新上电。
+    pub fn from_image(image: Vec<u8>) -> Self {
+        Self {
+            data: image,
+            writes: Cell::new(0),
+            erases: Cell::new(0),
+            fault: Cell::new(None),
+            powered_off: Cell::new(false),
+        }
+    }
+
+    /// Flash 的全部内容。
+    pub fn image(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// 设置要注入的故障，替换之前设置且尚未触发的故障。
+    pub fn arm(&self, fault: Fault) {
+        let at = match fault {
+            Fault::FailWrite { nth } | Fault::TruncateWrite { nth, .. } => self.writes.get() + nth,
+            Fault::FailErase { nth } | Fault::TruncateErase { nth, .. } => self.erases.get() + nth,
+        };
+        self.fault.set(Some((fault, at)));
+    }
+
+    /// 取消尚未触发的故障。
+    pub fn disarm(&self) {
+        self.fault.set(None);
+    }
+
+    /// 是否已经断电。
+    pub fn is_powered_off(&self) -> bool {
+        self.powered_off.get()
+    }
+
+    /// 翻转 `addr` 处字节的第 `bit` 位，模拟 Flash 的位错误。
+    pub fn flip_bit(&mut self, addr: usize, bit: u8) {
+        self.data[addr] ^= 1 << bit;
+    }
+
+    /// 已执行的写入次数 (包括失败的写入)。
+    pub fn writes(&self) -> usize {
+        self.writes.get()
+    }
+
+    /// 已执行的擦除次数 (包括失败的擦除)。
+    pub fn erases(&self) -> usize {
+        self.erases.get()
+    }
+
+    /// 内部方法：检查范围与电源状态
+    fn check(&self, offset: u32, len: usize) -> Result<core::ops::Range<usize>, Error> {
+        if self.powered_off.get() {
+            return Err(Error::UnknownError);
+        }
+        let range = offset as usize..offset as usize + len;
+        if range.end > self.data.len() {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(range)
+    }
+
+    /// 内部方法：取出在本次操作触发的故障
+    fn take_fault(&self, count: &Cell<usize>) -> Option<Fault> {
+        count.set(count.get() + 1);
+        match self.fault.get() {
+            Some((fault, at)) if at == count.get() => {
+                self.fault.set(None);
+                Some(fault)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl ErrorType for FaultyFlash {
+    type Error = Error;
+}
+
+impl ReadNorFlash for FaultyFlash {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let range = self.check(offset, bytes.len()).map_err(|_| Error::ReadError)?;
+        bytes.copy_from_slice(&self.data[range]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl NorFlash for FaultyFlash {
+    const WRITE_SIZE: usize = (FDB_WRITE_GRAN as usize + 7) / 8;
+    const ERASE_SIZE: usize = 4096;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        let range = self.check(from, to.saturating_sub(from) as usize).map_err(|_| Error::EraseError)?;
+        let range = match self.take_fault(&self.erases) {
+            Some(Fault::FailErase { .. }) => return Err(Error::EraseError),
+            Some(Fault::TruncateErase { len, .. }) => {
+                self.powered_off.set(true);
+                range.start..range.end.min(range.start + len)
+            }
+            _ => range,
+        };
+        self.data[range].fill(0xFF);
+        if self.powered_off.get() {
+            return Err(Error::EraseError);
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.check(offset, bytes.len()).map_err(|_| Error::WriteError)?;
+        let bytes = match self.take_fault(&self.writes) {
+            Some(Fault::FailWrite { .. }) => return Err(Error::WriteError),
+            Some(Fault::TruncateWrite { len, .. }) => {
+                self.powered_off.set(true);
+                &bytes[..len.min(bytes.len())]
+            }
+            _ => bytes,
+        };
+        // NOR Flash 只能将位从 1 变为 0
+        for (cell, byte) in self.data[offset as usize..].iter_mut().zip(bytes) {
+            *cell &= byte;
+        }
+        if self.powered_off.get() {
+            return Err(Error::WriteError);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+//! Flash 操作超时。
+//!
+//! 当 SPI 总线卡死或 Flash 芯片无响应时，底层驱动可能会长时间阻塞。
+//! 为数据库设置超时后，每一次 Flash 读、写、擦除操作都会被计时，
+//! 超时后当前数据库操作中剩余的 Flash 访问会立即失败，
+//! `set()` / `append()` 等调用返回 `Error::Timeout`，调用方可以据此执行复位总线等恢复逻辑。
+//!
+//! **注意**: 超时只能在驱动返回后被检测到，驱动本身仍需保证最终会返回。
+
+use core::time::Duration;
+
+use crate::{Error, FlashDispatch, StorageError};
+
+/// 用户提供的单调时钟
+///
+/// 数据库可能通过 `split()` 在多个线程间共享，因此时钟必须是 `Sync` 的。
+pub trait Timer: Sync {
+    /// 当前时间 (毫秒)，只需单调递增，起点任意
+    fn now_ms(&self) -> u64;
+}
+
+impl<F: Fn() -> u64 + Sync> Timer for F {
+    fn now_ms(&self) -> u64 {
+        self()
+    }
+}
+
+/// 基于 `std::time::Instant` 的时钟
+#[cfg(feature = "std")]
+pub struct StdTimer;
+
+#[cfg(feature = "std")]
+impl Timer for StdTimer {
+    fn now_ms(&self) -> u64 {
+        static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+        START.get_or_init(std::time::Instant::now).elapsed().as_millis() as u64
+    }
+}
+
+/// 单个 Flash 操作的超时配置与状态
+pub(crate) struct OpTimeout {
+    timer: &'static dyn Timer,
+    timeout_ms: u64,
+    expired: bool,
+}
+
+impl OpTimeout {
+    pub(crate) fn new(timer: &'static dyn Timer, timeout: Duration) -> Self {
+        Self {
+            timer,
+            timeout_ms: timeout.as_millis() as u64,
+            expired: false,
+        }
+    }
+}
+
+impl FlashDispatch {
+    /// 计时执行一次 Flash 操作，返回操作是否成功。
+    ///
+    /// 已经超时后，后续操作不再访问 Flash，直接失败。
+    pub(crate) fn run(
+        &mut self,
+        op: impl FnOnce(&crate::FlashVTable, *mut core::ffi::c_void, &mut Option<StorageError>) -> i32,
+    ) -> bool {
+        let timeout = match self.timeout.as_mut() {
+            Some(timeout) => timeout,
+            None => return op(&self.vtable, self.instance, &mut self.last_error) == 0,
+        };
+        if timeout.expired {
+            return false;
+        }
+        let start = timeout.timer.now_ms();
+        let ok = op(&self.vtable, self.instance, &mut self.last_error) == 0;
+        if timeout.timer.now_ms().saturating_sub(start) > timeout.timeout_ms {
+            timeout.expired = true;
+            return false;
+        }
+        ok
+    }
+
+    /// 结束一次数据库操作：如果期间发生了超时，清除超时状态并返回 `Error::Timeout`；
+    /// 因存储后端失败时，返回其原始错误 `Error::Storage`。
+    pub(crate) fn finish<T>(&mut self, result: Result<T, Error>) -> Result<T, Error> {
+        #[cfg(feature = "alloc")]
+        self.disarm_events();
+        self.end_write();
+        let last_error = self.last_error.take();
+        match self.timeout.as_mut() {
+            Some(timeout) if timeout.expired => {
+                timeout.expired = false;
+                Err(Error::Timeout)
+            }
+            _ => match (result, last_error) {
+                (Err(err), Some(cause)) if cause.matches(&err) => Err(Error::Storage(cause)),
+                (result, _) => result,
+            },
+        }
+    }
+}
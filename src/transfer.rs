@@ -0,0 +1,316 @@
+//! 带校验的分块传输，用于通过串口 / BLE 等链路导出或导入整个数据库分区。
+//!
+//! 数据库分区被切分为若干帧，每帧都携带自身的偏移量、分区总长度和 CRC32，
+//! 因此可以直接承载在 XMODEM、UART 或 BLE 等不可靠链路上：
+//! - 损坏的帧会被接收端拒绝，发送端只需重传该帧；
+//! - 重复的帧会被忽略；
+//! - 链路中断后，发送端可以从接收端报告的 `next_offset()` 处继续发送。
+//!
+//! 帧格式 (小端序)：
+//!
+//! | 字段     | 长度 | 说明                         |
+//! |----------|------|------------------------------|
+//! | magic    | 4    | 固定为 `FDBT`                |
+//! | offset   | 4    | 本帧数据在分区中的偏移       |
+//! | total    | 4    | 分区总长度                   |
+//! | len      | 2    | 本帧数据长度                 |
+//! | payload  | len  | 分区数据                     |
+//! | crc32    | 4    | 以上所有字段的 CRC32         |
+//!
+//! 接收端直接写入 `NorFlash`，不需要文件系统，也不需要 `alloc`。
+//!
+//! ```
+//! use flashdb_rs::transfer::{frame_len, Progress, Receiver, Sender};
+//! # use embedded_storage::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+//! # struct Ram([u8; 1024]);
+//! # impl ErrorType for Ram { type Error = flashdb_rs::Error; }
+//! # impl ReadNorFlash for Ram {
+//! #     const READ_SIZE: usize = 1;
+//! #     fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+//! #         bytes.copy_from_slice(&self.0[offset as usize..offset as usize + bytes.len()]);
+//! #         Ok(())
+//! #     }
+//! #     fn capacity(&self) -> usize { self.0.len() }
+//! # }
+//! # impl NorFlash for Ram {
+//! #     const WRITE_SIZE: usize = 1;
+//! #     const ERASE_SIZE: usize = 256;
+//! #     fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+//! #         self.0[from as usize..to as usize].fill(0xFF);
+//! #         Ok(())
+//! #     }
+//! #     fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+//! #         self.0[offset as usize..offset as usize + bytes.len()].copy_from_slice(bytes);
+//! #         Ok(())
+//! #     }
+//! # }
+//! let mut src = Ram([0xA5; 1024]);
+//! let mut dst = Ram([0x00; 1024]);
+//!
+//! let mut sender = Sender::new(&mut src, 128);
+//! let mut receiver = Receiver::new(&mut dst);
+//! let mut frame = [0u8; frame_len(128)];
+//! while let Some(len) = sender.next_frame(&mut frame)? {
+//!     // 通过串口发送 frame[..len]，对端调用 accept
+//!     if receiver.accept(&frame[..len])? == Progress::Complete {
+//!         break;
+//!     }
+//! }
+//! assert!(receiver.is_complete());
+//! # assert_eq!(src.0, dst.0);
+//! # Ok::<(), flashdb_rs::Error>(())
+//! ```
+
+use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+
+use crate::{fdb_calc_crc32, Error};
+#[cfg(feature = "kvdb")]
+use crate::KVDB;
+#[cfg(feature = "tsdb")]
+use crate::TSDB;
+
+/// 帧起始标识
+pub const MAGIC: [u8; 4] = *b"FDBT";
+
+/// 帧头长度：magic + offset + total + len
+pub const HEADER_LEN: usize = 14;
+
+/// 帧尾 CRC32 长度
+pub const TRAILER_LEN: usize = 4;
+
+/// 单帧数据的最大长度
+pub const MAX_CHUNK_SIZE: usize = u16::MAX as usize;
+
+/// 承载 `chunk_size` 字节数据所需的帧长度。
+pub const fn frame_len(chunk_size: usize) -> usize {
+    HEADER_LEN + chunk_size + TRAILER_LEN
+}
+
+/// 解析后的一帧数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame<'a> {
+    /// 数据在分区中的偏移
+    pub offset: u32,
+    /// 分区总长度
+    pub total: u32,
+    /// 分区数据
+    pub payload: &'a [u8],
+}
+
+impl<'a> Frame<'a> {
+    /// 解析并校验一帧数据。
+    ///
+    /// 标识、长度或 CRC 不正确时返回 `Error::Corrupted`。
+    pub fn decode(frame: &'a [u8]) -> Result<Self, Error> {
+        if frame.len() < HEADER_LEN + TRAILER_LEN || frame[..4] != MAGIC {
+            return Err(Error::Corrupted);
+        }
+        let len = u16::from_le_bytes([frame[12], frame[13]]) as usize;
+        if frame.len() != frame_len(len) {
+            return Err(Error::Corrupted);
+        }
+        let (body, crc) = frame.split_at(HEADER_LEN + len);
+        if crc32(body).to_le_bytes() != crc {
+            return Err(Error::Corrupted);
+        }
+        Ok(Self {
+            offset: u32::from_le_bytes([frame[4], frame[5], frame[6], frame[7]]),
+            total: u32::from_le_bytes([frame[8], frame[9], frame[10], frame[11]]),
+            payload: &body[HEADER_LEN..],
+        })
+    }
+}
+
+/// 发送端：将存储分区按顺序切分为帧。
+pub struct Sender<'a, S: ReadNorFlash> {
+    storage: &'a mut S,
+    chunk_size: usize,
+    offset: u32,
+    total: u32,
+}
+
+impl<'a, S: ReadNorFlash> Sender<'a, S> {
+    /// 创建发送端，导出 `storage` 的全部容量。
+    ///
+    /// `chunk_size` 为单帧数据的最大长度，会被截断到 `MAX_CHUNK_SIZE`，
+    /// 并向下对齐到 `S::READ_SIZE`。
+    pub fn new(storage: &'a mut S, chunk_size: usize) -> Self {
+        let total = storage.capacity() as u32;
+        let chunk_size = chunk_size.min(MAX_CHUNK_SIZE) / S::READ_SIZE * S::READ_SIZE;
+        Self {
+            storage,
+            chunk_size: chunk_size.max(S::READ_SIZE),
+            offset: 0,
+            total,
+        }
+    }
+
+    /// 从指定偏移处继续发送，通常为接收端报告的 `next_offset()`。
+    pub fn resume_from(&mut self, offset: u32) {
+        self.offset = offset.min(self.total);
+    }
+
+    /// 下一帧的偏移
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// 分区总长度
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    /// 将下一帧编码到 `buf` 中。
+    ///
+    /// # 返回
+    /// - `Ok(Some(len))`: 帧已写入 `buf[..len]`。
+    /// - `Ok(None)`: 所有数据均已发送。
+    /// - `Err(Error::InvalidArgument)`: `buf` 小于 `frame_len(chunk_size)`。
+    pub fn next_frame(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        if self.offset >= self.total {
+            return Ok(None);
+        }
+        let len = self.chunk_size.min((self.total - self.offset) as usize);
+        let frame_len = frame_len(len);
+        if buf.len() < frame_len {
+            return Err(Error::InvalidArgument);
+        }
+
+        buf[..4].copy_from_slice(&MAGIC);
+        buf[4..8].copy_from_slice(&self.offset.to_le_bytes());
+        buf[8..12].copy_from_slice(&self.total.to_le_bytes());
+        buf[12..14].copy_from_slice(&(len as u16).to_le_bytes());
+        self.storage
+            .read(self.offset, &mut buf[HEADER_LEN..HEADER_LEN + len])
+            .map_err(|_| Error::ReadError)?;
+        let crc = crc32(&buf[..HEADER_LEN + len]);
+        buf[HEADER_LEN + len..frame_len].copy_from_slice(&crc.to_le_bytes());
+
+        self.offset += len as u32;
+        Ok(Some(frame_len))
+    }
+}
+
+/// 接收端处理一帧后的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// 帧已写入，等待后续数据
+    Written,
+    /// 帧数据已经接收过，被忽略
+    Duplicate,
+    /// 全部数据已接收
+    Complete,
+}
+
+/// 接收端：校验帧并直接写入 `NorFlash`。
+///
+/// 每个擦除块在第一次写入前才会被擦除，因此中断后可以安全地继续接收。
+pub struct Receiver<'a, S: NorFlash> {
+    storage: &'a mut S,
+    offset: u32,
+    erased: u32,
+    total: Option<u32>,
+}
+
+impl<'a, S: NorFlash> Receiver<'a, S> {
+    /// 创建接收端，从偏移 0 开始写入。
+    pub fn new(storage: &'a mut S) -> Self {
+        Self {
+            storage,
+            offset: 0,
+            erased: 0,
+            total: None,
+        }
+    }
+
+    /// 从之前中断的位置继续接收。
+    ///
+    /// `offset` 之前的数据被视为已经写入，`offset` 所在擦除块的剩余部分也被视为已擦除。
+    pub fn resume_from(&mut self, offset: u32) {
+        let erase_size = S::ERASE_SIZE as u32;
+        self.offset = offset;
+        self.erased = offset.div_ceil(erase_size) * erase_size;
+    }
+
+    /// 期望的下一帧偏移，链路中断后发送端应从此处继续。
+    pub fn next_offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// 是否已接收全部数据
+    pub fn is_complete(&self) -> bool {
+        self.total.is_some_and(|total| self.offset >= total)
+    }
+
+    /// 处理一帧数据。
+    ///
+    /// # 返回
+    /// - `Err(Error::Corrupted)`: 帧损坏，应要求发送端重传。
+    /// - `Err(Error::InvalidArgument)`: 帧不连续 (偏移大于 `next_offset()`) 或分区长度与之前的帧不一致。
+    /// - `Err(Error::SavedFull)`: 分区长度超过了目标存储的容量。
+    pub fn accept(&mut self, frame: &[u8]) -> Result<Progress, Error> {
+        let frame = Frame::decode(frame)?;
+        match self.total {
+            Some(total) if total != frame.total => return Err(Error::InvalidArgument),
+            Some(_) => {}
+            None => {
+                if frame.total as usize > self.storage.capacity() {
+                    return Err(Error::SavedFull);
+                }
+                self.total = Some(frame.total);
+            }
+        }
+
+        let end = frame.offset + frame.payload.len() as u32;
+        if end > frame.total || frame.offset > self.offset {
+            return Err(Error::InvalidArgument);
+        }
+        if end <= self.offset {
+            return Ok(Progress::Duplicate);
+        }
+
+        // 只写入尚未接收的部分
+        let payload = &frame.payload[(self.offset - frame.offset) as usize..];
+        let erase_size = S::ERASE_SIZE as u32;
+        while self.erased < end {
+            self.storage
+                .erase(self.erased, self.erased + erase_size)
+                .map_err(|_| Error::EraseError)?;
+            self.erased += erase_size;
+        }
+        self.storage
+            .write(self.offset, payload)
+            .map_err(|_| Error::WriteError)?;
+        self.offset = end;
+
+        if self.is_complete() {
+            Ok(Progress::Complete)
+        } else {
+            Ok(Progress::Written)
+        }
+    }
+}
+
+#[cfg(feature = "kvdb")]
+impl<S: NorFlash> KVDB<S> {
+    /// 创建一个导出整个数据库分区的发送端。
+    ///
+    /// 导出期间数据库被独占借用，保证导出的是一致的快照。
+    pub fn exporter(&mut self, chunk_size: usize) -> Sender<'_, S> {
+        Sender::new(self.storage_mut(), chunk_size)
+    }
+}
+
+#[cfg(feature = "tsdb")]
+impl<S: NorFlash> TSDB<S> {
+    /// 创建一个导出整个数据库分区的发送端。
+    ///
+    /// 导出期间数据库被独占借用，保证导出的是一致的快照。
+    pub fn exporter(&mut self, chunk_size: usize) -> Sender<'_, S> {
+        Sender::new(self.storage_mut(), chunk_size)
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    unsafe { fdb_calc_crc32(0, data.as_ptr() as *const _, data.len()) }
+}
@@ -0,0 +1,172 @@
+use alloc::vec::Vec;
+use core::ffi::c_void;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{fdb_time_t, fdb_tsl_iter_by_time, fdb_tsl_t, Error, RawHandle};
+
+use super::{fdb_blob_make_by_tsl, TSLEntry, TSLStatus, TSDB};
+
+/// `TSDB::aggregate()` 的统计方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    Min,
+    Max,
+    Avg,
+    Sum,
+    Count,
+}
+
+/// 可以参与统计的数值，由 `TSDB::aggregate()` 的解码器返回
+pub trait Sample: Copy {
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! impl_sample {
+    ($($ty:ty),*) => {
+        $(impl Sample for $ty {
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        })*
+    };
+}
+
+impl_sample!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64);
+
+/// 统计的中间状态
+struct Accumulator {
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: usize,
+}
+
+impl Accumulator {
+    fn push(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn result(&self, aggregation: Aggregation) -> Option<f64> {
+        match aggregation {
+            Aggregation::Count => Some(self.count as f64),
+            Aggregation::Sum => Some(self.sum),
+            _ if self.count == 0 => None,
+            Aggregation::Min => Some(self.min),
+            Aggregation::Max => Some(self.max),
+            Aggregation::Avg => Some(self.sum / self.count as f64),
+        }
+    }
+}
+
+/// 传递给 C 迭代回调的上下文
+struct VisitValues<'a, 'b, S: NorFlash, F> {
+    db: &'a mut TSDB<S>,
+    visit: F,
+    buf: &'b mut Vec<u8>,
+    result: Result<(), Error>,
+}
+
+/// 依次读取 `[from, to]` 内每条可读日志的值，所有日志复用同一个缓冲区，`visit` 返回 `false` 时停止。
+///
+/// 与 `get_value()` 相同，只读取状态为 `PRE_WRITE`、`Write`、`UserStatus1` 的日志。
+pub(super) fn visit_values<S: NorFlash, F: FnMut(&TSLEntry, &[u8]) -> bool>(
+    db: &mut TSDB<S>,
+    from: i64,
+    to: i64,
+    buf: &mut Vec<u8>,
+    visit: F,
+) -> Result<(), Error> {
+    let (min, max) = (fdb_time_t::MIN as i64, fdb_time_t::MAX as i64);
+    if from > to || to < min || from > max {
+        return Ok(());
+    }
+    let handle = db.handle();
+    let mut data = VisitValues {
+        db,
+        visit,
+        buf,
+        result: Ok(()),
+    };
+    unsafe {
+        fdb_tsl_iter_by_time(
+            handle,
+            from.max(min) as fdb_time_t,
+            to.min(max) as fdb_time_t,
+            Some(visit_callback::<S, F>),
+            &mut data as *mut _ as *mut c_void,
+        )
+    };
+    data.result
+}
+
+/// 读取一条日志并交给访问函数，出错时停止迭代
+unsafe extern "C" fn visit_callback<S: NorFlash, F: FnMut(&TSLEntry, &[u8]) -> bool>(
+    tsl: fdb_tsl_t,
+    arg: *mut c_void,
+) -> bool {
+    let data = &mut *(arg as *mut VisitValues<'_, '_, S, F>);
+    let entry = TSLEntry::from(*tsl);
+    if !matches!(entry.status(), TSLStatus::PRE_WRITE | TSLStatus::Write | TSLStatus::UserStatus1) {
+        return false;
+    }
+    data.buf.resize(entry.value_len(), 0);
+    let mut blob = fdb_blob_make_by_tsl(data.buf, &entry, 0);
+    if data.db.fdb_blob_read(&mut blob) != data.buf.len() {
+        data.result = data.db.user_data.finish(Err(Error::ReadError));
+        return true;
+    }
+    !(data.visit)(&entry, data.buf)
+}
+
+impl<S: NorFlash> TSDB<S> {
+    /// 统计时间戳位于 `[from, to]` 内的日志，不会一次性将所有日志读入内存。
+    ///
+    /// 每条日志的值依次读入同一个缓冲区后交给 `decoder` 解码，解码器返回 `None` 的日志不参与统计。
+    /// 与 `get_value()` 相同，只统计状态为 `PRE_WRITE`、`Write`、`UserStatus1` 的日志。
+    ///
+    /// # 返回
+    /// - `Ok(None)`: 范围内没有可统计的日志 (`Min` / `Max` / `Avg`)；`Sum` 与 `Count` 此时返回 `Some(0.0)`。
+    /// - `Err(Error::ReadError)`: 读取日志的值失败。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{Aggregation, TSDB};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("aggregate_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// for (time, celsius) in [(10, 21i16), (20, 23), (30, 19), (40, 25)] {
+    ///     db.append_with_timestamp(time, &celsius.to_le_bytes())?;
+    /// }
+    /// let decode = |bytes: &[u8]| Some(i16::from_le_bytes(bytes.try_into().ok()?));
+    /// assert_eq!(db.aggregate(10, 30, Aggregation::Max, decode)?, Some(23.0));
+    /// assert_eq!(db.aggregate(10, 30, Aggregation::Avg, decode)?, Some(21.0));
+    /// assert_eq!(db.aggregate(50, 60, Aggregation::Min, decode)?, None);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn aggregate<T: Sample, F: FnMut(&[u8]) -> Option<T>>(
+        &mut self,
+        from: i64,
+        to: i64,
+        aggregation: Aggregation,
+        mut decoder: F,
+    ) -> Result<Option<f64>, Error> {
+        let mut acc = Accumulator {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sum: 0.0,
+            count: 0,
+        };
+        let mut buf = Vec::new();
+        visit_values(self, from.min(to), from.max(to), &mut buf, |_, value| {
+            if let Some(value) = decoder(value) {
+                acc.push(value.to_f64());
+            }
+            true
+        })?;
+        Ok(acc.result(aggregation))
+    }
+}
@@ -0,0 +1,92 @@
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{Error, FDB_TSL_STATUS_NUM, FDB_WRITE_GRAN};
+
+use super::{TSLEntry, TSLStatus, TSDB};
+
+/// 合并写入时使用的缓冲区大小，一次写入覆盖的索引区域不超过该长度
+const BATCH_BUF_SIZE: usize = 256;
+
+/// TSL 索引头中状态表的长度 (仅用于 1 bit 写入粒度)
+const TSL_STATUS_TABLE_SIZE: usize = (FDB_TSL_STATUS_NUM as usize + 7) / 8;
+
+impl<S: NorFlash> TSDB<S> {
+    /// 批量设置多条日志的状态。
+    ///
+    /// 与逐条调用 `set_status()` 相比，同一扇区中相邻的日志索引会被合并为一次读-改-写，
+    /// 例如确认上百条已上传的日志时，可以显著减少 Flash 写入次数与同步耗时。
+    /// `tsls` 按迭代顺序排列时合并效果最好，调用后其中的状态会被同步更新。
+    ///
+    /// 只有 1 bit 写入粒度 (`FDB_WRITE_GRAN == 1`) 的 Flash 允许重复写入已编程的区域，
+    /// 其他写入粒度下会退化为逐条写入。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{TSDB, TSLEntry, TSLStatus};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("batch_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// # for i in 1..=10 { db.append_with_timestamp(i, b"sample")?; }
+    /// let mut uploaded: Vec<TSLEntry> = Vec::new();
+    /// db.tsdb_iter(|_, tsl| { uploaded.push(tsl.clone()); true }, false);
+    /// db.set_status_batch(&mut uploaded, TSLStatus::UserStatus1)?;
+    /// assert_eq!(db.count(0, i64::MAX, TSLStatus::UserStatus1), 10);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn set_status_batch(&mut self, tsls: &mut [TSLEntry], status: TSLStatus) -> Result<(), Error> {
+        self.check_writable()?;
+        if FDB_WRITE_GRAN != 1 {
+            for tsl in tsls.iter_mut() {
+                self.set_status(tsl, status)?;
+                tsl.inner.status = status as _;
+            }
+            return Ok(());
+        }
+        // 与 `_fdb_set_status` 相同：状态 n 将状态表第 (n - 1) / 8 字节与 0xFF >> (n % 8) 按位与
+        let index = status as usize;
+        if index == 0 {
+            return Ok(());
+        }
+        let byte_index = (index - 1) / 8;
+        let mask = 0xFFu8 >> (index % 8);
+
+        let sec_size = self.raw().parent.sec_size;
+        let mut buf = [0u8; BATCH_BUF_SIZE];
+        let mut rest = tsls;
+        while let Some(first) = rest.first() {
+            // 收集与第一条日志位于同一扇区、且能放入同一缓冲区的后续日志
+            let start = first.inner.addr.index;
+            let mut end = start + TSL_STATUS_TABLE_SIZE as u32;
+            let count = rest
+                .iter()
+                .take_while(|tsl| {
+                    let addr = tsl.inner.addr.index;
+                    let fits = addr >= start
+                        && addr / sec_size == start / sec_size
+                        && (addr - start) as usize + TSL_STATUS_TABLE_SIZE <= BATCH_BUF_SIZE;
+                    if fits {
+                        end = end.max(addr + TSL_STATUS_TABLE_SIZE as u32);
+                    }
+                    fits
+                })
+                .count();
+
+            let region = &mut buf[..(end - start) as usize];
+            let (group, tail) = core::mem::take(&mut rest).split_at_mut(count);
+            let result = self.storage_mut().read(start, region).map_err(|_| Error::ReadError).and_then(|()| {
+                for tsl in group.iter() {
+                    region[(tsl.inner.addr.index - start) as usize + byte_index] &= mask;
+                }
+                self.storage_mut().write(start, region).map_err(|_| Error::WriteError)
+            });
+            #[cfg(feature = "alloc")]
+            let result = result.map_err(|err| err.with_context(crate::ErrorContext::for_timestamp(crate::Operation::SetStatus, group[0].time())));
+            result?;
+            for tsl in group.iter_mut() {
+                tsl.inner.status = status as _;
+            }
+            rest = tail;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use alloc::vec::Vec;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+
+use super::aggregate::visit_values;
+use super::{fdb_blob_make_by_tsl, TSLEntry, TSLStatus, TSDB};
+
+/// TSDB 中的一个逻辑通道。
+///
+/// 通过 `TSDB::channel()` 获取。每条日志的第一个字节为通道号，温度、事件、诊断信息等
+/// 不同类型的数据可以共用同一个 TSDB 分区，并按通道分别迭代与计数，而不必为每种数据单独划分数据库。
+///
+/// 所有通道共用同一条时间轴，时间戳在整个 TSDB 内严格递增。
+///
+/// # 示例
+///
+/// ```
+/// # use flashdb_rs::TSDB;
+/// # let dir = tempfile::tempdir()?;
+/// # let mut db = TSDB::new_file("channel_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+/// const TEMPERATURE: u8 = 1;
+/// const EVENTS: u8 = 2;
+///
+/// db.channel(TEMPERATURE).append_with_timestamp(1, &[21])?;
+/// db.channel(EVENTS).append_with_timestamp(2, b"boot")?;
+/// db.channel(TEMPERATURE).append_with_timestamp(3, &[22])?;
+///
+/// let mut temperature = db.channel(TEMPERATURE);
+/// assert_eq!(temperature.count(0, i64::MAX)?, 2);
+/// let mut values = Vec::new();
+/// temperature.iter_by_time(0, i64::MAX, |_, payload| {
+///     values.push(payload[0]);
+///     true
+/// })?;
+/// assert_eq!(values, [21, 22]);
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct Channel<'a, S: NorFlash> {
+    db: &'a mut TSDB<S>,
+    id: u8,
+}
+
+impl<S: NorFlash> TSDB<S> {
+    /// 获取通道 `id` 的视图，详见 [`Channel`]。
+    pub fn channel(&mut self, id: u8) -> Channel<'_, S> {
+        Channel { db: self, id }
+    }
+
+    /// 读取日志所属的通道号，日志不可读或没有值时返回 `None`。
+    pub fn channel_of(&mut self, entry: &TSLEntry) -> Result<Option<u8>, Error> {
+        if entry.value_len() == 0
+            || !matches!(entry.status(), TSLStatus::PRE_WRITE | TSLStatus::Write | TSLStatus::UserStatus1)
+        {
+            return Ok(None);
+        }
+        let mut id = [0u8; 1];
+        let mut blob = fdb_blob_make_by_tsl(&mut id, entry, 0);
+        if self.fdb_blob_read(&mut blob) != id.len() {
+            return self.user_data.finish(Err(Error::ReadError));
+        }
+        Ok(Some(id[0]))
+    }
+}
+
+impl<'a, S: NorFlash> Channel<'a, S> {
+    /// 通道号
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// 在本通道追加一条指定时间戳的日志。
+    ///
+    /// 通道号占用一个字节，`data` 的长度最多为 TSDB 单条日志最大长度减 1。
+    pub fn append_with_timestamp(&mut self, timestamp: i64, data: &[u8]) -> Result<(), Error> {
+        let record = self.record(data);
+        self.db.append_with_timestamp(timestamp, &record)
+    }
+
+    /// 以 TSDB 的时间戳来源在本通道追加一条日志，详见 `TSDB::append()`。
+    pub fn append(&mut self, data: &[u8]) -> Result<(), Error> {
+        let record = self.record(data);
+        self.db.append(&record)
+    }
+
+    /// 读取本通道日志的值 (不含通道号)，日志不可读或不属于本通道时返回 `None`。
+    pub fn get_value(&mut self, entry: &TSLEntry) -> Result<Option<Vec<u8>>, Error> {
+        match self.db.get_value(entry)? {
+            Some(mut record) if record.first() == Some(&self.id) => {
+                record.remove(0);
+                Ok(Some(record))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// 按时间范围迭代本通道的日志，`callback` 收到的值不含通道号，返回 `false` 可提前终止。
+    ///
+    /// 与 `get_value()` 相同，只迭代状态为 `PRE_WRITE`、`Write`、`UserStatus1` 的日志。
+    pub fn iter_by_time<F: FnMut(&TSLEntry, &[u8]) -> bool>(
+        &mut self,
+        from: i64,
+        to: i64,
+        mut callback: F,
+    ) -> Result<(), Error> {
+        let id = self.id;
+        let mut buf = Vec::new();
+        visit_values(self.db, from, to, &mut buf, |entry, record| match record.split_first() {
+            Some((&channel, payload)) if channel == id => callback(entry, payload),
+            _ => true,
+        })
+    }
+
+    /// 统计本通道中时间戳位于 `[from, to]` 内的可读日志数，每条日志只读取通道号。
+    pub fn count(&mut self, from: i64, to: i64) -> Result<usize, Error> {
+        let id = self.id;
+        let mut count = 0;
+        let mut result = Ok(());
+        self.db.tsdb_iter_by_time(from, to, |db, tsl| match db.channel_of(tsl) {
+            Ok(channel) => {
+                count += (channel == Some(id)) as usize;
+                true
+            }
+            Err(err) => {
+                result = Err(err);
+                false
+            }
+        });
+        result.map(|_| count)
+    }
+
+    fn record(&self, data: &[u8]) -> Vec<u8> {
+        let mut record = Vec::with_capacity(1 + data.len());
+        record.push(self.id);
+        record.extend_from_slice(data);
+        record
+    }
+}
@@ -0,0 +1,246 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{fdb_blob_make_write, fdb_time_t, fdb_tsl_append, Error, RawHandle};
+
+use super::TSDB;
+
+/// C 库是否以 64 位时间戳编译 (`time64` feature)。
+///
+/// 未启用时 `fdb_time_t` 为 `i32`，超出范围的时间戳 (例如毫秒级 UNIX 时间) 会被 `append_with_timestamp()`
+/// 以 `Error::TimestampOutOfRange` 拒绝。
+pub const fn is_time64() -> bool {
+    core::mem::size_of::<fdb_time_t>() == 8
+}
+
+/// 可以写入 TSDB 的时间戳范围，即 `fdb_time_t` 的取值范围。
+pub const fn timestamp_range() -> core::ops::RangeInclusive<i64> {
+    fdb_time_t::MIN as i64..=fdb_time_t::MAX as i64
+}
+
+/// 将查询范围的端点截取到 `fdb_time_t` 的范围内，超出的部分不可能存在日志
+pub(super) fn clamp_time(timestamp: i64) -> fdb_time_t {
+    timestamp.clamp(fdb_time_t::MIN as i64, fdb_time_t::MAX as i64) as fdb_time_t
+}
+
+/// 将时间戳转换为 `fdb_time_t`，超出范围时返回 `Error::TimestampOutOfRange` 而不是截断
+pub(super) fn to_fdb_time(timestamp: i64) -> Result<fdb_time_t, Error> {
+    if timestamp_range().contains(&timestamp) {
+        Ok(timestamp as fdb_time_t)
+    } else {
+        Err(Error::TimestampOutOfRange)
+    }
+}
+
+/// `TSDB::append()` 使用的时间戳来源
+pub trait TimeSource {
+    /// 当前时间戳，单位由调用方决定，但应与 `append_with_timestamp()` 使用的一致
+    fn now(&mut self) -> i64;
+
+    /// 当前时间戳，时钟无法给出有效时间时返回错误，`append()` 通过该方法获取时间戳。
+    ///
+    /// 默认实现调用 `now()`。
+    fn try_now(&mut self) -> Result<i64, Error> {
+        Ok(self.now())
+    }
+}
+
+impl<F: FnMut() -> i64> TimeSource for F {
+    fn now(&mut self) -> i64 {
+        self()
+    }
+}
+
+/// 基于 `std::time::SystemTime` 的时间戳来源。
+///
+/// 启用 `time64` 时返回毫秒级 UNIX 时间，否则返回秒级 UNIX 时间，避免 32 位时间戳溢出，
+/// 与默认的 [`super::Resolution`] 一致。未设置时间戳来源时 `append()` 按 `resolution()` 换算系统时间。
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemTimeSource;
+
+#[cfg(feature = "std")]
+impl TimeSource for SystemTimeSource {
+    /// # Panics
+    ///
+    /// 系统时间早于 UNIX 纪元时 panic，`append()` 使用的 `try_now()` 则返回错误。
+    fn now(&mut self) -> i64 {
+        self.try_now().expect("system time is before the UNIX epoch")
+    }
+
+    /// 系统时间早于 UNIX 纪元时返回 `Error::TimestampOutOfRange`。
+    fn try_now(&mut self) -> Result<i64, Error> {
+        super::Resolution::default()
+            .timestamp_of_system_time(std::time::SystemTime::now())
+            .ok_or(Error::TimestampOutOfRange)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: NorFlash> TSDB<S> {
+    /// 设置 `append()` 使用的时间戳来源，初始化前后均可调用。
+    pub fn set_time_source(&mut self, source: impl TimeSource + Send + 'static) {
+        self.time_source = Some(Box::new(source));
+    }
+
+    /// 移除时间戳来源，`append()` 改为使用 C 时间回调或系统时间。
+    pub fn clear_time_source(&mut self) {
+        self.time_source = None;
+    }
+}
+
+impl<S: NorFlash> TSDB<S> {
+    /// 注册 C 库内部使用的时间回调，初始化前后均可调用。
+    ///
+    /// C 库的回调没有用户参数，因此只能注册 `extern "C"` 静态函数；需要捕获状态的时钟请使用
+    /// `set_time_source()`。注册后，未设置 `TimeSource` 时 `append()` 通过 C 库的
+    /// `fdb_tsl_append()` 由该回调获取时间戳。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{fdb_time_t, TSDB};
+    /// use core::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// static TICKS: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// extern "C" fn rtc_now() -> fdb_time_t {
+    ///     TICKS.fetch_add(1, Ordering::Relaxed) as fdb_time_t + 1
+    /// }
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("time_fn_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// db.set_time_fn(rtc_now);
+    /// db.append(b"first")?;
+    /// db.append(b"second")?;
+    /// assert_eq!(db.last_time(), 2);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn set_time_fn(&mut self, get_time: extern "C" fn() -> fdb_time_t) {
+        self.inner.get_mut().get_time = Some(get_time);
+    }
+
+    /// 移除 C 库内部使用的时间回调。
+    pub fn clear_time_fn(&mut self) {
+        self.inner.get_mut().get_time = None;
+    }
+
+    /// 设置是否容忍不递增的时间戳，初始化前后均可调用，默认关闭。
+    ///
+    /// RTC 不可靠的设备经常在重启或校时后产生不大于 `last_time()` 的时间戳，C 库会拒绝这些日志。
+    /// 启用后，`append_with_timestamp()` 与 `append()` (无论时间戳来自 `TimeSource`、C 时间回调还是系统时间)
+    /// 会将这类时间戳调整为 `last_time() + 1` 后写入，调整次数通过 `clamped_timestamps()` 获取。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::TSDB;
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("clamp_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// db.set_clamp_timestamps(true);
+    /// db.append_with_timestamp(100, b"before reset")?;
+    /// // RTC 被重置
+    /// db.append_with_timestamp(3, b"after reset")?;
+    /// assert_eq!(db.last_time(), 101);
+    /// assert_eq!(db.clamped_timestamps(), 1);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn set_clamp_timestamps(&mut self, enable: bool) {
+        self.clamp_timestamps = enable;
+    }
+
+    /// 是否容忍不递增的时间戳。
+    pub fn clamp_timestamps(&self) -> bool {
+        self.clamp_timestamps
+    }
+
+    /// 自创建实例或上次 `reset_clamped_timestamps()` 以来被调整为 `last_time() + 1` 的时间戳数。
+    pub fn clamped_timestamps(&self) -> u64 {
+        self.clamped_timestamps
+    }
+
+    /// 清零时间戳调整计数。
+    pub fn reset_clamped_timestamps(&mut self) {
+        self.clamped_timestamps = 0;
+    }
+
+    /// 以当前时间追加日志条目。
+    ///
+    /// 时间戳依次取自 `set_time_source()` 设置的 [`TimeSource`]、`set_time_fn()` 注册的 C 回调，
+    /// 以及 `std` 环境下按 `resolution()` 换算的系统时间。
+    ///
+    /// C 库要求时间戳严格递增。时钟未前进 (例如同一毫秒内多次追加) 或被回拨时返回 `Error::WriteError`，
+    /// 通过 `set_clamp_timestamps()` 启用容错后改为使用上一条日志的时间戳加 1。
+    ///
+    /// # 返回
+    /// - `Err(Error::InvalidArgument)`: 没有任何时间戳来源 (非 `std` 环境)。
+    /// - `Err(Error::TimestampOutOfRange)`: 系统时间早于 UNIX 纪元。
+    /// - `TimeSource::try_now()` 返回的错误原样返回。
+    /// - 其余同 `append_with_timestamp()`。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::TSDB;
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("append_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// let mut tick = 0;
+    /// db.set_time_source(move || {
+    ///     tick += 100;
+    ///     tick
+    /// });
+    /// db.append(b"first")?;
+    /// db.append(b"second")?;
+    /// let times: Vec<i64> = db.iter().map(|tsl| tsl.time()).collect();
+    /// assert_eq!(times, [100, 200]);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn append(&mut self, data: &[u8]) -> Result<(), Error> {
+        #[cfg(feature = "alloc")]
+        let source = self.time_source.as_mut().map(|source| source.try_now()).transpose()?;
+        #[cfg(not(feature = "alloc"))]
+        let source: Option<i64> = None;
+        let now = match source {
+            Some(now) => now,
+            None if self.raw().get_time.is_some() => return self.append_by_time_fn(data),
+            #[cfg(feature = "std")]
+            None => self
+                .resolution
+                .timestamp_of_system_time(std::time::SystemTime::now())
+                .ok_or(Error::TimestampOutOfRange)?,
+            #[cfg(not(feature = "std"))]
+            None => return Err(Error::InvalidArgument),
+        };
+        self.append_with_timestamp(now, data)
+    }
+
+    /// 内部方法：时间戳不大于 `last_time()` 时调整为 `last_time() + 1` 并计数
+    pub(super) fn monotonic_timestamp(&mut self, timestamp: i64) -> i64 {
+        let last = self.last_time();
+        if timestamp > last {
+            return timestamp;
+        }
+        self.clamped_timestamps += 1;
+        last.saturating_add(1)
+    }
+
+    /// 内部方法：由 C 库通过时间回调获取时间戳并追加
+    fn append_by_time_fn(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.check_writable()?;
+        if let (true, Some(get_time)) = (self.clamp_timestamps, self.raw().get_time) {
+            let now = unsafe { get_time() };
+            return self.append_with_timestamp(now as i64, data);
+        }
+        let mut blob = fdb_blob_make_write(data);
+        #[cfg(feature = "alloc")]
+        self.user_data.arm_events();
+        let result = Error::convert(unsafe { fdb_tsl_append(self.handle(), &mut blob) });
+        let result = self.user_data.finish(result);
+        // 时间戳由 C 库获取，上下文中只有操作
+        #[cfg(feature = "alloc")]
+        let result = result.map_err(|err| err.with_context(crate::ErrorContext::new(crate::Operation::Append)));
+        result
+    }
+}
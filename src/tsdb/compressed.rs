@@ -0,0 +1,203 @@
+use alloc::vec::Vec;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+
+use super::aggregate::visit_values;
+use super::{TSLEntry, TSDB};
+
+/// 记录头：未压缩的值
+const RECORD_RAW: u8 = 0;
+/// 记录头：压缩的值，之后为 4 字节小端的原始长度
+const RECORD_LZ: u8 = 1;
+const LZ_HDR_SIZE: usize = 5;
+
+/// 最短的匹配长度，匹配以 `0x80 | (长度 - MIN_MATCH)` 与 2 字节小端的回溯距离表示
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 0x7F + MIN_MATCH;
+/// 字面量以 `长度 - 1` 开头，最多 128 字节
+const MAX_LITERALS: usize = 0x80;
+const MAX_OFFSET: usize = u16::MAX as usize;
+/// 匹配查找表的大小，位于栈上
+const HASH_BITS: u32 = 10;
+
+/// 透明压缩值的 TSDB 视图。
+///
+/// 通过 `TSDB::compressed()` 获取。长度不小于阈值的值在写入前以内置的 LZ77 算法压缩，
+/// 读取时解压；压缩后没有变小的值按原样保存。每条日志额外占用 1 字节的记录头，
+/// 适合在小分区上保存大量重复的传感器记录以延长保留时间。
+///
+/// **注意**: 通过此视图写入的日志必须同样通过此视图读取。
+///
+/// # 示例
+///
+/// ```
+/// # use flashdb_rs::TSDB;
+/// # let dir = tempfile::tempdir()?;
+/// # let mut db = TSDB::new_file("compressed_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 256)?;
+/// let record = b"temp=21.5;humi=40;temp=21.5;humi=40;temp=21.5;humi=40;temp=21.5;humi=40;";
+/// db.compressed(32).append_with_timestamp(1, record)?;
+///
+/// let tsl = db.iter().next().unwrap();
+/// assert!(tsl.value_len() < record.len());
+/// assert_eq!(db.compressed(32).get_value(&tsl)?.as_deref(), Some(&record[..]));
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct CompressedTSDB<'a, S: NorFlash> {
+    db: &'a mut TSDB<S>,
+    min_len: usize,
+}
+
+impl<S: NorFlash> TSDB<S> {
+    /// 获取压缩长度不小于 `min_len` 的值的视图，详见 [`CompressedTSDB`]。
+    pub fn compressed(&mut self, min_len: usize) -> CompressedTSDB<'_, S> {
+        CompressedTSDB { db: self, min_len }
+    }
+}
+
+impl<'a, S: NorFlash> CompressedTSDB<'a, S> {
+    /// 追加一条指定时间戳的日志，值按需压缩。
+    ///
+    /// 记录头占用一个字节，未压缩时 `data` 的长度最多为 TSDB 单条日志最大长度减 1。
+    pub fn append_with_timestamp(&mut self, timestamp: i64, data: &[u8]) -> Result<(), Error> {
+        let record = encode_record(data, self.min_len);
+        self.db.append_with_timestamp(timestamp, &record)
+    }
+
+    /// 以 TSDB 的时间戳来源追加一条日志，详见 `TSDB::append()`。
+    pub fn append(&mut self, data: &[u8]) -> Result<(), Error> {
+        let record = encode_record(data, self.min_len);
+        self.db.append(&record)
+    }
+
+    /// 读取并解压日志的值，日志不可读时返回 `None`。
+    ///
+    /// # 返回
+    /// - `Err(Error::Corrupted)`: 记录格式无效。
+    pub fn get_value(&mut self, entry: &TSLEntry) -> Result<Option<Vec<u8>>, Error> {
+        match self.db.get_value(entry)? {
+            Some(record) => decode_record(&record).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// 按时间范围迭代日志，`callback` 收到解压后的值，返回 `false` 可提前终止。
+    ///
+    /// 与 `get_value()` 相同，只迭代状态为 `PRE_WRITE`、`Write`、`UserStatus1` 的日志。
+    pub fn iter_by_time<F: FnMut(&TSLEntry, &[u8]) -> bool>(
+        &mut self,
+        from: i64,
+        to: i64,
+        mut callback: F,
+    ) -> Result<(), Error> {
+        let mut buf = Vec::new();
+        let mut result = Ok(());
+        visit_values(self.db, from, to, &mut buf, |entry, record| match decode_record(record) {
+            Ok(value) => callback(entry, &value),
+            Err(err) => {
+                result = Err(err);
+                false
+            }
+        })?;
+        result
+    }
+}
+
+/// 生成日志记录，压缩后没有变小时按原样保存
+fn encode_record(data: &[u8], min_len: usize) -> Vec<u8> {
+    if data.len() >= min_len {
+        let mut record = Vec::with_capacity(data.len());
+        record.push(RECORD_LZ);
+        record.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        compress(data, &mut record);
+        if record.len() <= data.len() {
+            return record;
+        }
+    }
+    let mut record = Vec::with_capacity(1 + data.len());
+    record.push(RECORD_RAW);
+    record.extend_from_slice(data);
+    record
+}
+
+fn decode_record(record: &[u8]) -> Result<Vec<u8>, Error> {
+    match record.split_first() {
+        Some((&RECORD_RAW, value)) => Ok(value.to_vec()),
+        Some((&RECORD_LZ, _)) if record.len() >= LZ_HDR_SIZE => {
+            let len = u32::from_le_bytes([record[1], record[2], record[3], record[4]]) as usize;
+            decompress(&record[LZ_HDR_SIZE..], len)
+        }
+        _ => Err(Error::Corrupted),
+    }
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let value = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]);
+    (value.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+}
+
+/// 贪心的 LZ77 压缩，每个位置只查找最近一次出现的相同前缀
+fn compress(data: &[u8], out: &mut Vec<u8>) {
+    let mut table = [usize::MAX; 1 << HASH_BITS];
+    let (mut pos, mut literals) = (0, 0);
+    while pos + MIN_MATCH <= data.len() {
+        let slot = &mut table[hash(&data[pos..])];
+        let candidate = core::mem::replace(slot, pos);
+        if candidate != usize::MAX
+            && pos - candidate <= MAX_OFFSET
+            && data[candidate..candidate + MIN_MATCH] == data[pos..pos + MIN_MATCH]
+        {
+            let mut len = MIN_MATCH;
+            while len < MAX_MATCH && pos + len < data.len() && data[candidate + len] == data[pos + len] {
+                len += 1;
+            }
+            push_literals(&data[literals..pos], out);
+            out.push(0x80 | (len - MIN_MATCH) as u8);
+            out.extend_from_slice(&((pos - candidate) as u16).to_le_bytes());
+            pos += len;
+            literals = pos;
+        } else {
+            pos += 1;
+        }
+    }
+    push_literals(&data[literals..], out);
+}
+
+fn push_literals(literals: &[u8], out: &mut Vec<u8>) {
+    for chunk in literals.chunks(MAX_LITERALS) {
+        out.push((chunk.len() - 1) as u8);
+        out.extend_from_slice(chunk);
+    }
+}
+
+fn decompress(mut input: &[u8], len: usize) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(len.min(input.len() * MAX_MATCH));
+    while let Some((&token, rest)) = input.split_first() {
+        if token < 0x80 {
+            let count = token as usize + 1;
+            let literals = rest.get(..count).ok_or(Error::Corrupted)?;
+            out.extend_from_slice(literals);
+            input = &rest[count..];
+        } else {
+            let offset = rest.get(..2).ok_or(Error::Corrupted)?;
+            let offset = u16::from_le_bytes([offset[0], offset[1]]) as usize;
+            if offset == 0 || offset > out.len() {
+                return Err(Error::Corrupted);
+            }
+            // 匹配可以与自身重叠，逐字节复制
+            let start = out.len() - offset;
+            for i in 0..(token & 0x7F) as usize + MIN_MATCH {
+                out.push(out[start + i]);
+            }
+            input = &rest[2..];
+        }
+        if out.len() > len {
+            return Err(Error::Corrupted);
+        }
+    }
+    if out.len() != len {
+        return Err(Error::Corrupted);
+    }
+    Ok(out)
+}
@@ -0,0 +1,123 @@
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::fdb_time_t;
+#[cfg(feature = "alloc")]
+use crate::Error;
+
+use super::iter::find_tsl;
+use super::{TSLEntry, TSDB};
+
+/// 可双向移动的 TSDB 游标，由 `TSDB::cursor()` 获取。
+///
+/// 游标位于两条日志之间：`next()` 返回游标之后的第一条日志并越过它，
+/// `prev()` 返回游标之前的最后一条日志并退回到它之前，交替调用会返回同一条日志。
+/// 游标只记录时间戳，分页界面可以保存当前页首尾的时间戳，之后通过 `seek()` 从任意位置继续浏览，
+/// 而不必重新遍历整个数据库。
+///
+/// # 示例
+///
+/// ```
+/// # use flashdb_rs::TSDB;
+/// # let dir = tempfile::tempdir()?;
+/// # let mut db = TSDB::new_file("cursor_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+/// for time in (10..=100).step_by(10) {
+///     db.append_with_timestamp(time, &[time as u8])?;
+/// }
+/// let mut cursor = db.cursor();
+/// cursor.seek(35);
+/// // 向后翻页
+/// let page: Vec<i64> = cursor.by_ref().take(3).map(|tsl| tsl.time()).collect();
+/// assert_eq!(page, [40, 50, 60]);
+/// assert_eq!(cursor.read_value()?.unwrap(), [60]);
+/// // 向前翻页
+/// assert_eq!(cursor.prev().map(|tsl| tsl.time()), Some(60));
+/// assert_eq!(cursor.prev().map(|tsl| tsl.time()), Some(50));
+/// // 从最新的日志开始倒序浏览
+/// cursor.seek_end();
+/// assert_eq!(cursor.prev().map(|tsl| tsl.time()), Some(100));
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct TsCursor<'a, S: NorFlash> {
+    db: &'a mut TSDB<S>,
+    /// 游标之后第一条日志的最小时间戳，`None` 表示位于末尾
+    pos: Option<i64>,
+    /// 最近一次 `next()` / `prev()` 返回的日志
+    current: Option<TSLEntry>,
+}
+
+impl<'a, S: NorFlash> TsCursor<'a, S> {
+    pub fn new(db: &'a mut TSDB<S>) -> Self {
+        Self {
+            db,
+            pos: Some(i64::MIN),
+            current: None,
+        }
+    }
+
+    /// 将游标移动到时间戳 `ts` 之前：`next()` 将返回第一条时间戳不小于 `ts` 的日志。
+    pub fn seek(&mut self, ts: i64) {
+        self.pos = Some(ts);
+        self.current = None;
+    }
+
+    /// 将游标移动到第一条日志之前。
+    pub fn seek_start(&mut self) {
+        self.seek(i64::MIN);
+    }
+
+    /// 将游标移动到最后一条日志之后。
+    pub fn seek_end(&mut self) {
+        self.pos = None;
+        self.current = None;
+    }
+
+    /// 返回游标之前的最后一条日志，并将游标移动到它之前。
+    pub fn prev(&mut self) -> Option<TSLEntry> {
+        let from = match self.pos {
+            None => fdb_time_t::MAX,
+            Some(pos) if pos <= fdb_time_t::MIN as i64 => return None,
+            Some(pos) => (pos - 1).min(fdb_time_t::MAX as i64) as fdb_time_t,
+        };
+        let entry = find_tsl(self.db, from, fdb_time_t::MIN)?;
+        self.pos = Some(entry.time());
+        self.current = Some(entry.clone());
+        Some(entry)
+    }
+
+    /// 最近一次 `next()` / `prev()` 返回的日志，移动游标后为 `None`。
+    pub fn current(&self) -> Option<&TSLEntry> {
+        self.current.as_ref()
+    }
+
+    /// 读取最近一次 `next()` / `prev()` 返回的日志的值，没有当前日志时返回 `None`。
+    #[cfg(feature = "alloc")]
+    pub fn read_value(&mut self) -> Result<Option<alloc::vec::Vec<u8>>, Error> {
+        match self.current.as_ref() {
+            Some(entry) => self.db.get_value(entry),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<'a, S: NorFlash> Iterator for TsCursor<'a, S> {
+    type Item = TSLEntry;
+
+    /// 返回游标之后的第一条日志，并将游标移动到它之后。
+    fn next(&mut self) -> Option<Self::Item> {
+        let from = match self.pos? {
+            pos if pos > fdb_time_t::MAX as i64 => return None,
+            pos => pos.max(fdb_time_t::MIN as i64) as fdb_time_t,
+        };
+        let entry = find_tsl(self.db, from, fdb_time_t::MAX)?;
+        self.pos = entry.time().checked_add(1);
+        self.current = Some(entry.clone());
+        Some(entry)
+    }
+}
+
+impl<S: NorFlash> TSDB<S> {
+    /// 获取位于第一条日志之前的游标，详见 [`TsCursor`]。
+    pub fn cursor(&mut self) -> TsCursor<'_, S> {
+        TsCursor::new(self)
+    }
+}
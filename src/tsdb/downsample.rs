@@ -0,0 +1,100 @@
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+
+use super::aggregate::visit_values;
+use super::window::{bucket_bounds, next_entry};
+use super::{TSLEntry, TSDB};
+
+/// 降采样迭代器，由 `TSDB::iter_downsampled()` 返回。
+///
+/// 从 `from` 开始将时间轴划分为等长的时间桶，每个含有日志的桶产生一个 `(桶起始时间戳, 归约结果)`，
+/// 没有日志的桶被跳过。每次 `next()` 只读取一个桶内的日志，并复用同一个缓冲区。
+pub struct DownsampledIter<'a, S: NorFlash, R, F> {
+    db: &'a mut TSDB<S>,
+    from: i64,
+    to: i64,
+    /// 下一个待查找的时间戳，`None` 表示已结束
+    pos: Option<i64>,
+    bucket: i64,
+    reducer: F,
+    buf: Vec<u8>,
+    _marker: core::marker::PhantomData<fn() -> R>,
+}
+
+impl<'a, S: NorFlash, R, F: FnMut(Option<R>, &TSLEntry, &[u8]) -> R> Iterator for DownsampledIter<'a, S, R, F> {
+    type Item = Result<(i64, R), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let first = next_entry(self.db, &mut self.pos, self.to)?;
+            let (start, end) = bucket_bounds(self.from, self.bucket, self.to, first.time());
+            self.pos = end.checked_add(1);
+
+            let mut acc = None;
+            let reducer = &mut self.reducer;
+            if let Err(err) = visit_values(self.db, first.time(), end, &mut self.buf, |entry, value| {
+                acc = Some(reducer(acc.take(), entry, value));
+                true
+            }) {
+                self.pos = None;
+                return Some(Err(err));
+            }
+            // 桶内的日志都不可读时继续查找下一个桶
+            if let Some(acc) = acc {
+                return Some(Ok((start, acc)));
+            }
+        }
+    }
+}
+
+impl<S: NorFlash> TSDB<S> {
+    /// 获取将 `[from, to]` 内的日志按 `bucket` 分组归约的迭代器，用于在小屏幕上绘制长时间的历史曲线。
+    ///
+    /// `bucket` 按 `resolution()` 换算为时间戳，至少为 1。`reducer` 以折叠的方式处理桶内的每条日志：
+    /// 第一个参数是桶内已有的归约结果 (桶内第一条日志时为 `None`)，返回新的结果。
+    /// 与 `get_value()` 相同，只处理状态为 `PRE_WRITE`、`Write`、`UserStatus1` 的日志。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use core::time::Duration;
+    /// # use flashdb_rs::TSDB;
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("downsample_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// for time in 1..60 {
+    ///     db.append_with_timestamp(time * 1000, &[time as u8])?;
+    /// }
+    /// // 每 10 秒取一个最大值
+    /// let peaks: Vec<(i64, u8)> = db
+    ///     .iter_downsampled(0, 59_999, Duration::from_secs(10), |peak: Option<u8>, _, value| {
+    ///         peak.unwrap_or(0).max(value[0])
+    ///     })
+    ///     .collect::<Result<_, _>>()?;
+    /// assert_eq!(peaks.len(), 6);
+    /// assert_eq!(peaks[1], (10_000, 19));
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn iter_downsampled<R, F: FnMut(Option<R>, &TSLEntry, &[u8]) -> R>(
+        &mut self,
+        from: i64,
+        to: i64,
+        bucket: Duration,
+        reducer: F,
+    ) -> DownsampledIter<'_, S, R, F> {
+        let bucket = self.resolution.timestamp_of(bucket).max(1);
+        DownsampledIter {
+            db: self,
+            from,
+            to,
+            pos: Some(from),
+            bucket,
+            reducer,
+            buf: Vec::new(),
+            _marker: core::marker::PhantomData,
+        }
+    }
+}
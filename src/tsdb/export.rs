@@ -0,0 +1,308 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write as _;
+use core::ops::RangeBounds;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{fdb_time_t, Error};
+
+use super::aggregate::visit_values;
+use super::iter::{find_tsl, inclusive_range};
+use super::{TSLEntry, TSLStatus, TSDB};
+
+/// `import()` 每次从 reader 读取的字节数
+const IMPORT_CHUNK: usize = 256;
+
+/// 导出时日志值的文本编码
+#[derive(Clone, Copy)]
+pub enum Encoding {
+    /// 小写十六进制
+    Hex,
+    /// 标准 base64 (带填充)
+    Base64,
+    /// 用户提供的格式化函数，将值追加到字符串中；结果会按导出格式转义
+    Custom(fn(&[u8], &mut String)),
+}
+
+/// `TSDB::export()` 的输出格式
+#[derive(Clone, Copy)]
+pub enum ExportFormat {
+    /// 表头为 `timestamp,status,payload` 的 CSV
+    Csv(Encoding),
+    /// 由 `{"timestamp", "status", "payload"}` 对象组成的 JSON 数组
+    Json(Encoding),
+}
+
+impl<S: NorFlash> TSDB<S> {
+    /// 将时间戳位于 `range` 内的日志导出到 `writer`，返回导出的日志数。
+    ///
+    /// 用于从设备上取出日志并在桌面端分析。每次只读取一条日志，不需要将整个范围读入内存。
+    /// 与 `get_value()` 相同，只导出状态为 `PRE_WRITE`、`Write`、`UserStatus1` 的日志。
+    ///
+    /// # 返回
+    /// - `Err(Error::WriteError)`: 写入 `writer` 失败。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{Encoding, ExportFormat, TSDB};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("export_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// db.append_with_timestamp(1, &[0xCA, 0xFE])?;
+    /// db.append_with_timestamp(2, b"ok")?;
+    /// let mut csv = Vec::new();
+    /// assert_eq!(db.export(.., ExportFormat::Csv(Encoding::Hex), &mut csv)?, 2);
+    /// assert_eq!(csv, b"timestamp,status,payload\n1,Write,cafe\n2,Write,6f6b\n");
+    ///
+    /// fn text(value: &[u8], out: &mut String) {
+    ///     out.push_str(&String::from_utf8_lossy(value));
+    /// }
+    /// let mut json = Vec::new();
+    /// db.export(2.., ExportFormat::Json(Encoding::Custom(text)), &mut json)?;
+    /// assert_eq!(json, b"[\n  {\"timestamp\": 2, \"status\": \"Write\", \"payload\": \"ok\"}\n]\n");
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn export<W: embedded_io::Write>(
+        &mut self,
+        range: impl RangeBounds<i64>,
+        format: ExportFormat,
+        writer: &mut W,
+    ) -> Result<usize, Error> {
+        let write = |writer: &mut W, text: &str| writer.write_all(text.as_bytes()).map_err(|_| Error::WriteError);
+        match format {
+            ExportFormat::Csv(_) => write(writer, "timestamp,status,payload\n")?,
+            ExportFormat::Json(_) => write(writer, "[")?,
+        }
+
+        let mut count = 0;
+        if let Some((from, to)) = inclusive_range(range) {
+            let mut buf = Vec::new();
+            let mut line = String::new();
+            let mut result = Ok(());
+            visit_values(self, from, to, &mut buf, |entry, value| {
+                line.clear();
+                format_entry(format, count == 0, entry, value, &mut line);
+                count += 1;
+                result = write(writer, &line);
+                result.is_ok()
+            })?;
+            result?;
+        }
+
+        match format {
+            ExportFormat::Csv(_) => Ok(count),
+            ExportFormat::Json(_) => {
+                write(writer, if count == 0 { "]\n" } else { "\n]\n" })?;
+                Ok(count)
+            }
+        }
+    }
+
+    /// 解析 `export()` 的输出，按原有的时间戳与状态重新追加日志，返回导入的日志数。
+    ///
+    /// 用于设备间迁移数据或准备测试数据。只接受 `export()` 产生的格式 (每行一条日志)，
+    /// `Encoding::Custom` 无法还原。日志逐行导入，出错时之前的日志已经写入；
+    /// 与 `append_with_timestamp()` 相同，时间戳必须大于 `last_time()`。
+    ///
+    /// # 返回
+    /// - `Err(Error::InvalidArgument)`: 某一行格式错误，或编码为 `Encoding::Custom`。
+    /// - `Err(Error::ReadError)`: 从 `reader` 读取失败。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{Encoding, ExportFormat, TSDB, TSLStatus};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut device = TSDB::new_file("import_doc_src", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// # let mut fixture = TSDB::new_file("import_doc_dst", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// device.append_with_timestamp(10, b"boot")?;
+    /// device.append_with_timestamp(20, b"ready")?;
+    /// let mut exported = Vec::new();
+    /// device.export(.., ExportFormat::Json(Encoding::Base64), &mut exported)?;
+    ///
+    /// assert_eq!(fixture.import(ExportFormat::Json(Encoding::Base64), &mut &exported[..])?, 2);
+    /// assert_eq!(fixture.last_time(), 20);
+    /// assert_eq!(fixture.count(0, i64::MAX, TSLStatus::Write), 2);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn import<R: embedded_io::Read>(&mut self, format: ExportFormat, reader: &mut R) -> Result<usize, Error> {
+        let (ExportFormat::Csv(encoding) | ExportFormat::Json(encoding)) = format;
+        if let Encoding::Custom(_) = encoding {
+            return Err(Error::InvalidArgument);
+        }
+
+        let mut pending = Vec::new();
+        let mut chunk = [0u8; IMPORT_CHUNK];
+        let mut header = matches!(format, ExportFormat::Csv(_));
+        let mut count = 0;
+        loop {
+            let n = reader.read(&mut chunk).map_err(|_| Error::ReadError)?;
+            pending.extend_from_slice(&chunk[..n]);
+            // 读到末尾时最后一行可能没有换行符
+            let end = match n {
+                0 => pending.len(),
+                _ => pending.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1),
+            };
+            for line in pending[..end].split(|&b| b == b'\n') {
+                let line = core::str::from_utf8(line).map_err(|_| Error::InvalidArgument)?.trim();
+                let record = match format {
+                    ExportFormat::Csv(_) if core::mem::take(&mut header) => match line {
+                        "timestamp,status,payload" => None,
+                        _ => return Err(Error::InvalidArgument),
+                    },
+                    ExportFormat::Csv(_) => parse_csv(line)?,
+                    ExportFormat::Json(_) => parse_json(line)?,
+                };
+                if let Some((time, status, payload)) = record {
+                    self.import_entry(time, status, &decode(encoding, payload)?)?;
+                    count += 1;
+                }
+            }
+            pending.drain(..end);
+            if n == 0 {
+                return Ok(count);
+            }
+        }
+    }
+
+    /// 追加一条导入的日志并恢复其状态
+    fn import_entry(&mut self, time: i64, status: TSLStatus, value: &[u8]) -> Result<(), Error> {
+        self.append_with_timestamp(time, value)?;
+        if matches!(status, TSLStatus::PRE_WRITE | TSLStatus::Write) {
+            return Ok(());
+        }
+        match find_tsl(self, time as fdb_time_t, time as fdb_time_t) {
+            Some(mut entry) => self.set_status(&mut entry, status),
+            None => Err(Error::ReadError),
+        }
+    }
+}
+
+/// 将一条日志格式化为一行输出
+fn format_entry(format: ExportFormat, first: bool, entry: &TSLEntry, value: &[u8], out: &mut String) {
+    match format {
+        ExportFormat::Csv(encoding) => {
+            let _ = write!(out, "{},{:?},", entry.time(), entry.status());
+            match encoding {
+                Encoding::Custom(_) => {
+                    // 自定义格式可能包含逗号或换行，总是加引号
+                    let mut payload = String::new();
+                    encode(encoding, value, &mut payload);
+                    out.push('"');
+                    out.push_str(&payload.replace('"', "\"\""));
+                    out.push('"');
+                }
+                _ => encode(encoding, value, out),
+            }
+            out.push('\n');
+        }
+        ExportFormat::Json(encoding) => {
+            out.push_str(if first { "\n  " } else { ",\n  " });
+            let _ = write!(out, "{{\"timestamp\": {}, \"status\": \"{:?}\", \"payload\": \"", entry.time(), entry.status());
+            let mut payload = String::new();
+            encode(encoding, value, &mut payload);
+            escape_json(&payload, out);
+            out.push_str("\"}");
+        }
+    }
+}
+
+fn encode(encoding: Encoding, value: &[u8], out: &mut String) {
+    match encoding {
+        Encoding::Hex => value.iter().for_each(|b| {
+            let _ = write!(out, "{:02x}", b);
+        }),
+        Encoding::Base64 => out.push_str(&crate::base64::encode(value)),
+        Encoding::Custom(format) => format(value, out),
+    }
+}
+
+/// 按 JSON 字符串的规则转义
+fn escape_json(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+}
+
+/// 解析一行 CSV 数据，空行返回 `None`
+fn parse_csv(line: &str) -> Result<Option<(i64, TSLStatus, &str)>, Error> {
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let mut fields = line.splitn(3, ',');
+    match (fields.next(), fields.next(), fields.next()) {
+        (Some(time), Some(status), Some(payload)) => Ok(Some((parse_time(time)?, parse_status(status)?, payload))),
+        _ => Err(Error::InvalidArgument),
+    }
+}
+
+/// 解析 JSON 数组中的一行，数组的括号与空行返回 `None`
+fn parse_json(line: &str) -> Result<Option<(i64, TSLStatus, &str)>, Error> {
+    let line = line.strip_suffix(',').unwrap_or(line);
+    if matches!(line, "" | "[" | "]" | "[]") {
+        return Ok(None);
+    }
+    let object = line
+        .strip_prefix('{')
+        .and_then(|line| line.strip_suffix('}'))
+        .ok_or(Error::InvalidArgument)?;
+    // 导出的十六进制与 base64 值中不含逗号与转义字符
+    let field = |name: &str| {
+        object
+            .split(',')
+            .filter_map(|field| field.split_once(':'))
+            .find(|(key, _)| key.trim() == name)
+            .map(|(_, value)| value.trim())
+            .ok_or(Error::InvalidArgument)
+    };
+    let time = parse_time(field("\"timestamp\"")?)?;
+    let status = parse_status(unquote(field("\"status\"")?)?)?;
+    let payload = unquote(field("\"payload\"")?)?;
+    Ok(Some((time, status, payload)))
+}
+
+/// 去掉 JSON 字符串两侧的引号
+fn unquote(text: &str) -> Result<&str, Error> {
+    text.strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .ok_or(Error::InvalidArgument)
+}
+
+fn parse_time(text: &str) -> Result<i64, Error> {
+    text.parse().map_err(|_| Error::InvalidArgument)
+}
+
+/// 与 `export()` 输出的状态名称对应
+fn parse_status(text: &str) -> Result<TSLStatus, Error> {
+    match text {
+        "PRE_WRITE" => Ok(TSLStatus::PRE_WRITE),
+        "Write" => Ok(TSLStatus::Write),
+        "UserStatus1" => Ok(TSLStatus::UserStatus1),
+        "Deleted" => Ok(TSLStatus::Deleted),
+        "UserStatus2" => Ok(TSLStatus::UserStatus2),
+        _ => Err(Error::InvalidArgument),
+    }
+}
+
+fn decode(encoding: Encoding, text: &str) -> Result<Vec<u8>, Error> {
+    match encoding {
+        Encoding::Hex if text.len() % 2 == 0 => (0..text.len())
+            .step_by(2)
+            .map(|i| text.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or(Error::InvalidArgument),
+        Encoding::Base64 => crate::base64::decode(text),
+        _ => Err(Error::InvalidArgument),
+    }
+}
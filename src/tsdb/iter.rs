@@ -0,0 +1,276 @@
+use core::ffi::c_void;
+use core::ops::{Bound, RangeBounds};
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{fdb_time_t, fdb_tsl, fdb_tsl_iter_by_time, fdb_tsl_iter_reverse, fdb_tsl_t, RawHandle};
+
+use super::{TSLEntry, TSLStatus, TSDB};
+
+/// TSDB 日志迭代器，由 `TSDB::iter()` / `TSDB::iter_by_time()` 返回。
+///
+/// 日志的时间戳严格递增，迭代器只记录下一个待返回的时间戳，
+/// 每次 `next()` 都从该时间戳开始重新调用 C 库的按时间迭代并在找到第一条日志后停止，
+/// 因此不需要缓冲区，也不需要 `alloc`。实现了 `DoubleEndedIterator`，`rev()` 即可从最新的日志开始。
+///
+/// 与 `tsdb_iter()` 相同，默认返回除 `UNUSED` 以外所有状态的日志；
+/// `with_status()` 可以只返回指定状态的日志，状态在 C 库的迭代回调中过滤。
+pub struct TSDBIterator<'a, S: NorFlash> {
+    db: &'a mut TSDB<S>,
+    /// 尚未返回的时间范围 (包含两端)
+    front: fdb_time_t,
+    back: fdb_time_t,
+    status: Option<TSLStatus>,
+    is_done: bool,
+}
+
+impl<'a, S: NorFlash> TSDBIterator<'a, S> {
+    pub fn new(db: &'a mut TSDB<S>, range: impl RangeBounds<i64>) -> Self {
+        let (front, back) = inclusive_range(range).unwrap_or((1, 0));
+        // 超出 `fdb_time_t` 范围的部分不可能存在日志
+        let (min, max) = (fdb_time_t::MIN as i64, fdb_time_t::MAX as i64);
+        Self {
+            db,
+            front: front.clamp(min, max) as fdb_time_t,
+            back: back.clamp(min, max) as fdb_time_t,
+            status: None,
+            is_done: front > back || front > max || back < min,
+        }
+    }
+
+    /// 只返回状态为 `status` 的日志。
+    pub fn with_status(mut self, status: TSLStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+}
+
+/// 将时间范围转换为闭区间，范围为空时返回 `None`
+pub(super) fn inclusive_range(range: impl RangeBounds<i64>) -> Option<(i64, i64)> {
+    let from = match range.start_bound() {
+        Bound::Included(&from) => from,
+        Bound::Excluded(&from) => from.checked_add(1)?,
+        Bound::Unbounded => i64::MIN,
+    };
+    let to = match range.end_bound() {
+        Bound::Included(&to) => to,
+        Bound::Excluded(&to) => to.checked_sub(1)?,
+        Bound::Unbounded => i64::MAX,
+    };
+    (from <= to).then_some((from, to))
+}
+
+/// 从 `from` 向 `to` 方向查找第一条日志，`from > to` 时 C 库反向迭代
+pub(super) fn find_tsl<S: NorFlash>(db: &mut TSDB<S>, from: fdb_time_t, to: fdb_time_t) -> Option<TSLEntry> {
+    find_tsl_with_status(db, from, to, None)
+}
+
+/// 同 `find_tsl`，`status` 不为 `None` 时只查找该状态的日志
+pub(super) fn find_tsl_with_status<S: NorFlash>(
+    db: &mut TSDB<S>,
+    from: fdb_time_t,
+    to: fdb_time_t,
+    status: Option<TSLStatus>,
+) -> Option<TSLEntry> {
+    let mut find = FindTsl { status, found: None };
+    unsafe {
+        fdb_tsl_iter_by_time(
+            db.handle(),
+            from,
+            to,
+            Some(first_tsl),
+            &mut find as *mut _ as *mut c_void,
+        )
+    };
+    find.found.map(TSLEntry::from)
+}
+
+/// 传递给 C 迭代回调的查找条件与结果
+struct FindTsl {
+    status: Option<TSLStatus>,
+    found: Option<fdb_tsl>,
+}
+
+/// 传递给 C 反向迭代回调的上下文
+struct LastN<F> {
+    remaining: usize,
+    visit: F,
+}
+
+/// 将日志交给访问函数，达到数量后停止迭代
+unsafe extern "C" fn last_n_callback<F: FnMut(&TSLEntry)>(tsl: fdb_tsl_t, arg: *mut c_void) -> bool {
+    let data = &mut *(arg as *mut LastN<F>);
+    (data.visit)(&TSLEntry::from(*tsl));
+    data.remaining -= 1;
+    data.remaining == 0
+}
+
+/// 记录第一条符合条件的日志并停止迭代
+unsafe extern "C" fn first_tsl(tsl: fdb_tsl_t, arg: *mut c_void) -> bool {
+    let find = &mut *(arg as *mut FindTsl);
+    if find.status.is_some_and(|status| TSLStatus::from((*tsl).status) != status) {
+        return false;
+    }
+    find.found = Some(*tsl);
+    true
+}
+
+impl<'a, S: NorFlash> Iterator for TSDBIterator<'a, S> {
+    type Item = TSLEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+        let entry = match find_tsl_with_status(self.db, self.front, self.back, self.status) {
+            Some(entry) => entry,
+            None => {
+                self.is_done = true;
+                return None;
+            }
+        };
+        let time = entry.inner.time;
+        match time.checked_add(1) {
+            Some(next) if time < self.back => self.front = next,
+            _ => self.is_done = true,
+        }
+        Some(entry)
+    }
+}
+
+impl<'a, S: NorFlash> DoubleEndedIterator for TSDBIterator<'a, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+        let entry = match find_tsl_with_status(self.db, self.back, self.front, self.status) {
+            Some(entry) => entry,
+            None => {
+                self.is_done = true;
+                return None;
+            }
+        };
+        let time = entry.inner.time;
+        match time.checked_sub(1) {
+            Some(prev) if time > self.front => self.back = prev,
+            _ => self.is_done = true,
+        }
+        Some(entry)
+    }
+}
+
+impl<S: NorFlash> TSDB<S> {
+    /// 获取按时间顺序返回所有日志的迭代器。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{TSDB, TSLStatus};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("iter_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// for time in 1..=10 {
+    ///     db.append_with_timestamp(time, b"sample")?;
+    /// }
+    /// // 最新的 3 条日志
+    /// let latest: Vec<i64> = db.iter().rev().take(3).map(|tsl| tsl.time()).collect();
+    /// assert_eq!(latest, [10, 9, 8]);
+    /// let pending = db.iter().filter(|tsl| tsl.status() == TSLStatus::Write).count();
+    /// assert_eq!(pending, 10);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn iter(&mut self) -> TSDBIterator<'_, S> {
+        TSDBIterator::new(self, ..)
+    }
+
+    /// 获取只返回时间戳位于 `range` 内的日志的迭代器。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::TSDB;
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("iter_by_time_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// for time in (10..=100).step_by(10) {
+    ///     db.append_with_timestamp(time, b"sample")?;
+    /// }
+    /// let times: Vec<i64> = db.iter_by_time(25..60).map(|tsl| tsl.time()).collect();
+    /// assert_eq!(times, [30, 40, 50]);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn iter_by_time(&mut self, range: impl RangeBounds<i64>) -> TSDBIterator<'_, S> {
+        TSDBIterator::new(self, range)
+    }
+
+    /// 从最新的日志开始依次访问最多 `n` 条日志，返回访问的日志数。
+    ///
+    /// 内部只进行一次反向迭代，访问 `n` 条日志后立即停止。与 `tsdb_iter()` 相同，返回除 `UNUSED` 以外所有状态的日志。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::TSDB;
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("last_n_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// for time in 1..=10 {
+    ///     db.append_with_timestamp(time, b"event")?;
+    /// }
+    /// let mut recent = [0; 3];
+    /// let count = db.last_n(3, |tsl| recent[(10 - tsl.time()) as usize] = tsl.time());
+    /// assert_eq!(count, 3);
+    /// assert_eq!(recent, [10, 9, 8]);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn last_n<F: FnMut(&TSLEntry)>(&mut self, n: usize, visit: F) -> usize {
+        if n == 0 {
+            return 0;
+        }
+        let mut data = LastN { remaining: n, visit };
+        unsafe {
+            fdb_tsl_iter_reverse(
+                self.handle(),
+                Some(last_n_callback::<F>),
+                &mut data as *mut _ as *mut c_void,
+            )
+        };
+        n - data.remaining
+    }
+
+    /// 获取最新的最多 `n` 条日志，按从新到旧的顺序排列，详见 `last_n()`。
+    #[cfg(feature = "alloc")]
+    pub fn last_n_vec(&mut self, n: usize) -> alloc::vec::Vec<TSLEntry> {
+        let mut entries = alloc::vec::Vec::with_capacity(n.min(64));
+        self.last_n(n, |tsl| entries.push(tsl.clone()));
+        entries
+    }
+
+    /// 获取按时间顺序只返回状态为 `status` 的日志的迭代器。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{TSDB, TSLEntry, TSLStatus};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("iter_by_status_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// for time in 1..=10 {
+    ///     db.append_with_timestamp(time, b"sample")?;
+    /// }
+    /// let mut synced: Vec<TSLEntry> = db.iter_by_time(1..=6).collect();
+    /// db.set_status_batch(&mut synced, TSLStatus::UserStatus1)?;
+    /// // 尚未同步的日志
+    /// let pending: Vec<i64> = db.iter_by_status(TSLStatus::Write).map(|tsl| tsl.time()).collect();
+    /// assert_eq!(pending, [7, 8, 9, 10]);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn iter_by_status(&mut self, status: TSLStatus) -> TSDBIterator<'_, S> {
+        TSDBIterator::new(self, ..).with_status(status)
+    }
+
+    /// 获取只返回时间戳位于 `range` 内且状态为 `status` 的日志的迭代器。
+    pub fn iter_by_time_and_status(
+        &mut self,
+        range: impl RangeBounds<i64>,
+        status: TSLStatus,
+    ) -> TSDBIterator<'_, S> {
+        TSDBIterator::new(self, range).with_status(status)
+    }
+}
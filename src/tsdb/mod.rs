@@ -4,32 +4,116 @@ pub use types::*;
 mod reader;
 pub use reader::*;
 
+mod batch;
+
+mod iter;
+pub use iter::*;
+
+mod cursor;
+pub use cursor::*;
+
+mod window;
+pub use window::*;
+
+mod clock;
+pub use clock::*;
+
+mod resolution;
+pub use resolution::*;
+
+mod retention;
+
+mod purge;
+
+mod verify;
+pub use verify::*;
+
+mod summary;
+pub use summary::*;
+
+#[cfg(feature = "alloc")]
+mod aggregate;
+#[cfg(feature = "alloc")]
+pub use aggregate::*;
+
+#[cfg(feature = "alloc")]
+mod downsample;
+#[cfg(feature = "alloc")]
+pub use downsample::*;
+
+#[cfg(feature = "alloc")]
+mod channel;
+#[cfg(feature = "alloc")]
+pub use channel::*;
+
+#[cfg(feature = "alloc")]
+mod export;
+#[cfg(feature = "alloc")]
+pub use export::*;
+
+#[cfg(feature = "serde")]
+mod typed;
+
+#[cfg(feature = "compression")]
+mod compressed;
+#[cfg(feature = "compression")]
+pub use compressed::*;
+
+#[cfg(feature = "alloc")]
+mod schema;
+#[cfg(feature = "alloc")]
+pub use schema::*;
+
 use crate::{
-    fdb_blob, fdb_blob_make_write, fdb_blob_read, fdb_db_t, fdb_tsdb, fdb_tsdb_control_read,
-    fdb_tsdb_control_write, fdb_tsdb_deinit, fdb_tsdb_init, fdb_tsdb_t, fdb_tsl_append_with_ts,
-    fdb_tsl_clean, fdb_tsl_iter, fdb_tsl_iter_by_time, fdb_tsl_iter_reverse, fdb_tsl_query_count,
-    fdb_tsl_set_status, Error, FlashDispatch, RawHandle, FDB_KV_NAME_MAX,
+    fdb_blob, fdb_blob_make_write, fdb_blob_read, fdb_db_t, fdb_time_t, fdb_tsdb,
+    fdb_tsdb_control_read, fdb_tsdb_control_write, fdb_tsdb_deinit, fdb_tsdb_init, fdb_tsdb_t,
+    fdb_tsl_append_with_ts, fdb_tsl_clean, fdb_tsl_iter, fdb_tsl_iter_by_time, fdb_tsl_iter_reverse,
+    fdb_tsl_query_count, fdb_tsl_set_status, Error, FlashDispatch, RawHandle, FDB_KV_NAME_MAX,
     FDB_TSDB_CTRL_GET_LAST_TIME, FDB_TSDB_CTRL_GET_ROLLOVER, FDB_TSDB_CTRL_GET_SEC_SIZE,
     FDB_TSDB_CTRL_SET_MAX_SIZE, FDB_TSDB_CTRL_SET_NOT_FORMAT, FDB_TSDB_CTRL_SET_ROLLOVER,
     FDB_TSDB_CTRL_SET_SEC_SIZE,
 };
 
+use crate::lock::DbLock;
+use crate::timeout::{OpTimeout, Timer};
 use core::{
-    ffi::{c_char, c_void},
+    cell::UnsafeCell,
+    ffi::c_char,
     marker::PhantomData,
+    mem::ManuallyDrop,
+    time::Duration,
 };
 
 use embedded_storage::nor_flash::NorFlash;
 
 pub struct TSDB<S: NorFlash> {
-    inner: fdb_tsdb,
-    storage: S,
+    // C 库在 `&self` 方法中同样会修改数据库结构体
+    inner: UnsafeCell<fdb_tsdb>,
+    // 由 `Drop` 释放，或被 `close()` 取出
+    storage: ManuallyDrop<S>,
     user_data: FlashDispatch,
     #[cfg(feature = "log")]
     name_buf: [u8; FDB_KV_NAME_MAX as usize + 1],
+    /// 数据库的扇区大小，默认为 `S::ERASE_SIZE`
+    sec_size: u32,
     initialized: bool,
-    // 由于 fdb_kvdb 内部引用了 storage 和 name_buf，结构体无法安全地在线程间移动，
-    // 因此标记为 !Send 和 !Sync。
+    // 存储已被 `close()` 取出
+    closed: bool,
+    // 初始化时登记的 Flash 区域
+    #[cfg(feature = "registry")]
+    region: Option<crate::registry::Region>,
+    /// `append()` 使用的时间戳来源
+    #[cfg(feature = "alloc")]
+    time_source: Option<alloc::boxed::Box<dyn TimeSource + Send>>,
+    /// 日志保留时长
+    retention: Option<Duration>,
+    /// 是否将不递增的时间戳调整为 `last_time + 1`
+    clamp_timestamps: bool,
+    /// 被调整的时间戳数
+    clamped_timestamps: u64,
+    /// 时间戳的单位
+    resolution: Resolution,
+    // C 库通过裸指针访问存储与调度器，不能在线程间共享，因此标记为 !Send 和 !Sync。
     _marker: PhantomData<*const ()>,
 }
 
@@ -37,8 +121,7 @@ pub struct TSDB<S: NorFlash> {
 impl TSDB<crate::storage::StdStorage> {
     /// 在 `std` 环境下，创建一个基于文件的 TSDB 实例。
     ///
-    /// 此函数返回一个 `Box<TSDB<StdStorage>>`，以确保数据库实例在内存中的地址是稳定的，
-    /// 防止因栈帧移动导致传递给 C 库的内部指针失效。
+    /// 此函数返回一个 `Box<TSDB<StdStorage>>`，避免在栈上放置较大的结构体。
     ///
     /// # 参数
     /// - `name`: 数据库名称
@@ -53,16 +136,48 @@ impl TSDB<crate::storage::StdStorage> {
         max_size: u32,
         entry_max: usize,
     ) -> Result<Box<Self>, Error> {
-        let storage = crate::storage::StdStorage::new(
-            path,
+        Self::new_file_with_strategy(
             name,
+            path,
             sec_size,
             max_size,
+            entry_max,
             crate::storage::FileStrategy::Multi,
-        )?;
+        )
+    }
+
+    /// 与 `new_file()` 相同，但可以选择文件存储策略。
+    ///
+    /// `FileStrategy::Single` 将整个数据库保存在 `path` 目录下的 `<name>.fdb` 单个文件中，
+    /// 便于作为一个文件备份或复制；`FileStrategy::Multi` 与 `new_file()` 相同，每个扇区一个文件。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::TSDB;
+    /// # use flashdb_rs::storage::FileStrategy;
+    /// # let dir = tempfile::tempdir()?;
+    /// let path = dir.path().to_str().unwrap();
+    /// let mut db = TSDB::new_file_with_strategy("events", path, 4096, 4 * 4096, 64, FileStrategy::Single)?;
+    /// db.append_with_timestamp(1, b"boot")?;
+    /// assert!(dir.path().join("events.fdb").is_file());
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn new_file_with_strategy(
+        name: &str,
+        path: &str,
+        sec_size: u32,
+        max_size: u32,
+        entry_max: usize,
+        strategy: crate::storage::FileStrategy,
+    ) -> Result<Box<Self>, Error> {
+        let storage = crate::storage::StdStorage::in_dir(path, name, sec_size, max_size, strategy)?;
 
         let mut db = Box::new(TSDB::new(storage));
         db.set_name(name)?;
+        db.set_sec_size(sec_size)?;
+        #[cfg(feature = "registry")]
+        db.set_region(crate::registry::Region::for_path(&std::path::Path::new(path).join(name)))?;
         db.init(entry_max)?;
         Ok(db)
     }
@@ -75,16 +190,88 @@ impl<S: NorFlash> TSDB<S> {
     /// * `storage` - 一个实现了 `NorFlash` trait 的存储实例。
     pub fn new(storage: S) -> Self {
         Self {
-            inner: Default::default(),
-            storage,
+            inner: UnsafeCell::new(Default::default()),
+            storage: ManuallyDrop::new(storage),
             user_data: FlashDispatch::new::<S>(),
             #[cfg(feature = "log")]
             name_buf: [0; FDB_KV_NAME_MAX as usize + 1],
+            sec_size: S::ERASE_SIZE as u32,
             initialized: false,
+            closed: false,
+            #[cfg(feature = "registry")]
+            region: None,
+            #[cfg(feature = "alloc")]
+            time_source: None,
+            retention: None,
+            clamp_timestamps: false,
+            clamped_timestamps: 0,
+            resolution: Resolution::default(),
             _marker: PhantomData,
         }
     }
 
+    /// 获取底层存储后端的只读引用。
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// 数据库是否已经成功初始化。
+    pub fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+
+    /// 设置数据库占用的 Flash 区域，`init()` 时若与其他数据库重叠则返回 `Error::Busy`。
+    ///
+    /// 详见 [`crate::registry`]。**注意**: 此方法必须在 `init()` 之前调用。
+    #[cfg(feature = "registry")]
+    pub fn set_region(&mut self, region: crate::registry::Region) -> Result<(), Error> {
+        if self.initialized {
+            return Err(Error::InvalidArgument);
+        }
+        self.region = Some(region);
+        Ok(())
+    }
+
+    /// 释放数据库，之后可以修改扇区大小等配置并再次调用 `init()`。
+    ///
+    /// 释放后读写数据返回 `Error::NotInitialized`。存储外部被修改后，也可以通过
+    /// `deinit()` 与 `init()` 重新加载。未初始化时什么也不做。
+    pub fn deinit(&mut self) {
+        self.deinit_raw();
+    }
+
+    /// 关闭数据库并取回存储后端，例如在 OTA 升级时复用同一块 Flash。
+    ///
+    /// 未初始化的数据库直接返回存储后端。
+    pub fn close(mut self) -> S {
+        self.deinit_raw();
+        self.closed = true;
+        // SAFETY: 设置 `closed` 后 `Drop` 不再释放存储，其余字段照常释放
+        unsafe { ManuallyDrop::take(&mut self.storage) }
+    }
+
+    /// 内部方法：通知 C 库释放数据库，未初始化时什么也不做
+    fn deinit_raw(&mut self) {
+        if self.initialized {
+            #[cfg(feature = "registry")]
+            if let Some(region) = self.region {
+                crate::registry::release(region);
+            }
+            unsafe { fdb_tsdb_deinit(self.handle()) };
+            self.initialized = false;
+        }
+    }
+
+    /// 内部方法：读取 C 数据库结构体，不能跨越 C 库调用持有。
+    pub(crate) fn raw(&self) -> &fdb_tsdb {
+        unsafe { &*self.inner.get() }
+    }
+
+    /// 内部方法：获取底层存储后端的可变引用，调用方需独占借用数据库。
+    pub(crate) fn storage_mut(&mut self) -> &mut S {
+        &mut *self.storage
+    }
+
     /// 设置数据库名称，仅用于日志输出。
     ///
     /// **注意**: 此方法必须在 `init()` 之前调用。
@@ -100,6 +287,35 @@ impl<S: NorFlash> TSDB<S> {
         Ok(())
     }
 
+    /// 开启或关闭该数据库的日志输出，可随时调用，默认开启。
+    ///
+    /// 只影响本数据库的 C 库日志，其他数据库与全局的 `log` 配置不受影响。
+    #[cfg(feature = "log")]
+    pub fn set_logging(&mut self, enabled: bool) {
+        self.user_data.logging = enabled;
+    }
+
+    /// 设置该数据库输出日志的最低严重程度，可随时调用，默认输出全部日志。
+    ///
+    /// C 库的日志按内容分为 `Error`、`Warn` 与 `Info` 三级，例如设为 `Level::Warn` 时只输出错误与警告。
+    #[cfg(feature = "log")]
+    pub fn set_log_level(&mut self, level: log::Level) {
+        self.user_data.log_level = level;
+    }
+
+    /// 设置数据库的扇区大小，必须是 `S::ERASE_SIZE` 的非零整数倍，否则返回 `Error::InvalidArgument`。
+    ///
+    /// 默认等于 `S::ERASE_SIZE`。较大的扇区可以容纳更大的TSL，但垃圾回收时需要搬移更多数据。
+    ///
+    /// **注意**: 此方法必须在 `init()` 之前调用，且同一份数据必须始终以相同的扇区大小打开。
+    pub fn set_sec_size(&mut self, sec_size: u32) -> Result<(), Error> {
+        if self.initialized || sec_size == 0 || sec_size as usize % S::ERASE_SIZE != 0 {
+            return Err(Error::InvalidArgument);
+        }
+        self.sec_size = sec_size;
+        Ok(())
+    }
+
     /// 设置数据库为不可格式化模式。
     ///
     /// 在此模式下，如果数据库初始化时发现头部信息损坏，将返回错误而不是自动格式化。
@@ -108,6 +324,105 @@ impl<S: NorFlash> TSDB<S> {
         self.fdb_tsdb_control_write(FDB_TSDB_CTRL_SET_NOT_FORMAT, enable);
     }
 
+    /// 设置 C 库要求同步写入时是否调用存储的 `flush()`。
+    ///
+    /// 用于 `BufferedStorage` 等带有写入缓冲的存储，详见 [`crate::buffered`]。
+    pub fn set_flush_on_sync(&mut self, enable: bool)
+    where
+        S: crate::buffered::FlushStorage,
+    {
+        self.user_data.vtable.sync = if enable { Some(crate::vtable_sync::<S>) } else { None };
+    }
+
+    /// 为每次 Flash 操作设置超时，超时后数据库操作返回 `Error::Timeout`。
+    ///
+    /// 详见 [`crate::timeout`]。
+    pub fn set_timeout(&mut self, timer: &'static dyn Timer, timeout: Duration) {
+        self.user_data.timeout = Some(OpTimeout::new(timer, timeout));
+    }
+
+    /// 取消 Flash 操作超时。
+    pub fn clear_timeout(&mut self) {
+        self.user_data.timeout = None;
+    }
+
+    /// 安装数据库锁，C 库的每个操作都在锁内执行。详见 [`crate::lock`]。
+    pub fn set_lock(&mut self, lock: &'static dyn DbLock) {
+        self.user_data.install_lock(&mut self.inner.get_mut().parent, Some(lock));
+    }
+
+    /// 移除数据库锁。
+    pub fn clear_lock(&mut self) {
+        self.user_data.install_lock(&mut self.inner.get_mut().parent, None);
+    }
+
+    /// 读取 Flash 上数据的格式版本，详见 [`crate::format`]。
+    ///
+    /// 存储区尚未格式化时返回 `None`。初始化前后均可调用。
+    pub fn format_version(&mut self) -> Result<Option<u8>, Error> {
+        crate::format::TSDB_MAGIC.probe(&mut *self.storage, self.sec_size)
+    }
+
+    /// 注册内部事件回调，详见 [`crate::events`]。
+    ///
+    /// TSDB 会在翻转写入覆盖最旧的扇区时产生 `Rollover` 与 `SectorRetired` 事件。
+    #[cfg(feature = "alloc")]
+    pub fn set_event_handler(&mut self, handler: impl FnMut(&crate::events::Event) + Send + 'static) {
+        self.user_data.events = Some(crate::events::EventHook::new(crate::events::Source::Tsdb, handler));
+    }
+
+    /// 移除内部事件回调。
+    #[cfg(feature = "alloc")]
+    pub fn clear_event_handler(&mut self) {
+        self.user_data.events = None;
+    }
+
+    /// 设置只读模式，用于查看日志的固件组件或检查另一个分区日志的 A/B 引导程序。
+    ///
+    /// 只读模式下追加日志、修改状态、清空、擦除扇区等操作返回 `Error::ReadOnly`，
+    /// 底层的 Flash 写入与擦除也会被拒绝。在 `init()` 之前启用时同时启用不可格式化模式，
+    /// 存储区未格式化或头部损坏时初始化失败，而不是格式化存储区。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{Error, TSDB, StdStorage};
+    /// # use flashdb_rs::storage::FileStrategy;
+    /// # let dir = tempfile::tempdir()?;
+    /// # let path = dir.path().to_str().unwrap();
+    /// let mut db = TSDB::new_file("read_only_doc", path, 4096, 4 * 4096, 64)?;
+    /// db.append_with_timestamp(1, b"boot")?;
+    /// drop(db);
+    ///
+    /// let storage = StdStorage::new(path, "read_only_doc", 4096, 4 * 4096, FileStrategy::Multi)?;
+    /// let mut viewer = Box::new(TSDB::new(storage));
+    /// viewer.set_name("read_only_doc")?;
+    /// viewer.set_read_only(true);
+    /// viewer.init(64)?;
+    /// assert_eq!(viewer.last_time(), 1);
+    /// assert!(matches!(viewer.append_with_timestamp(2, b"x"), Err(Error::ReadOnly)));
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn set_read_only(&mut self, enable: bool) {
+        self.user_data.read_only = enable;
+    }
+
+    /// 是否处于只读模式。
+    pub fn read_only(&self) -> bool {
+        self.user_data.read_only
+    }
+
+    /// 内部方法：未初始化时返回 `Error::NotInitialized`，只读模式下返回 `Error::ReadOnly`
+    pub(super) fn check_writable(&self) -> Result<(), Error> {
+        if !self.initialized {
+            return Err(Error::NotInitialized);
+        }
+        if self.user_data.read_only {
+            return Err(Error::ReadOnly);
+        }
+        Ok(())
+    }
+
     /// 检查数据库是否处于不可格式化模式。
     pub fn not_formatable(&mut self) -> bool {
         let mut enable = false;
@@ -140,9 +455,17 @@ impl<S: NorFlash> TSDB<S> {
 
     /// 获取上次追加 TSL 时的时间戳
     pub fn last_time(&self) -> i64 {
-        let mut size: i64 = 0;
-        self.fdb_tsdb_control_read(FDB_TSDB_CTRL_GET_LAST_TIME, &mut size);
-        size
+        let mut time: fdb_time_t = 0;
+        self.fdb_tsdb_control_read(FDB_TSDB_CTRL_GET_LAST_TIME, &mut time);
+        time as i64
+    }
+
+    /// 获取仍保留在 Flash 中的最旧日志的时间戳，数据库为空时返回 `None`。
+    ///
+    /// 从最旧的扇区开始查找第一条日志，包括已标记为 `Deleted` 的日志。
+    /// 翻转写入覆盖或 `purge_before()` 擦除旧扇区后，与 `last_time()` 一起即可得知历史记录覆盖的时间范围。
+    pub fn first_time(&mut self) -> Option<i64> {
+        iter::find_tsl(self, fdb_time_t::MIN, fdb_time_t::MAX).map(|tsl| tsl.time())
     }
 
     /// 初始化数据库。
@@ -154,11 +477,28 @@ impl<S: NorFlash> TSDB<S> {
     /// - `default_kvs`: (可选) 提供一组默认键值对。如果数据库是首次创建，
     ///   这些键值对将被写入数据库。
     pub fn init(&mut self, entry_max: usize) -> Result<(), Error> {
+        let result = self.init_db(entry_max);
+        #[cfg(feature = "alloc")]
+        let result = result.map_err(|err| err.with_context(crate::ErrorContext::new(crate::Operation::Init)));
+        result
+    }
+
+    /// 内部方法：检查格式版本并调用 C 库初始化数据库
+    fn init_db(&mut self, entry_max: usize) -> Result<(), Error> {
         if self.initialized {
             return Ok(());
         }
-        // 从 NorFlash trait 获取扇区大小和总容量
-        let sec_size = S::ERASE_SIZE as u32;
+        // 拒绝其他格式版本的数据，避免被 C 库当作未格式化的扇区擦除
+        crate::format::TSDB_MAGIC.check(&mut *self.storage, self.sec_size)?;
+        #[cfg(feature = "registry")]
+        if let Some(region) = self.region {
+            crate::registry::claim(region)?;
+        }
+        if self.user_data.read_only {
+            self.set_not_formatable(true);
+        }
+        // 扇区大小默认取自 NorFlash trait 的擦除大小
+        let sec_size = self.sec_size;
         let max_size = self.storage.capacity() as u32;
 
         unsafe {
@@ -169,8 +509,7 @@ impl<S: NorFlash> TSDB<S> {
             self.fdb_tsdb_control_write(FDB_TSDB_CTRL_SET_SEC_SIZE, sec_size);
             self.fdb_tsdb_control_write(FDB_TSDB_CTRL_SET_MAX_SIZE, max_size);
 
-            // 只有这里获取才不会导致悬空指针
-            self.user_data.instance = &mut self.storage as *mut _ as *mut c_void;
+            let user_data = self.user_data.bind(&mut self.inner.get_mut().parent, &mut *self.storage);
 
             #[cfg(feature = "log")]
             let name = self.name_buf.as_ptr() as *const c_char;
@@ -181,17 +520,19 @@ impl<S: NorFlash> TSDB<S> {
                 db_ptr as *mut fdb_tsdb,
                 name,
                 core::ptr::null(),
-                None,
+                self.raw().get_time,
                 entry_max,
-                &mut self.user_data as *mut _ as *mut c_void,
+                user_data,
             );
 
+            #[cfg(feature = "registry")]
+            if let (false, Some(region)) = (result == crate::fdb_err_t_FDB_NO_ERR, self.region) {
+                crate::registry::release(region);
+            }
             if result == crate::fdb_err_t_FDB_NO_ERR {
                 self.initialized = true;
-                Ok(())
-            } else {
-                Err(result.into())
             }
+            self.user_data.finish(Error::convert(result))
         }
     }
 }
@@ -217,17 +558,28 @@ impl<S: NorFlash> TSDB<S> {
     /// 追加带时间戳的日志条目
     ///
     /// # 参数
-    /// - `timestamp`: 时间戳（毫秒级UNIX时间）
+    /// - `timestamp`: 时间戳（单位见 `set_resolution()`，默认为毫秒级UNIX时间）
     /// - `data`: 要存储的字节数据
     ///
     /// # 返回
     /// - `Ok(())`: 追加成功
-    /// - `Err(Error)`: 存储失败（如空间不足）
+    /// - `Err(Error)`: 存储失败（如空间不足）；时间戳不大于 `last_time()` 且未通过
+    ///   `set_clamp_timestamps()` 启用容错时返回 `Error::WriteError`
+    /// - `Err(Error::TimestampOutOfRange)`: 时间戳超出 `fdb_time_t` 的范围，见 [`is_time64()`]
     pub fn append_with_timestamp(&mut self, timestamp: i64, data: &[u8]) -> Result<(), Error> {
+        self.check_writable()?;
+        let timestamp = if self.clamp_timestamps { self.monotonic_timestamp(timestamp) } else { timestamp };
+        let timestamp = clock::to_fdb_time(timestamp)?;
         // 创建可写Blob结构（封装数据缓冲区）
         let mut blob = fdb_blob_make_write(data);
+        #[cfg(feature = "alloc")]
+        self.user_data.arm_events();
         // 调用底层C函数追加带时间戳的TSL
-        Error::convert(unsafe { fdb_tsl_append_with_ts(self.handle(), &mut blob, timestamp as _) })
+        let result = Error::convert(unsafe { fdb_tsl_append_with_ts(self.handle(), &mut blob, timestamp) });
+        let result = self.user_data.finish(result);
+        #[cfg(feature = "alloc")]
+        let result = result.map_err(|err| err.with_context(crate::ErrorContext::for_timestamp(crate::Operation::Append, timestamp as i64)));
+        result
     }
 
     /// 设置日志条目的状态（逻辑标记）
@@ -240,8 +592,13 @@ impl<S: NorFlash> TSDB<S> {
     /// - 标记数据已上传至云端
     /// - 逻辑删除旧数据（非物理删除）
     pub fn set_status(&mut self, tsl: &mut TSLEntry, status: TSLStatus) -> Result<(), Error> {
+        self.check_writable()?;
         // 调用底层函数设置TSL状态
-        Error::convert(unsafe { fdb_tsl_set_status(self.handle(), tsl.handle(), status as _) })
+        let result = Error::convert(unsafe { fdb_tsl_set_status(self.handle(), tsl.handle(), status as _) });
+        let result = self.user_data.finish(result);
+        #[cfg(feature = "alloc")]
+        let result = result.map_err(|err| err.with_context(crate::ErrorContext::for_timestamp(crate::Operation::SetStatus, tsl.time())));
+        result
     }
 
     /// 查询指定时间范围内特定状态的日志数量
@@ -249,12 +606,22 @@ impl<S: NorFlash> TSDB<S> {
     /// # 参数
     /// - `from`: 起始时间戳
     /// - `to`: 结束时间戳
-    /// - `status`: 要筛选的状态
+    /// - `statuses`: 要筛选的状态，可以是单个状态或 [`StatusSet`] (如 `TSLStatus::Write | TSLStatus::UserStatus1`)
     ///
     /// # 注意
     /// - 结果通过底层API直接输出，未返回Rust值
-    pub fn count(&mut self, from: i64, to: i64, status: TSLStatus) -> usize {
-        unsafe { fdb_tsl_query_count(self.handle(), from as _, to as _, status as _) }
+    /// - 超出 `fdb_time_t` 范围的部分不可能存在日志，时间范围会被截取到该范围内
+    /// - 多个状态只扫描一次 Flash，见 `status_summary()`
+    pub fn count(&mut self, from: i64, to: i64, statuses: impl Into<StatusSet>) -> usize {
+        let statuses = statuses.into();
+        match statuses.single() {
+            Some(status) => {
+                let (from, to) = (clock::clamp_time(from), clock::clamp_time(to));
+                unsafe { fdb_tsl_query_count(self.handle(), from, to, status as _) }
+            }
+            None if statuses.is_empty() => 0,
+            None => self.status_summary(from, to).count(statuses),
+        }
     }
 
     /// 迭代所有日志条目（支持正向/反向）
@@ -307,8 +674,8 @@ impl<S: NorFlash> TSDB<S> {
         unsafe {
             fdb_tsl_iter_by_time(
                 db,
-                from as _,
-                to as _,
+                clock::clamp_time(from),
+                clock::clamp_time(to),
                 Some(iter_callback::<S, F>),
                 &mut callback_data as *mut _ as *mut _,
             )
@@ -321,6 +688,7 @@ impl<S: NorFlash> TSDB<S> {
     /// - 此操作会删除所有数据，不可恢复
     /// - 建议在初始化或测试时使用
     pub fn reset(&mut self) -> Result<(), Error> {
+        self.check_writable()?;
         unsafe { fdb_tsl_clean(self.handle()) };
         Ok(())
     }
@@ -336,27 +704,61 @@ impl<S: NorFlash> TSDB<S> {
     /// - `Err(Error)`: 读取失败（如数据损坏）
     #[cfg(feature = "alloc")]
     pub fn get_value(&mut self, tsl_obj: &TSLEntry) -> Result<Option<alloc::vec::Vec<u8>>, Error> {
-        // 转换TSL状态（unsafe操作，确保枚举值匹配）
-        let status = unsafe { core::mem::transmute(tsl_obj.status()) };
-        match status {
+        let mut data = alloc::vec![0u8; tsl_obj.value_len()];
+        match self.get_value_into(tsl_obj, &mut data) {
+            Ok(_) => Ok(Some(data)),
+            Err(Error::KeyNotFound) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// 将指定TSL条目的数据读入 `buf`，返回数据长度，不需要 `alloc`
+    ///
+    /// # 参数
+    /// - `tsl_obj`: TSL对象（包含状态和长度信息）
+    /// - `buf`: 至少为 `tsl_obj.value_len()` 字节的缓冲区，数据写入其开头
+    ///
+    /// # 返回
+    /// - `Ok(len)`: 状态有效时返回读取的长度
+    /// - `Err(Error::KeyNotFound)`: 状态为UNUSED/Deleted/UserStatus2，数据不可读
+    /// - `Err(Error::ValueLengthMismatch)`: `buf` 小于数据长度
+    /// - `Err(Error::ReadError)`: 读取失败（如数据损坏）
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::TSDB;
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("get_value_into_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// db.append_with_timestamp(1, b"sample")?;
+    /// let tsl = db.iter().next().unwrap();
+    /// let mut buf = [0u8; 64];
+    /// let len = db.get_value_into(&tsl, &mut buf)?;
+    /// assert_eq!(&buf[..len], b"sample");
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn get_value_into(&mut self, tsl_obj: &TSLEntry, buf: &mut [u8]) -> Result<usize, Error> {
+        match tsl_obj.status() {
             // 可读取状态（PRE_WRITE/Write/UserStatus1）
-            TSLStatus::PRE_WRITE | TSLStatus::Write | TSLStatus::UserStatus1 => {
-                // 创建指定长度的缓冲区
-                let mut data: alloc::vec::Vec<u8> = alloc::vec::Vec::with_capacity(tsl_obj.value_len());
-                unsafe { data.set_len(tsl_obj.value_len()) };
-                // 根据TSL创建Blob读取结构
-                let mut blob = fdb_blob_make_by_tsl(&mut data, tsl_obj, 0);
-
-                // 执行底层读取
-                let read_len = self.fdb_blob_read(&mut blob);
-                if read_len != data.len() {
-                    return Err(Error::ReadError);
-                }
-                Ok(Some(data))
-            }
+            TSLStatus::PRE_WRITE | TSLStatus::Write | TSLStatus::UserStatus1 => {}
             // 不可读取状态（UNUSED/Deleted/UserStatus2）
-            TSLStatus::UNUSED | TSLStatus::Deleted | TSLStatus::UserStatus2 => Ok(None),
+            TSLStatus::UNUSED | TSLStatus::Deleted | TSLStatus::UserStatus2 => return Err(Error::KeyNotFound),
+        }
+        let len = tsl_obj.value_len();
+        let Some(data) = buf.get_mut(..len) else {
+            return Err(Error::ValueLengthMismatch);
+        };
+        // 根据TSL创建Blob读取结构
+        let mut blob = fdb_blob_make_by_tsl(data, tsl_obj, 0);
+
+        // 执行底层读取
+        if self.fdb_blob_read(&mut blob) != len {
+            let result = self.user_data.finish(Err(Error::ReadError));
+            #[cfg(feature = "alloc")]
+            let result = result.map_err(|err| err.with_context(crate::ErrorContext::for_timestamp(crate::Operation::Read, tsl_obj.time())));
+            return result;
         }
+        Ok(len)
     }
 
     /// 打开TSL数据读取器
@@ -375,16 +777,22 @@ impl<S: NorFlash> RawHandle for TSDB<S> {
     type Handle = fdb_tsdb_t;
 
     fn handle(&self) -> Self::Handle {
-        &self.inner as *const _ as *mut _
+        let db: Self::Handle = self.inner.get();
+        // 名称只用于日志，每次调用 C 库前指向当前位置的缓冲区；结构体位于 `UnsafeCell` 中，可以经 `&self` 修改
+        #[cfg(feature = "log")]
+        if self.initialized {
+            unsafe { (*db).parent.name = self.name_buf.as_ptr() as *const c_char };
+        }
+        db
     }
 }
 
 impl<S: NorFlash> Drop for TSDB<S> {
     fn drop(&mut self) {
-        if self.initialized {
-            unsafe {
-                fdb_tsdb_deinit(self.handle());
-            };
+        self.deinit_raw();
+        if !self.closed {
+            // SAFETY: 存储尚未被 `close()` 取出，此后不会再被访问
+            unsafe { ManuallyDrop::drop(&mut self.storage) };
         }
     }
 }
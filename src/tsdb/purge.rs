@@ -0,0 +1,90 @@
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{fdb_err_t, fdb_time_t, fdb_tsdb_t, Error, RawHandle};
+
+use super::TSDB;
+
+extern "C" {
+    /// 由 `flashdb/patches/0002-tsdb-add-fdb_tsl_purge_before.patch` 添加：擦除所有日志都早于 `before` 的最旧扇区
+    fn fdb_tsl_purge_before(db: fdb_tsdb_t, before: fdb_time_t, purged: *mut usize) -> fdb_err_t;
+    /// 由 `flashdb/patches/0003-tsdb-add-fdb_tsl_vacuum.patch` 添加：擦除所有日志都已删除的最旧扇区
+    fn fdb_tsl_vacuum(db: fdb_tsdb_t, reclaimed: *mut usize) -> fdb_err_t;
+}
+
+impl<S: NorFlash> TSDB<S> {
+    /// 擦除所有日志都早于 `ts` 的扇区，返回被擦除的扇区数。
+    ///
+    /// 与将日志标记为 `Deleted` 不同，被擦除的扇区会立即变为空扇区，之后的写入不必等待翻转写入覆盖它们，
+    /// 空间的释放是确定的。扇区按从旧到新的顺序擦除，遇到第一个仍有不早于 `ts` 的日志的扇区即停止，
+    /// 因此该扇区中早于 `ts` 的日志以及正在写入的扇区会被保留。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{TSDB, TSLStatus};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("purge_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// for time in 1..=300 {
+    ///     db.append_with_timestamp(time, &[0; 32])?;
+    /// }
+    /// let purged = db.purge_before(200)?;
+    /// assert!(purged > 0);
+    /// let oldest = db.iter().next().unwrap().time();
+    /// assert!(oldest > 1 && oldest <= 200);
+    /// assert_eq!(db.count(200, 300, TSLStatus::Write), 101);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn purge_before(&mut self, ts: i64) -> Result<usize, Error> {
+        if !self.initialized {
+            return Err(Error::InitFailed);
+        }
+        self.check_writable()?;
+        if ts <= fdb_time_t::MIN as i64 {
+            return Ok(0);
+        }
+        let before = ts.min(fdb_time_t::MAX as i64) as fdb_time_t;
+        let mut purged = 0;
+        let result = Error::convert(unsafe { fdb_tsl_purge_before(self.handle(), before, &mut purged) });
+        let result = self.user_data.finish(result);
+        #[cfg(feature = "alloc")]
+        let result = result.map_err(|err| err.with_context(crate::ErrorContext::for_timestamp(crate::Operation::Gc, ts)));
+        result.map(|()| purged)
+    }
+
+    /// 擦除所有日志的状态都为 `Deleted` 或 `UserStatus2` 的扇区，返回被擦除的扇区数。
+    ///
+    /// `set_status(Deleted)` 只是逻辑删除，日志仍占用 Flash 空间。TSDB 是按时间顺序写入的环形缓冲区，
+    /// 中间的扇区被擦除后会破坏顺序，因此与 `purge_before()` 相同，只从最旧的扇区开始擦除，
+    /// 遇到第一个含有其他状态日志的扇区即停止，正在写入的扇区也会被保留。
+    /// 配合 `enforce_retention()` 从旧到新删除日志时，所有删除产生的空间都可以被回收。
+    /// 被擦除的扇区在翻转写入时先于仍保留的日志被重新使用；关闭翻转写入时写入不会回到存储区开头。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{TSDB, TSLEntry, TSLStatus};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("vacuum_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// for time in 1..=300 {
+    ///     db.append_with_timestamp(time, &[0; 32])?;
+    /// }
+    /// let mut old: Vec<TSLEntry> = db.iter_by_time(..=200).collect();
+    /// db.set_status_batch(&mut old, TSLStatus::Deleted)?;
+    /// assert!(db.vacuum()? > 0);
+    /// assert_eq!(db.count(0, i64::MAX, TSLStatus::Write), 100);
+    /// assert_eq!(db.vacuum()?, 0);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn vacuum(&mut self) -> Result<usize, Error> {
+        if !self.initialized {
+            return Err(Error::InitFailed);
+        }
+        self.check_writable()?;
+        let mut reclaimed = 0;
+        let result = Error::convert(unsafe { fdb_tsl_vacuum(self.handle(), &mut reclaimed) });
+        let result = self.user_data.finish(result);
+        #[cfg(feature = "alloc")]
+        let result = result.map_err(|err| err.with_context(crate::ErrorContext::new(crate::Operation::Gc)));
+        result.map(|()| reclaimed)
+    }
+}
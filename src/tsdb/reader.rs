@@ -35,6 +35,13 @@ impl<'a,S:NorFlash> embedded_io::Read for TSDBReader<'a,S> {
         // 安全：指针生命周期由迭代器保证
         let mut blob = fdb_blob_make_by_tsl(buf, &self.entry, self.position);
         let actual_read = self.inner.fdb_blob_read(&mut blob);
+        // C 库读取失败时返回 0，未到末尾的非空读取返回 0 即为失败
+        if actual_read == 0 && !buf.is_empty() {
+            let result = self.inner.user_data.finish(Err(Error::ReadError));
+            #[cfg(feature = "alloc")]
+            let result = result.map_err(|err| err.with_context(crate::ErrorContext::for_timestamp(crate::Operation::Read, self.entry.time())));
+            return result;
+        }
         self.position += actual_read;
         Ok(actual_read)
     }
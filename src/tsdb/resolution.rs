@@ -0,0 +1,122 @@
+use core::time::Duration;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+
+use super::{TSLEntry, TSDB};
+
+/// 时间戳的单位，由 `TSDB::set_resolution()` 设置。
+///
+/// 时间戳在 Flash 中只是一个整数，单位完全由应用决定。`append_at()`、`tsl_duration()` 等以
+/// `Duration` / `SystemTime` 表示时间的接口，以及 `set_retention()`、`iter_downsampled()`
+/// 中的时长都按此单位换算，避免调用方混用秒与毫秒。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Seconds,
+    Millis,
+    Micros,
+}
+
+impl Default for Resolution {
+    /// 启用 `time64` 时为毫秒，否则为秒，与 [`super::SystemTimeSource`] 一致
+    fn default() -> Self {
+        if cfg!(feature = "time64") {
+            Resolution::Millis
+        } else {
+            Resolution::Seconds
+        }
+    }
+}
+
+impl Resolution {
+    /// 将时长换算为时间戳，超出 `i64` 时取最大值
+    pub fn timestamp_of(self, duration: Duration) -> i64 {
+        let ticks = match self {
+            Resolution::Seconds => duration.as_secs() as u128,
+            Resolution::Millis => duration.as_millis(),
+            Resolution::Micros => duration.as_micros(),
+        };
+        ticks.min(i64::MAX as u128) as i64
+    }
+
+    /// 将时间戳换算为时长，负数时间戳返回 `None`
+    pub fn duration_of(self, timestamp: i64) -> Option<Duration> {
+        let ticks = u64::try_from(timestamp).ok()?;
+        Some(match self {
+            Resolution::Seconds => Duration::from_secs(ticks),
+            Resolution::Millis => Duration::from_millis(ticks),
+            Resolution::Micros => Duration::from_micros(ticks),
+        })
+    }
+
+    /// 将系统时间换算为 UNIX 时间戳，早于 UNIX 纪元时返回 `None`
+    #[cfg(feature = "std")]
+    pub fn timestamp_of_system_time(self, time: std::time::SystemTime) -> Option<i64> {
+        let since_epoch = time.duration_since(std::time::UNIX_EPOCH).ok()?;
+        Some(self.timestamp_of(since_epoch))
+    }
+
+    /// 将 UNIX 时间戳换算为系统时间
+    #[cfg(feature = "std")]
+    pub fn system_time_of(self, timestamp: i64) -> Option<std::time::SystemTime> {
+        std::time::UNIX_EPOCH.checked_add(self.duration_of(timestamp)?)
+    }
+}
+
+impl<S: NorFlash> TSDB<S> {
+    /// 设置时间戳的单位，初始化前后均可调用，详见 [`Resolution`]。
+    ///
+    /// 只影响换算，不会改写已有的日志；同一数据库应始终使用相同的单位。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use core::time::Duration;
+    /// # use flashdb_rs::{Resolution, TSDB};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("resolution_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// db.set_resolution(Resolution::Micros);
+    /// db.append_at(Duration::from_millis(1500), b"sample")?;
+    /// assert_eq!(db.last_time(), 1_500_000);
+    /// let tsl = db.iter().next().unwrap();
+    /// assert_eq!(db.tsl_duration(&tsl), Some(Duration::from_millis(1500)));
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn set_resolution(&mut self, resolution: Resolution) {
+        self.resolution = resolution;
+    }
+
+    /// 当前的时间戳单位。
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// 以 `since_epoch` 换算的时间戳追加日志，详见 `append_with_timestamp()`。
+    pub fn append_at(&mut self, since_epoch: Duration, data: &[u8]) -> Result<(), Error> {
+        let timestamp = self.resolution.timestamp_of(since_epoch);
+        self.append_with_timestamp(timestamp, data)
+    }
+
+    /// 以系统时间换算的 UNIX 时间戳追加日志。
+    ///
+    /// # 返回
+    /// - `Err(Error::InvalidArgument)`: `time` 早于 UNIX 纪元。
+    /// - 其余同 `append_with_timestamp()`。
+    #[cfg(feature = "std")]
+    pub fn append_at_system_time(&mut self, time: std::time::SystemTime, data: &[u8]) -> Result<(), Error> {
+        let timestamp = self.resolution.timestamp_of_system_time(time).ok_or(Error::InvalidArgument)?;
+        self.append_with_timestamp(timestamp, data)
+    }
+
+    /// 日志的时间戳换算成的时长，时间戳为负数时返回 `None`。
+    pub fn tsl_duration(&self, tsl: &TSLEntry) -> Option<Duration> {
+        self.resolution.duration_of(tsl.time())
+    }
+
+    /// 日志的时间戳作为 UNIX 时间戳换算成的系统时间。
+    #[cfg(feature = "std")]
+    pub fn tsl_system_time(&self, tsl: &TSLEntry) -> Option<std::time::SystemTime> {
+        self.resolution.system_time_of(tsl.time())
+    }
+}
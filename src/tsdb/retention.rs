@@ -0,0 +1,83 @@
+use core::time::Duration;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+
+use super::{TSLEntry, TSLStatus, TSDB};
+
+/// `enforce_retention()` 每次批量删除的日志数，缓冲区位于栈上
+const RETENTION_CHUNK: usize = 32;
+
+impl<S: NorFlash> TSDB<S> {
+    /// 设置日志保留时长，初始化前后均可调用。
+    ///
+    /// 只记录保留窗口，过期日志需要调用 `enforce_retention()` 删除。
+    pub fn set_retention(&mut self, window: Duration) {
+        self.retention = Some(window);
+    }
+
+    /// 取消日志保留时长。
+    pub fn clear_retention(&mut self) {
+        self.retention = None;
+    }
+
+    /// 当前的日志保留时长。
+    pub fn retention(&self) -> Option<Duration> {
+        self.retention
+    }
+
+    /// 将时间戳早于 `now - 保留时长` 的日志标记为 `Deleted`，返回本次删除的日志数。
+    ///
+    /// 保留时长按 `resolution()` 换算为时间戳，`now` 通常取自与 `append()` 相同的时间戳来源。
+    /// 未设置保留时长时不做任何操作。已删除的日志仍占用 Flash 空间，直到所在扇区被翻转写入覆盖或被 `purge_before()`、`vacuum()` 擦除。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use core::time::Duration;
+    /// # use flashdb_rs::{TSDB, TSLStatus};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("retention_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// db.set_retention(Duration::from_secs(60));
+    /// for time in (1..=10).map(|minute| minute * 60_000) {
+    ///     db.append_with_timestamp(time, b"sample")?;
+    /// }
+    /// // 只保留最近一分钟内的日志
+    /// assert_eq!(db.enforce_retention(600_000)?, 8);
+    /// assert_eq!(db.count(0, i64::MAX, TSLStatus::Write), 2);
+    /// assert_eq!(db.enforce_retention(600_000)?, 0);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn enforce_retention(&mut self, now: i64) -> Result<usize, Error> {
+        let Some(window) = self.retention else {
+            return Ok(0);
+        };
+        let window = self.resolution.timestamp_of(window);
+        let Some(cutoff) = now.checked_sub(window) else {
+            return Ok(0);
+        };
+
+        let mut deleted = 0;
+        let mut from = i64::MIN;
+        let mut chunk: [TSLEntry; RETENTION_CHUNK] = Default::default();
+        loop {
+            let mut len = 0;
+            for tsl in self.iter_by_time(from..cutoff) {
+                from = tsl.time() + 1;
+                if tsl.status() != TSLStatus::Deleted {
+                    chunk[len] = tsl;
+                    len += 1;
+                    if len == RETENTION_CHUNK {
+                        break;
+                    }
+                }
+            }
+            if len == 0 {
+                return Ok(deleted);
+            }
+            self.set_status_batch(&mut chunk[..len], TSLStatus::Deleted)?;
+            deleted += len;
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use alloc::vec::Vec;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::Error;
+
+use super::{TSLEntry, TSDB};
+
+/// 将某个版本的原始记录解码为当前的记录类型
+pub type Decoder<T> = fn(&[u8]) -> Result<T, Error>;
+
+/// 带版本号的 TSDB 记录视图。
+///
+/// 通过 `TSDB::schema()` 获取。每条记录的第一个字节为记录格式的版本号，
+/// 读取时根据版本号选择对应的解码器，因此固件在 OTA 升级后修改了记录布局，
+/// 旧记录仍能被透明地转换为新的记录类型。
+///
+/// # 示例
+///
+/// ```
+/// # use flashdb_rs::TSDB;
+/// #[derive(Debug, PartialEq)]
+/// struct Sample {
+///     temperature: i16,
+///     humidity: u8,
+/// }
+///
+/// // v1 只记录温度，v2 增加了湿度
+/// fn decode_v1(raw: &[u8]) -> Result<Sample, flashdb_rs::Error> {
+///     let temperature = i16::from_le_bytes(raw.try_into().map_err(|_| flashdb_rs::Error::Corrupted)?);
+///     Ok(Sample { temperature, humidity: 0 })
+/// }
+/// fn decode_v2(raw: &[u8]) -> Result<Sample, flashdb_rs::Error> {
+///     let [t0, t1, humidity]: [u8; 3] = raw.try_into().map_err(|_| flashdb_rs::Error::Corrupted)?;
+///     Ok(Sample { temperature: i16::from_le_bytes([t0, t1]), humidity })
+/// }
+///
+/// # let dir = tempfile::tempdir()?;
+/// # let mut db = TSDB::new_file("schema_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+/// let mut samples = db.schema(2).decoder(1, decode_v1).decoder(2, decode_v2);
+/// samples.append_with_timestamp(1, &[0xFA, 0x00])?; // 以当前版本 (v2) 写入
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct Schema<'a, S: NorFlash, T> {
+    db: &'a mut TSDB<S>,
+    version: u8,
+    decoders: Vec<(u8, Decoder<T>)>,
+}
+
+impl<S: NorFlash> TSDB<S> {
+    /// 获取带版本号的记录视图，新追加的记录将标记为 `version`。
+    pub fn schema<T>(&mut self, version: u8) -> Schema<'_, S, T> {
+        Schema {
+            db: self,
+            version,
+            decoders: Vec::new(),
+        }
+    }
+}
+
+impl<'a, S: NorFlash, T> Schema<'a, S, T> {
+    /// 注册某个版本的解码器，重复注册时后者覆盖前者。
+    pub fn decoder(mut self, version: u8, decoder: Decoder<T>) -> Self {
+        self.decoders.retain(|(v, _)| *v != version);
+        self.decoders.push((version, decoder));
+        self
+    }
+
+    /// 当前写入使用的版本号
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// 以当前版本号追加一条记录，`payload` 为当前版本的记录编码。
+    pub fn append_with_timestamp(&mut self, timestamp: i64, payload: &[u8]) -> Result<(), Error> {
+        let mut record = Vec::with_capacity(1 + payload.len());
+        record.push(self.version);
+        record.extend_from_slice(payload);
+        self.db.append_with_timestamp(timestamp, &record)
+    }
+
+    /// 读取并解码一条记录。
+    ///
+    /// # 返回
+    /// - `Ok(Some(record))`: 解码成功。
+    /// - `Ok(None)`: 记录不可读 (例如已删除)。
+    /// - `Err(Error::UnsupportedVersion)`: 没有为该版本注册解码器。
+    pub fn decode(&mut self, entry: &TSLEntry) -> Result<Option<T>, Error> {
+        match self.db.get_value(entry)? {
+            Some(raw) => decode_record(&self.decoders, &raw).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// 迭代所有记录并透明地解码为当前的记录类型。
+    ///
+    /// `callback` 返回 `false` 可提前终止；任何一条记录解码失败都会终止迭代并返回错误。
+    pub fn iter<F: FnMut(i64, T) -> bool + Send>(&mut self, callback: F, reverse: bool) -> Result<(), Error> {
+        let decoders = &self.decoders;
+        let mut visitor = Visitor::new(decoders, callback);
+        self.db.tsdb_iter(|db, tsl| visitor.visit(db, tsl), reverse);
+        visitor.result
+    }
+
+    /// 按时间范围迭代记录并透明地解码为当前的记录类型。
+    ///
+    /// `callback` 返回 `false` 可提前终止；任何一条记录解码失败都会终止迭代并返回错误。
+    pub fn iter_by_time<F: FnMut(i64, T) -> bool + Send>(
+        &mut self,
+        from: i64,
+        to: i64,
+        callback: F,
+    ) -> Result<(), Error> {
+        let decoders = &self.decoders;
+        let mut visitor = Visitor::new(decoders, callback);
+        self.db.tsdb_iter_by_time(from, to, |db, tsl| visitor.visit(db, tsl));
+        visitor.result
+    }
+}
+
+/// 迭代时解码记录并记录第一个错误
+struct Visitor<'d, T, F> {
+    decoders: &'d [(u8, Decoder<T>)],
+    callback: F,
+    result: Result<(), Error>,
+}
+
+impl<'d, T, F: FnMut(i64, T) -> bool> Visitor<'d, T, F> {
+    fn new(decoders: &'d [(u8, Decoder<T>)], callback: F) -> Self {
+        Self {
+            decoders,
+            callback,
+            result: Ok(()),
+        }
+    }
+
+    fn visit<S: NorFlash>(&mut self, db: &mut TSDB<S>, tsl: &mut TSLEntry) -> bool {
+        let record = db
+            .get_value(tsl)
+            .and_then(|raw| raw.map(|raw| decode_record(self.decoders, &raw)).transpose());
+        match record {
+            Ok(Some(record)) => (self.callback)(tsl.time(), record),
+            Ok(None) => true,
+            Err(err) => {
+                self.result = Err(err);
+                false
+            }
+        }
+    }
+}
+
+fn decode_record<T>(decoders: &[(u8, Decoder<T>)], raw: &[u8]) -> Result<T, Error> {
+    let (version, payload) = raw.split_first().ok_or(Error::Corrupted)?;
+    let (_, decoder) = decoders
+        .iter()
+        .find(|(v, _)| v == version)
+        .ok_or(Error::UnsupportedVersion)?;
+    decoder(payload)
+}
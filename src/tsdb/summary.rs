@@ -0,0 +1,198 @@
+use core::ffi::c_void;
+use core::ops::BitOr;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{fdb_tsl_iter_by_time, fdb_tsl_t, RawHandle};
+
+use super::clock::clamp_time;
+use super::{TSLStatus, TSDB};
+
+/// 日志状态的集合，用于 `TSDB::count()` 一次统计多种状态。
+///
+/// 单个 [`TSLStatus`]、状态数组或切片都可以转换为集合，也可以用 `|` 组合：
+///
+/// ```
+/// # use flashdb_rs::{StatusSet, TSLStatus};
+/// let readable = TSLStatus::Write | TSLStatus::UserStatus1;
+/// assert!(readable.contains(TSLStatus::UserStatus1));
+/// assert!(!readable.contains(TSLStatus::Deleted));
+/// assert_eq!(readable, StatusSet::from([TSLStatus::UserStatus1, TSLStatus::Write]));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatusSet(u8);
+
+impl StatusSet {
+    /// 空集合
+    pub const fn empty() -> Self {
+        StatusSet(0)
+    }
+
+    /// 加入一个状态
+    pub const fn with(self, status: TSLStatus) -> Self {
+        StatusSet(self.0 | Self::bit(status))
+    }
+
+    /// 是否包含指定状态
+    pub const fn contains(self, status: TSLStatus) -> bool {
+        self.0 & Self::bit(status) != 0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// 集合只有一个状态时返回该状态
+    pub(super) fn single(self) -> Option<TSLStatus> {
+        if self.0.count_ones() != 1 {
+            return None;
+        }
+        [
+            TSLStatus::UNUSED,
+            TSLStatus::PRE_WRITE,
+            TSLStatus::Write,
+            TSLStatus::UserStatus1,
+            TSLStatus::Deleted,
+            TSLStatus::UserStatus2,
+        ]
+        .into_iter()
+        .find(|&status| self.contains(status))
+    }
+
+    const fn bit(status: TSLStatus) -> u8 {
+        1 << status as u32
+    }
+}
+
+impl From<TSLStatus> for StatusSet {
+    fn from(status: TSLStatus) -> Self {
+        StatusSet::empty().with(status)
+    }
+}
+
+impl From<&[TSLStatus]> for StatusSet {
+    fn from(statuses: &[TSLStatus]) -> Self {
+        statuses.iter().fold(StatusSet::empty(), |set, &status| set.with(status))
+    }
+}
+
+impl<const N: usize> From<[TSLStatus; N]> for StatusSet {
+    fn from(statuses: [TSLStatus; N]) -> Self {
+        StatusSet::from(&statuses[..])
+    }
+}
+
+impl BitOr<TSLStatus> for StatusSet {
+    type Output = StatusSet;
+
+    fn bitor(self, status: TSLStatus) -> StatusSet {
+        self.with(status)
+    }
+}
+
+impl BitOr for TSLStatus {
+    type Output = StatusSet;
+
+    fn bitor(self, status: TSLStatus) -> StatusSet {
+        StatusSet::from(self).with(status)
+    }
+}
+
+/// 一段时间范围内各状态的日志数，由 `TSDB::status_summary()` 返回
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatusSummary {
+    pub pre_write: usize,
+    pub write: usize,
+    pub user_status1: usize,
+    pub deleted: usize,
+    pub user_status2: usize,
+}
+
+impl StatusSummary {
+    /// 指定状态的日志数，`UNUSED` 总是 0
+    pub fn get(&self, status: TSLStatus) -> usize {
+        match status {
+            TSLStatus::UNUSED => 0,
+            TSLStatus::PRE_WRITE => self.pre_write,
+            TSLStatus::Write => self.write,
+            TSLStatus::UserStatus1 => self.user_status1,
+            TSLStatus::Deleted => self.deleted,
+            TSLStatus::UserStatus2 => self.user_status2,
+        }
+    }
+
+    /// 集合内各状态的日志数之和
+    pub fn count(&self, statuses: impl Into<StatusSet>) -> usize {
+        let statuses = statuses.into();
+        [
+            TSLStatus::PRE_WRITE,
+            TSLStatus::Write,
+            TSLStatus::UserStatus1,
+            TSLStatus::Deleted,
+            TSLStatus::UserStatus2,
+        ]
+        .into_iter()
+        .filter(|&status| statuses.contains(status))
+        .map(|status| self.get(status))
+        .sum()
+    }
+
+    /// 日志总数
+    pub fn total(&self) -> usize {
+        self.pre_write + self.write + self.user_status1 + self.deleted + self.user_status2
+    }
+
+    fn add(&mut self, status: TSLStatus) {
+        match status {
+            TSLStatus::UNUSED => {}
+            TSLStatus::PRE_WRITE => self.pre_write += 1,
+            TSLStatus::Write => self.write += 1,
+            TSLStatus::UserStatus1 => self.user_status1 += 1,
+            TSLStatus::Deleted => self.deleted += 1,
+            TSLStatus::UserStatus2 => self.user_status2 += 1,
+        }
+    }
+}
+
+impl<S: NorFlash> TSDB<S> {
+    /// 一次遍历统计时间戳位于 `[from, to]` 内各状态的日志数。
+    ///
+    /// 与分别对每种状态调用 `count()` 相比，只需扫描一次 Flash。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::{TSDB, TSLEntry, TSLStatus};
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("summary_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// for time in 1..=10 {
+    ///     db.append_with_timestamp(time, b"sample")?;
+    /// }
+    /// let mut synced: Vec<TSLEntry> = db.iter_by_time(1..=4).collect();
+    /// db.set_status_batch(&mut synced, TSLStatus::UserStatus1)?;
+    /// let summary = db.status_summary(0, i64::MAX);
+    /// assert_eq!(summary.write, 6);
+    /// assert_eq!(summary.get(TSLStatus::UserStatus1), 4);
+    /// assert_eq!(summary.total(), 10);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn status_summary(&mut self, from: i64, to: i64) -> StatusSummary {
+        let mut summary = StatusSummary::default();
+        unsafe {
+            fdb_tsl_iter_by_time(
+                self.handle(),
+                clamp_time(from),
+                clamp_time(to),
+                Some(summary_callback),
+                &mut summary as *mut _ as *mut c_void,
+            )
+        };
+        summary
+    }
+}
+
+/// 累加一条日志的状态
+unsafe extern "C" fn summary_callback(tsl: fdb_tsl_t, arg: *mut c_void) -> bool {
+    (*(arg as *mut StatusSummary)).add(TSLStatus::from((*tsl).status));
+    false
+}
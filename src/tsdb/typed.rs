@@ -0,0 +1,48 @@
+use embedded_storage::nor_flash::NorFlash;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::Error;
+
+use super::{TSLEntry, TSDB};
+
+impl<S: NorFlash> TSDB<S> {
+    /// 以 postcard 编码 `value` 并追加为一条日志。
+    ///
+    /// # 返回
+    /// - `Err(Error::Serde)`: 编码失败。
+    /// - 其余同 `append_with_timestamp()`。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::TSDB;
+    /// #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    /// struct Reading {
+    ///     sensor: u8,
+    ///     celsius: f32,
+    /// }
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("typed_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// db.append_typed(1, &Reading { sensor: 3, celsius: 21.5 })?;
+    /// let entry = db.iter().next().unwrap();
+    /// let reading: Reading = db.get_typed(&entry)?.unwrap();
+    /// assert_eq!(reading, Reading { sensor: 3, celsius: 21.5 });
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn append_typed<T: Serialize>(&mut self, timestamp: i64, value: &T) -> Result<(), Error> {
+        let data = postcard::to_allocvec(value)?;
+        self.append_with_timestamp(timestamp, &data)
+    }
+
+    /// 读取日志的值并以 postcard 解码，状态不可读时返回 `None`，详见 `get_value()`。
+    ///
+    /// # 返回
+    /// - `Err(Error::Serde)`: 日志的值不是 `T` 的 postcard 编码。
+    pub fn get_typed<T: DeserializeOwned>(&mut self, entry: &TSLEntry) -> Result<Option<T>, Error> {
+        match self.get_value(entry)? {
+            Some(data) => Ok(Some(postcard::from_bytes(&data)?)),
+            None => Ok(None),
+        }
+    }
+}
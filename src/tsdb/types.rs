@@ -38,6 +38,21 @@ impl TSLEntry {
     pub fn time(&self) -> i64 {
         self.inner.time as i64
     }
+
+    /// 日志索引 (状态、时间戳、长度等元数据) 在分区内的地址
+    pub fn index_addr(&self) -> u32 {
+        self.inner.addr.index
+    }
+
+    /// 日志数据在分区内的地址。索引从扇区开头向后写入，数据从扇区末尾向前写入
+    pub fn log_addr(&self) -> u32 {
+        self.inner.addr.log
+    }
+
+    /// 日志所在扇区的序号，`sec_size` 通常取自 `TSDB::sec_size()`
+    pub fn sector_index(&self, sec_size: u32) -> u32 {
+        self.inner.addr.index / sec_size
+    }
 }
 
 impl RawHandle for TSLEntry {
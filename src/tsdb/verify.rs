@@ -0,0 +1,179 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use core::ffi::{c_int, c_void};
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{fdb_err_t, fdb_tsdb_t, fdb_tsl_status_t, Error, RawHandle};
+
+use super::{TSLStatus, TSDB};
+
+type VerifyCallback = unsafe extern "C" fn(addr: u32, kind: c_int, status: fdb_tsl_status_t, arg: *mut c_void);
+
+extern "C" {
+    /// 由 `flashdb/patches/0004-tsdb-add-fdb_tsl_verify.patch` 添加：遍历所有扇区与日志索引，报告每条正常的日志与每个问题
+    fn fdb_tsl_verify(db: fdb_tsdb_t, cb: VerifyCallback, arg: *mut c_void) -> fdb_err_t;
+}
+
+/// TSDB 完整性检查发现的问题类型
+///
+/// TSDB 的日志不带 CRC，检查基于扇区头与日志索引的结构。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TSDBIssueKind {
+    /// 扇区完全处于擦除状态，尚未格式化
+    UnformattedSector,
+    /// 扇区头的 magic 无效，整个扇区的数据无法读取
+    BadSectorHeader,
+    /// 已写满的扇区缺少结束信息，或日志索引在结束信息记录的位置之前中断
+    TruncatedSector,
+    /// 日志索引中的长度或数据地址超出扇区范围，该扇区之后的日志无法定位
+    BadLength,
+    /// 日志写入过程中断电 (`PRE_WRITE`)
+    InterruptedWrite,
+    /// 时间戳不大于扇区中前一条日志，按时间查询时可能被跳过
+    TimeOrder,
+}
+
+/// TSDB 完整性检查发现的一个问题
+#[derive(Debug, Clone, Copy)]
+pub struct TSDBIssue {
+    /// 问题所在的 Flash 地址 (扇区或日志索引的起始地址)
+    pub addr: u32,
+    /// 问题类型
+    pub kind: TSDBIssueKind,
+    /// 日志的状态，扇区级别的问题为 `None`
+    pub status: Option<TSLStatus>,
+    /// 是否可以在不丢失已提交数据的情况下恢复
+    pub recoverable: bool,
+}
+
+/// TSDB 完整性检查报告
+#[derive(Debug, Clone, Default)]
+pub struct TSDBVerifyReport {
+    /// 检查的扇区数
+    pub sectors: usize,
+    /// 结构完整的日志数 (`Write`、`UserStatus1`)
+    pub valid_entries: usize,
+    /// 结构完整、已删除的日志数 (`Deleted`、`UserStatus2`)
+    pub deleted_entries: usize,
+    /// 可恢复的问题数
+    pub recoverable: usize,
+    /// 不可恢复的问题数，对应的数据已经丢失或无法按时间查询
+    pub unrecoverable: usize,
+    /// 所有问题的详细信息
+    #[cfg(feature = "alloc")]
+    pub issues: Vec<TSDBIssue>,
+}
+
+impl TSDBVerifyReport {
+    /// 是否未发现任何问题
+    pub fn is_clean(&self) -> bool {
+        self.recoverable == 0 && self.unrecoverable == 0
+    }
+}
+
+impl<S: NorFlash> TSDB<S> {
+    /// 检查数据库的完整性。
+    ///
+    /// 遍历所有扇区与日志索引，校验扇区头、已写满扇区的结束信息、日志的长度与数据地址，
+    /// 以及扇区内时间戳的顺序，返回结构化的报告。检查过程不会修改 Flash。
+    /// 扇区头与索引损坏通常意味着 Flash 故障或写入时断电，时间戳乱序则通常来自软件问题。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use flashdb_rs::TSDB;
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut db = TSDB::new_file("tsdb_verify_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+    /// for time in 1..=10 {
+    ///     db.append_with_timestamp(time, b"sample")?;
+    /// }
+    /// let report = db.verify()?;
+    /// assert!(report.is_clean());
+    /// assert_eq!(report.valid_entries, 10);
+    /// # Ok::<(), flashdb_rs::Error>(())
+    /// ```
+    pub fn verify(&mut self) -> Result<TSDBVerifyReport, Error> {
+        #[cfg(feature = "alloc")]
+        {
+            let mut issues = Vec::new();
+            let mut report = self.verify_with(|issue| issues.push(*issue))?;
+            report.issues = issues;
+            Ok(report)
+        }
+        #[cfg(not(feature = "alloc"))]
+        self.verify_with(|_| {})
+    }
+
+    /// 检查数据库的完整性，并对每个发现的问题调用 `f`。
+    ///
+    /// 适用于没有 `alloc` 的环境，返回的报告中只包含统计信息。
+    pub fn verify_with(&mut self, mut f: impl FnMut(&TSDBIssue)) -> Result<TSDBVerifyReport, Error> {
+        if !self.initialized {
+            return Err(Error::InitFailed);
+        }
+        let mut state = VerifyState {
+            report: TSDBVerifyReport {
+                sectors: self.storage.capacity() / self.sec_size as usize,
+                ..Default::default()
+            },
+            visit: &mut f,
+        };
+        let result = Error::convert(unsafe {
+            fdb_tsl_verify(
+                self.handle(),
+                verify_callback,
+                &mut state as *mut VerifyState<'_> as *mut c_void,
+            )
+        });
+        self.user_data.finish(result)?;
+        Ok(state.report)
+    }
+}
+
+struct VerifyState<'a> {
+    report: TSDBVerifyReport,
+    visit: &'a mut dyn FnMut(&TSDBIssue),
+}
+
+/// 与 `fdb_tsdb.c` 中的 `enum tsl_verify_kind` 保持一致，0 表示正常的日志
+fn issue_kind(kind: c_int) -> Option<TSDBIssueKind> {
+    Some(match kind {
+        1 => TSDBIssueKind::UnformattedSector,
+        2 => TSDBIssueKind::BadSectorHeader,
+        3 => TSDBIssueKind::TruncatedSector,
+        4 => TSDBIssueKind::BadLength,
+        5 => TSDBIssueKind::InterruptedWrite,
+        6 => TSDBIssueKind::TimeOrder,
+        _ => return None,
+    })
+}
+
+unsafe extern "C" fn verify_callback(addr: u32, kind: c_int, status: fdb_tsl_status_t, arg: *mut c_void) {
+    let state = &mut *(arg as *mut VerifyState<'_>);
+    let status = TSLStatus::from(status);
+    let Some(kind) = issue_kind(kind) else {
+        match status {
+            TSLStatus::Deleted | TSLStatus::UserStatus2 => state.report.deleted_entries += 1,
+            _ => state.report.valid_entries += 1,
+        }
+        return;
+    };
+    let issue = TSDBIssue {
+        addr,
+        kind,
+        status: match kind {
+            TSDBIssueKind::UnformattedSector | TSDBIssueKind::BadSectorHeader | TSDBIssueKind::TruncatedSector => None,
+            _ => Some(status),
+        },
+        // 空白扇区重新格式化即可；中断的写入从未提交
+        recoverable: matches!(kind, TSDBIssueKind::UnformattedSector | TSDBIssueKind::InterruptedWrite),
+    };
+    if issue.recoverable {
+        state.report.recoverable += 1;
+    } else {
+        state.report.unrecoverable += 1;
+    }
+    (state.visit)(&issue);
+}
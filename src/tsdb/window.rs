@@ -0,0 +1,91 @@
+use core::time::Duration;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::fdb_time_t;
+
+use super::iter::find_tsl;
+use super::{TSDBIterator, TSLEntry, TSDB};
+
+/// 按固定时长分组的窗口迭代器，由 `TSDB::iter_windows()` 返回。
+///
+/// 从 `from` 开始将时间轴划分为等长的窗口，`next_window()` 返回下一个含有日志的窗口的起始时间戳
+/// 以及该窗口内日志的迭代器，没有日志的窗口被跳过。窗口迭代器借用数据库，
+/// 因此需要在取下一个窗口之前用完；整个过程不需要 `alloc`。
+///
+/// # 示例
+///
+/// ```
+/// # use core::time::Duration;
+/// # use flashdb_rs::TSDB;
+/// # let dir = tempfile::tempdir()?;
+/// # let mut db = TSDB::new_file("windows_doc", dir.path().to_str().unwrap(), 4096, 4 * 4096, 64)?;
+/// for minute in 1..=150 {
+///     db.append_with_timestamp(minute * 60_000, b"sample")?;
+/// }
+/// // 每小时的日志数
+/// let mut hours = db.iter_windows(0, i64::MAX, Duration::from_secs(3600));
+/// let mut counts = [0; 3];
+/// while let Some((start, entries)) = hours.next_window() {
+///     counts[(start / 3_600_000) as usize] = entries.count();
+/// }
+/// assert_eq!(counts, [59, 60, 31]);
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct WindowIter<'a, S: NorFlash> {
+    db: &'a mut TSDB<S>,
+    from: i64,
+    to: i64,
+    /// 下一个待查找的时间戳，`None` 表示已结束
+    pos: Option<i64>,
+    window: i64,
+}
+
+impl<'a, S: NorFlash> WindowIter<'a, S> {
+    /// 下一个含有日志的窗口：`(窗口起始时间戳, 窗口内日志的迭代器)`。
+    pub fn next_window(&mut self) -> Option<(i64, TSDBIterator<'_, S>)> {
+        let first = next_entry(self.db, &mut self.pos, self.to)?;
+        let (start, end) = bucket_bounds(self.from, self.window, self.to, first.time());
+        self.pos = end.checked_add(1);
+        Some((start, TSDBIterator::new(self.db, first.time()..=end)))
+    }
+}
+
+impl<S: NorFlash> TSDB<S> {
+    /// 获取将 `[from, to]` 内的日志按 `window` 分组的迭代器，详见 [`WindowIter`]。
+    ///
+    /// `window` 按 `resolution()` 换算为时间戳，至少为 1。
+    /// 与 `iter()` 相同，返回除 `UNUSED` 以外所有状态的日志。
+    pub fn iter_windows(&mut self, from: i64, to: i64, window: Duration) -> WindowIter<'_, S> {
+        let window = self.resolution.timestamp_of(window).max(1);
+        WindowIter {
+            db: self,
+            from,
+            to,
+            pos: Some(from),
+            window,
+        }
+    }
+}
+
+/// 查找 `[pos, to]` 内的第一条日志，没有时将 `pos` 置为 `None`
+pub(super) fn next_entry<S: NorFlash>(db: &mut TSDB<S>, pos: &mut Option<i64>, to: i64) -> Option<TSLEntry> {
+    let from = (*pos)?;
+    if from > to || from > fdb_time_t::MAX as i64 {
+        *pos = None;
+        return None;
+    }
+    let from = from.max(fdb_time_t::MIN as i64) as fdb_time_t;
+    let entry = find_tsl(db, from, to.min(fdb_time_t::MAX as i64) as fdb_time_t);
+    if entry.is_none() {
+        *pos = None;
+    }
+    entry
+}
+
+/// 从 `from` 开始按 `bucket` 划分时间轴时 `time` 所在的桶，结束时间戳不超过 `to`
+pub(super) fn bucket_bounds(from: i64, bucket: i64, to: i64, time: i64) -> (i64, i64) {
+    let offset = (time as i128 - from as i128) / bucket as i128 * bucket as i128;
+    let start = (from as i128 + offset) as i64;
+    (start, start.saturating_add(bucket - 1).min(to))
+}
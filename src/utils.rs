@@ -1,21 +1,68 @@
-use crate::{
-  fdb_kvdb_control, fdb_kvdb_t, fdb_tsdb_control,
-    fdb_tsdb_t, 
-};
-
+#[cfg(feature = "kvdb")]
+use crate::{fdb_kvdb_control, fdb_kvdb_t};
+#[cfg(feature = "tsdb")]
+use crate::{fdb_tsdb_control, fdb_tsdb_t};
+use crate::{fdb_blob, FDB_WRITE_GRAN};
 
+#[cfg(feature = "kvdb")]
 pub fn fdb_kvdb_control_write<T>(db: fdb_kvdb_t, cmd: u32, arg: T) {
     unsafe { fdb_kvdb_control(db, cmd as i32, &arg as *const _ as *mut _) }
 }
 
+#[cfg(feature = "kvdb")]
 pub fn fdb_kvdb_control_read<T>(db: fdb_kvdb_t, cmd: u32, arg: &mut T) {
     unsafe { fdb_kvdb_control(db, cmd as i32, arg as *mut _ as *mut _) }
 }
 
+#[cfg(feature = "tsdb")]
 pub fn fdb_tsdb_control_write<T>(db: fdb_tsdb_t, cmd: u32, arg: T) {
     unsafe { fdb_tsdb_control(db, cmd as i32, &arg as *const _ as *mut _) }
 }
 
+#[cfg(feature = "tsdb")]
 pub fn fdb_tsdb_control_read<T>(db: fdb_tsdb_t, cmd: u32, arg: &mut T) {
     unsafe { fdb_tsdb_control(db, cmd as i32, arg as *mut _ as *mut _) }
 }
+
+pub fn fdb_blob_make_read(v: &mut [u8]) -> fdb_blob {
+    fdb_blob {
+        buf: v.as_mut_ptr() as *mut _,
+        size: v.len(),
+        saved: unsafe { core::mem::zeroed() },
+    }
+}
+
+pub fn fdb_blob_make_write(v: &[u8]) -> fdb_blob {
+    fdb_blob {
+        buf: v.as_ptr() as *const _ as *mut _,
+        size: v.len(),
+        saved: unsafe { core::mem::zeroed() },
+    }
+}
+
+/// 与 `FDB_STATUS_TABLE_SIZE` 相同：状态表占用的字节数
+pub(crate) const fn status_table_size(status_num: usize) -> usize {
+    if FDB_WRITE_GRAN == 1 {
+        (status_num * FDB_WRITE_GRAN as usize + 7) / 8
+    } else {
+        ((status_num - 1) * FDB_WRITE_GRAN as usize + 7) / 8
+    }
+}
+
+/// 与 `_fdb_get_status` 相同：返回状态表中最后一个已写入的状态序号
+#[cfg(any(feature = "kvdb", all(feature = "tsdb", feature = "alloc")))]
+pub(crate) fn get_status(table: &[u8], status_num: usize) -> usize {
+    let mut index = status_num - 1;
+    while index > 0 {
+        let written = if FDB_WRITE_GRAN == 1 {
+            table[(index - 1) / 8] & (0x80 >> ((index - 1) % 8)) == 0
+        } else {
+            table[(index - 1) * FDB_WRITE_GRAN as usize / 8] == 0x00
+        };
+        if written {
+            break;
+        }
+        index -= 1;
+    }
+    index
+}
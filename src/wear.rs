@@ -0,0 +1,158 @@
+//! 统计每个扇区擦除次数的存储包装器，用于估算 Flash 寿命以及发现异常的垃圾回收行为。
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use embedded_storage::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+
+/// 统计擦除次数与写入字节数的存储包装器。
+///
+/// 擦除次数按 `S::ERASE_SIZE` 大小的扇区分别统计，`erase_counts()` 即擦除次数的分布。
+/// 计数只保存在内存中，需要跨重启累计时请自行持久化 `erase_counts()` 并在启动时通过 `with_counts()` 恢复。
+///
+/// # 示例
+///
+/// ```
+/// # use flashdb_rs::storage::{FileStrategy, StdStorage};
+/// # use flashdb_rs::wear::WearStats;
+/// # use flashdb_rs::KVDB;
+/// # let dir = tempfile::tempdir()?;
+/// let storage = StdStorage::new(dir.path(), "wear_doc", 4096, 4 * 4096, FileStrategy::Multi)?;
+/// let mut db = Box::new(KVDB::new(WearStats::new(storage)));
+/// db.set_name("wear_doc")?;
+/// db.init(None)?;
+/// for i in 0..500u32 {
+///     db.set("counter", &i.to_le_bytes())?;
+/// }
+/// let stats = db.storage();
+/// assert!(stats.total_erases() > 0);
+/// // 假设 Flash 的擦写寿命为 10 万次
+/// assert!(stats.wear_level(100_000) < 0.01);
+/// # Ok::<(), flashdb_rs::Error>(())
+/// ```
+pub struct WearStats<S: NorFlash> {
+    inner: S,
+    erases: Vec<u32>,
+    bytes_programmed: u64,
+}
+
+impl<S: NorFlash> WearStats<S> {
+    /// 包装底层 Flash，所有计数从 0 开始。
+    pub fn new(inner: S) -> Self {
+        let sectors = inner.capacity() / S::ERASE_SIZE;
+        Self {
+            inner,
+            erases: vec![0; sectors],
+            bytes_programmed: 0,
+        }
+    }
+
+    /// 包装底层 Flash，并从之前保存的每个扇区的擦除次数开始累计。
+    ///
+    /// `counts` 的长度与扇区数不一致时，多余的部分被忽略，不足的部分从 0 开始。
+    pub fn with_counts(inner: S, counts: &[u32]) -> Self {
+        let mut stats = Self::new(inner);
+        for (count, saved) in stats.erases.iter_mut().zip(counts) {
+            *count = *saved;
+        }
+        stats
+    }
+
+    /// 每个扇区的擦除次数，按地址顺序排列。
+    pub fn erase_counts(&self) -> &[u32] {
+        &self.erases
+    }
+
+    /// 所有扇区的擦除次数之和。
+    pub fn total_erases(&self) -> u64 {
+        self.erases.iter().map(|&count| count as u64).sum()
+    }
+
+    /// 擦除次数最多的扇区的擦除次数。
+    pub fn max_erases(&self) -> u32 {
+        self.erases.iter().copied().max().unwrap_or(0)
+    }
+
+    /// 擦除次数最少的扇区的擦除次数。
+    pub fn min_erases(&self) -> u32 {
+        self.erases.iter().copied().min().unwrap_or(0)
+    }
+
+    /// 写入的总字节数。
+    pub fn bytes_programmed(&self) -> u64 {
+        self.bytes_programmed
+    }
+
+    /// 磨损最严重的扇区已消耗的寿命比例，`endurance` 为 Flash 的额定擦写次数。
+    ///
+    /// 达到 1.0 时该扇区已达到额定寿命。
+    pub fn wear_level(&self, endurance: u32) -> f32 {
+        self.max_erases() as f32 / endurance.max(1) as f32
+    }
+
+    /// 磨损不均衡程度：擦除次数最多的扇区与平均值之比。
+    ///
+    /// 均衡的环形写入接近 1.0；远大于 1.0 说明少数扇区被反复擦除，例如垃圾回收在几个扇区之间来回搬运数据。
+    /// 尚未发生擦除时返回 `None`。
+    pub fn imbalance(&self) -> Option<f32> {
+        let total = self.total_erases();
+        if total == 0 {
+            return None;
+        }
+        let mean = total as f32 / self.erases.len() as f32;
+        Some(self.max_erases() as f32 / mean)
+    }
+
+    /// 将所有计数清零。
+    pub fn reset(&mut self) {
+        self.erases.fill(0);
+        self.bytes_programmed = 0;
+    }
+
+    /// 获取底层 Flash 的引用。
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// 取回底层 Flash。
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: NorFlash> ErrorType for WearStats<S> {
+    type Error = S::Error;
+}
+
+impl<S: NorFlash> ReadNorFlash for WearStats<S> {
+    const READ_SIZE: usize = S::READ_SIZE;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.inner.read(offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+}
+
+impl<S: NorFlash> NorFlash for WearStats<S> {
+    const WRITE_SIZE: usize = S::WRITE_SIZE;
+    const ERASE_SIZE: usize = S::ERASE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.inner.erase(from, to)?;
+        let first = from as usize / S::ERASE_SIZE;
+        let last = (to as usize).div_ceil(S::ERASE_SIZE);
+        for count in self.erases.iter_mut().take(last).skip(first) {
+            *count = count.saturating_add(1);
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.inner.write(offset, bytes)?;
+        self.bytes_programmed += bytes.len() as u64;
+        Ok(())
+    }
+}
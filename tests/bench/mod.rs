@@ -0,0 +1,38 @@
+#![cfg(all(feature = "std", feature = "kvdb", feature = "tsdb"))]
+#![cfg(test)]
+
+use anyhow::Result;
+use flashdb_rs::bench::{run_kvdb, run_tsdb, BenchConfig, KvWorkload, TsWorkload};
+use flashdb_rs::storage::{FileStrategy, StdStorage};
+use tempfile::TempDir;
+
+#[test]
+fn test_bench_workloads() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let config = BenchConfig {
+        ops: 200,
+        ..Default::default()
+    };
+
+    for workload in [KvWorkload::Fill, KvWorkload::Overwrite, KvWorkload::Churn] {
+        let path = temp_dir.path().join(format!("{:?}", workload));
+        let storage = StdStorage::new(&path, "bench", 4096, 32 * 4096, FileStrategy::Multi)?;
+        let report = run_kvdb(storage, workload, &config)?;
+        assert_eq!(report.ops, 200);
+        assert!(report.bytes_programmed >= report.payload_bytes);
+    }
+
+    // 小容量数据库上的反复覆盖写入必然触发 GC 擦除
+    let storage = StdStorage::new(temp_dir.path().join("gc"), "bench", 4096, 4 * 4096, FileStrategy::Multi)?;
+    let report = run_kvdb(storage, KvWorkload::Overwrite, &config)?;
+    assert!(report.erases > 4, "覆盖写入应触发 GC: {}", report);
+
+    for workload in [TsWorkload::Append, TsWorkload::TimeRangeQuery] {
+        let path = temp_dir.path().join(format!("{:?}", workload));
+        let storage = StdStorage::new(&path, "bench", 4096, 16 * 4096, FileStrategy::Multi)?;
+        let report = run_tsdb(storage, workload, &config)?;
+        assert_eq!(report.ops, 200);
+    }
+
+    Ok(())
+}
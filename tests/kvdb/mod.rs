@@ -1,9 +1,13 @@
-#![cfg(feature = "std")]
+#![cfg(all(feature = "std", feature = "kvdb"))]
 #![cfg(test)]
 
-use core::ffi::CStr;
 use embedded_io::{Read, Seek};
-use flashdb_rs::{define_default_kvs, KVDB};
+use embedded_storage::nor_flash::NorFlash;
+use flashdb_rs::events::{event_channel, Event};
+use flashdb_rs::storage::FileStrategy;
+use flashdb_rs::{define_default_kvs, CachedKVDB, KVStatus, KVStatusMask, KeyProvider, DefaultKvs, Error, GcPolicy, IssueKind, KVDBMetrics, LazyDb, Migration, ModifiedStamp, Overlay, StdStorage, WriteOp, KVDB};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
 use tempfile::TempDir;
 
 // 使用宏定义一组默认键值对，用于测试
@@ -165,3 +169,2176 @@ fn test_kvdb_garbage_collection() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_kvdb_scalar_accessors() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("scalar_db", path, 4096, 128 * 1024, None)?;
+
+    db.set_u8("u8", 0xAB)?;
+    db.set_u16("u16", 0xBEEF)?;
+    db.set_u32("u32", 0xDEAD_BEEF)?;
+    db.set_u64("u64", u64::MAX - 1)?;
+    db.set_i32("i32", -42)?;
+    db.set_f32("f32", 3.5)?;
+    db.set_bool("bool", true)?;
+
+    assert_eq!(db.get_u8("u8")?, Some(0xAB));
+    assert_eq!(db.get_u16("u16")?, Some(0xBEEF));
+    assert_eq!(db.get_u32("u32")?, Some(0xDEAD_BEEF));
+    assert_eq!(db.get_u64("u64")?, Some(u64::MAX - 1));
+    assert_eq!(db.get_i32("i32")?, Some(-42));
+    assert_eq!(db.get_f32("f32")?, Some(3.5));
+    assert_eq!(db.get_bool("bool")?, Some(true));
+
+    // 小端序存储
+    assert_eq!(db.get("u16")?.unwrap(), vec![0xEF, 0xBE]);
+    // 不存在的键
+    assert_eq!(db.get_u32("missing")?, None);
+    // 长度不匹配
+    assert!(matches!(
+        db.get_u16("u32"),
+        Err(flashdb_rs::Error::ValueLengthMismatch)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_runtime_default_kvs() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let storage = StdStorage::new(
+        path,
+        "runtime_defaults",
+        4096,
+        128 * 1024,
+        flashdb_rs::storage::FileStrategy::Multi,
+    )?;
+    let mut db = Box::new(KVDB::new(storage));
+
+    // 键值均来自堆上数据，并在初始化后立即释放
+    let serial = String::from("SN-0001");
+    let defaults = DefaultKvs::new()
+        .add("serial", serial.as_bytes())
+        .add("empty", b"");
+    drop(serial);
+    db.init_with_defaults(defaults)?;
+
+    assert_eq!(db.get("serial")?.unwrap(), b"SN-0001");
+    assert_eq!(db.get("empty")?.unwrap(), b"");
+
+    db.set("serial", b"changed")?;
+    db.reset()?;
+    assert_eq!(db.get("serial")?.unwrap(), b"SN-0001", "reset 应该恢复运行时默认值");
+
+    // 过长的键在初始化时被拒绝
+    let mut db2 = Box::new(KVDB::new(StdStorage::new(
+        path,
+        "bad_defaults",
+        4096,
+        128 * 1024,
+        flashdb_rs::storage::FileStrategy::Multi,
+    )?));
+    let long_key = "k".repeat(100);
+    assert!(db2.init_with_defaults(DefaultKvs::new().add(&long_key, b"v")).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_dedup() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("dedup_db", path, 4096, 128 * 1024, None)?;
+
+    let cert = vec![0xC5u8; 1024];
+    let mut dedup = db.dedup();
+    dedup.set("cert_a", &cert)?;
+    dedup.set("cert_b", &cert)?;
+    dedup.set("small", b"tiny")?;
+    assert_eq!(dedup.ref_count(&cert)?, 2);
+
+    assert_eq!(dedup.get("cert_a")?.unwrap(), cert);
+    assert_eq!(dedup.get("cert_b")?.unwrap(), cert);
+    assert_eq!(dedup.get("small")?.unwrap(), b"tiny");
+    assert!(dedup.get("missing")?.is_none());
+
+    // 共享的数据块只存储一次，键本身只保存引用
+    assert!(db.get("cert_a")?.unwrap().len() < 32);
+
+    let mut dedup = db.dedup();
+    dedup.delete("cert_a")?;
+    assert_eq!(dedup.ref_count(&cert)?, 1);
+    assert_eq!(dedup.get("cert_b")?.unwrap(), cert);
+
+    // 覆盖为其他内容后，引用计数归零，数据块被删除
+    dedup.set("cert_b", &vec![0x11u8; 512])?;
+    assert_eq!(dedup.ref_count(&cert)?, 0);
+    assert_eq!(dedup.get("cert_b")?.unwrap(), vec![0x11u8; 512]);
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_iter_keys() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("keys_db", path, 4096, 128 * 1024, None)?;
+
+    db.set("wifi_ssid", b"MyNetwork")?;
+    db.set("wifi_pass", b"secret")?;
+    db.set("volume", b"7")?;
+    db.delete("volume")?;
+
+    let mut keys: Vec<String> = db
+        .iter_keys()
+        .map(|name| name.as_str().unwrap().to_string())
+        .collect();
+    keys.sort();
+    assert_eq!(keys, vec!["wifi_pass", "wifi_ssid"]);
+
+    assert!(db.iter_keys().any(|name| name == "wifi_ssid"));
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_clear() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file(
+        "clear_db",
+        path,
+        4096,
+        128 * 1024,
+        Some(&MY_DEFAULT_KVS.0),
+    )?;
+    db.set("user_token", b"abc")?;
+
+    // clear 会连同默认值一起清除
+    db.clear()?;
+    assert!(db.get("user_token")?.is_none());
+    assert!(db.get("version")?.is_none());
+    assert_eq!(db.iter().count(), 0);
+
+    // reset 仍然可以恢复默认值
+    db.reset()?;
+    assert_eq!(db.get("version")?.unwrap(), b"1.0.0");
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_overlay() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("overlay_db", path, 4096, 128 * 1024, None)?;
+    db.set("wifi_ssid", b"production")?;
+    db.set_u32("interval", 60)?;
+
+    std::env::set_var("FDB_OVERLAY_TEST_WIFI_SSID", "lab");
+    let mut overlay = Overlay::from_env("FDB_OVERLAY_TEST_");
+    overlay.insert("interval", &5u32.to_le_bytes());
+    db.set_overlay(Some(overlay));
+
+    assert_eq!(db.get("wifi_ssid")?.unwrap(), b"lab");
+    assert_eq!(db.get_u32("interval")?, Some(5));
+
+    // 覆盖值不会写入 Flash
+    db.overlay_mut().unwrap().remove("interval");
+    assert_eq!(db.get_u32("interval")?, Some(60));
+    db.set_overlay(None);
+    assert_eq!(db.get("wifi_ssid")?.unwrap(), b"production");
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_verify() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("verify_db", path, 4096, 4 * 4096, None)?;
+    db.set("keep", b"value")?;
+    db.set("removed", b"value")?;
+    db.delete("removed")?;
+    db.set("corrupt_me", b"0123456789abcdef")?;
+
+    let report = db.verify()?;
+    assert!(report.is_clean(), "{:?}", report);
+    assert_eq!(report.sectors, 4);
+    assert_eq!(report.valid_entries, 2);
+    assert_eq!(report.deleted_entries, 1);
+    drop(db);
+
+    // 直接篡改 Flash 上的值，模拟位翻转
+    let sector = temp_dir.path().join("verify_db.fdb.0");
+    let mut raw = std::fs::read(&sector)?;
+    let pos = raw
+        .windows(16)
+        .position(|w| w == b"0123456789abcdef")
+        .unwrap();
+    raw[pos] ^= 0x01;
+    std::fs::write(&sector, raw)?;
+
+    let mut db = KVDB::new_file("verify_db", path, 4096, 4 * 4096, None)?;
+    let report = db.verify()?;
+    assert!(!report.is_clean());
+    assert_eq!(report.valid_entries, 1);
+    assert_eq!(report.unrecoverable, 1);
+    let issue = &report.issues[0];
+    assert_eq!(issue.kind, IssueKind::CrcMismatch);
+    assert!(!issue.recoverable);
+    assert_eq!(issue.name.unwrap(), "corrupt_me");
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_repair() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("repair_db", path, 4096, 4 * 4096, None)?;
+    db.set("keep", b"value")?;
+    db.set("corrupt_me", b"0123456789abcdef")?;
+    db.set("after", b"still here")?;
+    drop(db);
+
+    let sector = temp_dir.path().join("repair_db.fdb.0");
+    let mut raw = std::fs::read(&sector)?;
+    let pos = raw
+        .windows(16)
+        .position(|w| w == b"0123456789abcdef")
+        .unwrap();
+    raw[pos] ^= 0x01;
+    std::fs::write(&sector, raw)?;
+
+    let mut db = KVDB::new_file("repair_db", path, 4096, 4 * 4096, None)?;
+
+    // 演练模式只报告，不修改 Flash
+    let report = db.repair(true)?;
+    assert!(report.dry_run);
+    assert_eq!(report.dropped_entries, 1);
+    assert_eq!(report.removed[0].name.unwrap(), "corrupt_me");
+    assert_eq!(db.verify()?.unrecoverable, 1);
+
+    let report = db.repair(false)?;
+    assert_eq!(report.dropped_entries, 1);
+    assert_eq!(report.formatted_sectors, 0);
+
+    let verify = db.verify()?;
+    assert!(verify.is_clean(), "{:?}", verify);
+    assert_eq!(verify.valid_entries, 2);
+    assert_eq!(db.get("keep")?.unwrap(), b"value");
+    assert_eq!(db.get("after")?.unwrap(), b"still here");
+    assert!(db.get("corrupt_me")?.is_none());
+
+    // 修复后的数据库可以正常写入
+    db.set("corrupt_me", b"fresh")?;
+    assert_eq!(db.get("corrupt_me")?.unwrap(), b"fresh");
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_lazy_init() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    // 不可格式化的空白分区无法初始化，超时后返回错误
+    let storage = StdStorage::new(temp_dir.path().join("blank"), "lazy_db", 4096, 4 * 4096, FileStrategy::Multi)?;
+    let mut db = KVDB::new(storage);
+    db.set_not_formatable(true);
+    let mut lazy = Box::new(LazyDb::new(db, None));
+    let mut attempts = 0;
+    assert!(lazy
+        .try_init_with(|_| {
+            attempts += 1;
+            attempts < 3
+        })
+        .is_err());
+    assert_eq!(attempts, 3);
+    assert!(lazy.try_init_with_timeout(Duration::from_millis(30)).is_err());
+    assert!(!lazy.is_initialized());
+
+    let storage = StdStorage::new(temp_dir.path().join("lazy"), "lazy_db", 4096, 4 * 4096, FileStrategy::Multi)?;
+    let mut lazy = Box::new(LazyDb::new(KVDB::new(storage), None));
+    assert!(!lazy.is_initialized());
+    lazy.get()?.set("key", b"value")?;
+    assert!(lazy.is_initialized());
+    assert_eq!(lazy.get()?.get("key")?.unwrap(), b"value");
+
+    Ok(())
+}
+
+
+#[test]
+fn test_kvdb_get_with() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut db = KVDB::new_file("get_with_db", temp_dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+
+    let large: Vec<u8> = (0..200u8).collect();
+    db.set("small", b"abc")?;
+    db.set("large", &large)?;
+
+    assert_eq!(db.get_with("small", |v| v.to_vec())?, Some(b"abc".to_vec()));
+    assert_eq!(db.get_with("large", |v| v == large.as_slice())?, Some(true));
+    // 缓冲区复用后，较短的值不会带上之前的残留数据
+    assert_eq!(db.get_with("small", |v| v.len())?, Some(3));
+    assert_eq!(db.get_with("missing", |v| v.len())?, None);
+
+    let mut chunked = Vec::new();
+    let mut chunks = 0;
+    let len = db.get_chunks("large", |chunk| {
+        chunks += 1;
+        chunked.extend_from_slice(chunk);
+    })?;
+    assert_eq!(len, Some(large.len()));
+    assert!(chunks > 1);
+    assert_eq!(chunked, large);
+    assert_eq!(db.get_chunks("missing", |_| unreachable!())?, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_entry() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut db = KVDB::new_file("entry_db", temp_dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+
+    let increment = |v: &mut Vec<u8>| v[0] += 1;
+
+    // 首次访问写入默认值，and_modify 不会执行
+    let entry = db.entry("counter")?;
+    assert!(!entry.is_occupied());
+    assert_eq!(entry.and_modify(increment)?.or_insert(&[0])?, [0]);
+    assert_eq!(db.get("counter")?.unwrap(), [0]);
+
+    // 已存在时修改并写回，默认值被忽略
+    assert_eq!(db.entry("counter")?.and_modify(increment)?.or_insert(&[0])?, [1]);
+    assert_eq!(db.get("counter")?.unwrap(), [1]);
+
+    let value = db.entry("counter")?.or_insert_with(|| unreachable!())?;
+    assert_eq!(value, [1]);
+    assert_eq!(db.entry("lazy")?.or_insert_with(|| b"default".to_vec())?, b"default");
+    assert_eq!(db.entry("lazy")?.get(), Some(&b"default"[..]));
+
+    Ok(())
+}
+
+// 模拟总线卡死：卡死时每次读取时钟都会前进 100ms
+static WEDGED: AtomicBool = AtomicBool::new(false);
+static NOW_MS: AtomicU64 = AtomicU64::new(0);
+
+fn wedged_clock() -> u64 {
+    let step = if WEDGED.load(Ordering::SeqCst) { 100 } else { 0 };
+    NOW_MS.fetch_add(step, Ordering::SeqCst)
+}
+
+#[test]
+fn test_kvdb_timeout() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut db = KVDB::new_file("timeout_db", temp_dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+    db.set_timeout(&wedged_clock, Duration::from_millis(50));
+
+    db.set("key", b"before")?;
+
+    WEDGED.store(true, Ordering::SeqCst);
+    assert!(matches!(db.set("key", b"after"), Err(Error::Timeout)));
+    assert!(matches!(db.get("key"), Err(Error::Timeout)));
+
+    // 总线恢复后数据库可以继续使用
+    WEDGED.store(false, Ordering::SeqCst);
+    assert_eq!(db.get("key")?.unwrap(), b"before");
+    db.set("key", b"after")?;
+    assert_eq!(db.get("key")?.unwrap(), b"after");
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_update() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut db = KVDB::new_file("update_db", temp_dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+
+    let append = |old: Option<&[u8]>, out: &mut Vec<u8>| {
+        out.extend_from_slice(old.unwrap_or(b"start"));
+        out.push(b'+');
+    };
+    db.update("key", append)?;
+    assert_eq!(db.get("key")?.unwrap(), b"start+");
+    db.update("key", append)?;
+    assert_eq!(db.get("key")?.unwrap(), b"start++");
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_set_nx() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut db = KVDB::new_file("set_nx_db", temp_dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+
+    assert!(db.set_nx("device_id", b"SN-0001")?);
+    assert!(!db.set_nx("device_id", b"SN-0002")?);
+    assert_eq!(db.get("device_id")?.unwrap(), b"SN-0001");
+
+    // 删除后可以重新写入
+    db.delete("device_id")?;
+    assert!(db.set_nx("device_id", b"SN-0002")?);
+    assert_eq!(db.get("device_id")?.unwrap(), b"SN-0002");
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "kv-index")]
+fn test_kvdb_prefetch() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let storage = StdStorage::new(temp_dir.path(), "prefetch_db", 4096, 4 * 4096, FileStrategy::Multi)?;
+    let mut db = Box::new(KVDB::new(storage));
+    db.set_prefetch(true);
+    db.init(None)?;
+    assert!(db.prefetch());
+
+    for i in 0..20 {
+        db.set(&format!("key{i}"), format!("value{i}").as_bytes())?;
+    }
+    assert_eq!(db.get("key7")?.unwrap(), b"value7");
+
+    // 反复更新触发垃圾回收，缓存的地址失效后应回退到常规查找
+    for round in 0..50 {
+        db.set("key0", format!("round{round}").repeat(8).as_bytes())?;
+        assert_eq!(db.get("key7")?.unwrap(), b"value7");
+    }
+    assert_eq!(db.get("key0")?.unwrap(), "round49".repeat(8).as_bytes());
+
+    db.delete("key7")?;
+    assert!(db.get("key7")?.is_none());
+
+    // 重新打开后由初始化时的扫描建立索引
+    drop(db);
+    let storage = StdStorage::new(temp_dir.path(), "prefetch_db", 4096, 4 * 4096, FileStrategy::Multi)?;
+    let mut db = Box::new(KVDB::new(storage));
+    db.set_prefetch(true);
+    db.init(None)?;
+    for i in 1..20 {
+        let expected = format!("value{i}");
+        assert_eq!(db.get(&format!("key{i}"))?.as_deref(), (i != 7).then_some(expected.as_bytes()));
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "kv-index")]
+fn test_kvdb_index_capacity() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let storage = StdStorage::new(temp_dir.path(), "index_db", 4096, 4 * 4096, FileStrategy::Multi)?;
+    let mut db = Box::new(KVDB::new(storage));
+    db.init(None)?;
+    for i in 0..10 {
+        db.set(&format!("key{i}"), &[i])?;
+    }
+    drop(db);
+
+    let storage = StdStorage::new(temp_dir.path(), "index_db", 4096, 4 * 4096, FileStrategy::Multi)?;
+    let mut db = Box::new(KVDB::new(storage));
+    db.set_index_capacity(4);
+    db.init(None)?;
+    assert!(db.prefetch());
+    assert_eq!(db.index_len(), 4);
+    assert!(db.index_memory() <= 4 * 8);
+
+    // 未被索引的键使用常规查找
+    for i in 0..10 {
+        assert_eq!(db.get(&format!("key{i}"))?.unwrap(), [i]);
+    }
+    assert_eq!(db.index_len(), 4);
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_bloom_filter() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let storage = StdStorage::new(temp_dir.path(), "bloom_db", 4096, 4 * 4096, FileStrategy::Multi)?;
+    let mut db = Box::new(KVDB::new(storage));
+    db.init(None)?;
+    db.set("existing", b"1")?;
+    drop(db);
+
+    let storage = StdStorage::new(temp_dir.path(), "bloom_db", 4096, 4 * 4096, FileStrategy::Multi)?;
+    let mut db = Box::new(KVDB::new(storage));
+    db.set_bloom_filter(1024);
+    db.init(None)?;
+
+    // 初始化时扫描到的键与之后写入的键都不会被误判为不存在
+    assert!(db.contains_key("existing")?);
+    assert!(!db.contains_key("missing")?);
+    assert!(db.get("missing")?.is_none());
+    db.set("missing", b"2")?;
+    assert!(db.contains_key("missing")?);
+    assert_eq!(db.get("missing")?.unwrap(), b"2");
+
+    db.delete("existing")?;
+    assert!(!db.contains_key("existing")?);
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_events() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let storage = StdStorage::new(temp_dir.path(), "events_db", 4096, 4 * 4096, FileStrategy::Multi)?;
+    let mut db = Box::new(KVDB::new(storage));
+    db.init(None)?;
+    let (handler, events) = event_channel();
+    db.set_event_handler(handler);
+
+    // 反复覆盖同一个键，直到触发垃圾回收
+    for i in 0..64u32 {
+        db.set("blob", &[i as u8; 1024])?;
+    }
+    let received: Vec<Event> = events.try_iter().collect();
+    assert!(received.contains(&Event::GcStarted));
+    assert!(received.iter().any(|e| matches!(e, Event::SectorRetired { addr } if addr % 4096 == 0)));
+    assert_eq!(db.get("blob")?.unwrap(), vec![63u8; 1024]);
+
+    // 移除回调后不再产生事件
+    db.clear_event_handler();
+    for i in 0..64u32 {
+        db.set("blob", &[i as u8; 1024])?;
+    }
+    assert!(events.try_iter().next().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_cached() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let storage = StdStorage::new(temp_dir.path(), "cached_db", 4096, 4 * 4096, FileStrategy::Multi)?;
+    let mut db = Box::new(CachedKVDB::new(KVDB::new(storage), 2));
+    db.init(None)?;
+    db.inner_mut().set("a", b"1")?;
+    db.inner_mut().set("b", b"2")?;
+    db.inner_mut().set("c", b"3")?;
+
+    // 第一次读取访问 Flash，之后命中缓存
+    assert_eq!(db.get("a")?, Some(&b"1"[..]));
+    assert_eq!(db.get("a")?, Some(&b"1"[..]));
+    assert_eq!((db.hits(), db.misses()), (1, 1));
+
+    // 不存在的键同样被缓存
+    assert_eq!(db.get("missing")?, None);
+    assert_eq!(db.get("missing")?, None);
+    assert_eq!((db.hits(), db.misses()), (2, 2));
+
+    // 容量为 2，读取 b 会淘汰最久未使用的 a
+    assert_eq!(db.get("b")?, Some(&b"2"[..]));
+    assert_eq!(db.cached_len(), 2);
+    assert_eq!(db.get("a")?, Some(&b"1"[..]));
+    assert_eq!(db.misses(), 4);
+
+    // 写入与删除同步更新缓存
+    db.set("a", b"10")?;
+    assert_eq!(db.get("a")?, Some(&b"10"[..]));
+    db.delete("a")?;
+    assert_eq!(db.get("a")?, None);
+    assert_eq!(db.misses(), 4);
+    assert_eq!(db.inner_mut().get("a")?, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_format_version_guard() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let open = || StdStorage::new(temp_dir.path(), "format_db", 4096, 4 * 4096, FileStrategy::Multi);
+    let mut db = Box::new(KVDB::new(open()?));
+    assert_eq!(db.format_version()?, None);
+    db.init(None)?;
+    db.set("key", b"value")?;
+    assert_eq!(db.format_version()?, Some(0));
+    drop(db);
+
+    // 模拟由更新版本的 C 库写入的扇区：magic 变为 `FDB1`
+    // 扇区头部的 store / dirty 状态表之后按 4 字节对齐存放 magic
+    let gran = flashdb_rs::FDB_WRITE_GRAN;
+    let status_table = if gran == 1 { 1 } else { 3 * gran / 8 };
+    let magic_version_addr = 4096 + (2 * status_table).next_multiple_of(4) + 3;
+    open()?.write(magic_version_addr, b"1")?;
+    let mut db = Box::new(KVDB::new(open()?));
+    assert_eq!(db.format_version()?, Some(1));
+    match db.init(None) {
+        Err(Error::IncompatibleFormat { found: 1, supported: 0 }) => {}
+        other => panic!("expected IncompatibleFormat, got {:?}", other),
+    }
+    assert!(!db.is_initialized());
+    drop(db);
+
+    // 拒绝打开时不会修改任何数据
+    open()?.write(magic_version_addr, b"0")?;
+    let mut db = Box::new(KVDB::new(open()?));
+    db.init(None)?;
+    assert_eq!(db.get("key")?.unwrap(), b"value");
+
+    Ok(())
+}
+
+/// 仅用于测试加密视图流程的玩具 AEAD：按字节异或密钥流，并追加 FNV 哈希作为认证标签
+struct ToyKeys {
+    current: u8,
+    counter: u8,
+}
+
+impl ToyKeys {
+    fn tag(key_id: u8, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> [u8; 4] {
+        let mut hash: u32 = 0x811c9dc5;
+        for b in [key_id].iter().chain(nonce).chain(aad).chain(plaintext) {
+            hash = (hash ^ *b as u32).wrapping_mul(0x01000193);
+        }
+        hash.to_le_bytes()
+    }
+
+    fn apply(key_id: u8, nonce: &[u8], buffer: &mut [u8]) {
+        for (i, b) in buffer.iter_mut().enumerate() {
+            *b ^= key_id.wrapping_mul(31) ^ nonce[i % nonce.len()] ^ 0x5A;
+        }
+    }
+}
+
+impl KeyProvider for ToyKeys {
+    const NONCE_LEN: usize = 4;
+
+    fn key_id(&self) -> u8 {
+        self.current
+    }
+
+    fn fill_nonce(&mut self, nonce: &mut [u8]) {
+        self.counter = self.counter.wrapping_add(1);
+        nonce.fill(self.counter);
+    }
+
+    fn encrypt(&self, key_id: u8, nonce: &[u8], aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), Error> {
+        let tag = Self::tag(key_id, nonce, aad, buffer);
+        Self::apply(key_id, nonce, buffer);
+        buffer.extend_from_slice(&tag);
+        Ok(())
+    }
+
+    fn decrypt(&self, key_id: u8, nonce: &[u8], aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), Error> {
+        let split = buffer.len().checked_sub(4).ok_or(Error::DecryptError)?;
+        let tag = buffer.split_off(split);
+        Self::apply(key_id, nonce, buffer);
+        if tag != Self::tag(key_id, nonce, aad, buffer) {
+            return Err(Error::DecryptError);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_kvdb_encrypted() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut db = KVDB::new_file("encrypted_db", temp_dir.path().to_str().unwrap(), 4096, 4 * 4096, None)?;
+    let mut keys = ToyKeys { current: 1, counter: 0 };
+
+    db.encrypted(&mut keys).set("wifi_password", b"hunter2hunter2")?;
+    assert_eq!(db.encrypted(&mut keys).get("wifi_password")?.unwrap(), b"hunter2hunter2");
+    assert!(db.encrypted(&mut keys).get("missing")?.is_none());
+
+    // Flash 中不包含明文
+    let raw = db.get("wifi_password")?.unwrap();
+    assert!(!raw.windows(7).any(|w| w == b"hunter2"));
+
+    // 密文被挪用到其他键上时认证失败
+    db.set("token", &raw)?;
+    assert!(matches!(db.encrypted(&mut keys).get("token"), Err(Error::DecryptError)));
+
+    // 密钥轮换：旧值仍可读取，重新加密后使用新密钥
+    keys.current = 2;
+    assert_eq!(db.encrypted(&mut keys).get("wifi_password")?.unwrap(), b"hunter2hunter2");
+    assert!(db.encrypted(&mut keys).reencrypt("wifi_password")?);
+    assert!(!db.encrypted(&mut keys).reencrypt("wifi_password")?);
+    assert_eq!(db.get("wifi_password")?.unwrap()[1], 2);
+    assert_eq!(db.encrypted(&mut keys).get("wifi_password")?.unwrap(), b"hunter2hunter2");
+
+    db.encrypted(&mut keys).delete("wifi_password")?;
+    assert!(db.encrypted(&mut keys).get("wifi_password")?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_get_checked() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("checked_db", path, 4096, 4 * 4096, None)?;
+    db.set("keep", b"value")?;
+    db.set("corrupt_me", b"0123456789abcdef")?;
+    assert_eq!(db.get_checked("corrupt_me")?.unwrap(), b"0123456789abcdef");
+    assert!(db.get_checked("missing")?.is_none());
+    drop(db);
+
+    let sector = temp_dir.path().join("checked_db.fdb.0");
+    let mut raw = std::fs::read(&sector)?;
+    let pos = raw
+        .windows(16)
+        .position(|w| w == b"0123456789abcdef")
+        .unwrap();
+    raw[pos] ^= 0x01;
+    std::fs::write(&sector, raw)?;
+
+    let mut db = KVDB::new_file("checked_db", path, 4096, 4 * 4096, None)?;
+    // 默认情况下损坏的键与不存在的键无法区分
+    assert!(db.get("corrupt_me")?.is_none());
+    assert!(matches!(db.get_checked("corrupt_me"), Err(Error::Corrupted)));
+    assert!(db.get_checked("missing")?.is_none());
+    assert_eq!(db.get_checked("keep")?.unwrap(), b"value");
+
+    db.set_verify_reads(true);
+    assert!(matches!(db.get("corrupt_me"), Err(Error::Corrupted)));
+    assert_eq!(db.get("keep")?.unwrap(), b"value");
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_modified_at() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("modified_db", path, 4096, 4 * 4096, None)?;
+    db.set("legacy", b"old")?;
+    db.set_track_modified(Some(ModifiedStamp::Sequence));
+    db.set("a", b"1")?;
+    db.set("b", b"22")?;
+    db.set("a", b"333")?;
+
+    assert_eq!(db.modified_at("legacy")?, None);
+    assert_eq!(db.modified_at("b")?, Some(2));
+    assert_eq!(db.modified_at("a")?, Some(3));
+    assert_eq!(db.modified_at("missing")?, None);
+    // 读取时不包含修改时间尾部
+    assert_eq!(db.get("a")?.unwrap(), b"333");
+    assert_eq!(db.get("legacy")?.unwrap(), b"old");
+    let mut reader = db.get_reader("b")?;
+    let mut buf = [0u8; 16];
+    assert_eq!(reader.read(&mut buf)?, 2);
+    assert_eq!(reader.entry.modified_at(), Some(2));
+    drop(db);
+
+    // 重新打开后序号从已有的最大值继续
+    let mut db = KVDB::new(StdStorage::new(path, "modified_db", 4096, 4 * 4096, FileStrategy::Multi)?);
+    db.set_track_modified(Some(ModifiedStamp::Sequence));
+    db.init(None)?;
+    db.set("c", b"4")?;
+    assert_eq!(db.modified_at("c")?, Some(4));
+    let mut changed: Vec<_> = db
+        .iter()
+        .filter(|kv| kv.status() == flashdb_rs::KVStatus::Write && kv.modified_at() > Some(2))
+        .map(|kv| (kv.name().unwrap().to_string(), kv.value_len()))
+        .collect();
+    changed.sort();
+    assert_eq!(changed, [("a".to_string(), 3), ("c".to_string(), 1)]);
+
+    static NOW: AtomicU64 = AtomicU64::new(1_700_000_000);
+    db.set_track_modified(Some(ModifiedStamp::Clock(&|| NOW.load(Ordering::Relaxed))));
+    db.set("clock", b"x")?;
+    assert_eq!(db.modified_at("clock")?, Some(1_700_000_000));
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_write_hooks() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("hooks_db", path, 4096, 4 * 4096, None)?;
+    db.set("factory.mac", b"00:11:22:33:44:55")?;
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    db.set_write_hooks(
+        |op| match op {
+            WriteOp::Set { key, .. } | WriteOp::Delete { key } if key.starts_with("factory.") => {
+                Err(Error::WriteProtected)
+            }
+            WriteOp::Reset | WriteOp::Clear => Err(Error::WriteProtected),
+            _ => Ok(()),
+        },
+        move |op| tx.send(op.key().map(String::from)).unwrap(),
+    );
+
+    assert!(matches!(db.set("factory.mac", b"forged"), Err(Error::WriteProtected)));
+    assert!(matches!(db.delete("factory.mac"), Err(Error::WriteProtected)));
+    assert!(matches!(db.reset(), Err(Error::WriteProtected)));
+    assert!(matches!(db.clear(), Err(Error::WriteProtected)));
+    db.set("user.name", b"alice")?;
+    db.entry("user.count")?.or_insert(b"1")?;
+    db.delete("user.name")?;
+
+    // 被拒绝的写入不会触发 post 回调
+    let written: Vec<_> = rx.try_iter().collect();
+    assert_eq!(
+        written,
+        [Some("user.name".into()), Some("user.count".into()), Some("user.name".into())]
+    );
+    assert_eq!(db.get("factory.mac")?.unwrap(), b"00:11:22:33:44:55");
+
+    db.clear_write_hooks();
+    db.delete("factory.mac")?;
+    assert!(db.get("factory.mac")?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_migrate_resume() -> anyhow::Result<()> {
+    static FAIL_V2: AtomicBool = AtomicBool::new(true);
+    const MIGRATIONS: &[Migration<StdStorage>] = &[
+        Migration {
+            version: 1,
+            apply: |db| {
+                let count = db.get_u32("count")?.unwrap_or(0);
+                db.set_u32("count", count + 1)
+            },
+        },
+        Migration {
+            version: 2,
+            apply: |db| {
+                db.set("v2", b"done")?;
+                // 模拟迁移过程中掉电
+                if FAIL_V2.swap(false, Ordering::SeqCst) {
+                    return Err(Error::WriteError);
+                }
+                Ok(())
+            },
+        },
+    ];
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("migrate_db", path, 4096, 4 * 4096, None)?;
+    assert_eq!(db.schema_version()?, 0);
+    assert!(matches!(db.migrate(MIGRATIONS), Err(Error::WriteError)));
+    assert_eq!(db.schema_version()?, 1);
+    drop(db);
+
+    // 重启后只重新执行中断的那一步
+    let mut db = KVDB::new_file("migrate_db", path, 4096, 4 * 4096, None)?;
+    assert_eq!(db.migrate(MIGRATIONS)?, 2);
+    assert_eq!(db.get_u32("count")?, Some(1));
+    assert_eq!(db.get("v2")?.unwrap(), b"done");
+    assert_eq!(db.migrate(MIGRATIONS)?, 2);
+    assert_eq!(db.get_u32("count")?, Some(1));
+
+    // 旧固件不认识新版本的数据
+    assert!(matches!(db.migrate(&MIGRATIONS[..1]), Err(Error::UnsupportedVersion)));
+    let unordered = [
+        Migration { version: 2, apply: MIGRATIONS[1].apply },
+        Migration { version: 2, apply: MIGRATIONS[0].apply },
+    ];
+    assert!(matches!(db.migrate(&unordered), Err(Error::InvalidArgument)));
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_config() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let storage = StdStorage::new(temp_dir.path(), "config_db", 4096, 8 * 4096, FileStrategy::Multi)?;
+    let db = KVDB::new(storage);
+    let config = db.config();
+    assert_eq!(config.sec_size, 4096);
+    assert_eq!(config.max_size, 8 * 4096);
+    // 未启用任何 kv-cache-* 特性时使用 C 库的默认值
+    assert_eq!(config.kv_cache_table_size, flashdb_rs::FDB_KV_CACHE_TABLE_SIZE as usize);
+    assert_eq!(config.sector_cache_table_size, flashdb_rs::FDB_SECTOR_CACHE_TABLE_SIZE as usize);
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_iter_with_status() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("status_db", path, 4096, 4 * 4096, None)?;
+    db.set("a", b"1")?;
+    db.set("b", b"0123456789abcdef")?;
+    db.set("a", b"2")?;
+    db.set("c", b"3")?;
+    db.delete("c")?;
+
+    let live: Vec<_> = db
+        .iter_with_status(KVStatusMask::LIVE)
+        .map(|kv| kv.map(|kv| kv.name().unwrap().to_string()))
+        .collect::<Result<_, _>>()?;
+    let mut expected: Vec<_> = db.iter().map(|kv| kv.name().unwrap().to_string()).collect();
+    expected.sort();
+    let mut sorted = live.clone();
+    sorted.sort();
+    assert_eq!(sorted, expected);
+
+    let deleted: Vec<_> = db
+        .iter_with_status(KVStatus::DELETED.into())
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(deleted.len(), 2);
+    assert!(deleted.iter().all(|kv| kv.status() == KVStatus::DELETED && kv.is_valid()));
+    assert_eq!(db.iter_with_status(KVStatusMask::ALL).count(), 4);
+    assert_eq!(db.iter_with_status(KVStatusMask::NONE).count(), 0);
+    drop(db);
+
+    // CRC 损坏的值不会出现在 iter() 中，但仍可以被诊断工具看到
+    let sector = temp_dir.path().join("status_db.fdb.0");
+    let mut raw = std::fs::read(&sector)?;
+    let pos = raw.windows(16).position(|w| w == b"0123456789abcdef").unwrap();
+    raw[pos] ^= 0x01;
+    std::fs::write(&sector, raw)?;
+
+    let mut db = KVDB::new_file("status_db", path, 4096, 4 * 4096, None)?;
+    assert!(db.iter().all(|kv| kv.name() != Some("b")));
+    let corrupted: Vec<_> = db
+        .iter_with_status(KVStatusMask::LIVE)
+        .filter_map(Result::ok)
+        .filter(|kv| !kv.is_valid())
+        .collect();
+    assert_eq!(corrupted.len(), 1);
+    assert_eq!(corrupted[0].name(), Some("b"));
+    assert_eq!(corrupted[0].value_len(), 16);
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_get_entry() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("get_entry_db", path, 4096, 4 * 4096, None)?;
+    db.set("key", b"value")?;
+    db.set("gone", b"x")?;
+    db.delete("gone")?;
+
+    let (entry, value) = db.get_entry("key")?.unwrap();
+    assert_eq!(value, b"value");
+    assert_eq!(entry.name(), Some("key"));
+    assert_eq!(entry.status(), KVStatus::Write);
+    assert_eq!(entry.value_len(), 5);
+    assert!(entry.is_valid());
+    assert!(db.get_entry("gone")?.is_none());
+    assert!(db.get_entry("missing")?.is_none());
+
+    // 覆盖层中的值不影响 Flash 中的元数据
+    let mut overlay = Overlay::new();
+    overlay.insert("key", b"override");
+    db.set_overlay(Some(overlay));
+    assert_eq!(db.get("key")?.unwrap(), b"override");
+    assert_eq!(db.get_entry("key")?.unwrap().1, b"value");
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_compact() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("compact_db", path, 4096, 8 * 4096, None)?;
+
+    // 反复覆盖，产生脏扇区但尚未触发垃圾回收
+    for round in 0..6u8 {
+        for i in 0..10 {
+            db.set(&format!("key{}", i), &[round; 200])?;
+        }
+    }
+    db.set("kept", b"value")?;
+    db.delete("key9")?;
+    let report = db.verify()?;
+    assert!(report.dirty_sectors > 0);
+
+    assert_eq!(db.compact()?, report.dirty_sectors);
+    let report = db.verify()?;
+    assert!(report.is_clean());
+    assert_eq!(report.dirty_sectors, 0);
+    assert_eq!(report.valid_entries, 10);
+    // 没有脏扇区时无事可做
+    assert_eq!(db.compact()?, 0);
+
+    for i in 0..9 {
+        assert_eq!(db.get(&format!("key{}", i))?.unwrap(), [5u8; 200]);
+    }
+    assert!(db.get("key9")?.is_none());
+    assert_eq!(db.get("kept")?.unwrap(), b"value");
+
+    drop(db);
+    let mut db = KVDB::new_file("compact_db", path, 4096, 8 * 4096, None)?;
+    assert_eq!(db.get("key0")?.unwrap(), [5u8; 200]);
+    assert_eq!(db.get("kept")?.unwrap(), b"value");
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_object_store() -> anyhow::Result<()> {
+    use embedded_io::{Read, Seek, SeekFrom, Write};
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("object_db", path, 4096, 16 * 4096, None)?;
+
+    // 超过单个扇区的值
+    let data: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+    let mut objects = db.objects();
+    assert_eq!(objects.chunk_size(), 2048);
+    let mut writer = objects.writer("blob")?;
+    for part in data.chunks(777) {
+        writer.write_all(part)?;
+    }
+    assert_eq!(writer.len(), data.len());
+    writer.finish()?;
+    assert_eq!(objects.len("blob")?, Some(data.len()));
+    assert_eq!(objects.get("blob")?.unwrap(), data);
+
+    // 跨块的随机读取
+    let mut reader = objects.reader("blob")?.unwrap();
+    reader.seek(SeekFrom::Start(2040))?;
+    let mut buf = [0u8; 100];
+    reader.read_exact(&mut buf).map_err(|_| Error::ReadError)?;
+    assert_eq!(buf[..], data[2040..2140]);
+    assert_eq!(reader.seek(SeekFrom::End(0))?, data.len() as u64);
+    assert_eq!(reader.read(&mut buf)?, 0);
+
+    // 覆盖为更短的对象，多余的块被清理
+    objects.put("blob", b"short")?;
+    assert_eq!(objects.get("blob")?.unwrap(), b"short");
+    assert!(!db.contains_key("blob.1")?);
+
+    // 未完成的写入不会留下对象
+    let mut objects = db.objects().with_chunk_size(100)?;
+    let mut writer = objects.writer("blob")?;
+    writer.write_all(&data[..500])?;
+    drop(writer);
+    assert!(objects.get("blob")?.is_none());
+    objects.put("blob", &data[..250])?;
+    assert!(!db.contains_key("blob.3")?);
+
+    let mut objects = db.objects();
+    assert_eq!(objects.get("blob")?.unwrap(), data[..250]);
+    assert!(objects.remove("blob")?);
+    assert!(!objects.remove("blob")?);
+    assert!(objects.get("blob")?.is_none());
+    assert!(!db.contains_key("blob.0")?);
+
+    assert!(matches!(db.objects().with_chunk_size(0), Err(Error::InvalidArgument)));
+    assert!(matches!(db.objects().with_chunk_size(4096), Err(Error::InvalidArgument)));
+    db.set("plain", b"value")?;
+    assert!(matches!(db.objects().get("plain"), Err(Error::Corrupted)));
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_gc_policy() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("gc_policy_db", path, 4096, 8 * 4096, None)?;
+    assert_eq!(db.gc_policy(), GcPolicy::default());
+    assert_eq!(db.config().gc_policy.reserved_sectors(), 1);
+
+    assert!(matches!(db.set_gc_policy(GcPolicy::new().with_dirty_percent(101)), Err(Error::InvalidArgument)));
+    assert!(matches!(db.set_gc_policy(GcPolicy::new().with_reserved_sectors(0)), Err(Error::InvalidArgument)));
+    assert!(matches!(db.set_gc_policy(GcPolicy::new().with_reserved_sectors(8)), Err(Error::InvalidArgument)));
+
+    // 默认策略下脏扇区会一直累积到空扇区耗尽
+    for round in 0..12u8 {
+        db.set("value", &[round; 1000])?;
+    }
+    assert!(db.verify()?.dirty_sectors >= 2);
+
+    // 保留 4 个空扇区：每次写入后被占用的扇区 (包括脏扇区) 不超过 4 个
+    db.set_gc_policy(GcPolicy::new().with_reserved_sectors(4))?;
+    assert_eq!(db.config().gc_policy.reserved_sectors(), 4);
+    for round in 0..40u8 {
+        db.set("value", &[round; 1000])?;
+        assert!(db.verify()?.dirty_sectors <= 4);
+    }
+    assert_eq!(db.get("value")?.unwrap(), [39u8; 1000]);
+
+    db.set_gc_policy(GcPolicy::new().with_dirty_percent(25))?;
+    for round in 0..40u8 {
+        db.set(&format!("key{}", round % 4), &[round; 500])?;
+        assert!(db.verify()?.dirty_sectors < 2);
+    }
+    db.delete("key0")?;
+    assert!(db.verify()?.dirty_sectors < 2);
+    assert_eq!(db.get("key3")?.unwrap(), [39u8; 500]);
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_metrics() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("metrics_db", path, 4096, 8 * 4096, None)?;
+    // 初始化时格式化了所有扇区
+    assert!(db.metrics().erases >= 8);
+    assert_eq!(db.metrics().gc_runs, 0);
+    assert_eq!(db.metrics().sectors, 8);
+
+    db.reset_metrics();
+    assert_eq!(db.metrics().write_amplification(), None);
+    db.set("key", &[1u8; 97])?;
+    let metrics = db.metrics();
+    assert_eq!(metrics.payload_bytes, 100);
+    assert!(metrics.flash_bytes_written > 100);
+    assert_eq!(metrics.erases, 0);
+
+    // 删除不计入应用写入的数据
+    db.delete("key")?;
+    assert_eq!(db.metrics().payload_bytes, 100);
+    assert!(db.metrics().flash_bytes_written > metrics.flash_bytes_written);
+
+    for round in 0..6u8 {
+        db.set("value", &[round; 1000])?;
+    }
+    assert_eq!(db.metrics().gc_runs, 0);
+    assert!(db.compact()? > 0);
+    let metrics = db.metrics();
+    assert_eq!(metrics.gc_runs, 1);
+    assert!(metrics.erases > 0);
+    assert_eq!(metrics.erases_per_sector(), metrics.erases as f32 / 8.0);
+
+    db.reset_metrics();
+    assert_eq!(db.metrics(), KVDBMetrics { sectors: 8, ..Default::default() });
+
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_iter_with_values() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("values_db", path, 4096, 4 * 4096, None)?;
+    db.set("a", b"1")?;
+    db.set("b", &[0u8; 300])?;
+    db.set("a", b"updated")?;
+    db.set("empty", b"")?;
+    db.delete("b")?;
+    db.set_track_modified(Some(ModifiedStamp::Sequence));
+    db.set("c", b"stamped")?;
+
+    let mut pairs = db.iter_with_values().collect::<Result<Vec<_>, _>>()?;
+    pairs.sort();
+    assert_eq!(
+        pairs,
+        [
+            ("a".to_string(), b"updated".to_vec()),
+            ("c".to_string(), b"stamped".to_vec()),
+            ("empty".to_string(), Vec::new()),
+        ]
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_kvdb_json_roundtrip() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("json_src", path, 4096, 4 * 4096, None)?;
+    db.set("text", "多行\n文本".as_bytes())?;
+    db.set("empty", b"")?;
+    db.set("control", b"a\x01b")?;
+    for len in 0..6 {
+        let value: Vec<u8> = (0..len).map(|i| 0xF0 + i as u8).collect();
+        db.set(&format!("bin{}", len), &value)?;
+    }
+    let json = db.to_json()?;
+    assert!(json.contains("\"text\": \"多行\\n文本\""));
+    assert!(json.contains("\"bin3\": {\n    \"base64\": \"8PHy\""));
+
+    let mut copy = KVDB::new_file("json_dst", path, 4096, 4 * 4096, None)?;
+    copy.set("existing", b"kept")?;
+    assert_eq!(copy.from_json(&json)?, 9);
+    let mut expected = db.iter_with_values().collect::<Result<Vec<_>, _>>()?;
+    expected.push(("existing".to_string(), b"kept".to_vec()));
+    expected.sort();
+    let mut actual = copy.iter_with_values().collect::<Result<Vec<_>, _>>()?;
+    actual.sort();
+    assert_eq!(actual, expected);
+
+    // 格式错误时不写入任何数据
+    assert!(matches!(copy.from_json("[1, 2]"), Err(Error::InvalidArgument)));
+    assert!(matches!(copy.from_json("{\"a\": \"1\", \"b\": 2}"), Err(Error::InvalidArgument)));
+    assert!(matches!(copy.from_json("{\"a\": {\"base64\": \"A===\"}}"), Err(Error::InvalidArgument)));
+    assert!(matches!(copy.from_json("{\"a\": {\"base64\": \"8P==8PHy\"}}"), Err(Error::InvalidArgument)));
+    assert!(matches!(copy.from_json("{"), Err(Error::Json(_))));
+    assert!(copy.get("a")?.is_none());
+
+    Ok(())
+}
+
+#[cfg(all(feature = "mmap", unix))]
+#[test]
+fn test_kvdb_mmap_storage() -> anyhow::Result<()> {
+    use flashdb_rs::storage::MmapStorage;
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("mmap.fdb");
+    let mut db = Box::new(KVDB::new(MmapStorage::new(&path, 4096, 8 * 4096)?));
+    db.set_name("mmap_db")?;
+    db.init(None)?;
+    for i in 0..200 {
+        db.set(&format!("key{}", i % 20), format!("value{}", i).as_bytes())?;
+    }
+    assert_eq!(db.get("key3")?.unwrap(), b"value183");
+    drop(db);
+    assert_eq!(std::fs::metadata(&path)?.len(), 8 * 4096);
+
+    // 与单文件模式的 StdStorage 格式相同
+    let storage = StdStorage::new(&path, "mmap_db", 4096, 8 * 4096, FileStrategy::Single)?;
+    let mut db = Box::new(KVDB::new(storage));
+    db.set_name("mmap_db")?;
+    db.init(None)?;
+    assert_eq!(db.get("key19")?.unwrap(), b"value199");
+    db.set("from_std", b"hello")?;
+    drop(db);
+
+    let mut db = Box::new(KVDB::new(MmapStorage::new(&path, 4096, 8 * 4096)?));
+    db.set_name("mmap_db")?;
+    db.init(None)?;
+    assert_eq!(db.get("from_std")?.unwrap(), b"hello");
+
+    // 越界访问被拒绝
+    let mut storage = MmapStorage::new(temp_dir.path().join("small.fdb"), 4096, 4096)?;
+    let mut buf = [0u8; 8];
+    assert!(storage.write(4092, &buf).is_err());
+    embedded_storage::nor_flash::ReadNorFlash::read(&mut storage, 0, &mut buf)?;
+    assert_eq!(buf, [0xFF; 8]);
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_single_file_strategy() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file_with_strategy("single", path, 4096, 4 * 4096, None, FileStrategy::Single)?;
+    db.set("key", b"value")?;
+    drop(db);
+
+    let files: Vec<_> = std::fs::read_dir(temp_dir.path())?.collect::<Result<_, _>>()?;
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].file_name(), "single.fdb");
+    assert_eq!(files[0].metadata()?.len(), 4 * 4096);
+
+    let mut db = KVDB::new_file_with_strategy("single", path, 4096, 4 * 4096, None, FileStrategy::Single)?;
+    assert_eq!(db.get("key")?.unwrap(), b"value");
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_preallocated_single_file() -> anyhow::Result<()> {
+    use embedded_storage::nor_flash::ReadNorFlash;
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("prealloc.fdb");
+    // 多文件模式不支持预分配
+    assert!(StdStorage::new(temp_dir.path(), "multi", 4096, 4 * 4096, FileStrategy::Multi)?.preallocate().is_err());
+
+    let storage = StdStorage::new(&path, "prealloc", 4096, 4 * 4096, FileStrategy::Single)?.preallocate()?;
+    assert!(storage.is_preallocated());
+    assert_eq!(std::fs::metadata(&path)?.len(), 4 * 4096);
+    assert!(std::fs::read(&path)?.iter().all(|&b| b == 0xFF));
+
+    let mut db = Box::new(KVDB::new(storage));
+    db.set_name("prealloc")?;
+    db.init(None)?;
+    for i in 0..500u32 {
+        db.set("counter", &i.to_le_bytes())?;
+    }
+    assert_eq!(std::fs::metadata(&path)?.len(), 4 * 4096);
+    drop(db);
+
+    // 重新预分配不会覆盖已有内容，超出容量的访问被拒绝
+    let mut storage = StdStorage::new(&path, "prealloc", 4096, 4 * 4096, FileStrategy::Single)?.preallocate()?;
+    let mut buf = [0u8; 4];
+    assert!(storage.read(4 * 4096 - 2, &mut buf).is_err());
+    assert!(storage.write(4 * 4096, &buf).is_err());
+    let mut db = Box::new(KVDB::new(storage));
+    db.set_name("prealloc")?;
+    db.init(None)?;
+    assert_eq!(db.get("counter")?.as_deref(), Some(&499u32.to_le_bytes()[..]));
+    Ok(())
+}
+
+/// 只能以 `UNIT` 字节为单位编程、每个单元擦除后只能编程一次的 Flash
+struct UnitFlash<const UNIT: usize> {
+    data: Vec<u8>,
+    programmed: Vec<bool>,
+}
+
+/// 按双字 (8 字节) 编程的 Flash
+type DoubleWordFlash = UnitFlash<8>;
+
+impl<const UNIT: usize> embedded_storage::nor_flash::ErrorType for UnitFlash<UNIT> {
+    type Error = Error;
+}
+
+impl<const UNIT: usize> embedded_storage::nor_flash::ReadNorFlash for UnitFlash<UNIT> {
+    const READ_SIZE: usize = 4;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        assert!(offset.is_multiple_of(4) && bytes.len().is_multiple_of(4), "unaligned read");
+        bytes.copy_from_slice(&self.data[offset as usize..offset as usize + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl<const UNIT: usize> NorFlash for UnitFlash<UNIT> {
+    const WRITE_SIZE: usize = UNIT;
+    const ERASE_SIZE: usize = 4096;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.data[from as usize..to as usize].fill(0xFF);
+        self.programmed[from as usize / UNIT..to as usize / UNIT].fill(false);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        assert!(offset.is_multiple_of(UNIT as u32) && bytes.len().is_multiple_of(UNIT), "unaligned write");
+        for unit in offset as usize / UNIT..(offset as usize + bytes.len()) / UNIT {
+            assert!(!self.programmed[unit], "unit programmed twice");
+            self.programmed[unit] = true;
+        }
+        self.data[offset as usize..offset as usize + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// 仅用于测试的异或流密码
+struct XorCipher(u8);
+
+impl flashdb_rs::crypt::SectorCipher for XorCipher {
+    fn encrypt(&self, sector: u32, offset: u32, unit: &mut [u8]) {
+        for (i, b) in unit.iter_mut().enumerate() {
+            *b ^= self.0 ^ (sector as u8).wrapping_mul(31) ^ (offset as u8).wrapping_add(i as u8);
+        }
+    }
+
+    fn decrypt(&self, sector: u32, offset: u32, unit: &mut [u8]) {
+        self.encrypt(sector, offset, unit)
+    }
+}
+
+#[test]
+fn test_gran_adaptor() -> anyhow::Result<()> {
+    use embedded_storage::nor_flash::ReadNorFlash;
+    use flashdb_rs::gran::GranAdaptor;
+
+    let flash = DoubleWordFlash {
+        data: vec![0xFF; 2 * 4096],
+        programmed: vec![false; 2 * 4096 / 8],
+    };
+    let mut flash = GranAdaptor::new(flash);
+
+    // 顺序的小块写入被合并到同一写入单元
+    flash.write(0, b"abc")?;
+    flash.write(3, b"defgh")?;
+    flash.write(8, b"0123456789")?;
+    let mut buf = [0u8; 18];
+    flash.read(0, &mut buf)?;
+    assert_eq!(&buf, b"abcdefgh0123456789");
+    assert_eq!(&flash.inner().data[16..24], &[0xFF; 8], "the partial unit is still buffered");
+
+    // 写入其他单元时以 0xFF 补齐
+    flash.write(32, b"xy")?;
+    assert_eq!(&flash.inner().data[16..24], b"89\xFF\xFF\xFF\xFF\xFF\xFF");
+
+    // 擦除丢弃被擦除单元中的缓冲内容
+    flash.erase(0, 4096)?;
+    let mut buf = [0u8; 4];
+    flash.read(31, &mut buf)?;
+    assert_eq!(buf, [0xFF; 4]);
+
+    flash.write(4096 + 5, b"tail")?;
+    let inner = flash.into_inner()?;
+    assert_eq!(&inner.data[4096..4096 + 16], b"\xFF\xFF\xFF\xFF\xFFtail\xFF\xFF\xFF\xFF\xFF\xFF\xFF");
+    Ok(())
+}
+
+#[test]
+fn test_encrypted_storage() -> anyhow::Result<()> {
+    use embedded_storage::nor_flash::ReadNorFlash;
+    use flashdb_rs::crypt::EncryptedStorage;
+
+    let flash = DoubleWordFlash {
+        data: vec![0xFF; 2 * 4096],
+        programmed: vec![false; 2 * 4096 / 8],
+    };
+    let mut storage = EncryptedStorage::new(flash, XorCipher(0x5A));
+
+    // 擦除状态的区域读取为 0xFF
+    let mut buf = [0u8; 13];
+    storage.read(4096 + 3, &mut buf)?;
+    assert_eq!(buf, [0xFF; 13]);
+
+    // 未写满的单元以 0xFF 补齐
+    storage.write(8, b"secret-value")?;
+    let mut buf = [0u8; 16];
+    storage.read(8, &mut buf)?;
+    assert_eq!(&buf, b"secret-value\xFF\xFF\xFF\xFF");
+    let raw = &storage.inner().data;
+    assert!(!raw.windows(6).any(|w| w == b"secret"), "plaintext leaked to flash");
+
+    // 相同的明文在不同扇区产生不同的密文
+    storage.write(4096 + 8, b"secret-value")?;
+    let raw = &storage.inner().data;
+    assert_ne!(raw[8..24], raw[4096 + 8..4096 + 24]);
+    let mut buf = [0u8; 5];
+    storage.read(4096 + 15, &mut buf)?;
+    assert_eq!(&buf, b"value");
+
+    storage.erase(0, 4096)?;
+    let mut buf = [0u8; 8];
+    storage.read(8, &mut buf)?;
+    assert_eq!(buf, [0xFF; 8]);
+    storage.write(8, b"12345678")?;
+    storage.read(8, &mut buf)?;
+    assert_eq!(&buf, b"12345678");
+    Ok(())
+}
+
+/// 写入单元与 C 库的 `FDB_WRITE_GRAN` 相同的 Flash
+#[cfg(any(feature = "write-gran-8", feature = "write-gran-32", feature = "write-gran-64", feature = "write-gran-128"))]
+type GranFlash = UnitFlash<{ (flashdb_rs::FDB_WRITE_GRAN as usize).div_ceil(8) }>;
+
+#[cfg(any(feature = "write-gran-8", feature = "write-gran-32", feature = "write-gran-64", feature = "write-gran-128"))]
+fn gran_flash() -> GranFlash {
+    UnitFlash {
+        data: vec![0xFF; 4 * 4096],
+        programmed: vec![false; 4 * 4096 / GranFlash::WRITE_SIZE],
+    }
+}
+
+/// 在存储上反复覆盖写入并触发 GC，重新打开后检查数据
+#[cfg(any(feature = "write-gran-8", feature = "write-gran-32", feature = "write-gran-64", feature = "write-gran-128"))]
+fn run_kvdb_roundtrip<S: NorFlash>(storage: S, reopen: impl FnOnce(S) -> S) -> anyhow::Result<S> {
+    let mut db = Box::new(KVDB::new(storage));
+    db.init(None)?;
+    for i in 0..200u32 {
+        db.set("counter", &i.to_le_bytes())?;
+        db.set(&format!("key{}", i % 7), b"odd-length value")?;
+    }
+    db.delete("key0")?;
+    let storage = reopen(db.close());
+
+    let mut db = Box::new(KVDB::new(storage));
+    db.init(None)?;
+    assert_eq!(db.get("counter")?.as_deref(), Some(&199u32.to_le_bytes()[..]));
+    assert_eq!(db.get("key3")?.as_deref(), Some(&b"odd-length value"[..]));
+    assert!(db.get("key0")?.is_none());
+    assert!(db.verify()?.is_clean());
+    Ok(db.close())
+}
+
+/// 追加到发生翻转，重新打开后检查日志
+#[cfg(all(feature = "tsdb", any(feature = "write-gran-8", feature = "write-gran-32")))]
+fn run_tsdb_roundtrip<S: NorFlash>(storage: S, reopen: impl FnOnce(S) -> S) -> anyhow::Result<S> {
+    use flashdb_rs::tsdb::TSDB;
+
+    let mut db = Box::new(TSDB::new(storage));
+    db.init(64)?;
+    for i in 1..=600i64 {
+        db.append_with_timestamp(i, format!("sample {i}").as_bytes())?;
+    }
+    let storage = reopen(db.close());
+
+    let mut db = Box::new(TSDB::new(storage));
+    db.init(64)?;
+    assert_eq!(db.last_time(), 600);
+    let entries: Vec<_> = db.iter().collect();
+    assert!(entries.len() > 100 && entries.len() < 600, "the oldest logs are rolled over");
+    let last = entries.last().unwrap();
+    assert_eq!(db.get_value(last)?.as_deref(), Some(&b"sample 600"[..]));
+    assert!(db.verify()?.is_clean());
+    Ok(db.close())
+}
+
+// C 库以与写入单元相同的 FDB_WRITE_GRAN 编译时，状态表的每次变化都写入独立的单元，
+// 数据库可以运行在每个单元只能编程一次的 Flash 上
+#[test]
+#[cfg(any(feature = "write-gran-8", feature = "write-gran-32", feature = "write-gran-64", feature = "write-gran-128"))]
+fn test_gran_adaptor_database() -> anyhow::Result<()> {
+    use flashdb_rs::gran::GranAdaptor;
+
+    // 关闭数据库后写入缓冲区中未写满的单元，模拟断电前的 flush
+    let reopen = |flash: GranAdaptor<GranFlash>| GranAdaptor::new(flash.into_inner().unwrap());
+    let flash = run_kvdb_roundtrip(GranAdaptor::new(gran_flash()), reopen)?.into_inner()?;
+    assert!(flash.programmed.iter().any(|&unit| unit));
+
+    // C 库的 TSDB 不支持 64 / 128 bit 写入粒度
+    #[cfg(all(feature = "tsdb", any(feature = "write-gran-8", feature = "write-gran-32")))]
+    run_tsdb_roundtrip(GranAdaptor::new(gran_flash()), reopen)?;
+    Ok(())
+}
+
+// 加密要求写入单元至少为 8 字节，因此只能用于 KVDB
+#[test]
+#[cfg(any(feature = "write-gran-64", feature = "write-gran-128"))]
+fn test_encrypted_storage_database() -> anyhow::Result<()> {
+    use flashdb_rs::crypt::EncryptedStorage;
+
+    let storage = run_kvdb_roundtrip(EncryptedStorage::new(gran_flash(), XorCipher(0x5A)), |storage| storage)?;
+    let raw = &storage.inner().data;
+    assert!(!raw.windows(6).any(|w| w == b"length" || w == b"counte"), "plaintext leaked to flash");
+    Ok(())
+}
+
+#[test]
+fn test_buffered_storage() -> anyhow::Result<()> {
+    use embedded_storage::nor_flash::ReadNorFlash;
+    use flashdb_rs::buffered::{BufferedStorage, FlushStorage};
+
+    let flash = DoubleWordFlash {
+        data: vec![0xFF; 2 * 4096],
+        programmed: vec![false; 2 * 4096 / 8],
+    };
+    let mut storage = BufferedStorage::<_, 32>::new(flash);
+
+    // 连续的写入留在页缓冲区中
+    storage.write(8, b"01234567")?;
+    storage.write(16, b"89abcdef")?;
+    assert_eq!(storage.pending(), 16);
+    assert_eq!(&storage.inner().data[8..24], &[0xFF; 16]);
+    let mut buf = [0u8; 16];
+    storage.read(8, &mut buf)?;
+    assert_eq!(&buf, b"0123456789abcdef");
+
+    // 写满一页时写入，剩余部分进入下一页
+    storage.write(24, b"ghijklmnopqrstuv")?;
+    assert_eq!(&storage.inner().data[8..32], b"0123456789abcdefghijklmn");
+    assert_eq!(storage.pending(), 8);
+
+    // 不连续的写入先写入缓冲区
+    storage.write(128, b"xxxxxxxx")?;
+    assert_eq!(&storage.inner().data[32..40], b"opqrstuv");
+
+    // 擦除丢弃被擦除范围内的缓冲内容
+    storage.erase(0, 4096)?;
+    assert_eq!(storage.pending(), 0);
+    storage.write(4096, &[0xA5; 64])?;
+    assert_eq!(storage.pending(), 0, "aligned page writes bypass the buffer");
+    storage.write(4096 + 64, b"12345678")?;
+    FlushStorage::flush(&mut storage)?;
+    assert_eq!(&storage.inner().data[4096 + 64..4096 + 72], b"12345678");
+
+    // 配合数据库使用，C 库同步写入时写入缓冲区
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let storage = StdStorage::new(path, "buffered_db", 4096, 4 * 4096, FileStrategy::Multi)?;
+    let mut db = Box::new(KVDB::new(BufferedStorage::<_, 256>::new(storage)));
+    db.set_name("buffered_db")?;
+    db.set_flush_on_sync(true);
+    db.init(None)?;
+    for i in 0..50 {
+        db.set(&format!("key{}", i % 10), format!("value{}", i).as_bytes())?;
+    }
+    // 不通过缓冲区直接读取文件
+    let mut plain = KVDB::new_file("buffered_db", path, 4096, 4 * 4096, None)?;
+    assert_eq!(plain.get("key7")?.unwrap(), b"value47");
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_error_context() -> anyhow::Result<()> {
+    use embedded_storage::nor_flash::NorFlashErrorKind;
+    use flashdb_rs::test_utils::{Fault, FaultyFlash};
+    use flashdb_rs::{ErrorKind, Operation, StorageOp};
+
+    let mut db = Box::new(KVDB::new(FaultyFlash::new(4 * 4096)));
+    db.init(None)?;
+    db.set("key", b"value")?;
+
+    // 写入失败时返回存储后端的原始错误与失败的地址
+    db.storage().arm(Fault::FailWrite { nth: 1 });
+    let err = db.set("key", b"new-value").unwrap_err();
+    let addr = match err.root() {
+        Error::Storage(err) => {
+            assert_eq!(err.op(), StorageOp::Write);
+            assert_eq!(err.kind(), NorFlashErrorKind::Other);
+            assert_eq!(err.detail(), "WriteError");
+            assert!(err.to_string().starts_with("Storage write failed at 0x"));
+            err.addr()
+        }
+        other => panic!("unexpected error: {other:?}"),
+    };
+
+    // 并附加出错的操作与键
+    let context = err.context().unwrap();
+    assert_eq!(context.op(), Operation::Set);
+    assert_eq!(context.key(), Some("key"));
+    assert_eq!(context.addr(), Some(addr));
+    assert!(err.to_string().starts_with(&format!("set \"key\" at {addr:#010x}: Storage write failed")));
+
+    // 类别与 C 库错误码取自原始错误
+    assert_eq!(err.kind(), ErrorKind::Io);
+    assert_eq!(err.code(), Some(flashdb_rs::fdb_err_t_FDB_WRITE_ERR));
+
+    // 与读写无关的错误不附加上下文
+    let err = db.set(&"k".repeat(100), b"v").unwrap_err();
+    assert!(matches!(err, Error::KvNameError));
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.code(), Some(flashdb_rs::fdb_err_t_FDB_KV_NAME_ERR));
+    assert_eq!(Error::SavedFull.kind(), ErrorKind::Capacity);
+    assert_eq!(Error::Corrupted.kind(), ErrorKind::Corruption);
+    assert_eq!(Error::Corrupted.code(), None);
+
+    // 错误只附加到对应的那一次操作上
+    db.set("key", b"new-value")?;
+    assert_eq!(db.get("key")?.as_deref(), Some(&b"new-value"[..]));
+
+    // 其他访问键的操作同样附加上下文
+    db.storage().arm(Fault::FailWrite { nth: 1 });
+    let err = db.set_nx("other", b"value").unwrap_err();
+    assert_eq!(err.context().map(|context| (context.op(), context.key())), Some((Operation::SetNx, Some("other"))));
+    assert!(err.to_string().starts_with("set_nx \"other\" at 0x"));
+
+    // 断电后所有读取都失败
+    db.storage().arm(Fault::TruncateWrite { nth: 1, len: 0 });
+    assert!(db.set("other", b"value").is_err());
+    let err = db.get_checked("key").unwrap_err();
+    assert!(matches!(err.root(), Error::Storage(_) | Error::ReadError));
+    assert_eq!(err.context().map(|context| (context.op(), context.key())), Some((Operation::GetChecked, Some("key"))));
+    let err = db.iter_with_status(flashdb_rs::KVStatusMask::ALL).find_map(|item| item.err()).unwrap();
+    assert_eq!(err.context().map(|context| context.op()), Some(Operation::Iterate));
+    let err = db.compact().unwrap_err();
+    assert_eq!(err.context().map(|context| context.op()), Some(Operation::Gc));
+    assert_eq!(err.kind(), ErrorKind::Io);
+
+    // 断电的 Flash 上初始化失败
+    let mut flash = FaultyFlash::new(4 * 4096);
+    flash.arm(Fault::TruncateWrite { nth: 1, len: 0 });
+    assert!(flash.write(0, &[0; 4]).is_err());
+    let mut db = Box::new(KVDB::new(flash));
+    let err = db.init(None).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Io);
+    assert_eq!(err.context().map(|context| context.op()), Some(Operation::Init));
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_power_loss() -> anyhow::Result<()> {
+    use flashdb_rs::test_utils::{Fault, FaultyFlash};
+
+    fn open(flash: FaultyFlash) -> anyhow::Result<Box<KVDB<FaultyFlash>>> {
+        let mut db = Box::new(KVDB::new(flash));
+        db.set_name("power_loss")?;
+        db.init(None)?;
+        Ok(db)
+    }
+
+    // 统计一次更新所需的写入次数
+    let mut db = open(FaultyFlash::new(4 * 4096))?;
+    db.set("key", b"old-value")?;
+    let image = db.storage().image().to_vec();
+    let before = db.storage().writes();
+    db.set("key", b"new-value")?;
+    let writes = db.storage().writes() - before;
+    assert!(writes > 1);
+    drop(db);
+
+    // 在更新的每一次写入的中途断电，重新上电后值要么是旧值要么是新值
+    for nth in 1..=writes {
+        let mut db = open(FaultyFlash::from_image(image.clone()))?;
+        db.storage().arm(Fault::TruncateWrite { nth, len: 1 });
+        assert!(db.set("key", b"new-value").is_err());
+        assert!(db.storage().is_powered_off());
+        let image = db.storage().image().to_vec();
+        drop(db);
+
+        let mut db = open(FaultyFlash::from_image(image))?;
+        let value = db.get("key")?.unwrap();
+        assert!(value == b"old-value" || value == b"new-value", "write {}: {:?}", nth, value);
+        db.set("other", b"still writable")?;
+        assert_eq!(db.get("other")?.unwrap(), b"still writable");
+    }
+
+    // 写入失败不会断电，数据库仍然可用
+    let mut db = open(FaultyFlash::from_image(image.clone()))?;
+    db.storage().arm(Fault::FailWrite { nth: 1 });
+    assert!(db.set("key", b"new-value").is_err());
+    db.set("key", b"new-value")?;
+    assert_eq!(db.get("key")?.unwrap(), b"new-value");
+
+    // 位翻转被 CRC 检测到
+    let pos = image.windows(9).position(|w| w == b"old-value").unwrap();
+    let mut flash = FaultyFlash::from_image(image);
+    flash.flip_bit(pos, 0);
+    let mut db = open(flash)?;
+    let report = db.verify()?;
+    assert_eq!(report.issues[0].kind, IssueKind::CrcMismatch);
+    Ok(())
+}
+
+#[test]
+fn test_wear_stats() -> anyhow::Result<()> {
+    use flashdb_rs::test_utils::FaultyFlash;
+    use flashdb_rs::wear::WearStats;
+
+    let mut db = Box::new(KVDB::new(WearStats::new(FaultyFlash::new(4 * 4096))));
+    db.set_name("wear")?;
+    db.init(None)?;
+    // 初始化时格式化所有扇区
+    assert!(db.storage().erase_counts().iter().all(|&c| c >= 1), "{:?}", db.storage().erase_counts());
+
+    for i in 0..2000u32 {
+        db.set("counter", &i.to_le_bytes())?;
+    }
+    let stats = db.storage();
+    assert_eq!(stats.erase_counts().len(), 4);
+    assert_eq!(stats.total_erases(), stats.erase_counts().iter().map(|&c| c as u64).sum::<u64>());
+    assert!(stats.min_erases() > 1, "GC should rotate through every sector: {:?}", stats.erase_counts());
+    // 单个键的反复覆盖在环形缓冲区中均匀地磨损所有扇区
+    assert!(stats.imbalance().unwrap() < 1.5, "{:?}", stats.erase_counts());
+    assert!(stats.bytes_programmed() > 2000 * 4);
+    assert!(stats.wear_level(10) > 0.1);
+
+    // 从保存的计数继续累计
+    let saved = stats.erase_counts().to_vec();
+    let restored = WearStats::with_counts(FaultyFlash::new(4 * 4096), &saved);
+    assert_eq!(restored.erase_counts(), &saved[..]);
+    let mut restored = restored;
+    restored.reset();
+    assert_eq!(restored.total_erases(), 0);
+    assert_eq!(restored.imbalance(), None);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "tsdb")]
+fn test_partition_shared_flash() -> anyhow::Result<()> {
+    use core::cell::RefCell;
+    use embedded_storage::nor_flash::ReadNorFlash;
+    use flashdb_rs::partition::{check_partitions, Partition};
+    use flashdb_rs::test_utils::FaultyFlash;
+    use flashdb_rs::TSDB;
+
+    flashdb_rs::static_assert_partitions!(FaultyFlash, 8 * 4096, [(0, 4 * 4096), (4 * 4096, 4 * 4096)]);
+
+    let chip = RefCell::new(FaultyFlash::new(8 * 4096));
+    // 未对齐或超出容量的分区被拒绝
+    assert!(Partition::new(&chip, 100, 4096).is_err());
+    assert!(Partition::new(&chip, 0, 100).is_err());
+    assert!(Partition::new(&chip, 4 * 4096, 8 * 4096).is_err());
+    assert!(std::panic::catch_unwind(|| check_partitions::<FaultyFlash>(8 * 4096, &[(0, 8192), (4096, 8192)])).is_err());
+
+    let mut kv = Box::new(KVDB::new(Partition::new(&chip, 0, 4 * 4096)?));
+    kv.set_name("part_kv")?;
+    kv.init(None)?;
+    let mut ts = Box::new(TSDB::new(Partition::new(&chip, 4 * 4096, 4 * 4096)?));
+    ts.set_name("part_ts")?;
+    ts.init(64)?;
+
+    for i in 0..200u32 {
+        kv.set("counter", &i.to_le_bytes())?;
+        ts.append_with_timestamp(i as i64 + 1, &i.to_le_bytes())?;
+    }
+    assert_eq!(kv.get("counter")?.as_deref(), Some(&199u32.to_le_bytes()[..]));
+    assert_eq!(ts.count(0, i64::MAX, flashdb_rs::TSLStatus::Write), 200);
+
+    // 分区之外的访问被拒绝
+    {
+        let mut part = Partition::new(&chip, 4 * 4096, 4 * 4096)?;
+        assert_eq!(part.capacity(), 4 * 4096);
+        let mut buf = [0u8; 8];
+        assert!(part.read(4 * 4096 - 4, &mut buf).is_err());
+        assert!(part.write(4 * 4096, &buf).is_err());
+        assert!(part.erase(0, 8 * 4096).is_err());
+    }
+
+    // 重新打开后两个数据库互不影响
+    drop(kv);
+    drop(ts);
+    let mut kv = Box::new(KVDB::new(Partition::new(&chip, 0, 4 * 4096)?));
+    kv.set_name("part_kv")?;
+    kv.init(None)?;
+    assert_eq!(kv.get("counter")?.as_deref(), Some(&199u32.to_le_bytes()[..]));
+    let mut ts = Box::new(TSDB::new(Partition::new(&chip, 4 * 4096, 4 * 4096)?));
+    ts.set_name("part_ts")?;
+    ts.init(64)?;
+    assert_eq!(ts.count(0, i64::MAX, flashdb_rs::TSLStatus::Write), 200);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "tsdb")]
+fn test_strict_flash() -> anyhow::Result<()> {
+    use embedded_storage::nor_flash::ReadNorFlash;
+    use flashdb_rs::strict::{StrictError, StrictFlash, ViolationKind};
+    use flashdb_rs::test_utils::FaultyFlash;
+    use flashdb_rs::TSDB;
+
+    // C 库的正常访问不违反任何约束
+    let mut flash = StrictFlash::new(FaultyFlash::new(4 * 4096));
+    flash.set_panic(true);
+    let mut db = Box::new(KVDB::new(flash));
+    db.set_name("strict")?;
+    db.init(None)?;
+    for i in 0..300u32 {
+        db.set(&format!("key{}", i % 20), &i.to_le_bytes())?;
+    }
+    db.delete("key0")?;
+    assert_eq!(db.storage().violations(), 0);
+
+    let mut flash = StrictFlash::new(FaultyFlash::new(4 * 4096));
+    flash.set_panic(true);
+    let mut ts = Box::new(TSDB::new(flash));
+    ts.set_name("strict")?;
+    ts.init(128)?;
+    for i in 1..=300 {
+        ts.append_with_timestamp(i, &[i as u8; 100])?;
+    }
+    assert_eq!(ts.storage().violations(), 0);
+
+    // 违规的访问被拒绝并记录
+    let mut flash = StrictFlash::new(DoubleWordFlash {
+        data: vec![0xFF; 2 * 4096],
+        programmed: vec![false; 2 * 4096 / 8],
+    });
+    let mut buf = [0u8; 8];
+    assert!(matches!(flash.write(4, &buf), Err(StrictError::Violation(_))));
+    assert_eq!(flash.last_violation().unwrap().kind, ViolationKind::UnalignedWrite);
+    assert!(flash.read(2, &mut buf[..4]).is_err());
+    assert_eq!(flash.last_violation().unwrap().kind, ViolationKind::UnalignedRead);
+    assert!(flash.erase(0, 100).is_err());
+    assert_eq!(flash.last_violation().unwrap().kind, ViolationKind::UnalignedErase);
+    assert!(flash.erase(0, 3 * 4096).is_err());
+    assert_eq!(flash.last_violation().unwrap().kind, ViolationKind::OutOfBounds);
+
+    flash.write(8, &[0xF0; 8]).unwrap();
+    assert!(flash.write(8, &[0x0F; 8]).is_err());
+    let violation = flash.last_violation().unwrap();
+    assert_eq!(violation.kind, ViolationKind::NotErased { addr: 8, current: 0xF0, new: 0x0F });
+    assert_eq!(violation.to_string(), "write 0x8..0x10 programs 0x0f over 0xf0 at 0x8 without erase");
+    assert_eq!(flash.violations(), 5);
+    flash.erase(0, 4096).unwrap();
+    flash.write(8, &[0x0F; 8]).unwrap();
+    flash.read(8, &mut buf).unwrap();
+    assert_eq!(buf, [0x0F; 8]);
+    Ok(())
+}
+
+#[test]
+fn test_slice_storage() -> anyhow::Result<()> {
+    use embedded_storage::nor_flash::ReadNorFlash;
+    use flashdb_rs::slice::SliceStorage;
+
+    let mut small = [0xFF; 100];
+    assert!(SliceStorage::<4096>::new(&mut small).is_err());
+
+    let mut flash = [0xFF; 8 * 2048];
+    {
+        let mut db = Box::new(KVDB::new(SliceStorage::<2048>::new(&mut flash)?));
+        db.set_name("slice")?;
+        db.init(None)?;
+        assert_eq!(db.config().sec_size, 2048);
+        for i in 0..100u32 {
+            db.set("counter", &i.to_le_bytes())?;
+        }
+    }
+    // 重新打开同一块内存
+    let mut db = Box::new(KVDB::new(SliceStorage::<2048>::new(&mut flash)?));
+    db.set_name("slice")?;
+    db.init(None)?;
+    assert_eq!(db.get("counter")?.as_deref(), Some(&99u32.to_le_bytes()[..]));
+    drop(db);
+
+    let mut storage = SliceStorage::<2048>::new(&mut flash)?;
+    let mut buf = [0u8; 4];
+    assert!(storage.read(8 * 2048 - 2, &mut buf).is_err());
+    assert!(storage.erase(100, 2048).is_err());
+    storage.erase(0, 2048)?;
+    storage.write(0, &[0x0F])?;
+    storage.write(0, &[0xF5])?;
+    storage.read(0, &mut buf)?;
+    assert_eq!(buf, [0x05, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(storage.as_slice().len(), 8 * 2048);
+    Ok(())
+}
+
+#[test]
+fn test_mirrored_storage() -> anyhow::Result<()> {
+    use embedded_storage::nor_flash::ReadNorFlash;
+    use flashdb_rs::mirror::{MirrorSide, MirroredStorage};
+    use flashdb_rs::test_utils::{Fault, FaultyFlash};
+
+    let mirror = MirroredStorage::new(FaultyFlash::new(4 * 4096), FaultyFlash::new(4 * 4096));
+    let mut db = Box::new(KVDB::new(mirror));
+    db.set_name("mirror")?;
+    db.init(None)?;
+    db.set("serial", b"SN-0001")?;
+    assert_eq!(db.storage().a().image(), db.storage().b().image());
+
+    // 副本 A 中的值发生位翻转
+    let mut image_a = db.storage().a().image().to_vec();
+    let image_b = db.storage().b().image().to_vec();
+    drop(db);
+    let pos = image_a.windows(7).position(|w| w == b"SN-0001").unwrap();
+    image_a[pos + 3] ^= 0x01;
+    let mirror = MirroredStorage::new(FaultyFlash::from_image(image_a), FaultyFlash::from_image(image_b));
+    let mut db = Box::new(KVDB::new(mirror));
+    db.set_name("mirror")?;
+    db.init(None)?;
+    assert!(matches!(db.get_checked("serial"), Err(Error::Corrupted)));
+    assert_eq!(db.get_redundant("serial")?.as_deref(), Some(&b"SN-0001"[..]));
+    assert_eq!(db.storage().active(), MirrorSide::B);
+    assert_eq!(db.storage().degraded(), Some(MirrorSide::A));
+    drop(db);
+
+    // 一份副本写入失败时仍然成功，并标记降级
+    let mut mirror = MirroredStorage::new(FaultyFlash::new(2 * 4096), FaultyFlash::new(2 * 4096));
+    mirror.b().arm(Fault::FailWrite { nth: 1 });
+    mirror.write(0, b"abcd").unwrap();
+    assert_eq!(mirror.degraded(), Some(MirrorSide::B));
+    // 唯一健康的副本失败时返回错误
+    mirror.a().arm(Fault::FailWrite { nth: 1 });
+    assert!(mirror.write(4, b"efgh").is_err());
+    mirror.write(4, b"efgh").unwrap();
+
+    // 从健康的副本恢复
+    mirror.resync().unwrap();
+    assert_eq!(mirror.degraded(), None);
+    assert_eq!(mirror.a().image(), mirror.b().image());
+    let mut buf = [0u8; 8];
+    mirror.read(0, &mut buf).unwrap();
+    assert_eq!(&buf, b"abcdefgh");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "tsdb")]
+fn test_db_lock() -> anyhow::Result<()> {
+    use flashdb_rs::lock::StdLock;
+    use flashdb_rs::TSDB;
+    use std::sync::atomic::AtomicUsize;
+
+    static HELD: AtomicBool = AtomicBool::new(false);
+    static LOCKS: AtomicUsize = AtomicUsize::new(0);
+    static COUNTING: (fn(), fn()) = (
+        || {
+            // C 库不会嵌套加锁
+            assert!(!HELD.swap(true, Ordering::SeqCst), "lock is not reentrant");
+            LOCKS.fetch_add(1, Ordering::SeqCst);
+        },
+        || assert!(HELD.swap(false, Ordering::SeqCst), "unlock without lock"),
+    );
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = KVDB::new_file("lock", path, 4096, 4 * 4096, None)?;
+    db.set_lock(&COUNTING);
+    for i in 0..200u32 {
+        db.set("counter", &i.to_le_bytes())?;
+    }
+    assert_eq!(db.get("counter")?.as_deref(), Some(&199u32.to_le_bytes()[..]));
+    assert!(LOCKS.load(Ordering::SeqCst) >= 201);
+    assert!(!HELD.load(Ordering::SeqCst));
+
+    db.clear_lock();
+    let before = LOCKS.load(Ordering::SeqCst);
+    db.set("counter", b"x")?;
+    assert_eq!(LOCKS.load(Ordering::SeqCst), before);
+
+    static LOCK: StdLock = StdLock::new();
+    let mut ts = TSDB::new_file("lock_ts", path, 4096, 4 * 4096, 64)?;
+    ts.set_lock(&LOCK);
+    for i in 1..=100 {
+        ts.append_with_timestamp(i, b"sample")?;
+    }
+    assert_eq!(ts.count(0, i64::MAX, flashdb_rs::TSLStatus::Write), 100);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "tsdb")]
+fn test_critical_section_lock() -> anyhow::Result<()> {
+    use flashdb_rs::lock::{CriticalSectionLock, DbLock};
+    use flashdb_rs::test_utils::FaultyFlash;
+    use flashdb_rs::TSDB;
+    use std::sync::atomic::AtomicU32;
+
+    /// 模拟的中断屏蔽状态与进入临界区的次数
+    static MASKED: AtomicBool = AtomicBool::new(false);
+    static ENTERED: AtomicU32 = AtomicU32::new(0);
+
+    unsafe fn acquire() -> bool {
+        ENTERED.fetch_add(1, Ordering::SeqCst);
+        MASKED.swap(true, Ordering::SeqCst)
+    }
+
+    unsafe fn release(was_masked: bool) {
+        MASKED.store(was_masked, Ordering::SeqCst);
+    }
+
+    static LOCK: CriticalSectionLock<bool> = CriticalSectionLock::new(acquire, release);
+
+    let mut ts = Box::new(TSDB::new(FaultyFlash::new(8 * 4096)));
+    ts.set_lock(&LOCK);
+    ts.init(64)?;
+    let mut kv = Box::new(KVDB::new(FaultyFlash::new(4 * 4096)));
+    kv.set_lock(&LOCK);
+    kv.init(None)?;
+    for i in 1..=50 {
+        ts.append_with_timestamp(i, &(i as u32).to_le_bytes())?;
+    }
+    assert!(!MASKED.load(Ordering::SeqCst));
+
+    // 持有锁时访问共用同一个锁的另一个数据库
+    LOCK.lock();
+    kv.set("last", &ts.last_time().to_le_bytes())?;
+    assert!(MASKED.load(Ordering::SeqCst));
+    LOCK.unlock();
+    assert!(!MASKED.load(Ordering::SeqCst));
+    assert_eq!(kv.get("last")?.as_deref(), Some(&50i64.to_le_bytes()[..]));
+
+    // 已经处于临界区内 (例如中断服务程序) 时退出后保持屏蔽
+    MASKED.store(true, Ordering::SeqCst);
+    LOCK.lock();
+    LOCK.unlock();
+    assert!(MASKED.load(Ordering::SeqCst));
+    MASKED.store(false, Ordering::SeqCst);
+    assert!(ENTERED.load(Ordering::SeqCst) > 50);
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_move_after_init() -> anyhow::Result<()> {
+    use flashdb_rs::test_utils::FaultyFlash;
+
+    /// 在函数内初始化，按值返回
+    fn open(name: &str) -> Result<KVDB<FaultyFlash>, Error> {
+        let mut db = KVDB::new(FaultyFlash::new(4 * 4096));
+        db.set_name(name)?;
+        db.init(None)?;
+        db.set("name", name.as_bytes())?;
+        Ok(db)
+    }
+
+    // 初始化后的实例被移动到集合中，扩容时还会再次移动
+    let mut dbs = Vec::new();
+    for i in 0..8 {
+        dbs.push(open(&format!("moved{i}"))?);
+    }
+    for (i, db) in dbs.iter_mut().enumerate() {
+        assert_eq!(db.get("name")?, Some(format!("moved{i}").into_bytes()));
+        for n in 0..200u32 {
+            db.set("counter", &n.to_le_bytes())?;
+        }
+    }
+    let mut db = dbs.swap_remove(3);
+    assert_eq!(db.get("counter")?.as_deref(), Some(&199u32.to_le_bytes()[..]));
+    assert_eq!(db.iter_keys().count(), 2);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "tsdb")]
+fn test_kvdb_close() -> anyhow::Result<()> {
+    use flashdb_rs::test_utils::FaultyFlash;
+
+    let mut db = KVDB::new(FaultyFlash::new(4 * 4096));
+    db.init(None)?;
+    db.set("fw", b"1.0.0")?;
+    let flash = db.close();
+
+    // 取回的存储可以交给新的数据库
+    let mut db = KVDB::new(flash);
+    db.init(None)?;
+    assert_eq!(db.get("fw")?.as_deref(), Some(&b"1.0.0"[..]));
+
+    // 未初始化的数据库同样可以关闭
+    let flash = KVDB::new(db.close()).close();
+    let mut ts = flashdb_rs::TSDB::new(flash);
+    ts.init(64)?;
+    ts.append_with_timestamp(1, b"sample")?;
+    let mut ts = flashdb_rs::TSDB::new(ts.close());
+    ts.init(64)?;
+    assert_eq!(ts.last_time(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_kvdb_deinit_reinit() -> anyhow::Result<()> {
+    use flashdb_rs::test_utils::FaultyFlash;
+
+    let mut db = KVDB::new(FaultyFlash::new(8 * 4096));
+    assert!(matches!(db.get("boot"), Err(Error::NotInitialized)));
+    db.init(None)?;
+    db.set("boot", b"1")?;
+
+    db.deinit();
+    assert!(!db.is_initialized());
+    assert!(matches!(db.get("boot"), Err(Error::NotInitialized)));
+    assert!(matches!(db.set("boot", b"2"), Err(Error::NotInitialized)));
+    assert!(matches!(db.delete("boot"), Err(Error::NotInitialized)));
+    // 释放后可以重新配置
+    db.set_sec_size(2 * 4096)?;
+    db.set_sec_size(4096)?;
+    db.deinit();
+
+    db.init(None)?;
+    assert!(db.set_sec_size(2 * 4096).is_err());
+    assert_eq!(db.get("boot")?.as_deref(), Some(&b"1"[..]));
+    db.set("boot", b"2")?;
+    db.deinit();
+    db.init(None)?;
+    assert_eq!(db.get("boot")?.as_deref(), Some(&b"2"[..]));
+    Ok(())
+}
+
+#[cfg(all(feature = "registry", feature = "tsdb"))]
+#[test]
+fn test_region_registry() -> anyhow::Result<()> {
+    use core::cell::RefCell;
+    use flashdb_rs::partition::Partition;
+    use flashdb_rs::registry::Region;
+    use flashdb_rs::test_utils::FaultyFlash;
+    use flashdb_rs::TSDB;
+
+    // 同一个文件不能被打开两次，关闭后可以再次打开
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let db = KVDB::new_file("registry", path, 4096, 4 * 4096, None)?;
+    assert!(matches!(KVDB::new_file("registry", path, 4096, 4 * 4096, None), Err(Error::Busy)));
+    let _other = KVDB::new_file("registry_other", path, 4096, 4 * 4096, None)?;
+    drop(db);
+    let db = KVDB::new_file("registry", path, 4096, 4 * 4096, None)?;
+    db.close();
+
+    // 分区按地址范围登记
+    let chip = RefCell::new(FaultyFlash::new(16 * 4096));
+    let env = Partition::new(&chip, 0, 8 * 4096)?;
+    let region = Region::of_partition(&env);
+    let mut kv = Box::new(KVDB::new(env));
+    kv.set_region(region)?;
+    kv.init(None)?;
+    assert!(kv.set_region(region).is_err());
+
+    let overlapping = Partition::new(&chip, 4 * 4096, 8 * 4096)?;
+    let mut ts = Box::new(TSDB::new(overlapping));
+    ts.set_region(Region::of_partition(ts.storage()))?;
+    assert!(matches!(ts.init(64), Err(Error::Busy)));
+    assert!(!ts.is_initialized());
+
+    // 释放后重叠的区域可以被另一个数据库使用
+    kv.deinit();
+    ts.init(64)?;
+    assert!(matches!(kv.init(None), Err(Error::Busy)));
+    drop(ts);
+    kv.init(None)?;
+    Ok(())
+}
@@ -1,2 +1,4 @@
+mod bench;
 mod kvdb;
+mod transfer;
 mod tsdb;
\ No newline at end of file
@@ -0,0 +1,70 @@
+#![cfg(all(feature = "std", feature = "kvdb"))]
+#![cfg(test)]
+
+use anyhow::Result;
+use flashdb_rs::storage::{FileStrategy, StdStorage};
+use flashdb_rs::transfer::{frame_len, Frame, Progress, Receiver};
+use flashdb_rs::{Error, KVDB};
+use tempfile::TempDir;
+
+#[test]
+fn test_transfer_kvdb_roundtrip() -> Result<()> {
+    let src_dir = TempDir::new()?;
+    let dst_dir = TempDir::new()?;
+    let src_path = src_dir.path().to_str().unwrap();
+    let dst_path = dst_dir.path().to_str().unwrap();
+
+    let mut db = KVDB::new_file("field", src_path, 4096, 4 * 4096, None)?;
+    for i in 0..50 {
+        db.set(&format!("key{}", i), format!("value{}", i).as_bytes())?;
+    }
+
+    // 在发送端生成所有帧
+    let mut frames = Vec::new();
+    let mut sender = db.exporter(1000);
+    let mut buf = [0u8; frame_len(1000)];
+    while let Some(len) = sender.next_frame(&mut buf)? {
+        frames.push(buf[..len].to_vec());
+    }
+    drop(db);
+    assert_eq!(frames.len(), (4 * 4096usize).div_ceil(1000));
+    assert_eq!(Frame::decode(&frames[0])?.total, 4 * 4096);
+
+    let mut storage = StdStorage::new(dst_path, "field", 4096, 4 * 4096, FileStrategy::Multi)?;
+    let mut receiver = Receiver::new(&mut storage);
+
+    // 损坏的帧会被拒绝
+    let mut corrupted = frames[0].clone();
+    corrupted[20] ^= 0xFF;
+    assert!(matches!(receiver.accept(&corrupted), Err(Error::Corrupted)));
+    // 不连续的帧会被拒绝
+    assert!(matches!(receiver.accept(&frames[2]), Err(Error::InvalidArgument)));
+
+    for frame in &frames[..5] {
+        assert_eq!(receiver.accept(frame)?, Progress::Written);
+    }
+    // 重复的帧会被忽略
+    assert_eq!(receiver.accept(&frames[3])?, Progress::Duplicate);
+    let resume_at = receiver.next_offset();
+
+    // 模拟链路中断后继续传输
+    let mut receiver = Receiver::new(&mut storage);
+    receiver.resume_from(resume_at);
+    let mut progress = Progress::Written;
+    for frame in &frames[5..] {
+        progress = receiver.accept(frame)?;
+    }
+    assert_eq!(progress, Progress::Complete);
+    assert!(receiver.is_complete());
+    drop(storage);
+
+    let mut db = KVDB::new_file("field", dst_path, 4096, 4 * 4096, None)?;
+    for i in 0..50 {
+        assert_eq!(
+            db.get(&format!("key{}", i))?.unwrap(),
+            format!("value{}", i).as_bytes()
+        );
+    }
+
+    Ok(())
+}
@@ -1,9 +1,14 @@
-#![cfg(feature = "std")]
+#![cfg(all(feature = "std", feature = "tsdb"))]
 #![cfg(test)]
 
 use anyhow::Result;
 use embedded_io::{Read, Seek};
+use embedded_storage::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+use flashdb_rs::events::{event_channel, Event};
+use flashdb_rs::storage::FileStrategy;
 use flashdb_rs::tsdb::{TSDB, TSLEntry, TSLStatus};
+use flashdb_rs::{Error, StdStorage};
+use std::cell::Cell;
 use tempfile::TempDir;
 
 #[test]
@@ -245,4 +250,1807 @@ fn test_tsdb_reader_operations() -> Result<()> {
     assert_eq!(&buffer[..read_len], &test_data[20..26]);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_tsdb_schema_versions() -> Result<()> {
+    #[derive(Debug, PartialEq)]
+    struct Sample {
+        temperature: i16,
+        humidity: u8,
+    }
+
+    fn decode_v1(raw: &[u8]) -> Result<Sample, Error> {
+        let raw: [u8; 2] = raw.try_into().map_err(|_| Error::Corrupted)?;
+        Ok(Sample { temperature: i16::from_le_bytes(raw), humidity: 0 })
+    }
+
+    fn decode_v2(raw: &[u8]) -> Result<Sample, Error> {
+        let [t0, t1, humidity]: [u8; 3] = raw.try_into().map_err(|_| Error::Corrupted)?;
+        Ok(Sample { temperature: i16::from_le_bytes([t0, t1]), humidity })
+    }
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("schema_test", path, 4096, 16 * 4096, 64)?;
+
+    // 旧固件以 v1 格式写入
+    tsdb.schema::<Sample>(1).append_with_timestamp(1, &250i16.to_le_bytes())?;
+    // 新固件以 v2 格式写入
+    let mut samples = tsdb.schema(2).decoder(1, decode_v1).decoder(2, decode_v2);
+    samples.append_with_timestamp(2, &[0x2C, 0x01, 45])?;
+
+    let mut decoded = Vec::new();
+    samples.iter(
+        |time, sample| {
+            decoded.push((time, sample));
+            true
+        },
+        false,
+    )?;
+    assert_eq!(
+        decoded,
+        vec![
+            (1, Sample { temperature: 250, humidity: 0 }),
+            (2, Sample { temperature: 300, humidity: 45 }),
+        ]
+    );
+
+    let mut count = 0;
+    samples.iter_by_time(2, 2, |_, sample| {
+        assert_eq!(sample.humidity, 45);
+        count += 1;
+        true
+    })?;
+    assert_eq!(count, 1);
+
+    // 未注册解码器的版本会返回错误
+    let mut v2_only = tsdb.schema(2).decoder(2, decode_v2);
+    let result = v2_only.iter(|_, _| true, false);
+    assert!(matches!(result, Err(Error::UnsupportedVersion)));
+
+    Ok(())
+}
+
+/// 统计写入次数的存储包装
+struct CountingStorage<'a> {
+    inner: StdStorage,
+    writes: &'a Cell<usize>,
+}
+
+impl ErrorType for CountingStorage<'_> {
+    type Error = <StdStorage as ErrorType>::Error;
+}
+
+impl ReadNorFlash for CountingStorage<'_> {
+    const READ_SIZE: usize = StdStorage::READ_SIZE;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.inner.read(offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+}
+
+impl NorFlash for CountingStorage<'_> {
+    const WRITE_SIZE: usize = StdStorage::WRITE_SIZE;
+    const ERASE_SIZE: usize = StdStorage::ERASE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.inner.erase(from, to)
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.writes.set(self.writes.get() + 1);
+        self.inner.write(offset, bytes)
+    }
+}
+
+#[test]
+fn test_tsdb_set_status_batch() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let writes = Cell::new(0);
+    let storage = StdStorage::new(temp_dir.path(), "batch_test", 4096, 16 * 4096, FileStrategy::Multi)?;
+    let mut tsdb = Box::new(TSDB::new(CountingStorage { inner: storage, writes: &writes }));
+    tsdb.init(64)?;
+
+    for i in 1..=200 {
+        tsdb.append_with_timestamp(i, &(i as u32).to_le_bytes())?;
+    }
+
+    // 确认前 150 条日志
+    let mut acked = Vec::new();
+    tsdb.tsdb_iter(
+        |_, tsl| {
+            acked.push(tsl.clone());
+            acked.len() < 150
+        },
+        false,
+    );
+    writes.set(0);
+    tsdb.set_status_batch(&mut acked, TSLStatus::UserStatus1)?;
+    // 写入粒度不为 1 bit 时逐条写入
+    if flashdb_rs::FDB_WRITE_GRAN == 1 {
+        assert!(writes.get() < 150 / 4, "batched {} writes", writes.get());
+    }
+    assert!(acked.iter().all(|tsl| tsl.status() == TSLStatus::UserStatus1));
+
+    assert_eq!(tsdb.count(0, i64::MAX, TSLStatus::UserStatus1), 150);
+    assert_eq!(tsdb.count(0, i64::MAX, TSLStatus::Write), 50);
+    // 其余索引数据保持不变
+    tsdb.tsdb_iter(
+        |db, tsl| {
+            let value = db.get_value(tsl).unwrap().unwrap();
+            assert_eq!(value, (tsl.time() as u32).to_le_bytes());
+            true
+        },
+        false,
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_rollover_events() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("rollover_events", path, 4096, 16 * 1024, 256)?;
+    let (handler, events) = event_channel();
+    tsdb.set_event_handler(handler);
+
+    for i in 1..=100 {
+        tsdb.append_with_timestamp(i, &[0u8; 200])?;
+    }
+
+    let received: Vec<Event> = events.try_iter().collect();
+    let rollovers: Vec<(i64, i64)> = received
+        .iter()
+        .filter_map(|e| match *e {
+            Event::Rollover { lost_from, lost_to } => Some((lost_from, lost_to)),
+            _ => None,
+        })
+        .collect();
+    assert!(!rollovers.is_empty(), "写满后应该产生翻转事件");
+    // 第一次翻转丢失的是最早写入的日志
+    assert_eq!(rollovers[0].0, 1);
+    for (lost_from, lost_to) in &rollovers {
+        assert!(lost_from <= lost_to);
+    }
+    assert!(received.iter().any(|e| matches!(e, Event::SectorRetired { .. })));
+
+    // 丢失范围之外的最新日志仍然存在
+    let mut oldest = None;
+    tsdb.tsdb_iter(|_, tsl| {
+        oldest = Some(tsl.time());
+        false
+    }, false);
+    assert!(oldest.unwrap() > rollovers.last().unwrap().1);
+
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_split() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let tsdb = TSDB::new_file("split_test", path, 4096, 64 * 1024, 64)?;
+    let (reader, mut writer) = tsdb.split();
+
+    // 采样线程持续追加，上传线程同时遍历
+    let sampler = std::thread::spawn(move || -> Result<()> {
+        for i in 1..=200 {
+            writer.append_with_timestamp(i, &(i as u32).to_le_bytes())?;
+        }
+        Ok(())
+    });
+    let uploader = {
+        let reader = reader.clone();
+        std::thread::spawn(move || -> Result<()> {
+            while reader.last_time() < 200 {
+                let mut last = 0;
+                reader.iter(|tsl, value| {
+                    // 每次遍历看到的都是完整、有序的前缀
+                    assert_eq!(value, (tsl.time() as u32).to_le_bytes());
+                    assert_eq!(tsl.time(), last + 1);
+                    last = tsl.time();
+                    true
+                }, false)?;
+            }
+            Ok(())
+        })
+    };
+    sampler.join().unwrap()?;
+    uploader.join().unwrap()?;
+
+    assert_eq!(reader.count(0, i64::MAX, TSLStatus::Write), 200);
+    let mut values = Vec::new();
+    reader.iter_by_time(10, 12, |_, value| {
+        values.push(value.to_vec());
+        true
+    })?;
+    assert_eq!(values, vec![10u32.to_le_bytes(), 11u32.to_le_bytes(), 12u32.to_le_bytes()]);
+
+    Ok(())
+}
+
+#[test]
+fn test_shared_tsdb() -> Result<()> {
+    use flashdb_rs::shared::SharedTSDB;
+    use std::sync::Arc;
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let tsdb = TSDB::new_file("shared_test", path, 4096, 64 * 1024, 64)?;
+    let shared = Arc::new(SharedTSDB::new(tsdb));
+
+    // 多个线程通过同一个实例交替追加
+    let workers: Vec<_> = (0..4i64)
+        .map(|worker| {
+            let shared = shared.clone();
+            std::thread::spawn(move || -> Result<()> {
+                for i in 0..50 {
+                    shared.with(|db| {
+                        let time = db.last_time() + 1;
+                        db.append_with_timestamp(time, &(worker * 100 + i).to_le_bytes())
+                    })?;
+                }
+                Ok(())
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap()?;
+    }
+    assert_eq!(shared.with(|db| db.count(0, i64::MAX, TSLStatus::Write)), 200);
+
+    // 锁被占用时 try_with 立即返回
+    shared.with(|_| assert!(shared.try_with(|db| db.last_time()).is_none()));
+    assert_eq!(shared.try_with(|db| db.last_time()), Some(200));
+
+    let tsdb = Arc::try_unwrap(shared).ok().unwrap().into_inner();
+    assert_eq!(tsdb.last_time(), 200);
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_iterator() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("iterator_test", path, 4096, 8 * 4096, 256)?;
+    assert_eq!(tsdb.iter().count(), 0);
+
+    // 跨越多个扇区
+    for time in 1..=300 {
+        tsdb.append_with_timestamp(time * 10, &[time as u8; 40])?;
+    }
+    let times: Vec<i64> = tsdb.iter().map(|tsl| tsl.time()).collect();
+    assert_eq!(times, (1..=300).map(|t| t * 10).collect::<Vec<_>>());
+    let reversed: Vec<i64> = tsdb.iter().rev().map(|tsl| tsl.time()).collect();
+    assert_eq!(reversed, (1..=300).rev().map(|t| t * 10).collect::<Vec<_>>());
+
+    assert_eq!(tsdb.iter_by_time(995..=1030).map(|tsl| tsl.time()).collect::<Vec<_>>(), [1000, 1010, 1020, 1030]);
+    assert_eq!(tsdb.iter_by_time(1000..1030).rev().map(|tsl| tsl.time()).collect::<Vec<_>>(), [1020, 1010, 1000]);
+    assert_eq!(tsdb.iter_by_time(2991..).count(), 1);
+    assert_eq!(tsdb.iter_by_time(..=10).count(), 1);
+    assert_eq!(tsdb.iter_by_time(11..20).count(), 0);
+    let (from, to) = (30, 10);
+    assert_eq!(tsdb.iter_by_time(from..to).count(), 0);
+
+    // 两端交替取值不会重复返回同一条日志
+    let mut iter = tsdb.iter_by_time(10..=50);
+    assert_eq!(iter.next().map(|tsl| tsl.time()), Some(10));
+    assert_eq!(iter.next_back().map(|tsl| tsl.time()), Some(50));
+    assert_eq!(iter.next().map(|tsl| tsl.time()), Some(20));
+    assert_eq!(iter.next_back().map(|tsl| tsl.time()), Some(40));
+    assert_eq!(iter.next().map(|tsl| tsl.time()), Some(30));
+    assert!(iter.next_back().is_none());
+
+    // 读取值
+    let entry = tsdb.iter().nth(4).unwrap();
+    assert_eq!(tsdb.get_value(&entry)?.unwrap(), [5u8; 40]);
+
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_cursor() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("cursor_test", path, 4096, 8 * 4096, 256)?;
+    {
+        let mut cursor = tsdb.cursor();
+        assert!(cursor.next().is_none());
+        assert!(cursor.prev().is_none());
+    }
+
+    for time in 1..=200 {
+        tsdb.append_with_timestamp(time * 10, &(time as u32).to_le_bytes())?;
+    }
+    let mut cursor = tsdb.cursor();
+    assert!(cursor.prev().is_none());
+    assert_eq!(cursor.next().map(|tsl| tsl.time()), Some(10));
+    assert_eq!(cursor.current().map(|tsl| tsl.time()), Some(10));
+
+    // 定位到两条日志之间以及恰好位于某条日志上
+    cursor.seek(1005);
+    assert!(cursor.current().is_none());
+    assert_eq!(cursor.read_value()?, None);
+    assert_eq!(cursor.next().map(|tsl| tsl.time()), Some(1010));
+    assert_eq!(cursor.read_value()?.unwrap(), 101u32.to_le_bytes());
+    cursor.seek(1010);
+    assert_eq!(cursor.prev().map(|tsl| tsl.time()), Some(1000));
+    assert_eq!(cursor.next().map(|tsl| tsl.time()), Some(1000));
+    assert_eq!(cursor.next().map(|tsl| tsl.time()), Some(1010));
+
+    // 倒序翻页跨越扇区
+    cursor.seek_end();
+    assert!(cursor.next().is_none());
+    let mut times = Vec::new();
+    while let Some(tsl) = cursor.prev() {
+        times.push(tsl.time());
+    }
+    assert_eq!(times, (1..=200).rev().map(|t| t * 10).collect::<Vec<_>>());
+    assert_eq!(cursor.next().map(|tsl| tsl.time()), Some(10));
+
+    cursor.seek(2001);
+    assert!(cursor.next().is_none());
+    assert_eq!(cursor.prev().map(|tsl| tsl.time()), Some(2000));
+    cursor.seek_start();
+    assert_eq!(cursor.count(), 200);
+
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_append_time_source() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("time_source_test", path, 4096, 4 * 4096, 64)?;
+
+    // 默认使用系统时间
+    let before = tsdb.resolution().timestamp_of(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?);
+    tsdb.append(b"system")?;
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    tsdb.append(b"system")?;
+    let times: Vec<i64> = tsdb.iter().map(|tsl| tsl.time()).collect();
+    assert_eq!(times.len(), 2);
+    assert!(times[0] >= before);
+    assert!(times[1] > times[0]);
+
+    // 自定义时钟停滞或回拨时默认拒绝
+    let last = times[1];
+    let clock = std::sync::Arc::new(std::sync::atomic::AtomicI64::new(last + 1000));
+    let source = clock.clone();
+    tsdb.set_time_source(move || source.load(std::sync::atomic::Ordering::Relaxed));
+    tsdb.append(b"a")?;
+    assert!(matches!(tsdb.append(b"stalled"), Err(err) if matches!(err.root(), Error::WriteError)));
+    clock.store(0, std::sync::atomic::Ordering::Relaxed);
+    assert!(matches!(tsdb.append(b"rewound"), Err(err) if matches!(err.root(), Error::WriteError)));
+    assert_eq!(tsdb.clamped_timestamps(), 0);
+
+    // 启用容错后调整为上一条日志的时间戳加 1
+    tsdb.set_clamp_timestamps(true);
+    tsdb.append(b"b")?;
+    clock.store(last + 1000, std::sync::atomic::Ordering::Relaxed);
+    tsdb.append(b"c")?;
+    clock.store(last + 5000, std::sync::atomic::Ordering::Relaxed);
+    tsdb.append(b"d")?;
+    assert_eq!(tsdb.clamped_timestamps(), 2);
+    let times: Vec<i64> = tsdb.iter_by_time(last + 1..).map(|tsl| tsl.time()).collect();
+    assert_eq!(times, [last + 1000, last + 1001, last + 1002, last + 5000]);
+
+    // 时钟无法给出时间时返回错误，不写入日志
+    struct BrokenClock;
+    impl flashdb_rs::TimeSource for BrokenClock {
+        fn now(&mut self) -> i64 {
+            unreachable!()
+        }
+        fn try_now(&mut self) -> std::result::Result<i64, Error> {
+            Err(Error::TimestampOutOfRange)
+        }
+    }
+    tsdb.set_time_source(BrokenClock);
+    assert!(matches!(tsdb.append(b"broken"), Err(Error::TimestampOutOfRange)));
+
+    // 系统时间落后于自定义时钟，同样被调整
+    tsdb.clear_time_source();
+    tsdb.append(b"system")?;
+    assert_eq!(tsdb.last_time(), last + 5001);
+    assert_eq!(tsdb.count(0, i64::MAX, TSLStatus::Write), 7);
+    Ok(())
+}
+
+static RTC_TICKS: std::sync::atomic::AtomicI64 = std::sync::atomic::AtomicI64::new(0);
+
+extern "C" fn rtc_now() -> flashdb_rs::fdb_time_t {
+    RTC_TICKS.fetch_add(10, std::sync::atomic::Ordering::Relaxed) as flashdb_rs::fdb_time_t + 10
+}
+
+#[test]
+fn test_tsdb_time_fn() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let storage = StdStorage::new(path, "time_fn_test", 4096, 4 * 4096, FileStrategy::Multi)?;
+
+    // 初始化前注册，随 fdb_tsdb_init 传入 C 库
+    let mut tsdb = Box::new(TSDB::new(storage));
+    tsdb.set_time_fn(rtc_now);
+    tsdb.init(64)?;
+    tsdb.append(b"a")?;
+    tsdb.append(b"b")?;
+    assert_eq!(tsdb.iter().map(|tsl| tsl.time()).collect::<Vec<_>>(), [10, 20]);
+
+    // TimeSource 优先于 C 回调
+    tsdb.set_time_source(|| 1000);
+    tsdb.append(b"c")?;
+    assert_eq!(tsdb.last_time(), 1000);
+    tsdb.clear_time_source();
+
+    // C 回调返回的时间戳不递增时由 C 库拒绝
+    assert!(matches!(tsdb.append(b"d"), Err(err) if matches!(err.root(), Error::WriteError)));
+    RTC_TICKS.store(2000, std::sync::atomic::Ordering::Relaxed);
+    tsdb.append(b"e")?;
+    assert_eq!(tsdb.last_time(), 2010);
+
+    // 移除回调后回退到系统时间
+    tsdb.clear_time_fn();
+    tsdb.append(b"f")?;
+    assert!(tsdb.last_time() > 2010);
+    assert_eq!(tsdb.count(0, i64::MAX, TSLStatus::Write), 5);
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_aggregate() -> Result<()> {
+    use flashdb_rs::Aggregation;
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("aggregate_test", path, 4096, 8 * 4096, 64)?;
+
+    // 跨越多个扇区
+    for time in 1..=500i64 {
+        tsdb.append_with_timestamp(time, &(time * 2).to_le_bytes())?;
+    }
+    let decode = |bytes: &[u8]| Some(i64::from_le_bytes(bytes.try_into().ok()?));
+    assert_eq!(tsdb.aggregate(1, 500, Aggregation::Count, decode)?, Some(500.0));
+    assert_eq!(tsdb.aggregate(1, 500, Aggregation::Sum, decode)?, Some(500.0 * 501.0));
+    assert_eq!(tsdb.aggregate(100, 200, Aggregation::Min, decode)?, Some(200.0));
+    assert_eq!(tsdb.aggregate(100, 200, Aggregation::Max, decode)?, Some(400.0));
+    assert_eq!(tsdb.aggregate(100, 200, Aggregation::Avg, decode)?, Some(300.0));
+    // 范围两端可以颠倒
+    assert_eq!(tsdb.aggregate(200, 100, Aggregation::Count, decode)?, Some(101.0));
+
+    // 解码失败与已删除的日志不参与统计
+    let mut entries: Vec<TSLEntry> = tsdb.iter_by_time(1..=10).collect();
+    tsdb.set_status_batch(&mut entries, TSLStatus::Deleted)?;
+    let odd = |bytes: &[u8]| {
+        let value = i64::from_le_bytes(bytes.try_into().ok()?);
+        (value % 4 == 2).then_some(value as f64)
+    };
+    assert_eq!(tsdb.aggregate(1, 20, Aggregation::Count, odd)?, Some(5.0));
+    assert_eq!(tsdb.aggregate(1, 20, Aggregation::Min, odd)?, Some(22.0));
+
+    assert_eq!(tsdb.aggregate(600, 700, Aggregation::Count, decode)?, Some(0.0));
+    assert_eq!(tsdb.aggregate(600, 700, Aggregation::Sum, decode)?, Some(0.0));
+    assert_eq!(tsdb.aggregate(600, 700, Aggregation::Avg, decode)?, None);
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_downsample() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("downsample_test", path, 4096, 8 * 4096, 64)?;
+    tsdb.set_resolution(flashdb_rs::Resolution::Millis);
+
+    // 10..2000 秒每 10 秒一条，中间 500..1000 秒缺失
+    for time in (10..2000i64).step_by(10).filter(|t| !(500..1000).contains(t)) {
+        tsdb.append_with_timestamp(time * 1000, &(time as u32).to_le_bytes())?;
+    }
+    let mean = |acc: Option<(u32, u32)>, _: &TSLEntry, value: &[u8]| {
+        let (sum, count) = acc.unwrap_or((0, 0));
+        (sum + u32::from_le_bytes(value.try_into().unwrap()), count + 1)
+    };
+    let buckets: Vec<(i64, (u32, u32))> = tsdb
+        .iter_downsampled(0, 1_999_999, std::time::Duration::from_secs(100), mean)
+        .collect::<Result<_, _>>()?;
+    // 缺失的 5 个桶被跳过
+    assert_eq!(buckets.len(), 15);
+    assert_eq!(buckets[0], (0, ((10..100).step_by(10).sum(), 9)));
+    assert_eq!(buckets[5].0, 1_000_000);
+    assert_eq!(buckets[14], (1_900_000, ((1900..2000).step_by(10).sum(), 10)));
+
+    // 桶从 from 开始划分，to 截断最后一个桶
+    let buckets: Vec<(i64, (u32, u32))> = tsdb
+        .iter_downsampled(1_050_000, 1_260_000, std::time::Duration::from_secs(100), mean)
+        .collect::<Result<_, _>>()?;
+    let counts: Vec<(i64, u32)> = buckets.iter().map(|&(start, (_, count))| (start, count)).collect();
+    assert_eq!(counts, [(1_050_000, 10), (1_150_000, 10), (1_250_000, 2)]);
+
+    // 已删除的日志不参与归约
+    let mut entries: Vec<TSLEntry> = tsdb.iter_by_time(0..100_000).collect();
+    tsdb.set_status_batch(&mut entries, TSLStatus::Deleted)?;
+    let first = tsdb
+        .iter_downsampled(0, i64::MAX, std::time::Duration::from_secs(100), mean)
+        .next()
+        .unwrap()?;
+    assert_eq!(first.0, 100_000);
+    assert_eq!(tsdb.iter_downsampled(5_000_000, 6_000_000, std::time::Duration::ZERO, mean).count(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_retention() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("retention_test", path, 4096, 8 * 4096, 64)?;
+    tsdb.set_resolution(flashdb_rs::Resolution::Millis);
+
+    for time in 1..=300i64 {
+        tsdb.append_with_timestamp(time * 1000, &(time as u32).to_le_bytes())?;
+    }
+    // 未设置保留时长时不删除
+    assert_eq!(tsdb.enforce_retention(i64::MAX)?, 0);
+
+    tsdb.set_retention(std::time::Duration::from_secs(100));
+    assert_eq!(tsdb.retention(), Some(std::time::Duration::from_secs(100)));
+    // 跨越多个批次与扇区
+    assert_eq!(tsdb.enforce_retention(300_000)?, 199);
+    assert_eq!(tsdb.count(0, i64::MAX, TSLStatus::Write), 101);
+    assert_eq!(tsdb.iter().find(|tsl| tsl.status() == TSLStatus::Write).map(|tsl| tsl.time()), Some(200_000));
+
+    // 已删除的日志不会重复计数，其他状态的日志同样会被删除
+    let mut synced: Vec<TSLEntry> = tsdb.iter_by_time(200_000..=210_000).collect();
+    tsdb.set_status_batch(&mut synced, TSLStatus::UserStatus1)?;
+    assert_eq!(tsdb.enforce_retention(315_000)?, 15);
+    assert_eq!(tsdb.count(0, i64::MAX, TSLStatus::UserStatus1), 0);
+    assert_eq!(tsdb.count(0, i64::MAX, TSLStatus::Deleted), 214);
+
+    tsdb.clear_retention();
+    assert_eq!(tsdb.enforce_retention(i64::MAX)?, 0);
+    assert_eq!(tsdb.enforce_retention(i64::MIN)?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_purge_before() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("purge_test", path, 4096, 8 * 4096, 128)?;
+
+    let mut last = 0i64;
+    for round in 0..24 {
+        // 每轮写入约 1.5 个扇区，多轮后擦除出的空扇区会跨越存储区末尾
+        for _ in 0..80 {
+            last += 1;
+            tsdb.append_with_timestamp(last, &[round as u8; 40])?;
+        }
+        let before = tsdb.iter().map(|tsl| tsl.time()).collect::<Vec<_>>();
+        let purged = tsdb.purge_before(last - 150)?;
+        let after = tsdb.iter().map(|tsl| tsl.time()).collect::<Vec<_>>();
+        assert!(after.len() <= before.len());
+        assert_eq!(after[..], before[before.len() - after.len()..]);
+        if purged > 0 {
+            assert!(after.len() < before.len());
+        }
+        // 只擦除整个扇区都早于 ts 的扇区
+        assert!(after[0] <= (last - 150).max(before[0]));
+
+        // 重新打开后最旧扇区与当前扇区保持不变
+        drop(tsdb);
+        tsdb = TSDB::new_file("purge_test", path, 4096, 8 * 4096, 128)?;
+        assert_eq!(tsdb.last_time(), last);
+        assert_eq!(tsdb.iter().map(|tsl| tsl.time()).collect::<Vec<_>>(), after);
+        let mut reversed = tsdb.iter().rev().map(|tsl| tsl.time()).collect::<Vec<_>>();
+        reversed.reverse();
+        assert_eq!(reversed, after);
+        assert_eq!(*after.last().unwrap(), last);
+        assert!(after.windows(2).all(|w| w[1] == w[0] + 1));
+    }
+
+    assert_eq!(tsdb.purge_before(i64::MIN)?, 0);
+    // 正在写入的扇区不会被擦除
+    assert!(tsdb.purge_before(i64::MAX)? > 0);
+    assert_eq!(tsdb.purge_before(i64::MAX)?, 0);
+    assert_eq!(tsdb.iter().last().map(|tsl| tsl.time()), Some(last));
+    assert!(tsdb.iter().count() < 80);
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_init_after_purge() -> Result<()> {
+    use flashdb_rs::test_utils::{Fault, FaultyFlash};
+
+    let open = |image: &[u8]| -> Result<Box<TSDB<FaultyFlash>>> {
+        let mut tsdb = Box::new(TSDB::new(FaultyFlash::from_image(image.to_vec())));
+        tsdb.init(64)?;
+        Ok(tsdb)
+    };
+    let times = |tsdb: &mut TSDB<FaultyFlash>| tsdb.iter().map(|tsl| tsl.time()).collect::<Vec<_>>();
+    let mut tsdb = Box::new(TSDB::new(FaultyFlash::new(4 * 4096)));
+    tsdb.init(64)?;
+    let sec_size = tsdb.sec_size();
+    let last_sector = |tsdb: &mut TSDB<FaultyFlash>| tsdb.iter().last().unwrap().sector_index(sec_size);
+
+    // 写入扇区 0 ~ 2，再擦除扇区 0：空扇区位于正在写入的扇区之前
+    let mut last = 0i64;
+    while last < 2 || last_sector(&mut tsdb) < 2 {
+        last += 1;
+        tsdb.append_with_timestamp(last, &[0x5A; 40])?;
+    }
+    let second = tsdb.iter().find(|tsl| tsl.sector_index(sec_size) == 1).unwrap().time();
+    assert_eq!(tsdb.purge_before(second)?, 1);
+    let kept = times(&mut tsdb);
+    assert_eq!(kept[0], second);
+
+    // 上游的初始化会把这种布局当作多个扇区同时在用而格式化整个数据库
+    let mut tsdb = open(tsdb.storage().image())?;
+    assert_eq!(times(&mut tsdb), kept);
+    let report = tsdb.verify()?;
+    assert!(report.is_clean(), "{:?}", report);
+    assert_eq!(report.valid_entries, kept.len());
+
+    // 写满扇区 2，找到切换到扇区 3 的那次追加
+    let (image, writes) = loop {
+        let image = tsdb.storage().image().to_vec();
+        let writes = tsdb.storage().writes();
+        last += 1;
+        tsdb.append_with_timestamp(last, &[0x5A; 40])?;
+        if last_sector(&mut tsdb) == 3 {
+            break (image, tsdb.storage().writes() - writes);
+        }
+    };
+    let before = times(&mut *open(&image)?);
+
+    // 在切换扇区的每一次写入时断电：扇区 2 可能已标记为写满而扇区 3 仍为空，
+    // 重新上电后当前扇区必须是扇区 3，最旧的扇区仍是扇区 1，而不是存储区开头的空扇区 0
+    for nth in 1..=writes {
+        let mut tsdb = open(&image)?;
+        tsdb.storage().arm(Fault::TruncateWrite { nth, len: 0 });
+        assert!(tsdb.append_with_timestamp(last, &[0x5A; 40]).is_err());
+
+        let mut tsdb = open(tsdb.storage().image())?;
+        let reopened = times(&mut tsdb);
+        assert_eq!(reopened[..before.len()], before[..]);
+        assert!(reopened.len() <= before.len() + 1);
+        tsdb.append_with_timestamp(last + 1, &[0xA5; 40])?;
+        let after = times(&mut tsdb);
+        assert_eq!(after[0], second);
+        assert_eq!(*after.last().unwrap(), last + 1);
+        assert_eq!(last_sector(&mut tsdb), 3);
+        assert!(after.windows(2).all(|w| w[0] < w[1]));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_last_n() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("last_n_test", path, 4096, 8 * 4096, 64)?;
+
+    assert!(tsdb.last_n_vec(5).is_empty());
+    for time in 1..=300i64 {
+        tsdb.append_with_timestamp(time, &(time as u32).to_le_bytes())?;
+    }
+
+    // 跨越扇区
+    let recent = tsdb.last_n_vec(120);
+    assert_eq!(recent.len(), 120);
+    assert!(recent.iter().map(|tsl| tsl.time()).eq((181..=300).rev()));
+    assert_eq!(tsdb.get_value(&recent[0])?.unwrap(), 300u32.to_le_bytes());
+
+    let mut visited = 0;
+    assert_eq!(tsdb.last_n(0, |_| visited += 1), 0);
+    assert_eq!(visited, 0);
+    assert_eq!(tsdb.last_n(1000, |_| visited += 1), 300);
+    assert_eq!(visited, 300);
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_tsdb_typed() -> Result<()> {
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    enum Record {
+        Temperature { sensor: u8, celsius: f32 },
+        Alarm(String),
+    }
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("typed_test", path, 4096, 4 * 4096, 64)?;
+
+    let records = [
+        Record::Temperature { sensor: 1, celsius: 20.5 },
+        Record::Alarm("door open".into()),
+        Record::Temperature { sensor: 2, celsius: -4.0 },
+    ];
+    for (time, record) in records.iter().enumerate() {
+        tsdb.append_typed(time as i64 + 1, record)?;
+    }
+    let entries: Vec<TSLEntry> = tsdb.iter().collect();
+    for (entry, record) in entries.iter().zip(&records) {
+        assert_eq!(tsdb.get_typed::<Record>(entry)?.as_ref(), Some(record));
+    }
+
+    // 编码过长或类型不匹配
+    assert!(tsdb.append_typed(10, &Record::Alarm("x".repeat(100))).is_err());
+    tsdb.append_with_timestamp(11, &[0xFF])?;
+    let raw = tsdb.iter().last().unwrap();
+    assert!(matches!(tsdb.get_typed::<Record>(&raw), Err(Error::Serde(_))));
+
+    let mut deleted = entries[0].clone();
+    tsdb.set_status(&mut deleted, TSLStatus::Deleted)?;
+    let deleted = tsdb.iter().next().unwrap();
+    assert_eq!(tsdb.get_typed::<Record>(&deleted)?, None);
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_channels() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("channel_test", path, 4096, 8 * 4096, 64)?;
+
+    // 三个通道交替写入，跨越多个扇区
+    for time in 1..=300i64 {
+        let id = (time % 3) as u8;
+        tsdb.channel(id).append_with_timestamp(time, &(time as u32).to_le_bytes())?;
+    }
+    for id in 0..3 {
+        assert_eq!(tsdb.channel(id).count(0, i64::MAX)?, 100);
+    }
+    assert_eq!(tsdb.channel(1).count(1, 30)?, 10);
+    assert_eq!(tsdb.channel(7).count(0, i64::MAX)?, 0);
+
+    let mut times = Vec::new();
+    tsdb.channel(2).iter_by_time(0, i64::MAX, |tsl, payload| {
+        assert_eq!(payload, (tsl.time() as u32).to_le_bytes());
+        times.push(tsl.time());
+        times.len() < 5
+    })?;
+    assert_eq!(times, [2, 5, 8, 11, 14]);
+
+    let entry = tsdb.iter_by_time(100..=100).next().unwrap();
+    assert_eq!(tsdb.channel_of(&entry)?, Some(1));
+    assert_eq!(tsdb.channel(1).get_value(&entry)?.unwrap(), 100u32.to_le_bytes());
+    assert_eq!(tsdb.channel(0).get_value(&entry)?, None);
+
+    // 通道号占用一个字节
+    assert!(tsdb.channel(0).append_with_timestamp(301, &[0; 64]).is_err());
+    tsdb.channel(0).append_with_timestamp(302, &[0; 63])?;
+
+    // 已删除的日志不再计入通道
+    let mut deleted = entry.clone();
+    tsdb.set_status(&mut deleted, TSLStatus::Deleted)?;
+    assert_eq!(tsdb.channel(1).count(0, i64::MAX)?, 99);
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_status_summary() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("summary_test", path, 4096, 8 * 4096, 64)?;
+
+    assert_eq!(tsdb.status_summary(0, i64::MAX).total(), 0);
+    for time in 1..=300i64 {
+        tsdb.append_with_timestamp(time, b"sample")?;
+    }
+    let mut entries: Vec<TSLEntry> = tsdb.iter().collect();
+    tsdb.set_status_batch(&mut entries[..50], TSLStatus::UserStatus1)?;
+    tsdb.set_status_batch(&mut entries[50..80], TSLStatus::UserStatus2)?;
+    tsdb.set_status_batch(&mut entries[80..100], TSLStatus::Deleted)?;
+
+    // 与逐个状态调用 count() 的结果一致
+    for (from, to) in [(0, i64::MAX), (40, 90), (250, 1000), (90, 40)] {
+        let summary = tsdb.status_summary(from, to);
+        for status in [
+            TSLStatus::PRE_WRITE,
+            TSLStatus::Write,
+            TSLStatus::UserStatus1,
+            TSLStatus::Deleted,
+            TSLStatus::UserStatus2,
+        ] {
+            assert_eq!(summary.get(status), tsdb.count(from.min(to), from.max(to), status));
+        }
+    }
+    let summary = tsdb.status_summary(0, i64::MAX);
+    assert_eq!(summary.write, 200);
+    assert_eq!(summary.total(), 300);
+    assert_eq!(tsdb.status_summary(1000, 2000), Default::default());
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_iter_by_status() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("iter_by_status_test", path, 4096, 8 * 4096, 64)?;
+
+    for time in 1..=300i64 {
+        tsdb.append_with_timestamp(time, b"sample")?;
+    }
+    // 每 3 条中同步 1 条，跨越多个扇区
+    let mut synced: Vec<TSLEntry> = tsdb.iter().filter(|tsl| tsl.time() % 3 == 0).collect();
+    tsdb.set_status_batch(&mut synced, TSLStatus::UserStatus1)?;
+
+    let pending: Vec<i64> = tsdb.iter_by_status(TSLStatus::Write).map(|tsl| tsl.time()).collect();
+    assert_eq!(pending, (1..=300).filter(|t| t % 3 != 0).collect::<Vec<_>>());
+    assert!(tsdb.iter_by_status(TSLStatus::UserStatus1).all(|tsl| tsl.status() == TSLStatus::UserStatus1));
+    assert_eq!(tsdb.iter_by_status(TSLStatus::Deleted).count(), 0);
+
+    let times: Vec<i64> = tsdb
+        .iter_by_time_and_status(100..=120, TSLStatus::UserStatus1)
+        .rev()
+        .map(|tsl| tsl.time())
+        .collect();
+    assert_eq!(times, [120, 117, 114, 111, 108, 105, 102]);
+    assert_eq!(
+        tsdb.iter_by_time_and_status(100..=120, TSLStatus::Write).count(),
+        tsdb.count(100, 120, TSLStatus::Write)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_export() -> Result<()> {
+    use flashdb_rs::{Encoding, ExportFormat};
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("export_test", path, 4096, 8 * 4096, 64)?;
+
+    let mut empty = Vec::new();
+    assert_eq!(tsdb.export(.., ExportFormat::Json(Encoding::Hex), &mut empty)?, 0);
+    assert_eq!(empty, b"[]\n");
+
+    for time in 1..=300i64 {
+        tsdb.append_with_timestamp(time, &(time as u16).to_be_bytes())?;
+    }
+    let mut deleted: Vec<TSLEntry> = tsdb.iter_by_time(1..=100).collect();
+    tsdb.set_status_batch(&mut deleted, TSLStatus::Deleted)?;
+    let mut synced: Vec<TSLEntry> = tsdb.iter_by_time(101..=150).collect();
+    tsdb.set_status_batch(&mut synced, TSLStatus::UserStatus1)?;
+
+    // 已删除的日志不会被导出，跨越扇区
+    let mut csv = Vec::new();
+    assert_eq!(tsdb.export(..=200, ExportFormat::Csv(Encoding::Base64), &mut csv)?, 100);
+    let csv = String::from_utf8(csv)?;
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 101);
+    assert_eq!(lines[1], "101,UserStatus1,AGU=");
+    assert_eq!(lines[100], "200,Write,AMg=");
+
+    // 自定义格式按 CSV 与 JSON 规则转义
+    fn quoted(value: &[u8], out: &mut String) {
+        out.push_str(&format!("\"{}\",\n\\", value[1]));
+    }
+    let mut csv = Vec::new();
+    tsdb.export(300..=300, ExportFormat::Csv(Encoding::Custom(quoted)), &mut csv)?;
+    assert_eq!(csv, b"timestamp,status,payload\n300,Write,\"\"\"44\"\",\n\\\"\n");
+    let mut json = Vec::new();
+    tsdb.export(299.., ExportFormat::Json(Encoding::Custom(quoted)), &mut json)?;
+    assert_eq!(
+        String::from_utf8(json)?,
+        "[\n  {\"timestamp\": 299, \"status\": \"Write\", \"payload\": \"\\\"43\\\",\\n\\\\\"},\n  {\"timestamp\": 300, \"status\": \"Write\", \"payload\": \"\\\"44\\\",\\n\\\\\"}\n]\n"
+    );
+
+    // 写入失败时返回错误
+    let mut small = [0u8; 64];
+    assert!(matches!(
+        tsdb.export(.., ExportFormat::Csv(Encoding::Hex), &mut &mut small[..]),
+        Err(Error::WriteError)
+    ));
+    assert_eq!(tsdb.export(400.., ExportFormat::Csv(Encoding::Hex), &mut Vec::new())?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_import() -> Result<()> {
+    use flashdb_rs::{Encoding, ExportFormat};
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut source = TSDB::new_file("import_src", path, 4096, 8 * 4096, 64)?;
+    for time in 1..=300i64 {
+        source.append_with_timestamp(time, &(time as u16).to_be_bytes())?;
+    }
+    let mut synced: Vec<TSLEntry> = source.iter_by_time(1..=50).collect();
+    source.set_status_batch(&mut synced, TSLStatus::UserStatus1)?;
+
+    // 两种格式与编码都能还原时间戳、状态与值，跨越扇区
+    for (name, format) in [
+        ("import_csv", ExportFormat::Csv(Encoding::Hex)),
+        ("import_json", ExportFormat::Json(Encoding::Base64)),
+    ] {
+        let mut exported = Vec::new();
+        assert_eq!(source.export(.., format, &mut exported)?, 300);
+        let mut target = TSDB::new_file(name, path, 4096, 8 * 4096, 64)?;
+        assert_eq!(target.import(format, &mut &exported[..])?, 300);
+        assert_eq!(target.last_time(), 300);
+        assert_eq!(target.count(0, i64::MAX, TSLStatus::UserStatus1), 50);
+        assert_eq!(target.count(0, i64::MAX, TSLStatus::Write), 250);
+        let entries: Vec<TSLEntry> = target.iter().collect();
+        assert_eq!(entries.len(), 300);
+        for (expected, entry) in (1..=300i64).zip(entries) {
+            assert_eq!(entry.time(), expected);
+            assert_eq!(target.get_value(&entry)?, Some((expected as u16).to_be_bytes().to_vec()));
+        }
+    }
+
+    let mut target = TSDB::new_file("import_err", path, 4096, 8 * 4096, 64)?;
+    // 最后一行没有换行符
+    assert_eq!(target.import(ExportFormat::Csv(Encoding::Hex), &mut &b"timestamp,status,payload\n1,Write,cafe"[..])?, 1);
+    let entry = target.iter().next().unwrap();
+    assert_eq!(target.get_value(&entry)?, Some(vec![0xCA, 0xFE]));
+    // 格式错误、无法还原的编码与不递增的时间戳
+    for (format, text) in [
+        (ExportFormat::Csv(Encoding::Hex), &b"1,Write,cafe\n"[..]),
+        (ExportFormat::Csv(Encoding::Hex), b"timestamp,status,payload\n2,Write,caf\n"),
+        (ExportFormat::Json(Encoding::Base64), b"[\n  {\"timestamp\": 2, \"status\": \"Lost\", \"payload\": \"\"}\n]\n"),
+        (ExportFormat::Json(Encoding::Custom(|_, _| {})), b"[]\n"),
+    ] {
+        assert!(matches!(target.import(format, &mut &text[..]), Err(Error::InvalidArgument)));
+    }
+    assert!(target
+        .import(ExportFormat::Csv(Encoding::Hex), &mut &b"timestamp,status,payload\n1,Write,00\n"[..])
+        .is_err());
+    assert_eq!(target.count(0, i64::MAX, TSLStatus::Write), 1);
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_first_time() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("first_time_test", path, 4096, 4 * 4096, 64)?;
+    assert_eq!(tsdb.first_time(), None);
+
+    tsdb.append_with_timestamp(5, b"a")?;
+    tsdb.append_with_timestamp(6, b"b")?;
+    assert_eq!(tsdb.first_time(), Some(5));
+
+    // 翻转写入覆盖最旧的扇区后随之前移
+    let mut time = 7;
+    while tsdb.first_time() == Some(5) {
+        tsdb.append_with_timestamp(time, &[0; 32])?;
+        time += 1;
+    }
+    let first = tsdb.first_time().unwrap();
+    assert!(first > 6 && first < tsdb.last_time());
+    assert_eq!(tsdb.iter().next().map(|tsl| tsl.time()), Some(first));
+
+    // 重新打开后不变，已删除的日志仍然计入
+    let mut oldest = tsdb.iter().next().unwrap();
+    tsdb.set_status(&mut oldest, TSLStatus::Deleted)?;
+    drop(tsdb);
+    let mut tsdb = TSDB::new_file("first_time_test", path, 4096, 4 * 4096, 64)?;
+    assert_eq!(tsdb.first_time(), Some(first));
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_clamp_timestamps() -> Result<()> {
+    extern "C" fn stuck_rtc() -> flashdb_rs::fdb_time_t {
+        5
+    }
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("clamp_test", path, 4096, 4 * 4096, 64)?;
+    assert!(!tsdb.clamp_timestamps());
+
+    // 默认拒绝不递增的时间戳
+    tsdb.append_with_timestamp(100, b"a")?;
+    let err = tsdb.append_with_timestamp(100, b"b").unwrap_err();
+    assert!(matches!(err.root(), Error::WriteError));
+    assert_eq!(err.context().and_then(|context| context.timestamp()), Some(100));
+    assert_eq!(err.to_string(), "append @100: Write operation failed");
+    assert_eq!(tsdb.clamped_timestamps(), 0);
+
+    tsdb.set_clamp_timestamps(true);
+    tsdb.append_with_timestamp(100, b"b")?;
+    tsdb.append_with_timestamp(-7, b"c")?;
+    tsdb.append_with_timestamp(500, b"d")?;
+    assert_eq!(tsdb.clamped_timestamps(), 2);
+
+    // C 回调返回的时间戳同样被调整
+    tsdb.set_time_fn(stuck_rtc);
+    tsdb.append(b"e")?;
+    tsdb.append(b"f")?;
+    assert_eq!(tsdb.clamped_timestamps(), 4);
+
+    let entries: Vec<TSLEntry> = tsdb.iter().collect();
+    let times: Vec<i64> = entries.iter().map(|tsl| tsl.time()).collect();
+    assert_eq!(times, [100, 101, 102, 500, 501, 502]);
+    let mut values = Vec::new();
+    for entry in &entries {
+        values.push(tsdb.get_value(entry)?.unwrap());
+    }
+    assert_eq!(values, [b"a", b"b", b"c", b"d", b"e", b"f"]);
+
+    tsdb.reset_clamped_timestamps();
+    assert_eq!(tsdb.clamped_timestamps(), 0);
+    tsdb.set_clamp_timestamps(false);
+    assert!(matches!(tsdb.append(b"g"), Err(err) if matches!(err.root(), Error::WriteError)));
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_error_context() -> Result<()> {
+    use flashdb_rs::test_utils::{Fault, FaultyFlash};
+    use flashdb_rs::Operation;
+
+    let mut tsdb = Box::new(TSDB::new(FaultyFlash::new(4 * 4096)));
+    tsdb.init(64)?;
+    for time in 1..=150 {
+        tsdb.append_with_timestamp(time, &[0x5A; 40])?;
+    }
+    let context = |err: &Error| err.context().map(|context| (context.op(), context.timestamp()));
+
+    // 修改状态失败时附加日志的时间戳
+    let mut entries: Vec<TSLEntry> = tsdb.iter_by_time(10..20).collect();
+    tsdb.storage().arm(Fault::FailWrite { nth: 1 });
+    let err = tsdb.set_status(&mut entries[0], TSLStatus::UserStatus1).unwrap_err();
+    assert_eq!(context(&err), Some((Operation::SetStatus, Some(10))));
+    tsdb.storage().arm(Fault::FailWrite { nth: 1 });
+    let err = tsdb.set_status_batch(&mut entries[1..], TSLStatus::UserStatus1).unwrap_err();
+    assert_eq!(context(&err), Some((Operation::SetStatus, Some(11))));
+
+    // 回收扇区时擦除失败
+    tsdb.storage().arm(Fault::FailErase { nth: 1 });
+    let err = tsdb.purge_before(120).unwrap_err();
+    assert!(matches!(err.root(), Error::Storage(_)));
+    assert_eq!(context(&err), Some((Operation::Gc, Some(120))));
+    assert_eq!(err.to_string().split(':').next(), Some(format!("gc @120 at {:#010x}", err.context().unwrap().addr().unwrap()).as_str()));
+
+    // 断电后读取日志的值失败
+    let entry = tsdb.iter_by_time(100..).next().unwrap();
+    tsdb.storage().arm(Fault::TruncateWrite { nth: 1, len: 0 });
+    assert!(tsdb.append_with_timestamp(151, b"lost").is_err());
+    let err = tsdb.get_value(&entry).unwrap_err();
+    assert_eq!(context(&err), Some((Operation::Read, Some(100))));
+    assert_eq!(err.kind(), flashdb_rs::ErrorKind::Io);
+
+    // 断电的 Flash 上初始化失败
+    let mut flash = FaultyFlash::new(4 * 4096);
+    flash.arm(Fault::TruncateWrite { nth: 1, len: 0 });
+    assert!(flash.write(0, &[0; 4]).is_err());
+    let mut tsdb = Box::new(TSDB::new(flash));
+    let err = tsdb.init(64).unwrap_err();
+    assert_eq!(context(&err), Some((Operation::Init, None)));
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_resolution() -> Result<()> {
+    use flashdb_rs::Resolution;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("resolution_test", path, 4096, 8 * 4096, 64)?;
+    let expected = if cfg!(feature = "time64") { Resolution::Millis } else { Resolution::Seconds };
+    assert_eq!(tsdb.resolution(), expected);
+
+    // 同一时刻在不同单位下的换算
+    let at = Duration::from_micros(1_700_000_123_456);
+    assert_eq!(Resolution::Seconds.timestamp_of(at), 1_700_000);
+    assert_eq!(Resolution::Millis.timestamp_of(at), 1_700_000_123);
+    assert_eq!(Resolution::Micros.timestamp_of(at), 1_700_000_123_456);
+    assert_eq!(Resolution::Micros.timestamp_of(Duration::MAX), i64::MAX);
+    assert_eq!(Resolution::Seconds.duration_of(-1), None);
+
+    tsdb.set_resolution(Resolution::Seconds);
+    tsdb.append_at(at, b"a")?;
+    assert_eq!(tsdb.last_time(), 1_700_000);
+    let time = UNIX_EPOCH + Duration::from_secs(1_800_000);
+    tsdb.append_at_system_time(time, b"b")?;
+    assert!(matches!(
+        tsdb.append_at_system_time(UNIX_EPOCH - Duration::from_secs(1), b"c"),
+        Err(Error::InvalidArgument)
+    ));
+    let last = tsdb.iter().last().unwrap();
+    assert_eq!(tsdb.tsl_duration(&last), Some(Duration::from_secs(1_800_000)));
+    assert_eq!(tsdb.tsl_system_time(&last), Some(time));
+
+    // 未设置时间戳来源时按单位换算系统时间
+    let before = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    tsdb.append(b"d")?;
+    assert!(tsdb.last_time() >= before && tsdb.last_time() <= before + 1);
+
+    // 保留时长同样按单位换算
+    tsdb.set_retention(Duration::from_secs(10));
+    assert_eq!(tsdb.enforce_retention(1_800_005)?, 1);
+    assert_eq!(tsdb.count(0, i64::MAX, TSLStatus::Write), 2);
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_timestamp_range() -> Result<()> {
+    use flashdb_rs::{is_time64, timestamp_range};
+
+    assert_eq!(is_time64(), cfg!(feature = "time64"));
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("time_range_test", path, 4096, 4 * 4096, 64)?;
+
+    let max = *timestamp_range().end();
+    if is_time64() {
+        assert_eq!(timestamp_range(), i64::MIN..=i64::MAX);
+    } else {
+        // 毫秒级 UNIX 时间超出 32 位时间戳，拒绝而不是截断
+        assert!(matches!(
+            tsdb.append_with_timestamp(1_700_000_000_000, b"ms"),
+            Err(Error::TimestampOutOfRange)
+        ));
+        assert!(matches!(tsdb.append_with_timestamp(max + 1, b"x"), Err(Error::TimestampOutOfRange)));
+        assert_eq!(tsdb.last_time(), 0);
+    }
+
+    // 范围两端的时间戳可以原样写入与读回
+    tsdb.append_with_timestamp(max - 1, b"a")?;
+    tsdb.append_with_timestamp(max, b"b")?;
+    assert_eq!(tsdb.last_time(), max);
+    assert_eq!(tsdb.iter().map(|tsl| tsl.time()).collect::<Vec<_>>(), [max - 1, max]);
+    assert_eq!(tsdb.count(i64::MIN, i64::MAX, TSLStatus::Write), 2);
+    // 已到达范围末端时无法再调整出递增的时间戳
+    tsdb.set_clamp_timestamps(true);
+    assert!(tsdb.append_with_timestamp(0, b"c").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_get_value_into() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("get_value_into_test", path, 4096, 4 * 4096, 64)?;
+    tsdb.append_with_timestamp(1, b"hello")?;
+    tsdb.append_with_timestamp(2, b"")?;
+    let entries: Vec<TSLEntry> = tsdb.iter().collect();
+
+    // 缓冲区可以大于数据长度，数据写入开头
+    let mut buf = [0xFFu8; 8];
+    assert_eq!(tsdb.get_value_into(&entries[0], &mut buf)?, 5);
+    assert_eq!(&buf, b"hello\xFF\xFF\xFF");
+    assert_eq!(tsdb.get_value_into(&entries[1], &mut [])?, 0);
+    assert!(matches!(tsdb.get_value_into(&entries[0], &mut [0; 4]), Err(Error::ValueLengthMismatch)));
+
+    // 不可读的状态
+    let mut deleted = entries[0].clone();
+    tsdb.set_status(&mut deleted, TSLStatus::Deleted)?;
+    let deleted = tsdb.iter().next().unwrap();
+    assert!(matches!(tsdb.get_value_into(&deleted, &mut buf), Err(Error::KeyNotFound)));
+    assert_eq!(tsdb.get_value(&deleted)?, None);
+    Ok(())
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn test_tsdb_compressed() -> Result<()> {
+    use rand::{Rng, SeedableRng};
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("compressed_test", path, 4096, 16 * 4096, 1024)?;
+
+    // 重复的传感器记录被压缩，随机数据与短值按原样保存
+    let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+    let repetitive: Vec<u8> = (0..600).map(|i| b"sensor=42;"[i % 10]).collect();
+    let noise: Vec<u8> = (0..300).map(|_| rng.gen()).collect();
+    let mixed: Vec<u8> = (0..800u32).map(|i| if i % 97 < 60 { (i % 7) as u8 } else { rng.gen() }).collect();
+    let values = [repetitive.clone(), noise.clone(), b"short".to_vec(), Vec::new(), mixed, vec![0xAA; 1000]];
+    for (time, value) in values.iter().enumerate() {
+        tsdb.compressed(16).append_with_timestamp(time as i64 + 1, value)?;
+    }
+
+    let entries: Vec<TSLEntry> = tsdb.iter().collect();
+    assert!(entries[0].value_len() < repetitive.len() / 4);
+    assert_eq!(entries[1].value_len(), noise.len() + 1);
+    assert_eq!(entries[2].value_len(), 6);
+    assert!(entries[5].value_len() < 50);
+    for (entry, value) in entries.iter().zip(&values) {
+        assert_eq!(tsdb.compressed(16).get_value(entry)?.as_ref(), Some(value));
+    }
+
+    let mut visited = Vec::new();
+    tsdb.compressed(16).iter_by_time(2, 5, |entry, value| {
+        visited.push((entry.time(), value.to_vec()));
+        true
+    })?;
+    assert_eq!(visited.len(), 4);
+    assert!(visited.iter().all(|(time, value)| *value == values[*time as usize - 1]));
+
+    // 随机长度与重复程度的往返
+    for round in 0..200 {
+        let len = rng.gen_range(0..1000);
+        let alphabet = rng.gen_range(1..=255u8);
+        let value: Vec<u8> = (0..len).map(|_| rng.gen_range(0..=alphabet.min(round as u8 + 1))).collect();
+        tsdb.compressed(0).append_with_timestamp(100 + round, &value)?;
+        let entry = tsdb.iter().last().unwrap();
+        assert_eq!(tsdb.compressed(0).get_value(&entry)?, Some(value));
+    }
+
+    // 不是通过此视图写入的日志
+    tsdb.append_with_timestamp(1000, &[9, 1, 2])?;
+    tsdb.append_with_timestamp(1001, &[1, 200, 0, 0, 0, 0x80, 1, 0])?;
+    for entry in tsdb.iter_by_time(1000..).collect::<Vec<_>>() {
+        assert!(matches!(tsdb.compressed(16).get_value(&entry), Err(Error::Corrupted)));
+    }
+    assert!(matches!(tsdb.compressed(16).iter_by_time(1000, 1001, |_, _| true), Err(Error::Corrupted)));
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_count_status_set() -> Result<()> {
+    use flashdb_rs::StatusSet;
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("count_set_test", path, 4096, 8 * 4096, 64)?;
+    for time in 1..=200i64 {
+        tsdb.append_with_timestamp(time, &[0; 16])?;
+    }
+    let mut synced: Vec<TSLEntry> = tsdb.iter_by_time(1..=60).collect();
+    tsdb.set_status_batch(&mut synced, TSLStatus::UserStatus1)?;
+    let mut deleted: Vec<TSLEntry> = tsdb.iter_by_time(1..=20).collect();
+    tsdb.set_status_batch(&mut deleted, TSLStatus::Deleted)?;
+
+    assert_eq!(tsdb.count(0, i64::MAX, TSLStatus::Write), 140);
+    assert_eq!(tsdb.count(0, i64::MAX, TSLStatus::Write | TSLStatus::UserStatus1), 180);
+    assert_eq!(tsdb.count(0, 100, [TSLStatus::UserStatus1, TSLStatus::Deleted]), 60);
+    let all = [TSLStatus::PRE_WRITE, TSLStatus::Write, TSLStatus::UserStatus1, TSLStatus::Deleted, TSLStatus::UserStatus2];
+    assert_eq!(tsdb.count(0, i64::MAX, &all[..]), 200);
+    assert_eq!(tsdb.count(0, i64::MAX, StatusSet::empty()), 0);
+    assert_eq!(tsdb.count(0, i64::MAX, StatusSet::empty() | TSLStatus::UNUSED | TSLStatus::Deleted), 20);
+    assert_eq!(tsdb.count(100, 150, TSLStatus::Write | TSLStatus::Deleted), tsdb.count(100, 150, TSLStatus::Write));
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_vacuum() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("vacuum_test", path, 4096, 4 * 4096, 128)?;
+
+    // 写满整个环形缓冲区
+    let mut last = 0i64;
+    while tsdb.first_time() == Some(1) || last < 2 {
+        last += 1;
+        tsdb.append_with_timestamp(last, &[0x5A; 40])?;
+    }
+    assert_eq!(tsdb.vacuum()?, 0);
+    let first = tsdb.first_time().unwrap();
+
+    // 最旧的日志仍然有效时不回收后面的扇区
+    let mut middle: Vec<TSLEntry> = tsdb.iter_by_time(first + 100..=last - 10).collect();
+    tsdb.set_status_batch(&mut middle, TSLStatus::Deleted)?;
+    assert_eq!(tsdb.vacuum()?, 0);
+
+    // Deleted 与 UserStatus2 都视为可回收
+    let mut oldest: Vec<TSLEntry> = tsdb.iter_by_time(first..first + 100).collect();
+    tsdb.set_status_batch(&mut oldest[..50], TSLStatus::Deleted)?;
+    tsdb.set_status_batch(&mut oldest[50..], TSLStatus::UserStatus2)?;
+    let reclaimed = tsdb.vacuum()?;
+    assert!(reclaimed >= 2);
+    assert_eq!(tsdb.vacuum()?, 0);
+    let live = tsdb.first_time().unwrap();
+    assert!(live >= first + 100);
+
+    // 回收的空间先被使用，不会翻转覆盖仍保留的日志；重新打开后顺序不变
+    for _ in 0..100 {
+        last += 1;
+        tsdb.append_with_timestamp(last, &[0xA5; 40])?;
+    }
+    assert_eq!(tsdb.first_time(), Some(live));
+    let times: Vec<i64> = tsdb.iter().map(|tsl| tsl.time()).collect();
+    drop(tsdb);
+    let mut tsdb = TSDB::new_file("vacuum_test", path, 4096, 4 * 4096, 128)?;
+    assert_eq!(tsdb.iter().map(|tsl| tsl.time()).collect::<Vec<_>>(), times);
+    assert_eq!(tsdb.last_time(), last);
+    assert_eq!(tsdb.first_time(), Some(live));
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_tsl_addresses() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("tsl_addr_test", path, 4096, 4 * 4096, 64)?;
+    for time in 1..=150i64 {
+        tsdb.append_with_timestamp(time, &[time as u8; 40])?;
+    }
+    let sec_size = tsdb.sec_size();
+    let entries: Vec<TSLEntry> = tsdb.iter().collect();
+
+    assert_eq!(entries[0].sector_index(sec_size), 0);
+    assert!(entries.last().unwrap().sector_index(sec_size) >= 1);
+    for entry in &entries {
+        // 索引与数据位于同一扇区，数据在索引之后
+        assert_eq!(entry.log_addr() / sec_size, entry.sector_index(sec_size));
+        assert!(entry.log_addr() > entry.index_addr());
+        assert!(entry.log_addr() as usize + entry.value_len() <= ((entry.sector_index(sec_size) + 1) * sec_size) as usize);
+    }
+    for pair in entries.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        match b.sector_index(sec_size) - a.sector_index(sec_size) {
+            // 同一扇区内索引向后增长，数据向前增长
+            0 => assert!(b.index_addr() > a.index_addr() && b.log_addr() < a.log_addr()),
+            1 => assert!(b.index_addr() % sec_size < a.index_addr() % sec_size),
+            _ => panic!("日志跨越了扇区"),
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_iter_windows() -> Result<()> {
+    use std::time::Duration;
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("windows_test", path, 4096, 8 * 4096, 64)?;
+    tsdb.set_resolution(flashdb_rs::Resolution::Seconds);
+    // 每 10 秒一条，中间 300..600 秒缺失，跨越扇区
+    for time in (10..1200i64).step_by(10).filter(|t| !(300..600).contains(t)) {
+        tsdb.append_with_timestamp(time, &[0; 24])?;
+    }
+
+    // 窗口从 from 开始对齐，空窗口被跳过
+    let mut windows = Vec::new();
+    let mut iter = tsdb.iter_windows(5, 1000, Duration::from_secs(100));
+    while let Some((start, entries)) = iter.next_window() {
+        windows.push((start, entries.map(|tsl| tsl.time()).collect::<Vec<_>>()));
+    }
+    let starts: Vec<i64> = windows.iter().map(|(start, _)| *start).collect();
+    assert_eq!(starts, [5, 105, 205, 505, 605, 705, 805, 905]);
+    assert_eq!(windows[3].1, [600]);
+    assert_eq!(windows[0].1, (10..=100).step_by(10).collect::<Vec<_>>());
+    assert_eq!(windows[2].1, (210..=290).step_by(10).collect::<Vec<_>>());
+    // 最后一个窗口截止于 to
+    assert_eq!(windows[7].1, (910..=1000).step_by(10).collect::<Vec<_>>());
+    assert!(windows.iter().all(|(start, times)| times.iter().all(|t| (*start..*start + 100).contains(t))));
+
+    // 窗口内的迭代器可以只消费一部分或按状态过滤
+    let mut deleted: Vec<TSLEntry> = tsdb.iter_by_time(700..800).collect();
+    tsdb.set_status_batch(&mut deleted, TSLStatus::Deleted)?;
+    let mut iter = tsdb.iter_windows(0, i64::MAX, Duration::from_secs(100));
+    let mut written = Vec::new();
+    while let Some((start, mut entries)) = iter.next_window() {
+        entries.next();
+        written.push((start, entries.with_status(TSLStatus::Write).count()));
+    }
+    assert_eq!(written.len(), 9);
+    assert_eq!(written[0], (0, 8));
+    assert_eq!(written[4], (700, 0));
+    assert_eq!(written[8], (1100, 9));
+
+    let mut empty = tsdb.iter_windows(1200, i64::MAX, Duration::from_secs(100));
+    assert!(empty.next_window().is_none());
+    assert!(empty.next_window().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_read_only() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = TSDB::new_file("read_only_test", path, 4096, 4 * 4096, 64)?;
+    for i in 1..=10 {
+        db.append_with_timestamp(i, format!("log{}", i).as_bytes())?;
+    }
+    drop(db);
+
+    let snapshot = |dir: &std::path::Path| -> Result<Vec<(std::path::PathBuf, Vec<u8>)>> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            files.push((path.clone(), std::fs::read(&path)?));
+        }
+        files.sort();
+        Ok(files)
+    };
+    let before = snapshot(temp_dir.path())?;
+
+    let storage = StdStorage::new(path, "read_only_test", 4096, 4 * 4096, FileStrategy::Multi)?;
+    let mut db = Box::new(TSDB::new(storage));
+    db.set_name("read_only_test")?;
+    db.set_read_only(true);
+    assert!(db.read_only());
+    db.init(64)?;
+
+    // 读取不受影响
+    assert_eq!(db.last_time(), 10);
+    let mut tsl = db.iter().nth(4).unwrap();
+    assert_eq!(db.get_value(&tsl)?.as_deref(), Some(&b"log5"[..]));
+    assert_eq!(db.count(0, i64::MAX, TSLStatus::Write), 10);
+
+    // 所有写操作被拒绝
+    assert!(matches!(db.append_with_timestamp(11, b"x"), Err(Error::ReadOnly)));
+    assert!(matches!(db.append(b"x"), Err(Error::ReadOnly)));
+    assert!(matches!(db.set_status(&mut tsl, TSLStatus::Deleted), Err(Error::ReadOnly)));
+    let mut batch = vec![tsl.clone()];
+    assert!(matches!(db.set_status_batch(&mut batch, TSLStatus::Deleted), Err(Error::ReadOnly)));
+    assert!(matches!(db.purge_before(5), Err(Error::ReadOnly)));
+    assert!(matches!(db.vacuum(), Err(Error::ReadOnly)));
+    assert!(matches!(db.reset(), Err(Error::ReadOnly)));
+    drop(db);
+    assert!(snapshot(temp_dir.path())? == before, "只读模式不应修改 Flash");
+
+    // 空白存储区不会被格式化
+    let blank_dir = TempDir::new()?;
+    let storage = StdStorage::new(blank_dir.path(), "blank", 4096, 4 * 4096, FileStrategy::Multi)?;
+    let mut db = Box::new(TSDB::new(storage));
+    db.set_name("blank")?;
+    db.set_read_only(true);
+    assert!(db.init(64).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_verify() -> Result<()> {
+    use flashdb_rs::tsdb::{is_time64, TSDBIssueKind};
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut db = TSDB::new_file("verify_tsdb", path, 4096, 4 * 4096, 64)?;
+    for time in 1_000_001..=1_000_003 {
+        db.append_with_timestamp(time, b"sample")?;
+    }
+    let mut first = db.iter().next().unwrap();
+    db.set_status(&mut first, TSLStatus::Deleted)?;
+
+    let report = db.verify()?;
+    assert!(report.is_clean(), "{:?}", report);
+    assert_eq!(report.sectors, 4);
+    assert_eq!(report.valid_entries, 2);
+    assert_eq!(report.deleted_entries, 1);
+
+    // 已写满并翻转写入的扇区
+    let mut full = TSDB::new_file("verify_full", path, 4096, 4 * 4096, 64)?;
+    for time in 1..=500 {
+        full.append_with_timestamp(time, &[0; 32])?;
+    }
+    let report = full.verify()?;
+    assert!(report.is_clean(), "{:?}", report);
+    assert_eq!(report.valid_entries, full.count(0, i64::MAX, TSLStatus::Write));
+    drop(full);
+
+    // 篡改第二条日志索引中的长度
+    let sector = temp_dir.path().join("verify_tsdb.fdb.0");
+    let mut raw = std::fs::read(&sector)?;
+    let time_size = if is_time64() { 8 } else { 4 };
+    let time = 1_000_002i64.to_le_bytes();
+    let pos = raw.windows(time_size).position(|w| w == &time[..time_size]).unwrap();
+    raw[pos + time_size..pos + time_size + 4].copy_from_slice(&0xFFFF_0000u32.to_le_bytes());
+    std::fs::write(&sector, raw)?;
+
+    let report = db.verify()?;
+    assert_eq!(report.valid_entries, 0);
+    assert_eq!(report.deleted_entries, 1);
+    assert_eq!(report.unrecoverable, 1);
+    let issue = &report.issues[0];
+    assert_eq!(issue.kind, TSDBIssueKind::BadLength);
+    assert_eq!(issue.status, Some(TSLStatus::Write));
+    assert!(!issue.recoverable);
+
+    // 破坏另一个扇区头的 magic
+    let sector = temp_dir.path().join("verify_tsdb.fdb.2");
+    let mut raw = std::fs::read(&sector)?;
+    let pos = raw.windows(4).position(|w| w == b"TSL0").unwrap();
+    raw[pos] ^= 0x01;
+    std::fs::write(&sector, raw)?;
+
+    let report = db.verify()?;
+    assert_eq!(report.unrecoverable, 2);
+    let issue = report.issues.iter().find(|i| i.kind == TSDBIssueKind::BadSectorHeader).unwrap();
+    assert_eq!(issue.addr, 2 * 4096);
+    assert_eq!(issue.status, None);
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_power_loss() -> Result<()> {
+    use flashdb_rs::test_utils::{Fault, FaultyFlash};
+
+    fn open(flash: FaultyFlash) -> Result<Box<TSDB<FaultyFlash>>> {
+        let mut db = Box::new(TSDB::new(flash));
+        db.set_name("power_loss")?;
+        db.init(64)?;
+        Ok(db)
+    }
+
+    let mut db = open(FaultyFlash::new(4 * 4096))?;
+    for time in 1..=10 {
+        db.append_with_timestamp(time, b"sample")?;
+    }
+    let image = db.storage().image().to_vec();
+    let before = db.storage().writes();
+    db.append_with_timestamp(11, b"sample")?;
+    let writes = db.storage().writes() - before;
+    drop(db);
+
+    for nth in 1..=writes {
+        let mut db = open(FaultyFlash::from_image(image.clone()))?;
+        db.storage().arm(Fault::TruncateWrite { nth, len: 1 });
+        assert!(db.append_with_timestamp(11, b"sample").is_err());
+        let image = db.storage().image().to_vec();
+        drop(db);
+
+        // 重新上电后已提交的日志完好，中断的日志要么完整要么不存在
+        let mut db = open(FaultyFlash::from_image(image))?;
+        let count = db.count(0, i64::MAX, TSLStatus::Write);
+        assert!(count == 10 || count == 11, "write {}: {}", nth, count);
+        db.append_with_timestamp(12, b"sample")?;
+        assert_eq!(db.count(0, i64::MAX, TSLStatus::Write), count + 1);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_sec_size_larger_than_erase_size() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+
+    // StdStorage 只接受 ERASE_SIZE 整数倍的扇区大小
+    assert!(StdStorage::new(path, "bad_sec", 1000, 16 * 1024, FileStrategy::Multi).is_err());
+
+    for strategy in [FileStrategy::Multi, FileStrategy::Single] {
+        let name = format!("sec8k_{:?}", strategy);
+        let mut tsdb = TSDB::new_file_with_strategy(&name, path, 8192, 64 * 1024, 6000, strategy)?;
+        assert_eq!(tsdb.sec_size(), 8192);
+        // 大于 ERASE_SIZE 的日志只能保存在 8K 扇区中
+        for i in 1..=20 {
+            tsdb.append_with_timestamp(i, &[i as u8; 5000])?;
+        }
+        assert_eq!(tsdb.verify()?.sectors, 8);
+        drop(tsdb);
+
+        let mut tsdb = TSDB::new_file_with_strategy(&name, path, 8192, 64 * 1024, 6000, strategy)?;
+        let mut count = 0;
+        tsdb.tsdb_iter(|db, tsl| {
+            let value = db.get_value(tsl).unwrap().unwrap();
+            assert!(value.len() == 5000 && value.iter().all(|&b| b == tsl.time() as u8));
+            count += 1;
+            true
+        }, false);
+        // 每个 8K 扇区只能容纳一条日志，旧日志被翻转覆盖
+        assert!((7..=8).contains(&count), "{count}");
+        assert_eq!(tsdb.last_time(), 20);
+    }
+
+    // 初始化之后不能再修改扇区大小
+    let mut tsdb = TSDB::new(StdStorage::new(path, "sec_check", 4096, 16 * 1024, FileStrategy::Multi)?);
+    assert!(tsdb.set_sec_size(6000).is_err());
+    tsdb.set_sec_size(8192)?;
+    tsdb.init(256)?;
+    assert!(tsdb.set_sec_size(4096).is_err());
+    assert_eq!(tsdb.sec_size(), 8192);
+    Ok(())
+}
+
+#[test]
+fn test_async_tsdb() -> Result<()> {
+    use core::future::Future;
+    use core::task::{Context, Poll, Waker};
+    use flashdb_rs::asynch::{AsyncKVDB, AsyncTSDB};
+    use flashdb_rs::KVDB;
+
+    /// 运行 future，返回结果以及让出执行器的次数
+    fn block_on<F: Future>(f: F) -> (F::Output, usize) {
+        let mut f = std::pin::pin!(f);
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut yields = 0;
+        loop {
+            match f.as_mut().poll(&mut cx) {
+                Poll::Ready(out) => return (out, yields),
+                Poll::Pending => yields += 1,
+            }
+        }
+    }
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    let mut tsdb = TSDB::new_file("async_ts", path, 4096, 8 * 4096, 64)?;
+    let (result, yields) = block_on(async {
+        let mut db = AsyncTSDB::new(&mut tsdb).with_chunk(10);
+        for i in 1..=100 {
+            db.append_with_timestamp(i, &(i as u32).to_le_bytes()).await?;
+        }
+        let mut seen = Vec::new();
+        db.for_each_by_time(21, 80, |tsl, value| {
+            assert_eq!(value, (tsl.time() as u32).to_le_bytes());
+            seen.push(tsl.time());
+            true
+        })
+        .await?;
+        Ok::<_, Error>(seen)
+    });
+    assert_eq!(result?, (21..=80).collect::<Vec<i64>>());
+    // 每次追加让出一次，遍历每 10 条让出一次
+    assert_eq!(yields, 100 + 6);
+
+    let mut kvdb = KVDB::new_file("async_kv", path, 4096, 4 * 4096, None)?;
+    let (result, yields) = block_on(async {
+        let mut db = AsyncKVDB::new(&mut kvdb).with_chunk(2);
+        for i in 0..5u8 {
+            db.set(&format!("key{i}"), &[i]).await?;
+        }
+        db.delete("key0").await?;
+        let mut total = 0;
+        db.for_each(|_, value| {
+            total += value[0] as u32;
+            true
+        })
+        .await?;
+        Ok::<_, Error>(total)
+    });
+    assert_eq!(result?, 1 + 2 + 3 + 4);
+    assert_eq!(yields, 6 + 1 + 2);
+    Ok(())
+}
+
+#[test]
+fn test_db_handle() -> Result<()> {
+    use core::future::Future;
+    use core::task::{Context, Poll, Waker};
+    use flashdb_rs::handle::TsdbHandle;
+    use std::sync::Arc;
+    use std::task::Wake;
+    use std::thread::Thread;
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// 等待期间挂起当前线程，由数据库线程唤醒
+    fn block_on<F: Future>(f: F) -> F::Output {
+        let mut f = std::pin::pin!(f);
+        let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match f.as_mut().poll(&mut cx) {
+                Poll::Ready(out) => return out,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap().to_string();
+    let db = TsdbHandle::spawn(move || TSDB::new_file("handle_test", &path, 4096, 16 * 4096, 64))?;
+
+    // 多个线程通过克隆的句柄并发写入，操作在数据库线程上依次执行
+    let workers: Vec<_> = (0..4i64)
+        .map(|worker| {
+            let db = db.clone();
+            std::thread::spawn(move || {
+                block_on(async {
+                    for i in 0..25 {
+                        db.append_with_timestamp(worker * 100 + i + 1, &[worker as u8]).await?;
+                    }
+                    Ok::<_, Error>(())
+                })
+            })
+        })
+        .collect();
+    // 时间戳必须递增，部分追加会被拒绝，只检查每个请求都得到了回应
+    for worker in workers {
+        let _ = worker.join().unwrap();
+    }
+    let count = block_on(db.count(0, i64::MAX, TSLStatus::Write))?;
+    assert!(count >= 25);
+
+    let last = block_on(db.call(|db| db.last_time()))?;
+    block_on(db.append_with_timestamp(last + 1, b"tail"))?;
+    let logs = block_on(db.query(last + 1, last + 1))?;
+    assert_eq!(logs, vec![(last + 1, b"tail".to_vec())]);
+
+    // 操作 panic 后数据库线程退出，后续请求返回错误而不是一直等待
+    assert!(block_on(db.call::<(), _>(|_| panic!("boom"))).is_err());
+    assert!(block_on(db.count(0, i64::MAX, TSLStatus::Write)).is_err());
+
+    // 打开失败时 spawn 返回错误
+    assert!(TsdbHandle::<flashdb_rs::StdStorage>::spawn(|| Err(Error::InitFailed)).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_tsdb_deinit_reinit() -> Result<()> {
+    use flashdb_rs::test_utils::FaultyFlash;
+
+    let mut db = TSDB::new(FaultyFlash::new(8 * 4096));
+    assert!(matches!(db.append_with_timestamp(1, b"a"), Err(Error::NotInitialized)));
+    db.init(64)?;
+    db.append_with_timestamp(1, b"a")?;
+    db.deinit();
+    assert!(matches!(db.append_with_timestamp(2, b"b"), Err(Error::NotInitialized)));
+
+    // 重新加载后继续追加
+    db.init(64)?;
+    assert_eq!(db.last_time(), 1);
+    db.append_with_timestamp(2, b"b")?;
+    assert_eq!(db.count(0, i64::MAX, TSLStatus::Write), 2);
+    Ok(())
+}
+
+#[cfg(feature = "log")]
+#[test]
+fn test_tsdb_per_instance_logging() -> Result<()> {
+    use std::sync::Mutex;
+
+    // 只收集本测试线程的日志，避免与并行运行的其他测试互相干扰
+    struct Capture(Mutex<Vec<(Option<String>, log::Level, String)>>);
+    impl log::Log for Capture {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+        fn log(&self, record: &log::Record) {
+            let thread = std::thread::current().name().map(String::from);
+            let line = (thread, record.level(), record.args().to_string());
+            self.0.lock().unwrap().push(line);
+        }
+        fn flush(&self) {}
+    }
+    static CAPTURE: Capture = Capture(Mutex::new(Vec::new()));
+    let _ = log::set_logger(&CAPTURE);
+    log::set_max_level(log::LevelFilter::Trace);
+    let take = || {
+        let me = std::thread::current().name().map(String::from);
+        let mut lines = CAPTURE.0.lock().unwrap();
+        let (mine, others) = lines.drain(..).partition::<Vec<_>, _>(|(thread, ..)| *thread == me);
+        *lines = others;
+        mine.into_iter().map(|(_, level, line)| (level, line)).collect::<Vec<_>>()
+    };
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().to_str().unwrap();
+    // 另一个线程上的数据库同时输出日志
+    let other_dir = TempDir::new()?;
+    let other_path = other_dir.path().to_str().unwrap().to_string();
+    let other = std::thread::spawn(move || {
+        for _ in 0..20 {
+            let _ = std::fs::remove_dir_all(&other_path);
+            std::fs::create_dir_all(&other_path).unwrap();
+            TSDB::new_file("other", &other_path, 4096, 4 * 4096, 64).unwrap();
+        }
+    });
+    let mut busy = TSDB::new_file("busy", path, 4096, 4 * 4096, 64)?;
+    let mut quiet = TSDB::new_file("quiet", path, 4096, 4 * 4096, 64)?;
+    other.join().unwrap();
+    // 每条日志都是完整的一行并带有数据库名称，不会混入其他线程的输出
+    let lines = take();
+    assert!(lines.iter().any(|(_, line)| line.starts_with("[FlashDB][tsl][busy]")));
+    assert!(lines.iter().any(|(_, line)| line.starts_with("[FlashDB][tsl][quiet]")));
+    assert!(lines.iter().all(|(_, line)| line.starts_with("[FlashDB]") && !line.contains("other")));
+
+    busy.append_with_timestamp(10, b"a")?;
+    quiet.append_with_timestamp(10, b"a")?;
+    take();
+
+    // 关闭 quiet 的日志，busy 只输出警告及以上
+    quiet.set_logging(false);
+    busy.set_log_level(log::Level::Warn);
+    busy.reset()?;
+    let _ = busy.append_with_timestamp(5, b"b");
+    let _ = busy.append_with_timestamp(5, b"b");
+    let _ = quiet.append_with_timestamp(5, b"b");
+    let lines = take();
+    assert!(lines.iter().all(|(level, line)| *level <= log::Level::Warn && line.contains("[busy]")));
+    assert!(lines.iter().any(|(level, line)| *level == log::Level::Warn && line.contains("Warning")));
+
+    // 重新开启后恢复输出
+    quiet.set_logging(true);
+    let _ = quiet.append_with_timestamp(5, b"b");
+    assert!(take().iter().any(|(_, line)| line.contains("[quiet]")));
+    Ok(())
+}